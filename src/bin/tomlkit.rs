@@ -8,21 +8,66 @@ use std::io;
 use std::io::{Read, Error, Write};
 use pirate::{Matches, Match, Vars, matches, usage, vars};
 use tomllib::TOMLParser;
+use tomllib::diff;
+use tomllib::patch;
+use tomllib::merge3;
+use tomllib::schema;
+use tomllib::ini;
+use tomllib::properties;
+use tomllib::csv as csv_table;
+use tomllib::format::FormatOptions;
 use tomllib::types::{ParseResult, Children, Value, TOMLError, TimeOffset, DateTime, Date, Time, TimeOffsetAmount,
                      PosNeg};
 use csv::Reader;
 
 macro_rules! usage(
   ($tval:expr) => (
-    usage($tval);
+    print_usage($tval);
     return;
   );
   ($submac:ident!( $($args:tt)* ), $tval:expr) => (
     $submac!($($args)*);
-    usage($tval);
+    print_usage($tval);
     std::process::exit(-1);
   );
 );
+
+// Subcommands are given before any options, so they're handled before pirate sees the arguments
+const SUBCOMMANDS: &[(&str, &str)] = &[
+  ("diff [--json] OLD_FILE NEW_FILE", "Print the differences in meaning between two documents, one per line or as \
+    JSON, and exit with 1 if there are any."),
+  ("merge-driver BASE OURS THEIRS", "Merge the changes THEIRS made to BASE into OURS, writing the result to OURS, \
+    and exit with 1 if there are conflicts, which are marked in OURS. Register it as a git merge driver with \
+    \"tomlkit merge-driver %O %A %B\"."),
+  ("infer-schema [--json] FILE...", "Print a schema inferred from sample documents, in the TOML format tomllib \
+    reads or as a JSON Schema."),
+  ("toml-test decode", "Read a document on standard input and write it as the tagged JSON of the toml-test suite on \
+    standard output, or exit with 1 if it isn't valid."),
+  ("toml-test encode", "Read the tagged JSON of the toml-test suite on standard input and write it as a document on \
+    standard output, or exit with 1 if it can't be converted."),
+  ("import ini|properties [--strings] FILE", "Print an INI or .properties file as TOML, with values read as the \
+    type they look like unless --strings is given."),
+  ("export ini|properties FILE", "Print a document as an INI or .properties file."),
+  ("export csv KEY FILE", "Print the array of tables KEY as CSV, with a column for every key of its tables."),
+  ("import csv [--replace] [--type COLUMN=TYPE]... KEY CSV_FILE FILE", "Append the rows of CSV_FILE to the array \
+    of tables KEY in FILE, or with --replace make it hold exactly those rows while keeping the comments of the \
+    tables already there. Cells are read as the schema type given with --type, such as string or float, or else as \
+    the type they look like."),
+  ("fmt [--check] [--config CONFIG] FILE...", "Rewrite the files in one consistent style, keeping their comments, \
+    with the options in CONFIG such as indent-tables = true or max-width = 100. With --check nothing is written, \
+    the files that aren't formatted are printed and tomlkit exits with 1 if there are any."),
+];
+
+// Prints pirate's usage followed by the subcommands, one per line like the options
+fn print_usage(vars: &Vars) {
+  usage(vars);
+  println!("\nSubcommands, given before any options:");
+  let width = SUBCOMMANDS.iter().map(|subcommand| subcommand.0.len()).max().unwrap_or(0);
+  for &(name, description) in SUBCOMMANDS.iter() {
+    println!("  {:width$}  {}", name, description, width = width);
+  }
+}
+
 // Bugs: Automatic Options group with -h/--help
 //       Positional arguments look like flags/options in the description area
 //       Required argument overrides --help
@@ -48,6 +93,10 @@ fn main() {
       specified value. For instance: \"foo.bar,hello,basic-string,baz.qux,82374,int\" will set the key \"foo.bar\" to \
       a basic string value of \"hello\" and the key \"baz.qux\" to an integer value of 82374. If multiple keys are \
       specified and any one of them fails to set a value, the whole command will fail with an error message.:",
+    "/apply-patch#Given the path to a JSON patch file, a list of \"add\", \"remove\", \"replace\", \"move\", \"copy\" \
+      and \"test\" operations like [{\"op\": \"replace\", \"path\": \"foo.bar\", \"value\": 5}], apply the operations \
+      in order. If any operation fails, including a \"test\" operation whose value doesn't match, the document is left \
+      unchanged and the command fails with the index of that operation.:",
     "#Pre-command Options",
     "h/help#Show this screen.",
     "/set-true#For commands that print \"true\" or \"false\", this will change what value is printed for \"true\", \
@@ -71,6 +120,9 @@ fn main() {
     keys as that would make them unusable. Warning: This will make it impossible to distinguish between each type of \
     string and it can make it impossible to tell the return type e.g. Is 1234 without quotes an integer or some kind \
     of string? Is true a string or a boolean?",
+    "/query#Treat the keys given to --get-value as queries, which can use \"*\" for any key, \"[*]\" for any index, \
+    \"..\" for any depth and \"[?key > 1000]\" to filter tables by a value. Every matching key and its value is \
+    printed as \"key = value\". A query that doesn't match any values fails with an error message.",
     "q/quiet#For commands that modify rather than return a result, turn off printing \"Success\" for each successful \
     modification.",
     "#Post-command Options",
@@ -89,6 +141,34 @@ fn main() {
   };
 
   let args: Vec<String> = env::args().collect();
+  if args.len() > 1 && args[1] == "diff" {
+    diff_documents(&args[2..]);
+    return;
+  }
+  if args.len() > 1 && args[1] == "merge-driver" {
+    merge_documents(&args[2..]);
+    return;
+  }
+  if args.len() > 1 && args[1] == "infer-schema" {
+    infer_schema(&args[2..]);
+    return;
+  }
+  if args.len() > 1 && args[1] == "toml-test" {
+    toml_test(&args[2..]);
+    return;
+  }
+  if args.len() > 1 && args[1] == "fmt" {
+    format_files(&args[2..]);
+    return;
+  }
+  if args.len() > 2 && (args[1] == "import" || args[1] == "export") && args[2] == "csv" {
+    convert_csv(args[1] == "import", &args[3..]);
+    return;
+  }
+  if args.len() > 1 && (args[1] == "import" || args[1] == "export") {
+    convert(args[1] == "import", &args[2..]);
+    return;
+  }
   let matches: Matches = match matches(&args, &mut vars) {
     Ok(m) => m,
     Err(e) => {
@@ -139,6 +219,7 @@ struct Options {
   quiet: bool,
   arr_len: bool,
   strip_quotes: bool,
+  query: bool,
   print_doc: bool,
 }
 
@@ -147,7 +228,7 @@ impl Options {
     let mut opts = Options{true_vals: "true".to_string(), false_vals: "false".to_string(),
                    separator: ", ".to_string(), arr_start: "[".to_string(),
                    arr_end: "]".to_string(), arr_sep: ", ".to_string(),
-                   quiet: false, arr_len: false, strip_quotes: false, print_doc: false};
+                   quiet: false, arr_len: false, strip_quotes: false, query: false, print_doc: false};
     // Pre-command options
     if matches.has_match("set-true") {
       if let Some(t) = matches.get("set-true") {
@@ -197,6 +278,9 @@ impl Options {
     if matches.has_match("strip-quotes") {
       opts.strip_quotes = true;
     }
+    if matches.has_match("query") {
+      opts.query = true;
+    }
     // Post-command options
     if matches.has_match("print-doc") {
       opts.print_doc = true;
@@ -218,38 +302,20 @@ fn process_document(file_path: &String, opts: &Options, matches: &Matches, vars:
   // Parse the document
   let parser: TOMLParser = TOMLParser::new();
   let (mut parser, result) = parser.parse(&file);
-  match result {
-    ParseResult::Partial(_,_,_) => {
-      println!("Error \"{}\": Document only partially parsed. Please correct any errors before trying again.",
-        file_path);
-      std::process::exit(-1);
-    },
-    ParseResult::PartialError(_,_,_,_) => {
-      println!("Error \"{}\": Document only partially parsed with errors. Please correct any errors before trying \
-        again.", file_path);
-      std::process::exit(-1);
-    },
-    ParseResult::Failure(_,_) => {
-      println!("Error \"{}\": Completely failed to parse document. Please correct any error before trying again.",
-        file_path);
-      std::process::exit(-1);
-    },
-    ParseResult::FullError(errors) => {
-      println!("Error \"{}\": Parsed entire document, but with errors: {:?}.", file_path, errors);
-      std::process::exit(-1);
-    },
-    _ => (), // If verbose output Full or FullError
-  }
+  check_result(file_path, result);
 
   let mut command: bool = false;
   let mut result: Vec<Result<String, String>> = vec![Ok("".to_string())];
-  let mut out_file = file_path;
   let mut hasval_keycount = 0;
   // Commands only one command allowed per invocation for this version
   if matches.has_match("get-value") {
     command = true;
     if let Some(k) = matches.get("get-value") {
-      result.push(get_value(k, &opts.separator, opts.strip_quotes, &parser));
+      if opts.query {
+        result.push(query(k, &opts.separator, opts.strip_quotes, &parser));
+      } else {
+        result.push(get_value(k, &opts.separator, opts.strip_quotes, &parser));
+      }
     } else {
       usage!(println!("Error \"{}\": A required argument is missing for g/get-value.", file_path), &vars);
     }
@@ -290,29 +356,27 @@ fn process_document(file_path: &String, opts: &Options, matches: &Matches, vars:
       command = true;
       if let Some(kv) = matches.get("set-value") {
         result.push(set_value(kv, &opts.separator, opts.quiet, &mut parser));
-        if matches.has_match("output-file") {
-          match matches.get("output-file") {
-            Some(out) => out_file = out,
-            None => {
-              usage!(println!("Error \"{}\": A required argument is missing for output-file.", file_path), &vars);
-            },
-          }
-        }
         if let Ok(_) = result[result.len() - 1] {
-          // Write back out to the file
-          match write_to_file(out_file, &parser) {
-            Ok(()) => (),
-            Err(err) => {
-              println!("Error \"{}\": Unable to write to file: \"{}\". Reason: {}", file_path, out_file, err);
-              std::process::exit(-1);
-            },
-          }
+          save(file_path, matches, vars, &parser);
         }
       } else {
         usage!(println!("Error \"{}\": A required argument is missing for s/set-value.", file_path), &vars);
       }
     }
   }
+  if let Ok(_) = result[result.len() - 1] {
+    if matches.has_match("apply-patch") {
+      command = true;
+      if let Some(patch_path) = matches.get("apply-patch") {
+        result.push(apply_patch(patch_path, opts.quiet, &mut parser));
+        if let Ok(_) = result[result.len() - 1] {
+          save(file_path, matches, vars, &parser);
+        }
+      } else {
+        usage!(println!("Error \"{}\": A required argument is missing for apply-patch.", file_path), &vars);
+      }
+    }
+  }
   if !command {
     // No command specified print usage
     usage!(println!("Error \"{}\": No command was specified.", file_path), &vars);
@@ -345,6 +409,348 @@ fn process_document(file_path: &String, opts: &Options, matches: &Matches, vars:
   }
 }
 
+// Writes the modified document to the output file, or back to `file_path` if there isn't one
+fn save(file_path: &String, matches: &Matches, vars: &Vars, parser: &TOMLParser) {
+  let mut out_file = file_path;
+  if matches.has_match("output-file") {
+    match matches.get("output-file") {
+      Some(out) => out_file = out,
+      None => {
+        usage!(println!("Error \"{}\": A required argument is missing for output-file.", file_path), &vars);
+      },
+    }
+  }
+  match write_to_file(out_file, parser) {
+    Ok(()) => (),
+    Err(err) => {
+      println!("Error \"{}\": Unable to write to file: \"{}\". Reason: {}", file_path, out_file, err);
+      std::process::exit(-1);
+    },
+  }
+}
+
+// Exits with an error message if the document at `file_path` didn't parse completely
+fn check_result(file_path: &String, result: ParseResult) {
+  match result {
+    ParseResult::Partial(_,_,_) => {
+      println!("Error \"{}\": Document only partially parsed. Please correct any errors before trying again.",
+        file_path);
+      std::process::exit(-1);
+    },
+    ParseResult::PartialError(_,_,_,_) => {
+      println!("Error \"{}\": Document only partially parsed with errors. Please correct any errors before trying \
+        again.", file_path);
+      std::process::exit(-1);
+    },
+    ParseResult::Failure(_,_) => {
+      println!("Error \"{}\": Completely failed to parse document. Please correct any error before trying again.",
+        file_path);
+      std::process::exit(-1);
+    },
+    ParseResult::FullError(errors) => {
+      println!("Error \"{}\": Parsed entire document, but with errors: {:?}.", file_path, errors);
+      std::process::exit(-1);
+    },
+    _ => (), // If verbose output Full or FullError
+  }
+}
+
+fn diff_documents(args: &[String]) {
+  let json = args.iter().any(|arg| arg == "--json");
+  let files: Vec<&String> = args.iter().filter(|arg| *arg != "--json").collect();
+  if files.len() != 2 {
+    println!("Error: diff needs exactly two files: tomlkit diff [--json] OLD_FILE NEW_FILE");
+    std::process::exit(-1);
+  }
+  let mut contents = vec![String::new(), String::new()];
+  for (file_path, file) in files.iter().zip(contents.iter_mut()) {
+    if let Err(err) = get_file(file_path, file) {
+      println!("Error \"{}\": Unable to open file: {}", file_path, err);
+      std::process::exit(-1);
+    }
+  }
+  let (old, result) = TOMLParser::new().parse(&contents[0]);
+  check_result(files[0], result);
+  let (new, result) = TOMLParser::new().parse(&contents[1]);
+  check_result(files[1], result);
+  let changes = old.diff(&new);
+  if json {
+    println!("{}", diff::to_json(&changes));
+  } else {
+    for change in &changes {
+      println!("{}", change);
+    }
+  }
+  if !changes.is_empty() {
+    std::process::exit(1);
+  }
+}
+
+fn merge_documents(args: &[String]) {
+  if args.len() != 3 {
+    println!("Error: merge-driver needs exactly three files: tomlkit merge-driver BASE OURS THEIRS");
+    std::process::exit(-1);
+  }
+  let mut contents = vec![String::new(), String::new(), String::new()];
+  for (file_path, file) in args.iter().zip(contents.iter_mut()) {
+    if let Err(err) = get_file(file_path, file) {
+      println!("Error \"{}\": Unable to open file: {}", file_path, err);
+      std::process::exit(-1);
+    }
+  }
+  let (base, result) = TOMLParser::new().parse(&contents[0]);
+  check_result(&args[0], result);
+  let (mut ours, result) = TOMLParser::new().parse(&contents[1]);
+  check_result(&args[1], result);
+  let (theirs, result) = TOMLParser::new().parse(&contents[2]);
+  check_result(&args[2], result);
+  let conflicts = ours.merge3(&base, &theirs);
+  let merged = if conflicts.is_empty() {
+    format!("{}", ours)
+  } else {
+    merge3::conflict_markers(&ours, &theirs, &conflicts)
+  };
+  let written = File::create(&args[1]).and_then(|mut f| f.write_all(merged.as_bytes()).and_then(|_| f.sync_all()));
+  if let Err(err) = written {
+    println!("Error \"{}\": Unable to write file: {}", args[1], err);
+    std::process::exit(-1);
+  }
+  for conflict in &conflicts {
+    println!("{}", conflict);
+  }
+  if !conflicts.is_empty() {
+    std::process::exit(1);
+  }
+}
+
+fn infer_schema(args: &[String]) {
+  let json = args.iter().any(|arg| arg == "--json");
+  let files: Vec<&String> = args.iter().filter(|arg| *arg != "--json").collect();
+  if files.is_empty() {
+    println!("Error: infer-schema needs at least one file: tomlkit infer-schema [--json] FILE...");
+    std::process::exit(-1);
+  }
+  let mut contents = vec![String::new(); files.len()];
+  for (file_path, file) in files.iter().zip(contents.iter_mut()) {
+    if let Err(err) = get_file(file_path, file) {
+      println!("Error \"{}\": Unable to open file: {}", file_path, err);
+      std::process::exit(-1);
+    }
+  }
+  let mut docs = vec![];
+  for (file_path, content) in files.iter().zip(contents.iter()) {
+    let (doc, result) = TOMLParser::new().parse(content);
+    check_result(file_path, result);
+    docs.push(doc);
+  }
+  let schema = schema::infer_schema(&docs.iter().collect::<Vec<_>>());
+  if json {
+    print!("{}", schema.to_json_schema());
+  } else {
+    print!("{}", schema.to_toml());
+  }
+}
+
+// Runs tomllib as a decoder or an encoder for the toml-test suite, which only looks at standard output and the exit
+// code
+fn toml_test(args: &[String]) {
+  if args.len() != 1 || (args[0] != "decode" && args[0] != "encode") {
+    println!("Error: toml-test needs a mode: tomlkit toml-test decode|encode");
+    std::process::exit(-1);
+  }
+  let mut input = String::new();
+  if let Err(err) = io::stdin().read_to_string(&mut input) {
+    eprintln!("Error: Unable to read standard input: {}", err);
+    std::process::exit(1);
+  }
+  if args[0] == "encode" {
+    match TOMLParser::from_tagged_json(&input) {
+      Ok(doc) => print!("{}", doc),
+      Err(err) => {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+      },
+    }
+    return;
+  }
+  let (doc, result) = TOMLParser::new().parse(&input);
+  match result {
+    ParseResult::Full => println!("{}", doc.to_tagged_json()),
+    _ => {
+      eprintln!("Error: The document isn't valid TOML");
+      std::process::exit(1);
+    },
+  }
+}
+
+fn convert(import: bool, args: &[String]) {
+  let strings = import && args.iter().any(|arg| arg == "--strings");
+  let args: Vec<&String> = args.iter().filter(|arg| !import || *arg != "--strings").collect();
+  if args.len() != 2 || (args[0] != "ini" && args[0] != "properties") {
+    if import {
+      println!("Error: import needs a format and a file: tomlkit import ini|properties [--strings] FILE");
+    } else {
+      println!("Error: export needs a format and a file: tomlkit export ini|properties FILE");
+    }
+    std::process::exit(-1);
+  }
+  let mut contents = String::new();
+  if let Err(err) = get_file(args[1], &mut contents) {
+    println!("Error \"{}\": Unable to open file: {}", args[1], err);
+    std::process::exit(-1);
+  }
+  let converted = if import {
+    match &args[0][..] {
+      "ini" => ini::to_toml(&contents, !strings),
+      _ => properties::to_toml(&contents, !strings),
+    }
+  } else {
+    let (doc, result) = TOMLParser::new().parse(&contents);
+    check_result(args[1], result);
+    match &args[0][..] {
+      "ini" => ini::from_toml(&doc),
+      _ => properties::from_toml(&doc),
+    }
+  };
+  match converted {
+    Ok(text) => print!("{}", text),
+    Err(err) => {
+      println!("Error \"{}\": {}", args[1], err);
+      std::process::exit(-1);
+    },
+  }
+}
+
+fn format_files(args: &[String]) {
+  let mut check = false;
+  let mut config = None;
+  let mut files = vec![];
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    if arg == "--check" {
+      check = true;
+    } else if arg == "--config" {
+      config = args.next();
+      if config.is_none() {
+        println!("Error: --config needs a file: tomlkit fmt [--check] [--config CONFIG] FILE...");
+        std::process::exit(-1);
+      }
+    } else {
+      files.push(arg);
+    }
+  }
+  if files.is_empty() {
+    println!("Error: fmt needs at least one file: tomlkit fmt [--check] [--config CONFIG] FILE...");
+    std::process::exit(-1);
+  }
+  let options = match config {
+    Some(config_path) => {
+      let mut contents = String::new();
+      if let Err(err) = get_file(config_path, &mut contents) {
+        println!("Error \"{}\": Unable to open file: {}", config_path, err);
+        std::process::exit(-1);
+      }
+      let (config, result) = TOMLParser::new().parse(&contents);
+      check_result(config_path, result);
+      match FormatOptions::from_document(&config) {
+        Ok(options) => options,
+        Err(err) => {
+          println!("Error \"{}\": {}", config_path, err);
+          std::process::exit(-1);
+        },
+      }
+    },
+    None => FormatOptions::default(),
+  };
+  let mut unformatted = false;
+  for file_path in files {
+    let mut contents = String::new();
+    if let Err(err) = get_file(file_path, &mut contents) {
+      println!("Error \"{}\": Unable to open file: {}", file_path, err);
+      std::process::exit(-1);
+    }
+    let (doc, result) = TOMLParser::new().parse(&contents);
+    check_result(file_path, result);
+    let formatted = doc.format(&options);
+    if formatted == contents {
+      continue;
+    }
+    unformatted = true;
+    if check {
+      println!("{}", file_path);
+      continue;
+    }
+    let written = File::create(file_path)
+      .and_then(|mut f| f.write_all(formatted.as_bytes()).and_then(|_| f.sync_all()));
+    if let Err(err) = written {
+      println!("Error \"{}\": Unable to write file: {}", file_path, err);
+      std::process::exit(-1);
+    }
+  }
+  if check && unformatted {
+    std::process::exit(1);
+  }
+}
+
+fn convert_csv(import: bool, args: &[String]) {
+  let mut mode = csv_table::Mode::Append;
+  let mut types = vec![];
+  let mut files = vec![];
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    if import && arg == "--replace" {
+      mode = csv_table::Mode::Replace;
+    } else if import && arg == "--type" {
+      let hint = args.next().and_then(|hint| hint.split_once('='))
+        .and_then(|(column, name)| schema::Type::from_name(name).map(|kind| (column, kind)));
+      match hint {
+        Some(hint) => types.push(hint),
+        None => {
+          println!("Error: --type needs a column and a schema type, like --type price=float");
+          std::process::exit(-1);
+        },
+      }
+    } else {
+      files.push(arg);
+    }
+  }
+  if files.len() != if import { 3 } else { 2 } {
+    if import {
+      println!("Error: import csv needs a key and two files: tomlkit import csv [--replace] [--type COLUMN=TYPE]... \
+        KEY CSV_FILE FILE");
+    } else {
+      println!("Error: export csv needs a key and a file: tomlkit export csv KEY FILE");
+    }
+    std::process::exit(-1);
+  }
+  let mut contents = vec![String::new(); files.len() - 1];
+  for (file_path, file) in files[1..].iter().zip(contents.iter_mut()) {
+    if let Err(err) = get_file(file_path, file) {
+      println!("Error \"{}\": Unable to open file: {}", file_path, err);
+      std::process::exit(-1);
+    }
+  }
+  let file_path = files[files.len() - 1];
+  let (mut doc, result) = TOMLParser::new().parse(&contents[contents.len() - 1]);
+  check_result(file_path, result);
+  let converted = if import {
+    csv_table::import(&mut doc, files[0], &contents[0], &types, mode).map(|_| String::new())
+  } else {
+    csv_table::export(&doc, files[0])
+  };
+  match converted {
+    Ok(_) if import => if let Err(err) = write_to_file(file_path, &doc) {
+      println!("Error \"{}\": Unable to write file: {}", file_path, err);
+      std::process::exit(-1);
+    },
+    Ok(text) => print!("{}", text),
+    Err(err) => {
+      println!("Error \"{}\": {}", files[1], err);
+      std::process::exit(-1);
+    },
+  }
+}
+
 fn write_to_file(file_path: &String, doc: &TOMLParser) -> Result<(), Error> {
   let mut f = try!(File::create(file_path));
   try!(f.write_all(format!("{}",doc).as_bytes()));
@@ -385,6 +791,31 @@ fn get_value(csv: &str, sep: &String, strip_quotes: bool, doc: &TOMLParser) -> R
   Err(format!("Could not parse keys: \"{:?}\".", csv))
 }
 
+fn query(csv: &str, sep: &String, strip_quotes: bool, doc: &TOMLParser) -> Result<String, String> {
+  let query_results = csv_to_vec(csv);
+  if let Ok(queries) = query_results {
+    if queries.len() == 0 {
+      return Err(format!("No queries specified: \"{}\".", csv));
+    }
+    let mut matches = vec![];
+    for query in queries.iter() {
+      match doc.query(query) {
+        Ok(ref found) if found.len() == 0 => return Err(format!("Query \"{}\" didn't match any values.", query)),
+        Ok(found) => for (key, value) in found {
+          if strip_quotes {
+            matches.push(format!("{} = {}", key, format!("{}", value).trim_matches(|c| c == '\'' || c == '\"')));
+          } else {
+            matches.push(format!("{} = {}", key, value));
+          }
+        },
+        Err(err) => return Err(format!("{}", err)),
+      }
+    }
+    return Ok(matches.join(sep));
+  }
+  Err(format!("Could not parse queries: \"{}\".", csv))
+}
+
 fn has_value(csv: &str, sep: &String, true_vals: &String, false_vals: &String, keycount: &mut usize, doc: &TOMLParser)
   -> Result<String, String> {
   let key_results = csv_to_vec(csv);
@@ -542,6 +973,20 @@ fn has_children(csv: &str, sep: &String, true_vals: &String, false_vals: &String
   Err(format!("Could not parse keys: \"{}\".", csv))
 }
 
+fn apply_patch(patch_path: &String, quiet: bool, doc: &mut TOMLParser) -> Result<String, String> {
+  let mut patch_file = String::new();
+  if let Err(err) = get_file(patch_path, &mut patch_file) {
+    return Err(format!("Unable to open patch file \"{}\": {}", patch_path, err));
+  }
+  let ops = patch::from_json(&patch_file).map_err(|err| format!("{}", err))?;
+  doc.apply_patch(&ops).map_err(|err| format!("{}", err))?;
+  if quiet {
+    Ok(String::new())
+  } else {
+    Ok("Success".to_string())
+  }
+}
+
 fn set_value<'a>(kvs: &str, sep: &String, quiet: bool, doc: &mut TOMLParser) -> Result<String, String> {
   let keyval_results = csv_to_vec(kvs);
   if let Ok(keyvals) = keyval_results {
@@ -626,12 +1071,11 @@ fn set_value<'a>(kvs: &str, sep: &String, quiet: bool, doc: &mut TOMLParser) ->
         _ => return Err(format!("Type \"{}\" not recognized for key: \"{}\"", typ, key)),
       }
       if let Ok(value) = val_result {
-        if doc.set_value(key, value) {
-          if !quiet {
+        match doc.try_set_value(key, value) {
+          Ok(()) => if !quiet {
             result.push_str("Success");
-          }
-        } else {
-          return Err(format!("Could not set value of key: \"{}\" to value: \"{}\", with type \"{}\"", key, val, typ));
+          },
+          Err(err) => return Err(format!("Could not set value of key: \"{}\" to value: \"{}\", with type \"{}\": {}", key, val, typ, err)),
         }
       } else {
         return Err(format!("Unable to parse value: \"{}\" as type: \"{}\" for key: \"{}\"", val, typ, key));
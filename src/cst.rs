@@ -0,0 +1,712 @@
+//! A read-only view of the concrete syntax tree (CST) of a parsed TOML document.
+//!
+//! The CST keeps everything the parser saw: whitespace, comments, keys exactly as they were written and the quoting
+//! style of every string. It's meant for tools such as formatters, linters and syntax highlighters that need more than
+//! the keys and values returned by `TOMLParser::get_value`. Printing a `Document` with `Display` reproduces the
+//! document it was built from.
+//!
+//! The types in this module are versioned separately from the parser's internal structures. Any change to them that
+//! isn't backwards compatible bumps `CST_VERSION`.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::cst::{Item, ValueNode};
+//! use tomllib::types::StrType;
+//!
+//! let parser = TOMLParser::new();
+//! let (parser, _) = parser.parse("[table] # A comment\n  \"Key One\" = 'A Value'\n");
+//! let doc = parser.cst();
+//! let lines: Vec<_> = doc.lines().collect();
+//! assert_eq!(" A comment", lines[0].comment().unwrap().text());
+//! assert_eq!("  ", lines[1].indent());
+//! if let Some(&Item::KeyValue(ref kv)) = lines[1].item() {
+//!   assert_eq!("\"Key One\"", kv.key().text());
+//!   if let ValueNode::String(ref s) = *kv.value() {
+//!     assert_eq!("A Value", s.text());
+//!     assert_eq!(StrType::Literal, s.str_type());
+//!   }
+//! }
+//! assert_eq!("[table] # A comment\n  \"Key One\" = 'A Value'\n", format!("{}", doc));
+//! ```
+
+use std::fmt;
+use std::fmt::Display;
+use std::slice::Iter;
+use types::StrType;
+use internals::ast::structs::{Toml, Expression, KeyVal, TableType, TOMLValue, Array, InlineTable, CommentOrNewLines,
+  WSSep};
+
+/// The version of the CST types. It's bumped whenever the types in this module change in a way that isn't backwards
+/// compatible.
+pub const CST_VERSION: u32 = 1;
+
+/// The root of the concrete syntax tree. A document is a list of lines.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Document {
+  lines: Vec<Line>,
+}
+
+/// A single line of a document: optional indentation, an optional key-value pair or table header, optional trailing
+/// whitespace and an optional comment.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Line {
+  newline: String,
+  indent: String,
+  item: Option<Item>,
+  trailing_whitespace: String,
+  comment: Option<Comment>,
+}
+
+/// The main content of a line.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Item {
+  /// A key-value pair.
+  KeyValue(KeyValue),
+  /// A standard table (`[table]`) or array of tables (`[[table]]`) header.
+  Table(TableHeader),
+}
+
+/// A comment. The text doesn't include the leading `#`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Comment {
+  text: String,
+}
+
+/// A key as it was written in the document, including any quotes and escapes.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Key {
+  text: String,
+}
+
+/// A key-value pair: `<key><whitespace>=<whitespace><value>`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct KeyValue {
+  key: Key,
+  separator: Separator,
+  value: ValueNode,
+}
+
+/// The whitespace on either side of a separator such as `=`, `,` or `.`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Separator {
+  before: String,
+  after: String,
+}
+
+/// A table header. The whitespace just inside the brackets is available from `leading_whitespace` and
+/// `trailing_whitespace`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TableHeader {
+  array: bool,
+  leading_whitespace: String,
+  keys: Vec<HeaderKey>,
+  trailing_whitespace: String,
+}
+
+/// One key of a table header. Every key but the first is preceded by a dot `separator`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct HeaderKey {
+  separator: Option<Separator>,
+  key: Key,
+}
+
+/// A value as it was written in the document.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ValueNode {
+  /// An integer. Contains the integer as it was written.
+  Integer(String),
+  /// A float. Contains the float as it was written.
+  Float(String),
+  /// A boolean.
+  Boolean(bool),
+  /// A datetime. Contains the datetime as it was written.
+  DateTime(String),
+  /// A string of any of the 4 string types.
+  String(StringNode),
+  /// An array.
+  Array(ArrayNode),
+  /// An inline table.
+  InlineTable(InlineTableNode),
+}
+
+/// A string value. The text is the raw text between the quotes, escapes are not processed.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct StringNode {
+  text: String,
+  str_type: StrType,
+}
+
+/// Whitespace, newlines and comments that appear inside of arrays and inline tables.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Trivia {
+  /// Whitespace and newlines.
+  Whitespace(String),
+  /// A comment, the whitespace and newlines that come before it, and the newlines that come after it.
+  Comment(String, Comment, String),
+}
+
+/// An array: `[<trivia><elements><trivia>]`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ArrayNode {
+  leading: Vec<Trivia>,
+  elements: Vec<ArrayElement>,
+  trailing: Vec<Trivia>,
+}
+
+/// A value in an array, followed by an optional comma `separator` and any trivia after it.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ArrayElement {
+  value: ValueNode,
+  separator: Option<Separator>,
+  trivia: Vec<Trivia>,
+}
+
+/// An inline table: `{<whitespace><entries><whitespace>}`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct InlineTableNode {
+  leading_whitespace: String,
+  entries: Vec<InlineEntry>,
+  trailing_whitespace: String,
+}
+
+/// A key-value pair in an inline table, followed by an optional comma `separator` and any trivia after it.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct InlineEntry {
+  key_value: KeyValue,
+  separator: Option<Separator>,
+  trivia: Vec<Trivia>,
+}
+
+impl Document {
+  /// Returns an iterator over the lines of the document.
+  pub fn lines(&self) -> Iter<'_, Line> {
+    self.lines.iter()
+  }
+
+  /// Returns the number of lines in the document.
+  pub fn len(&self) -> usize {
+    self.lines.len()
+  }
+
+  /// Returns `true` if the document has no lines.
+  pub fn is_empty(&self) -> bool {
+    self.lines.is_empty()
+  }
+}
+
+impl Line {
+  /// Returns the newline that ends the previous line. Empty for the first line of the document.
+  pub fn newline(&self) -> &str {
+    &self.newline
+  }
+
+  /// Returns the whitespace at the start of the line. For blank lines this is all of the line's whitespace.
+  pub fn indent(&self) -> &str {
+    &self.indent
+  }
+
+  /// Returns the key-value pair or table header on this line, if any.
+  pub fn item(&self) -> Option<&Item> {
+    self.item.as_ref()
+  }
+
+  /// Returns the whitespace between the line's item and its comment or the end of the line.
+  pub fn trailing_whitespace(&self) -> &str {
+    &self.trailing_whitespace
+  }
+
+  /// Returns the comment at the end of the line, if any.
+  pub fn comment(&self) -> Option<&Comment> {
+    self.comment.as_ref()
+  }
+}
+
+impl Comment {
+  /// Returns the text of the comment without the leading `#`.
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+}
+
+impl Key {
+  /// Returns the key as it was written, including any quotes.
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  /// Returns `true` if the key is a quoted key.
+  pub fn is_quoted(&self) -> bool {
+    self.text.starts_with('"') || self.text.starts_with('\'')
+  }
+}
+
+impl KeyValue {
+  /// Returns the key.
+  pub fn key(&self) -> &Key {
+    &self.key
+  }
+
+  /// Returns the whitespace around the `=`.
+  pub fn separator(&self) -> &Separator {
+    &self.separator
+  }
+
+  /// Returns the value.
+  pub fn value(&self) -> &ValueNode {
+    &self.value
+  }
+}
+
+impl Separator {
+  /// Returns the whitespace before the separator.
+  pub fn before(&self) -> &str {
+    &self.before
+  }
+
+  /// Returns the whitespace after the separator.
+  pub fn after(&self) -> &str {
+    &self.after
+  }
+}
+
+impl TableHeader {
+  /// Returns `true` for an array of tables header (`[[table]]`) and `false` for a standard table header.
+  pub fn is_array(&self) -> bool {
+    self.array
+  }
+
+  /// Returns the whitespace between the opening bracket(s) and the first key.
+  pub fn leading_whitespace(&self) -> &str {
+    &self.leading_whitespace
+  }
+
+  /// Returns an iterator over the dotted keys of the header.
+  pub fn keys(&self) -> Iter<'_, HeaderKey> {
+    self.keys.iter()
+  }
+
+  /// Returns the whitespace between the last key and the closing bracket(s).
+  pub fn trailing_whitespace(&self) -> &str {
+    &self.trailing_whitespace
+  }
+}
+
+impl HeaderKey {
+  /// Returns the whitespace around the dot before this key. `None` for the first key.
+  pub fn separator(&self) -> Option<&Separator> {
+    self.separator.as_ref()
+  }
+
+  /// Returns the key.
+  pub fn key(&self) -> &Key {
+    &self.key
+  }
+}
+
+impl StringNode {
+  /// Returns the raw text between the quotes.
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  /// Returns the quoting style of the string.
+  pub fn str_type(&self) -> StrType {
+    self.str_type
+  }
+}
+
+impl ArrayNode {
+  /// Returns the trivia between the opening bracket and the first value.
+  pub fn leading(&self) -> Iter<'_, Trivia> {
+    self.leading.iter()
+  }
+
+  /// Returns an iterator over the elements of the array.
+  pub fn elements(&self) -> Iter<'_, ArrayElement> {
+    self.elements.iter()
+  }
+
+  /// Returns the trivia between the last value and the closing bracket.
+  pub fn trailing(&self) -> Iter<'_, Trivia> {
+    self.trailing.iter()
+  }
+}
+
+impl ArrayElement {
+  /// Returns the value.
+  pub fn value(&self) -> &ValueNode {
+    &self.value
+  }
+
+  /// Returns the whitespace around the comma after the value. `None` if there's no comma.
+  pub fn separator(&self) -> Option<&Separator> {
+    self.separator.as_ref()
+  }
+
+  /// Returns the trivia after the value and comma.
+  pub fn trivia(&self) -> Iter<'_, Trivia> {
+    self.trivia.iter()
+  }
+}
+
+impl InlineTableNode {
+  /// Returns the whitespace after the opening brace.
+  pub fn leading_whitespace(&self) -> &str {
+    &self.leading_whitespace
+  }
+
+  /// Returns an iterator over the entries of the inline table.
+  pub fn entries(&self) -> Iter<'_, InlineEntry> {
+    self.entries.iter()
+  }
+
+  /// Returns the whitespace before the closing brace.
+  pub fn trailing_whitespace(&self) -> &str {
+    &self.trailing_whitespace
+  }
+}
+
+impl InlineEntry {
+  /// Returns the key-value pair.
+  pub fn key_value(&self) -> &KeyValue {
+    &self.key_value
+  }
+
+  /// Returns the whitespace around the comma after the key-value pair. `None` if there's no comma.
+  pub fn separator(&self) -> Option<&Separator> {
+    self.separator.as_ref()
+  }
+
+  /// Returns the trivia after the key-value pair and comma.
+  pub fn trivia(&self) -> Iter<'_, Trivia> {
+    self.trivia.iter()
+  }
+}
+
+impl Display for Document {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for line in &self.lines {
+      write!(f, "{}", line)?;
+    }
+    Ok(())
+  }
+}
+
+impl Display for Line {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}", self.newline, self.indent)?;
+    if let Some(ref item) = self.item {
+      write!(f, "{}{}", item, self.trailing_whitespace)?;
+    }
+    if let Some(ref comment) = self.comment {
+      write!(f, "{}", comment)?;
+    }
+    Ok(())
+  }
+}
+
+impl Display for Item {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Item::KeyValue(ref kv) => write!(f, "{}", kv),
+      Item::Table(ref table) => write!(f, "{}", table),
+    }
+  }
+}
+
+impl Display for Comment {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "#{}", self.text)
+  }
+}
+
+impl Display for Key {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.text)
+  }
+}
+
+impl Display for KeyValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}={}{}", self.key, self.separator.before, self.separator.after, self.value)
+  }
+}
+
+impl Display for TableHeader {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let (open, close) = if self.array { ("[[", "]]") } else { ("[", "]") };
+    write!(f, "{}{}", open, self.leading_whitespace)?;
+    for key in &self.keys {
+      if let Some(ref sep) = key.separator {
+        write!(f, "{}.{}", sep.before, sep.after)?;
+      }
+      write!(f, "{}", key.key)?;
+    }
+    write!(f, "{}{}", self.trailing_whitespace, close)
+  }
+}
+
+impl Display for ValueNode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ValueNode::Integer(ref s) | ValueNode::Float(ref s) | ValueNode::DateTime(ref s) => write!(f, "{}", s),
+      ValueNode::Boolean(b) => write!(f, "{}", b),
+      ValueNode::String(ref s) => write!(f, "{}", s),
+      ValueNode::Array(ref a) => write!(f, "{}", a),
+      ValueNode::InlineTable(ref it) => write!(f, "{}", it),
+    }
+  }
+}
+
+impl Display for StringNode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.str_type {
+      StrType::Basic => write!(f, "\"{}\"", self.text),
+      StrType::MLBasic => write!(f, "\"\"\"{}\"\"\"", self.text),
+      StrType::Literal => write!(f, "'{}'", self.text),
+      StrType::MLLiteral => write!(f, "'''{}'''", self.text),
+    }
+  }
+}
+
+impl Display for Trivia {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Trivia::Whitespace(ref ws) => write!(f, "{}", ws),
+      Trivia::Comment(ref before, ref comment, ref after) => write!(f, "{}{}{}", before, comment, after),
+    }
+  }
+}
+
+impl Display for ArrayNode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "[")?;
+    for trivia in &self.leading {
+      write!(f, "{}", trivia)?;
+    }
+    for element in &self.elements {
+      write!(f, "{}", element.value)?;
+      if let Some(ref sep) = element.separator {
+        write!(f, "{},{}", sep.before, sep.after)?;
+      }
+      for trivia in &element.trivia {
+        write!(f, "{}", trivia)?;
+      }
+    }
+    for trivia in &self.trailing {
+      write!(f, "{}", trivia)?;
+    }
+    write!(f, "]")
+  }
+}
+
+impl Display for InlineTableNode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{{{}", self.leading_whitespace)?;
+    for entry in &self.entries {
+      write!(f, "{}", entry.key_value)?;
+      if let Some(ref sep) = entry.separator {
+        write!(f, "{},{}", sep.before, sep.after)?;
+      }
+      for trivia in &entry.trivia {
+        write!(f, "{}", trivia)?;
+      }
+    }
+    write!(f, "{}}}", self.trailing_whitespace)
+  }
+}
+
+// Builds the public CST from the parser's internal AST
+pub(crate) fn from_toml(toml: &Toml) -> Document {
+  Document{lines: toml.exprs.iter().map(|nlexpr| line(&nlexpr.nl, &nlexpr.expr)).collect()}
+}
+
+fn line(nl: &str, expr: &Expression) -> Line {
+  let item = match (expr.keyval.as_ref(), expr.table.as_ref()) {
+    (Some(kv), _) => Some(Item::KeyValue(key_value(kv))),
+    (None, Some(tt)) => Some(Item::Table(table_header(tt))),
+    (None, None) => None,
+  };
+  let trailing_whitespace = if item.is_some() { expr.ws.ws2.to_string() } else { String::new() };
+  Line{
+    newline: nl.to_string(),
+    indent: expr.ws.ws1.to_string(),
+    item,
+    trailing_whitespace,
+    comment: expr.comment.as_ref().map(|c| Comment{text: c.text.to_string()}),
+  }
+}
+
+fn separator(ws: &WSSep) -> Separator {
+  Separator{before: ws.ws1.to_string(), after: ws.ws2.to_string()}
+}
+
+fn key_value(kv: &KeyVal) -> KeyValue {
+  KeyValue{
+    key: Key{text: kv.key.to_string()},
+    separator: separator(&kv.keyval_sep),
+    value: value(&kv.val.borrow()),
+  }
+}
+
+fn table_header(tt: &TableType) -> TableHeader {
+  let (array, table) = match *tt {
+    TableType::Standard(ref t) => (false, t),
+    TableType::Array(ref t) => (true, t),
+  };
+  let (leading_whitespace, trailing_whitespace) = match table.keys.first() {
+    Some(first) => (first.ws.ws1.to_string(), first.ws.ws2.to_string()),
+    None => (String::new(), String::new()),
+  };
+  let keys = table.keys.iter().enumerate().map(|(i, k)| {
+    HeaderKey{
+      separator: if i == 0 { None } else { Some(separator(&k.ws)) },
+      key: Key{text: k.key.to_string()},
+    }
+  }).collect();
+  TableHeader{
+    array,
+    leading_whitespace,
+    keys,
+    trailing_whitespace,
+  }
+}
+
+fn value(val: &TOMLValue) -> ValueNode {
+  match *val {
+    TOMLValue::Integer(ref s) => ValueNode::Integer(s.to_string()),
+    TOMLValue::Float(ref s) => ValueNode::Float(s.to_string()),
+    TOMLValue::Boolean(b) => ValueNode::Boolean(b),
+    TOMLValue::DateTime(ref dt) => ValueNode::DateTime(format!("{}", dt)),
+    TOMLValue::String(ref s, st) => ValueNode::String(StringNode{text: s.to_string(), str_type: st}),
+    TOMLValue::Array(ref arr) => ValueNode::Array(array(&arr.borrow())),
+    TOMLValue::InlineTable(ref it) => ValueNode::InlineTable(inline_table(&it.borrow())),
+    TOMLValue::Table => panic!("Tables can't be the value of a key-value pair."),
+  }
+}
+
+fn trivia(comment_nls: &[CommentOrNewLines]) -> Vec<Trivia> {
+  comment_nls.iter().map(|cnl| {
+    match *cnl {
+      CommentOrNewLines::NewLines(ref nl) => Trivia::Whitespace(nl.to_string()),
+      CommentOrNewLines::Comment(ref c) => Trivia::Comment(c.pre_ws_nl.to_string(),
+        Comment{text: c.comment.text.to_string()}, c.newlines.to_string()),
+    }
+  }).collect()
+}
+
+fn array(arr: &Array) -> ArrayNode {
+  ArrayNode{
+    leading: trivia(&arr.comment_nls1),
+    elements: arr.values.iter().map(|av| {
+      ArrayElement{
+        value: value(&av.val.borrow()),
+        separator: av.array_sep.as_ref().map(separator),
+        trivia: trivia(&av.comment_nls),
+      }
+    }).collect(),
+    trailing: trivia(&arr.comment_nls2),
+  }
+}
+
+fn inline_table(it: &InlineTable) -> InlineTableNode {
+  InlineTableNode{
+    leading_whitespace: it.ws.ws1.to_string(),
+    entries: it.keyvals.iter().map(|tkv| {
+      InlineEntry{
+        key_value: key_value(&tkv.keyval),
+        separator: tkv.kv_sep.as_ref().map(separator),
+        trivia: trivia(&tkv.comment_nls),
+      }
+    }).collect(),
+    trailing_whitespace: it.ws.ws2.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use types::StrType;
+  use cst::{Item, ValueNode, Trivia, CST_VERSION};
+
+  #[test]
+  fn test_cst_round_trip() {
+    let input = r#"# Header comment
+animal = "bear"   # bear
+[ car . "interior" ]
+  seats = [ 1, # first
+    2 ,3 ]
+  colors = {  main = 'red' , trim = """black""" }
+[[car.owners]]
+Date = 2007-05-16T10:12:13.2324+04:00
+
+"#;
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(input);
+    let doc = parser.cst();
+    assert_eq!(1, CST_VERSION);
+    assert_eq!(input, format!("{}", doc));
+    assert_eq!(9, doc.len());
+  }
+
+  #[test]
+  fn test_cst_nodes() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse("[ a . \"b c\" ]\nk = [ 1, # one\n2 ]\n[[t]]\nit = { x = 'y' }");
+    let doc = parser.cst();
+    let lines: Vec<_> = doc.lines().collect();
+    match lines[0].item() {
+      Some(&Item::Table(ref t)) => {
+        assert!(!t.is_array());
+        assert_eq!(" ", t.leading_whitespace());
+        assert_eq!(" ", t.trailing_whitespace());
+        let keys: Vec<_> = t.keys().collect();
+        assert_eq!("a", keys[0].key().text());
+        assert!(keys[0].separator().is_none());
+        assert_eq!("\"b c\"", keys[1].key().text());
+        assert!(keys[1].key().is_quoted());
+        assert_eq!(" ", keys[1].separator().unwrap().before());
+      },
+      _ => panic!("Expected a table header"),
+    }
+    assert_eq!("\n", lines[1].newline());
+    match lines[1].item() {
+      Some(&Item::KeyValue(ref kv)) => {
+        assert_eq!(" ", kv.separator().before());
+        match *kv.value() {
+          ValueNode::Array(ref arr) => {
+            let elements: Vec<_> = arr.elements().collect();
+            assert_eq!(2, elements.len());
+            assert_eq!(ValueNode::Integer("1".to_string()), *elements[0].value());
+            match elements[0].trivia().next() {
+              Some(&Trivia::Comment(_, ref c, _)) => assert_eq!(" one", c.text()),
+              _ => panic!("Expected a comment"),
+            }
+          },
+          _ => panic!("Expected an array"),
+        }
+      },
+      _ => panic!("Expected a key-value pair"),
+    }
+    match lines[2].item() {
+      Some(&Item::Table(ref t)) => assert!(t.is_array()),
+      _ => panic!("Expected an array of tables header"),
+    }
+    match lines[3].item() {
+      Some(&Item::KeyValue(ref kv)) => match *kv.value() {
+        ValueNode::InlineTable(ref it) => {
+          let entry = it.entries().next().unwrap();
+          assert_eq!("x", entry.key_value().key().text());
+          match *entry.key_value().value() {
+            ValueNode::String(ref s) => assert_eq!(StrType::Literal, s.str_type()),
+            _ => panic!("Expected a string"),
+          }
+        },
+        _ => panic!("Expected an inline table"),
+      },
+      _ => panic!("Expected a key-value pair"),
+    }
+  }
+}
@@ -0,0 +1,355 @@
+//! Conversion between arrays of tables and CSV files.
+//!
+//! `export` writes an array of tables like `[[products]]` as a CSV file with a row for each table. The columns are
+//! the union of the keys of all of the tables, in the order they're first seen, and a table's cell is empty if it
+//! doesn't have that key. Keys in subtables and inline tables become dotted column names like `size.width`, and
+//! arrays are written as their TOML text, like `[1, 2]`. Arrays of tables inside of the tables are reported as
+//! errors.
+//!
+//! `import` goes the other way, turning the rows of a CSV file with a header row into tables of an array of tables.
+//! `Mode::Append` adds a table to the end of the array of tables for each row. `Mode::Replace` makes the array of
+//! tables hold exactly the rows of the file, updating the tables that are already there in place so the comments and
+//! formatting of everything a row doesn't change are kept. Only the keys of the file's columns are touched, and an
+//! empty cell removes its key. Cells are read as the type given for their column, or as the type of the value they
+//! replace, or else as the type they look like. The document is left unchanged if any cell can't be imported.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::csv::{self, Mode};
+//! use tomllib::schema::Type;
+//!
+//! let (mut parser, _) = TOMLParser::new().parse("[[products]]\nname = \"Hammer\" # Best seller\nsku = 738594937\n\n\
+//!   [[products]]\nname = \"Nail\"\nsku = 284758393\ncolor = \"gray\"\n");
+//! assert_eq!("name,sku,color\nHammer,738594937,\nNail,284758393,gray\n", csv::export(&parser, "products").unwrap());
+//!
+//! let rows = "name,sku,color\nHammer,738594938,\nNail,284758393,gray\nScrew,\"00123\",\n";
+//! csv::import(&mut parser, "products", rows, &[("sku", Type::String)], Mode::Replace).unwrap();
+//! assert_eq!("[[products]]\nname = \"Hammer\" # Best seller\nsku = \"738594938\"\n\n[[products]]\nname = \"Nail\"\n\
+//!   sku = \"284758393\"\ncolor = \"gray\"\n\n[[products]]\nname = \"Screw\"\nsku = \"00123\"\n",
+//!   format!("{}", parser));
+//! ```
+
+use std::borrow::Cow;
+use csv_crate::{Reader, Writer, RecordTerminator};
+use TOMLParser;
+use diff;
+use patch;
+use node::{self, Node, entries};
+use key::{self, KeyPath, KeySegment, key_name, quote_key};
+use ini::{infer, scalar_text};
+use json::{owned_value, unrepresentable};
+use schema::Type;
+use internals::parser::Parser;
+use internals::edit::TableKind;
+use internals::walker::child_key;
+use types::{Value, Children, ParseResult, ConvertError};
+
+/// How `import` adds the rows of a CSV file to an array of tables.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Mode {
+  /// Adds a table to the end of the array of tables for each row.
+  Append,
+  /// Makes the array of tables hold exactly the rows, updating the tables that are already there in place.
+  Replace,
+}
+
+// Adds the column names and cell texts of the values in `node`, a table in the array of tables or one nested in it
+fn row_cells(key: &str, prefix: &str, node: &Node, cells: &mut Vec<(String, String)>) -> Result<(), ConvertError> {
+  for (name, child) in entries(node).unwrap_or_default() {
+    let column = match prefix {
+      "" => quote_key(&key_name(&name)),
+      _ => format!("{}.{}", prefix, quote_key(&key_name(&name))),
+    };
+    let key = child_key(key, &name);
+    match child {
+      Node::Table(_) | Node::Value(Value::InlineTable(_)) => row_cells(&key, &column, &child, cells)?,
+      Node::ArrayOfTables(_) => return Err(unrepresentable(&key, "CSV cells can't hold arrays of tables")),
+      Node::Value(Value::Array(ref values)) if values.is_empty() => cells.push((column, "[]".to_string())),
+      Node::Value(ref val @ Value::Array(_)) => cells.push((column, val.to_string())),
+      Node::Value(ref val) => cells.push((column, scalar_text(&key, val, "")?)),
+    }
+  }
+  Ok(())
+}
+
+// Returns the number of tables in the array of tables `key`, which is 0 if it doesn't exist
+fn table_count(parser: &Parser, key: &str) -> Result<usize, ConvertError> {
+  match parser.table_kind(key) {
+    TableKind::Missing => Ok(0),
+    TableKind::ArrayOfTables => match parser.get_children(key) {
+      Some(Children::Count(count)) => Ok(count.get()),
+      _ => Ok(0),
+    },
+    _ => Err(unrepresentable(key, "it isn't an array of tables")),
+  }
+}
+
+/// Writes the array of tables at `key` as a CSV file with a header row and a row for each table. Returns
+/// `ConvertError::Unrepresentable` if `key` isn't an array of tables or one of its tables holds an array of tables.
+///
+/// # Examples
+///
+/// ```
+/// use tomllib::TOMLParser;
+/// use tomllib::csv;
+///
+/// let (parser, _) = TOMLParser::new().parse("[[points]]\nxy = [1, 2]\nlabel = { text = \"a, b\" }\n");
+/// assert_eq!("xy,label.text\n\"[1, 2]\",\"a, b\"\n", csv::export(&parser, "points").unwrap());
+/// ```
+pub fn export<K>(doc: &TOMLParser, key: K) -> Result<String, ConvertError> where K: Into<KeyPath> {
+  let key = doc.resolve(key);
+  if !matches!(doc.parser.table_kind(&key), TableKind::ArrayOfTables) {
+    return Err(unrepresentable(&key, "it isn't an array of tables"));
+  }
+  let tables = match node::build(&doc.parser, &key) {
+    Node::ArrayOfTables(tables) => tables,
+    _ => vec![],
+  };
+  let mut columns: Vec<String> = vec![];
+  let mut rows = vec![];
+  for (i, table) in tables.iter().enumerate() {
+    let mut cells = vec![];
+    row_cells(&format!("{}[{}]", key, i), "", table, &mut cells)?;
+    for (column, _) in &cells {
+      if !columns.contains(column) {
+        columns.push(column.clone());
+      }
+    }
+    rows.push(cells);
+  }
+  let mut writer = Writer::from_memory().record_terminator(RecordTerminator::Any(b'\n'));
+  let mut write = |record: Vec<&str>| writer.write(record.into_iter()).map_err(|err| unrepresentable(&key,
+    &err.to_string()));
+  write(columns.iter().map(|column| &column[..]).collect())?;
+  for cells in &rows {
+    write(columns.iter().map(|column| {
+      cells.iter().find(|cell| cell.0 == *column).map_or("", |cell| &cell.1[..])
+    }).collect())?;
+  }
+  Ok(writer.into_string())
+}
+
+// Reads the TOML array `text` is written as, if it is one
+fn array_value(text: &str) -> Option<Value<'static>> {
+  let line = format!("array = {}", text);
+  let (parser, result) = TOMLParser::new().parse(&line);
+  match (result, parser.get_value("array")) {
+    (ParseResult::Full, Some(ref val @ Value::Array(_))) => Some(owned_value(val)),
+    _ => None,
+  }
+}
+
+// Reads a cell as `kind`, or as the type it looks like if `kind` is `Type::Any`
+fn cell_value(key: &str, text: &str, kind: Type) -> Result<Value<'static>, ConvertError> {
+  let val = match kind {
+    Type::String => infer(text, false),
+    Type::Array | Type::Any if text.starts_with('[') => match array_value(text) {
+      Some(val) => val,
+      None => infer(text, kind == Type::Any),
+    },
+    _ => infer(text, true),
+  };
+  match (kind, Type::of(&Node::Value(val.clone()))) {
+    (Type::Any, _) => Ok(val),
+    (Type::Float, Type::Integer) => Ok(Value::Float(Cow::Owned(format!("{}.0", text)))),
+    (expected, found) if expected == found => Ok(val),
+    (expected, _) => Err(unrepresentable(key, &format!("\"{}\" isn't {}", text, expected))),
+  }
+}
+
+// Reads the header row into the names of the keys of each column
+fn read_columns(headers: &[String]) -> Result<Vec<Vec<String>>, ConvertError> {
+  let mut columns = vec![];
+  for (i, header) in headers.iter().enumerate() {
+    let invalid = || ConvertError::Parse(format!("column {} \"{}\" isn't a valid key", i + 1, header));
+    let mut names = vec![];
+    for segment in KeyPath::parse(header).map_err(|_| invalid())?.segments() {
+      match *segment {
+        KeySegment::Key(ref name) => names.push(name.clone()),
+        _ => return Err(invalid()),
+      }
+    }
+    if names.is_empty() {
+      return Err(invalid());
+    } else if columns.contains(&names) {
+      return Err(ConvertError::Parse(format!("column {} \"{}\" is given more than once", i + 1, header)));
+    }
+    columns.push(names);
+  }
+  Ok(columns)
+}
+
+// Writes the cells of a row into the table `table`, which is a new table if `is_new` is true
+fn write_row<'a>(parser: &mut Parser<'a>, table: &KeyPath, columns: &[Vec<String>], kinds: &[Option<Type>],
+  cells: &[String], is_new: bool) -> Result<(), ConvertError> {
+  for ((names, kind), text) in columns.iter().zip(kinds).zip(cells) {
+    let path = names.iter().fold(table.clone(), |path, name| path.key(&name[..]));
+    let key = key::resolve(parser, &path);
+    let old = if is_new { None } else { parser.get_value(key.clone()) };
+    let edit = if text.is_empty() {
+      match parser.table_kind(&key) {
+        TableKind::Missing => continue,
+        _ => parser.remove_key(&key),
+      }
+    } else {
+      let kind = match (kind, &old) {
+        (&Some(kind), _) => kind,
+        (&None, Some(Value::InlineTable(_))) | (&None, None) => Type::Any,
+        (&None, Some(old)) => Type::of(&Node::Value(old.clone())),
+      };
+      let val = cell_value(&key, text, kind)?;
+      if old.is_some_and(|old| diff::same(&old, &val)) {
+        continue;
+      }
+      patch::add(parser, &key, &Node::Value(val))
+    };
+    edit.map_err(|err| unrepresentable(&key, err.to_string().trim_end_matches('.')))?;
+  }
+  Ok(())
+}
+
+// Adds the rows to the array of tables `path`, following the rules of `mode`
+fn write_rows<'a>(parser: &mut Parser<'a>, path: &KeyPath, columns: &[Vec<String>], kinds: &[Option<Type>],
+  rows: &[Vec<String>], mode: Mode) -> Result<(), ConvertError> {
+  let key = key::resolve(parser, path);
+  let count = table_count(parser, &key)?;
+  let kept = match mode {
+    Mode::Append => 0,
+    Mode::Replace => {
+      for i in (rows.len()..count).rev() {
+        let table = format!("{}[{}]", key, i);
+        parser.remove_key(&table).map_err(|err| unrepresentable(&table, err.to_string().trim_end_matches('.')))?;
+      }
+      count.min(rows.len())
+    },
+  };
+  for (i, cells) in rows.iter().enumerate() {
+    if i < kept {
+      write_row(parser, &path.clone().index(i), columns, kinds, cells, false)?;
+    } else {
+      parser.append_array_table(&key).map_err(|err| unrepresentable(&key, err.to_string().trim_end_matches('.')))?;
+      let index = table_count(parser, &key)? - 1;
+      write_row(parser, &path.clone().index(index), columns, kinds, cells, true)?;
+    }
+  }
+  Ok(())
+}
+
+/// Adds the rows of a CSV file with a header row to the array of tables at `key`, which is created if it doesn't
+/// exist. The header row holds the key of each column, relative to a table, and `types` gives the type to read
+/// the cells of some columns as. Returns `ConvertError::Parse` if the file or its header row isn't valid or `types`
+/// names a column that isn't there or a table type, and `ConvertError::Unrepresentable` if a cell isn't of its
+/// column's type or can't be added to its table. The document is left unchanged if there's an error.
+///
+/// # Examples
+///
+/// ```
+/// use tomllib::TOMLParser;
+/// use tomllib::csv::{self, Mode};
+/// use tomllib::schema::Type;
+/// use tomllib::types::ConvertError;
+///
+/// let (mut parser, _) = TOMLParser::new().parse("title = \"Stock\"\n");
+/// csv::import(&mut parser, "items", "name,size.width\nbox,2\n", &[], Mode::Append).unwrap();
+/// assert_eq!("title = \"Stock\"\n\n[[items]]\nname = \"box\"\n\n[items.size]\nwidth = 2\n", format!("{}", parser));
+///
+/// assert_eq!(Err(ConvertError::Unrepresentable("items[1].size.width".to_string(),
+///   "\"wide\" isn't a float".to_string())),
+///   csv::import(&mut parser, "items", "name,size.width\nbag,wide\n", &[("size.width", Type::Float)], Mode::Append));
+/// ```
+pub fn import<'a, K>(doc: &mut TOMLParser<'a>, key: K, text: &str, types: &[(&str, Type)], mode: Mode)
+  -> Result<(), ConvertError> where K: Into<KeyPath> {
+  let mut reader = Reader::from_string(text).has_headers(true);
+  let parse_error = |err: ::csv_crate::Error| ConvertError::Parse(err.to_string());
+  let headers = reader.headers().map_err(parse_error)?;
+  let columns = read_columns(&headers)?;
+  let mut kinds = vec![None; columns.len()];
+  for &(column, kind) in types {
+    let index = match headers.iter().position(|header| header == column) {
+      Some(index) => index,
+      None => return Err(ConvertError::Parse(format!("there's no column \"{}\"", column))),
+    };
+    if let Type::Table | Type::ArrayOfTables = kind {
+      return Err(ConvertError::Parse(format!("column \"{}\" can't hold {}", column, kind)));
+    }
+    kinds[index] = Some(kind);
+  }
+  let rows = reader.records().collect::<Result<Vec<Vec<String>>, _>>().map_err(parse_error)?;
+  let path = key.into();
+  doc.nodes.take();
+  let snapshot = doc.parser.snapshot();
+  let written = write_rows(&mut doc.parser, &path, &columns, &kinds, &rows, mode);
+  if written.is_err() {
+    doc.parser.restore(snapshot);
+  }
+  written
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use csv::{export, import, Mode};
+  use schema::Type;
+  use types::ConvertError;
+
+  #[test]
+  fn test_export() {
+    let (parser, _) = TOMLParser::new().parse("[[products]]\nname = \"Hammer\"\nprice = 1_000.50\n\
+      dims = { w = 2, h = 3 }\nsince = 2016-01-01T00:00:00Z\n\n[[products]]\n\"sku id\" = 1\nname = \"a \\\"b\\\"\"\n\
+      tags = []\n\n[products.stock]\nshelf = \"A1\"\n");
+    assert_eq!("name,price,dims.w,dims.h,since,\"\"\"sku id\"\"\",tags,stock.shelf\n\
+      Hammer,1000.50,2,3,2016-01-01T00:00:00Z,,,\n\
+      \"a \"\"b\"\"\",,,,,1,[],A1\n", export(&parser, "products").unwrap());
+    let error = |doc: &str, key: &str| export(&TOMLParser::new().parse(doc).0, key).unwrap_err();
+    assert_eq!(ConvertError::Unrepresentable("a".to_string(), "it isn't an array of tables".to_string()),
+      error("[a]\nb = 1\n", "a"));
+    assert_eq!(ConvertError::Unrepresentable("x".to_string(), "it isn't an array of tables".to_string()),
+      error("[a]\nb = 1\n", "x"));
+    assert_eq!(ConvertError::Unrepresentable("a[0].b".to_string(), "CSV cells can't hold arrays of tables"
+      .to_string()), error("[[a]]\n[[a.b]]\nc = 1\n", "a"));
+  }
+
+  #[test]
+  fn test_import() {
+    let doc = "# Inventory\n[[products]]\n# The hammer\nname = \"Hammer\" # Best seller\nsku = 738594937\n\
+      version = \"1.0\"\n\n[[products]]\nname = \"Nail\"\nsku = 284758393\ncolor = \"gray\"\n\n[[products]]\n\
+      name = \"Screw\"\n";
+    let (mut parser, _) = TOMLParser::new().parse(doc);
+    let rows = "name,sku,version,color,tags\nHammer,738594938,1.1,,\"[\"\"a\"\", \"\"b\"\"]\"\n\
+      Nail,284758393,,grey,\n";
+    import(&mut parser, "products", rows, &[], Mode::Replace).unwrap();
+    assert_eq!("# Inventory\n[[products]]\n# The hammer\nname = \"Hammer\" # Best seller\nsku = 738594938\n\
+      version = \"1.1\"\ntags = [\"a\", \"b\"]\n\n[[products]]\nname = \"Nail\"\nsku = 284758393\ncolor = \"grey\"\n",
+      format!("{}", parser));
+
+    let rows = "name,sku,weight\nBolt,1,2\n";
+    import(&mut parser, "products", rows, &[("weight", Type::Float)], Mode::Append).unwrap();
+    assert_eq!("# Inventory\n[[products]]\n# The hammer\nname = \"Hammer\" # Best seller\nsku = 738594938\n\
+      version = \"1.1\"\ntags = [\"a\", \"b\"]\n\n[[products]]\nname = \"Nail\"\nsku = 284758393\ncolor = \"grey\"\n\n\
+      [[products]]\nname = \"Bolt\"\nsku = 1\nweight = 2.0\n", format!("{}", parser));
+  }
+
+  #[test]
+  fn test_import_errors() {
+    let doc = "[[a]]\nb = 1\n";
+    let (mut parser, _) = TOMLParser::new().parse(doc);
+    let mut error = |rows: &str, types: &[(&str, Type)]| import(&mut parser, "a", rows, types, Mode::Append)
+      .unwrap_err();
+    assert_eq!(ConvertError::Parse("column 2 \"c[0]\" isn't a valid key".to_string()), error("b,c[0]\n1,2\n", &[]));
+    assert_eq!(ConvertError::Parse("column 2 \"b\" is given more than once".to_string()), error("b,b\n1,2\n", &[]));
+    assert_eq!(ConvertError::Parse("there's no column \"c\"".to_string()), error("b\n1\n", &[("c", Type::Integer)]));
+    assert_eq!(ConvertError::Parse("column \"b\" can't hold a table".to_string()),
+      error("b\n1\n", &[("b", Type::Table)]));
+    assert_eq!(ConvertError::Unrepresentable("a[2].b".to_string(), "\"x\" isn't an integer".to_string()),
+      error("b\n2\nx\n", &[("b", Type::Integer)]));
+    assert_eq!(ConvertError::Unrepresentable("a[1].b.c".to_string(),
+      "Key \"a[1].b.c\" can't be created, \"a[1].b\" is not a table".to_string()), error("b,b.c\n1,2\n", &[]));
+    assert!(matches!(error("b\n1,2\n", &[]), ConvertError::Parse(_)));
+    assert_eq!(doc, format!("{}", parser));
+    let (mut parser, _) = TOMLParser::new().parse("a = 1\n");
+    assert_eq!(Err(ConvertError::Unrepresentable("a".to_string(), "it isn't an array of tables".to_string())),
+      import(&mut parser, "a", "b\n1\n", &[], Mode::Replace));
+  }
+}
@@ -0,0 +1,346 @@
+//! Semantic differences between two parsed TOML documents.
+//!
+//! Documents are compared by what they mean rather than how they're written, so whitespace, comments, the order of
+//! keys in a table, whether a table is written as a section or an inline table, underscores in numbers and the
+//! quoting style of strings are all ignored. Arrays and arrays of tables are compared element by element after lining
+//! up the elements both have in common, so inserting an element reports a single addition. A table or array that was
+//! removed from one key and added at another is reported as a move. Other values are only reported as moved if both
+//! keys have the same name, like a `port` moving to another table, so unrelated keys that happen to have the same
+//! value are reported as a removal and an addition.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::diff::Change;
+//! use tomllib::key::KeyPath;
+//! use tomllib::types::Value;
+//!
+//! let (old, _) = TOMLParser::new().parse("[server]\nport = 8_080\nname = \"web\"\nhosts = [\"a\", \"b\"]\n");
+//! let (new, _) = TOMLParser::new().parse("server = { port = 8080, name = 'web', hosts = [\"a\", \"c\", \"b\"] }\n");
+//! assert_eq!(vec![Change::Added(KeyPath::parse("server.hosts[1]").unwrap(), Value::basic_string("c").unwrap())],
+//!   old.diff(&new));
+//! ```
+
+use std::fmt;
+use std::fmt::Display;
+use std::borrow::Cow;
+use key::{KeyPath, KeySegment, key_name, normalize_key};
+use types::Value;
+use internals::json;
+
+/// One difference between two documents. Keys are full keys, and values that are tables are given as inline tables.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Change<'a> {
+  /// The key only exists in the new document.
+  Added(KeyPath, Value<'a>),
+  /// The key only exists in the old document.
+  Removed(KeyPath, Value<'a>),
+  /// The key exists in both documents with different values. Contains the old value followed by the new value.
+  Changed(KeyPath, Value<'a>, Value<'a>),
+  /// The value at the first key in the old document is at the second key in the new document.
+  Moved(KeyPath, KeyPath, Value<'a>),
+}
+
+impl<'a> Display for Change<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Change::Added(ref key, ref val) => write!(f, "+ {} = {}", key, val),
+      Change::Removed(ref key, ref val) => write!(f, "- {} = {}", key, val),
+      Change::Changed(ref key, ref old, ref new) => write!(f, "~ {} = {} -> {}", key, old, new),
+      Change::Moved(ref from, ref to, ref val) => write!(f, "> {} -> {} = {}", from, to, val),
+    }
+  }
+}
+
+// Removes the underscores and plus sign of an integer or float, which don't change its value
+pub(crate) fn clean_number(s: &str) -> String {
+  s.replace('_', "").trim_start_matches('+').to_string()
+}
+
+// Normalizes the ways of writing a UTC offset
+fn clean_datetime(s: &str) -> String {
+  s.replace(['Z', 'z'], "+00:00").replace('t', "T")
+}
+
+/// Returns true if `a` and `b` have the same meaning, ignoring underscores in numbers, string quoting and escapes,
+/// and the order and quoting of keys in inline tables.
+pub fn same(a: &Value, b: &Value) -> bool {
+  match (a, b) {
+    (Value::Integer(a), Value::Integer(b)) => {
+      let (a, b) = (clean_number(a), clean_number(b));
+      match (a.parse::<i64>(), b.parse::<i64>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+      }
+    },
+    (Value::Float(a), Value::Float(b)) => {
+      let (a, b) = (clean_number(a), clean_number(b));
+      match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+      }
+    },
+    (Value::Boolean(a), Value::Boolean(b)) => a == b,
+    (Value::DateTime(a), Value::DateTime(b)) => clean_datetime(&a.to_string()) == clean_datetime(&b.to_string()),
+    (Value::String(_, _), Value::String(_, _)) => a.string_content() == b.string_content(),
+    (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same(a, b)),
+    (Value::InlineTable(a), Value::InlineTable(b)) => a.len() == b.len() && a.iter().all(|pair| {
+      let name = normalize_key(&pair.0);
+      b.iter().any(|other| normalize_key(&other.0) == name && same(&pair.1, &other.1))
+    }),
+    _ => false,
+  }
+}
+
+// Pairs of indices of equal elements in the longest common subsequence of `a` and `b`
+fn common(a: &[Value], b: &[Value]) -> Vec<(usize, usize)> {
+  let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+  for i in (0..a.len()).rev() {
+    for j in (0..b.len()).rev() {
+      lengths[i][j] = if same(&a[i], &b[j]) {
+        lengths[i + 1][j + 1] + 1
+      } else {
+        lengths[i + 1][j].max(lengths[i][j + 1])
+      };
+    }
+  }
+  let (mut i, mut j, mut pairs) = (0, 0, vec![]);
+  while i < a.len() && j < b.len() {
+    if same(&a[i], &b[j]) {
+      pairs.push((i, j));
+      i += 1;
+      j += 1;
+    } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+      i += 1;
+    } else {
+      j += 1;
+    }
+  }
+  pairs
+}
+
+fn diff_arrays<'a>(path: &KeyPath, old: &[Value<'a>], new: &[Value<'a>], changes: &mut Vec<Change<'a>>) {
+  let (mut i, mut j) = (0, 0);
+  let mut pairs = common(old, new);
+  pairs.push((old.len(), new.len()));
+  for (next_i, next_j) in pairs {
+    // Elements between two common ones are compared in order, and whatever is left over was removed or added
+    while i < next_i && j < next_j {
+      diff_values(&path.clone().index(j), &old[i], &new[j], changes);
+      i += 1;
+      j += 1;
+    }
+    for (k, val) in old.iter().enumerate().take(next_i).skip(i) {
+      changes.push(Change::Removed(path.clone().index(k), val.clone()));
+    }
+    for (k, val) in new.iter().enumerate().take(next_j).skip(j) {
+      changes.push(Change::Added(path.clone().index(k), val.clone()));
+    }
+    i = next_i + 1;
+    j = next_j + 1;
+  }
+}
+
+fn diff_tables<'a>(path: &KeyPath, old: &[(Cow<'a, str>, Value<'a>)],
+  new: &[(Cow<'a, str>, Value<'a>)], changes: &mut Vec<Change<'a>>) {
+  for (name, val) in old {
+    let key = normalize_key(name);
+    match new.iter().find(|pair| normalize_key(&pair.0) == key) {
+      Some(pair) => diff_values(&path.clone().key(key_name(name)), val, &pair.1, changes),
+      None => changes.push(Change::Removed(path.clone().key(key_name(name)), val.clone())),
+    }
+  }
+  for (name, val) in new {
+    let key = normalize_key(name);
+    if !old.iter().any(|pair| normalize_key(&pair.0) == key) {
+      changes.push(Change::Added(path.clone().key(key_name(name)), val.clone()));
+    }
+  }
+}
+
+fn diff_values<'a>(path: &KeyPath, old: &Value<'a>, new: &Value<'a>, changes: &mut Vec<Change<'a>>) {
+  match (old, new) {
+    (Value::InlineTable(old), Value::InlineTable(new)) => diff_tables(path, old, new, changes),
+    (Value::Array(old), Value::Array(new)) => diff_arrays(path, old, new, changes),
+    _ => if !same(old, new) {
+      changes.push(Change::Changed(path.clone(), old.clone(), new.clone()));
+    },
+  }
+}
+
+// The name of the last key in `path`, ignoring indices
+fn leaf_name(path: &KeyPath) -> Option<&String> {
+  path.segments().iter().rev().find_map(|segment| match *segment {
+    KeySegment::Key(ref name) => Some(name),
+    _ => None,
+  })
+}
+
+// Tables and arrays are unlikely to be equal by chance, other values are only moved if they keep their key's name
+fn is_move(from: &KeyPath, to: &KeyPath, val: &Value) -> bool {
+  matches!(*val, Value::InlineTable(_) | Value::Array(_)) || leaf_name(from) == leaf_name(to)
+}
+
+/// Returns the differences between the documents `old` and `new`, given as inline tables, in the order of `old` with
+/// additions after the keys they follow.
+pub fn diff<'a>(old: &Value<'a>, new: &Value<'a>) -> Vec<Change<'a>> {
+  let mut changes = vec![];
+  diff_values(&KeyPath::root(), old, new, &mut changes);
+  // A removal and an addition of the same value is a move
+  let mut i = 0;
+  while i < changes.len() {
+    let removed = match changes[i] {
+      Change::Removed(ref from, ref val) => changes.iter().position(|change| match *change {
+        Change::Added(ref to, ref other) => same(val, other) && is_move(from, to, val),
+        _ => false,
+      }).map(|added| (added, from.clone(), val.clone())),
+      _ => None,
+    };
+    if let Some((added, from, val)) = removed {
+      if let Change::Added(to, _) = changes.remove(added) {
+        if added < i {
+          i -= 1;
+        }
+        changes[i] = Change::Moved(from, to, val);
+      }
+    }
+    i += 1;
+  }
+  changes
+}
+
+fn json_value(val: &Value) -> String {
+  match *val {
+    Value::Integer(ref s) => clean_number(s),
+    Value::Float(ref s) => {
+      let number = clean_number(s);
+      match number.parse::<f64>() {
+        Ok(f) if f.is_finite() => number,
+        _ => json::quote(s),
+      }
+    },
+    Value::Boolean(b) => b.to_string(),
+    Value::DateTime(ref dt) => json::quote(&dt.to_string()),
+    Value::String(_, _) => json::quote(&val.string_content().unwrap_or_default()),
+    Value::Array(ref values) => format!("[{}]", values.iter().map(json_value).collect::<Vec<String>>().join(", ")),
+    Value::InlineTable(ref pairs) => format!("{{{}}}", pairs.iter().map(|pair| {
+      format!("{}: {}", json::quote(&key_name(&pair.0)), json_value(&pair.1))
+    }).collect::<Vec<String>>().join(", ")),
+  }
+}
+
+/// Writes `changes` as a JSON array of objects. Every object has an `"op"` of `"added"`, `"removed"`, `"changed"` or
+/// `"moved"`. Additions and removals have a `"key"` and a `"value"`, changes have a `"key"`, an `"old"` and a `"new"`
+/// value, and moves have a `"from"` key, a `"to"` key and a `"value"`. Keys are written in key path syntax.
+pub fn to_json(changes: &[Change]) -> String {
+  let objects: Vec<String> = changes.iter().map(|change| match *change {
+    Change::Added(ref key, ref val) => format!("{{\"op\": \"added\", \"key\": {}, \"value\": {}}}",
+      json::quote(&key.to_string()), json_value(val)),
+    Change::Removed(ref key, ref val) => format!("{{\"op\": \"removed\", \"key\": {}, \"value\": {}}}",
+      json::quote(&key.to_string()), json_value(val)),
+    Change::Changed(ref key, ref old, ref new) =>
+      format!("{{\"op\": \"changed\", \"key\": {}, \"old\": {}, \"new\": {}}}", json::quote(&key.to_string()),
+        json_value(old), json_value(new)),
+    Change::Moved(ref from, ref to, ref val) =>
+      format!("{{\"op\": \"moved\", \"from\": {}, \"to\": {}, \"value\": {}}}", json::quote(&from.to_string()),
+        json::quote(&to.to_string()), json_value(val)),
+  }).collect();
+  if objects.is_empty() {
+    "[]".to_string()
+  } else {
+    format!("[\n  {}\n]", objects.join(",\n  "))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::rc::Rc;
+  use TOMLParser;
+  use diff::{Change, same, to_json};
+  use key::KeyPath;
+  use types::Value;
+
+  fn array(values: Vec<Value>) -> Value {
+    Value::Array(Rc::new(values))
+  }
+
+  fn key(key: &str) -> KeyPath {
+    KeyPath::parse(key).unwrap()
+  }
+
+  fn changes(old: &'static str, new: &'static str) -> Vec<Change<'static>> {
+    let (old, _) = TOMLParser::new().parse(old);
+    let (new, _) = TOMLParser::new().parse(new);
+    old.diff(&new)
+  }
+
+  #[test]
+  fn test_same() {
+    assert!(same(&Value::Integer("1_000".into()), &Value::int(1000)));
+    assert!(same(&Value::Float("1_0.5".into()), &Value::float(10.5)));
+    assert!(same(&Value::basic_string("a\\u0020\\\"b\\\"").unwrap(), &Value::literal_string("a \"b\"").unwrap()));
+    assert!(same(&Value::basic_string("\\u0041").unwrap(), &Value::ml_literal_string("\nA").unwrap()));
+    assert!(same(&Value::ml_basic_string("a \\\n   b").unwrap(), &Value::basic_string("a b").unwrap()));
+    assert!(!same(&Value::int(1), &Value::float(1.0)));
+    assert!(!same(&array(vec![Value::int(1), Value::int(2)]), &array(vec![Value::int(2), Value::int(1)])));
+  }
+
+  #[test]
+  fn test_diff() {
+    assert_eq!(Vec::<Change>::new(), changes("# A comment\na = 1_000\nb = \"x\"\n[c]\nd = [1, 2]\n",
+      "c = { d = [ 1,\n  2 ] }\nb = 'x'\na = 1000\n"));
+    assert_eq!(vec![
+      Change::Changed(key("a"), Value::int(1), Value::int(2)),
+      Change::Removed(key("b"), Value::int(3)),
+      Change::Removed(key("t.list[0]"), Value::int(1)),
+      Change::Changed(key("t.list[2]"), Value::int(4), Value::int(5)),
+      Change::Added(key("t.new"), Value::bool(true)),
+    ], changes("a = 1\nb = 3\n[t]\nlist = [1, 2, 3, 4]\n", "a = 2\n[t]\nlist = [2, 3, 5]\nnew = true\n"));
+  }
+
+  #[test]
+  fn test_diff_tables() {
+    assert_eq!(vec![
+      Change::Changed(key("bin[1].path"), Value::basic_string("b.rs").unwrap(), Value::basic_string("c.rs").unwrap()),
+      Change::Added(key("bin[2]"), Value::InlineTable(Rc::new(vec![
+        ("name".into(), Value::basic_string("d").unwrap())]))),
+    ], changes("[[bin]]\nname = \"a\"\n[[bin]]\nname = \"b\"\npath = \"b.rs\"\n",
+      "[[bin]]\nname = \"a\"\n[[bin]]\nname = \"b\"\npath = \"c.rs\"\n[[bin]]\nname = \"d\"\n"));
+    assert_eq!(vec![Change::Changed(key("a"), Value::int(1), Value::InlineTable(Rc::new(vec![
+        ("b".into(), Value::int(1))])))], changes("a = 1\n", "[a]\nb = 1\n"));
+  }
+
+  #[test]
+  fn test_moves() {
+    assert_eq!(vec![Change::Moved(key("old"), key("new"), Value::InlineTable(Rc::new(vec![
+        ("name".into(), Value::basic_string("x").unwrap())])))],
+      changes("[old]\nname = \"x\"\n", "[new]\nname = \"x\"\n"));
+    assert_eq!(vec![Change::Moved(key("a.port"), key("b.port"), Value::int(80))],
+      changes("a = { port = 80 }\nb = {}\n", "a = {}\nb = { port = 80 }\n"));
+    assert_eq!(vec![Change::Moved(key("a[0]"), key("a[2]"), Value::int(1))],
+      changes("a = [1, 2, 3]\n", "a = [2, 3, 1]\n"));
+    // Unrelated keys with equal values aren't moves
+    assert_eq!(vec![Change::Removed(key("debug"), Value::bool(true)), Change::Added(key("verbose"), Value::bool(true))],
+      changes("debug = true\n", "verbose = true\n"));
+    assert_eq!(vec![Change::Removed(key("a.b"), Value::int(1)), Change::Added(key("a.c"), Value::int(1))],
+      changes("a = { b = 1 }\n", "a = { c = 1 }\n"));
+  }
+
+  #[test]
+  fn test_empty_tables_and_arrays() {
+    let changes = changes("a = 1\n", "a = 1\nb = {}\nc = []\n");
+    assert_eq!(vec![Change::Added(key("b"), Value::InlineTable(Rc::new(vec![]))),
+      Change::Added(key("c"), array(vec![]))], changes);
+    assert_eq!(vec!["+ b = {}", "+ c = []"], changes.iter().map(|change| change.to_string()).collect::<Vec<String>>());
+  }
+
+  #[test]
+  fn test_to_json() {
+    let changes = changes("a = 1\nb = \"x\\ty\"\nc = 1979-05-27\n", "a = 2\nb = \"x\\ty\"\nd = [{ e = 1.5 }]\n");
+    assert_eq!("[\n  {\"op\": \"changed\", \"key\": \"a\", \"old\": 1, \"new\": 2},\n  \
+      {\"op\": \"removed\", \"key\": \"c\", \"value\": \"1979-05-27\"},\n  \
+      {\"op\": \"added\", \"key\": \"d\", \"value\": [{\"e\": 1.5}]}\n]", to_json(&changes));
+    assert_eq!("[]", to_json(&[]));
+  }
+}
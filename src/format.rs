@@ -0,0 +1,514 @@
+//! Formatting of documents in one consistent, configurable style.
+//!
+//! `TOMLParser::format` rewrites the whitespace of a document according to a `FormatOptions`, keeping its keys,
+//! values and comments exactly as they were written. Every line ends with `line_ending`, and:
+//!
+//! * Table headers are written without whitespace inside of their brackets or around their dots, like `[a.b]`, after
+//!   `blank_lines_between_tables` blank lines. Comment lines directly above a header move with it, and comment lines
+//!   that are separated from the header below them by blank lines stay separated by at least one.
+//! * Key-value pairs have a space on either side of the `=`, or none if `space_around_equals` is false. If
+//!   `align_equals` is true the `=` of consecutive pairs are lined up, where a blank line or a header ends a run.
+//! * If `indent_tables` is true, tables are indented by `indent` for every level they're nested below the top level,
+//!   along with their key-value pairs and comments. Otherwise every line starts at its first column.
+//! * Runs of blank lines become a single blank line, and blank lines at the start or end of the document or right
+//!   after a header are removed. Comments at the end of a line are separated from it by one space.
+//! * Arrays are written on one line, with spaces inside of the brackets if `bracket_spacing` is true. Arrays that
+//!   hold comments or would make their line longer than `max_width` are written with one element per line, indented
+//!   by `indent`, with a comma after the last element if `trailing_comma` is true. A comment in an array stays on
+//!   the line of the element it follows, or on a line of its own if it was on one.
+//! * Inline tables are written on one line, with spaces inside of the braces if `brace_spacing` is true. An inline
+//!   table that holds comments continues on the next line after each of them.
+//!
+//! Formatting a formatted document with the same options doesn't change it. Newlines inside of multi-line strings
+//! are part of their values and are kept as they are.
+//!
+//! `tomlkit fmt` formats files in place, reading its options from the document given with `--config`, which is read
+//! with `FormatOptions::from_document`.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::format::FormatOptions;
+//!
+//! let (parser, _) = TOMLParser::new().parse("# Servers\n[ servers ]\nname=\"alpha\"  # Primary\nip = \"10.0.0.1\"\n\
+//!   ports = [ 8001, # HTTP\n  8002 ]\n  [ servers . beta ]\n\n\n  ip=\"10.0.0.2\"\n");
+//! let options = FormatOptions{indent_tables: true, align_equals: true, ..FormatOptions::default()};
+//! let formatted = parser.format(&options);
+//! assert_eq!("# Servers\n[servers]\nname  = \"alpha\" # Primary\nip    = \"10.0.0.1\"\nports = [\n  8001, # HTTP\n  \
+//!   8002,\n]\n\n  [servers.beta]\n  ip = \"10.0.0.2\"\n", formatted);
+//!
+//! let (parser, _) = TOMLParser::new().parse(&formatted);
+//! assert_eq!(formatted, parser.format(&options));
+//! ```
+
+use TOMLParser;
+use node::{self, Node, entries};
+use key::key_name;
+use diff::clean_number;
+use cst::{Document, Item, KeyValue, TableHeader, Comment, ValueNode, ArrayNode, InlineTableNode, Trivia};
+use types::{Value, FormatError};
+
+/// The newline that `TOMLParser::format` ends lines with.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum LineEnding {
+  /// `\n`
+  Lf,
+  /// `\r\n`
+  CrLf,
+}
+
+impl LineEnding {
+  fn text(self) -> &'static str {
+    match self {
+      LineEnding::Lf => "\n",
+      LineEnding::CrLf => "\r\n",
+    }
+  }
+}
+
+/// How `TOMLParser::format` lays out a document. See the `format` module for what each option changes.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FormatOptions {
+  /// One level of indentation, made of spaces and tabs. Two spaces by default.
+  pub indent: String,
+  /// Whether tables nested in other tables are indented, along with their contents. `false` by default.
+  pub indent_tables: bool,
+  /// Whether the `=` of consecutive key-value pairs are lined up. `false` by default.
+  pub align_equals: bool,
+  /// Whether `=` has a space on either side. `true` by default.
+  pub space_around_equals: bool,
+  /// Whether arrays written on one line have spaces inside of their brackets, like `[ 1, 2 ]`. `false` by default.
+  pub bracket_spacing: bool,
+  /// Whether inline tables have spaces inside of their braces, like `{ a = 1 }`. `true` by default.
+  pub brace_spacing: bool,
+  /// The number of characters a line with an array can have before the array is written one element per line. 80
+  /// by default.
+  pub max_width: usize,
+  /// Whether arrays written one element per line have a comma after their last element. `true` by default.
+  pub trailing_comma: bool,
+  /// The number of blank lines in front of table headers. 1 by default.
+  pub blank_lines_between_tables: usize,
+  /// The newline every line ends with. `LineEnding::Lf` by default.
+  pub line_ending: LineEnding,
+}
+
+impl Default for FormatOptions {
+  fn default() -> FormatOptions {
+    FormatOptions{
+      indent: "  ".to_string(),
+      indent_tables: false,
+      align_equals: false,
+      space_around_equals: true,
+      bracket_spacing: false,
+      brace_spacing: true,
+      max_width: 80,
+      trailing_comma: true,
+      blank_lines_between_tables: 1,
+      line_ending: LineEnding::Lf,
+    }
+  }
+}
+
+impl FormatOptions {
+  /// Reads options from a document that has a key for each option to change, named like the field with dashes
+  /// instead of underscores, such as `max-width = 100`. `line-ending` is `"lf"` or `"crlf"`. Options the document
+  /// doesn't have keep their defaults. Returns `FormatError::InvalidOption` for keys that aren't options and for
+  /// values of the wrong type.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::format::{FormatOptions, LineEnding};
+  /// use tomllib::types::FormatError;
+  ///
+  /// let (config, _) = TOMLParser::new().parse("indent = \"\\t\"\nalign-equals = true\nline-ending = \"crlf\"\n");
+  /// let options = FormatOptions::from_document(&config).unwrap();
+  /// assert_eq!("\t", options.indent);
+  /// assert!(options.align_equals);
+  /// assert_eq!(LineEnding::CrLf, options.line_ending);
+  ///
+  /// let (config, _) = TOMLParser::new().parse("max-width = \"wide\"\n");
+  /// assert_eq!(Err(FormatError::InvalidOption("max-width".to_string(), "it has to be an integer".to_string())),
+  ///   FormatOptions::from_document(&config));
+  /// ```
+  pub fn from_document(doc: &TOMLParser) -> Result<FormatOptions, FormatError> {
+    let mut options = FormatOptions::default();
+    for (name, child) in entries(&node::build(&doc.parser, "")).unwrap_or_default() {
+      let key = key_name(&name);
+      let invalid = |reason: &str| FormatError::InvalidOption(key.clone(), reason.to_string());
+      let val = match child {
+        Node::Value(val) => val,
+        _ => return Err(invalid("it has to be a value")),
+      };
+      let flag = || match val {
+        Value::Boolean(b) => Ok(b),
+        _ => Err(invalid("it has to be a boolean")),
+      };
+      let count = || match val {
+        Value::Integer(ref n) => clean_number(n).parse::<usize>().map_err(|_| invalid("it can't be negative")),
+        _ => Err(invalid("it has to be an integer")),
+      };
+      match &key[..] {
+        "indent" => match val.string_content() {
+          Some(indent) if indent.chars().all(|c| c == ' ' || c == '\t') => options.indent = indent,
+          _ => return Err(invalid("it has to be a string of spaces and tabs")),
+        },
+        "indent-tables" => options.indent_tables = flag()?,
+        "align-equals" => options.align_equals = flag()?,
+        "space-around-equals" => options.space_around_equals = flag()?,
+        "bracket-spacing" => options.bracket_spacing = flag()?,
+        "brace-spacing" => options.brace_spacing = flag()?,
+        "max-width" => options.max_width = count()?,
+        "trailing-comma" => options.trailing_comma = flag()?,
+        "blank-lines-between-tables" => options.blank_lines_between_tables = count()?,
+        "line-ending" => options.line_ending = match val.string_content().as_deref() {
+          Some("lf") => LineEnding::Lf,
+          Some("crlf") => LineEnding::CrLf,
+          _ => return Err(invalid("it has to be \"lf\" or \"crlf\"")),
+        },
+        _ => return Err(invalid("it isn't a format option")),
+      }
+    }
+    Ok(options)
+  }
+}
+
+// A line of a document, as far as formatting is concerned
+enum Entry<'d> {
+  Blank,
+  Comment(&'d Comment),
+  KeyValue(&'d KeyValue, Option<&'d Comment>),
+  Table(&'d TableHeader, Option<&'d Comment>),
+}
+
+fn is_comment(trivia: &Trivia) -> bool {
+  matches!(*trivia, Trivia::Comment(_, _, _))
+}
+
+// Whether there are comments anywhere inside of an array or inline table
+fn has_comments(val: &ValueNode) -> bool {
+  match *val {
+    ValueNode::Array(ref arr) => arr.leading().chain(arr.trailing()).any(is_comment) ||
+      arr.elements().any(|element| element.trivia().any(is_comment) || has_comments(element.value())),
+    ValueNode::InlineTable(ref table) => table.entries().any(|entry| {
+      entry.trivia().any(is_comment) || has_comments(entry.key_value().value())
+    }),
+    _ => false,
+  }
+}
+
+// The number of levels a table is nested below the top level
+fn table_level(header: &TableHeader) -> usize {
+  header.keys().len() - 1
+}
+
+struct Formatter<'o> {
+  options: &'o FormatOptions,
+  nl: &'static str,
+}
+
+impl<'o> Formatter<'o> {
+  fn indent(&self, level: usize) -> String {
+    if self.options.indent_tables { self.options.indent.repeat(level) } else { String::new() }
+  }
+
+  fn equals(&self) -> &'static str {
+    if self.options.space_around_equals { " = " } else { "=" }
+  }
+
+  fn enclose(&self, open: &str, inner: &str, close: &str, spaced: bool) -> String {
+    if spaced && !inner.is_empty() {
+      format!("{} {} {}", open, inner, close)
+    } else {
+      format!("{}{}{}", open, inner, close)
+    }
+  }
+
+  // Writes a value that doesn't hold comments on one line
+  fn inline(&self, val: &ValueNode) -> String {
+    match *val {
+      ValueNode::Array(ref arr) => {
+        let values: Vec<String> = arr.elements().map(|element| self.inline(element.value())).collect();
+        self.enclose("[", &values.join(", "), "]", self.options.bracket_spacing)
+      },
+      ValueNode::InlineTable(ref table) => {
+        let pairs: Vec<String> = table.entries().map(|entry| {
+          format!("{}{}{}", entry.key_value().key(), self.equals(), self.inline(entry.key_value().value()))
+        }).collect();
+        self.enclose("{", &pairs.join(", "), "}", self.options.brace_spacing)
+      },
+      _ => val.to_string(),
+    }
+  }
+
+  // Writes a value at the end of `out`, whose current line starts with `indent`
+  fn value(&self, out: &mut String, val: &ValueNode, indent: &str) {
+    let column = out.rsplit('\n').next().map_or(0, |line| line.chars().count());
+    match *val {
+      ValueNode::Array(ref arr) if has_comments(val) ||
+        (arr.elements().len() > 0 && column + self.inline(val).chars().count() > self.options.max_width) =>
+        self.array(out, arr, indent),
+      ValueNode::InlineTable(ref table) if has_comments(val) => self.inline_table(out, table, indent),
+      _ => out.push_str(&self.inline(val)),
+    }
+  }
+
+  // Writes the comments among `trivia`, on the current line unless a newline came before them since the last value
+  fn comments<'t, I>(&self, out: &mut String, trivia: I, indent: &str, newline: &mut bool)
+    where I: Iterator<Item=&'t Trivia> {
+    for trivia in trivia {
+      match *trivia {
+        Trivia::Whitespace(ref ws) => *newline |= ws.contains('\n'),
+        Trivia::Comment(ref before, ref comment, _) => {
+          if *newline || before.contains('\n') {
+            out.push_str(self.nl);
+            out.push_str(indent);
+          } else {
+            out.push(' ');
+          }
+          out.push_str(&comment.to_string());
+          *newline = true;
+        },
+      }
+    }
+  }
+
+  // Writes an array with one element per line
+  fn array(&self, out: &mut String, arr: &ArrayNode, indent: &str) {
+    let inner = format!("{}{}", indent, self.options.indent);
+    let mut newline = false;
+    out.push('[');
+    self.comments(out, arr.leading(), &inner, &mut newline);
+    let count = arr.elements().len();
+    for (i, element) in arr.elements().enumerate() {
+      out.push_str(self.nl);
+      out.push_str(&inner);
+      self.value(out, element.value(), &inner);
+      if i + 1 < count || self.options.trailing_comma {
+        out.push(',');
+      }
+      newline = element.separator().is_some_and(|sep| sep.after().contains('\n'));
+      self.comments(out, element.trivia(), &inner, &mut newline);
+    }
+    self.comments(out, arr.trailing(), &inner, &mut newline);
+    out.push_str(self.nl);
+    out.push_str(indent);
+    out.push(']');
+  }
+
+  // Writes an inline table that holds comments, starting a new line after each of them
+  fn inline_table(&self, out: &mut String, table: &InlineTableNode, indent: &str) {
+    let inner = format!("{}{}", indent, self.options.indent);
+    let mut newline = false;
+    out.push('{');
+    let count = table.entries().len();
+    for (i, entry) in table.entries().enumerate() {
+      if newline {
+        out.push_str(self.nl);
+        out.push_str(&inner);
+      } else if i > 0 || self.options.brace_spacing {
+        out.push(' ');
+      }
+      out.push_str(&format!("{}{}", entry.key_value().key(), self.equals()));
+      self.value(out, entry.key_value().value(), &inner);
+      if i + 1 < count {
+        out.push(',');
+      }
+      newline = entry.separator().is_some_and(|sep| sep.after().contains('\n'));
+      self.comments(out, entry.trivia(), &inner, &mut newline);
+    }
+    if newline {
+      out.push_str(self.nl);
+      out.push_str(indent);
+    } else if self.options.brace_spacing {
+      out.push(' ');
+    }
+    out.push('}');
+  }
+
+  fn header(&self, header: &TableHeader) -> String {
+    let (open, close) = if header.is_array() { ("[[", "]]") } else { ("[", "]") };
+    let keys: Vec<&str> = header.keys().map(|key| key.key().text()).collect();
+    format!("{}{}{}{}", self.indent(table_level(header)), open, keys.join("."), close)
+  }
+
+  fn document(&self, doc: &Document) -> String {
+    let lines: Vec<Entry> = doc.lines().map(|line| match (line.item(), line.comment()) {
+      (Some(Item::KeyValue(kv)), comment) => Entry::KeyValue(kv, comment),
+      (Some(Item::Table(header)), comment) => Entry::Table(header, comment),
+      (None, Some(comment)) => Entry::Comment(comment),
+      (None, None) => Entry::Blank,
+    }).collect();
+    // The level of the header that each comment line is directly above, if it's directly above one
+    let mut attached = vec![None; lines.len()];
+    let mut next_level = None;
+    for (i, line) in lines.iter().enumerate().rev() {
+      next_level = match *line {
+        Entry::Table(header, _) => Some(table_level(header)),
+        Entry::Comment(_) => next_level,
+        _ => None,
+      };
+      if let Entry::Comment(_) = *line {
+        attached[i] = next_level;
+      }
+    }
+    // The length of the longest key in the run of key-value pairs that each pair is in
+    let mut widths = vec![0; lines.len()];
+    if self.options.align_equals {
+      let mut start = 0;
+      for end in 0..lines.len() + 1 {
+        if end < lines.len() && !matches!(lines[end], Entry::Blank | Entry::Table(_, _)) {
+          continue;
+        }
+        let width = lines[start..end].iter().filter_map(|line| match *line {
+          Entry::KeyValue(kv, _) => Some(kv.key().text().chars().count()),
+          _ => None,
+        }).max().unwrap_or(0);
+        widths[start..end].iter_mut().for_each(|w| *w = width);
+        start = end + 1;
+      }
+    }
+    let with_comment = |text: String, comment: Option<&Comment>| match comment {
+      Some(comment) => format!("{} {}", text, comment),
+      None => text,
+    };
+    let mut out: Vec<String> = vec![];
+    let mut level = 0;
+    let mut blank = false;
+    let mut table_start = true;
+    let mut above_header = false;
+    let mut after_comment = false;
+    for (i, line) in lines.iter().enumerate() {
+      // A comment that isn't directly above a header stays separated from it, so it isn't moved with it next time
+      let count = if after_comment { self.options.blank_lines_between_tables.max(1) } else {
+        self.options.blank_lines_between_tables
+      };
+      let separate = |out: &mut Vec<String>| if !out.is_empty() {
+        out.extend((0..count).map(|_| String::new()));
+      };
+      match *line {
+        Entry::Blank => blank = !table_start,
+        Entry::Comment(comment) if attached[i].is_some() => {
+          if !above_header {
+            separate(&mut out);
+            above_header = true;
+          }
+          out.push(format!("{}{}", self.indent(attached[i].unwrap_or(0)), comment));
+        },
+        Entry::Table(header, comment) => {
+          if !above_header {
+            separate(&mut out);
+          }
+          out.push(with_comment(self.header(header), comment));
+          level = table_level(header);
+          blank = false;
+          after_comment = false;
+          table_start = true;
+          above_header = false;
+        },
+        Entry::Comment(_) | Entry::KeyValue(_, _) => {
+          if blank {
+            out.push(String::new());
+          }
+          let indent = self.indent(level);
+          let text = match *line {
+            Entry::KeyValue(kv, comment) => {
+              let mut text = format!("{}{}", indent, kv.key());
+              text.push_str(&" ".repeat(widths[i].saturating_sub(kv.key().text().chars().count())));
+              text.push_str(self.equals());
+              self.value(&mut text, kv.value(), &indent);
+              with_comment(text, comment)
+            },
+            Entry::Comment(comment) => format!("{}{}", indent, comment),
+            _ => String::new(),
+          };
+          out.push(text);
+          blank = false;
+          table_start = false;
+          after_comment = matches!(*line, Entry::Comment(_));
+        },
+      }
+    }
+    out.iter().map(|line| format!("{}{}", line, self.nl)).collect()
+  }
+}
+
+// Formats a document, see `TOMLParser::format`
+pub(crate) fn format(doc: &Document, options: &FormatOptions) -> String {
+  Formatter{options, nl: options.line_ending.text()}.document(doc)
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use format::{FormatOptions, LineEnding};
+  use types::FormatError;
+
+  fn format(doc: &str, options: &FormatOptions) -> String {
+    let (parser, _) = TOMLParser::new().parse(doc);
+    let formatted = parser.format(options);
+    let (reparsed, _) = TOMLParser::new().parse(&formatted);
+    assert_eq!(formatted, reparsed.format(options), "formatting isn't idempotent");
+    assert_eq!(parser.to_tagged_json(), reparsed.to_tagged_json(), "formatting changed the values");
+    formatted
+  }
+
+  #[test]
+  fn test_format_defaults() {
+    let doc = "\n\n# Title\ntitle='x'   #  The title\n\n\n\n\"a b\"   =   { c=1 ,d= [ ] }\n# About a\n\n\
+      [ a ]\n\nb = [1,2,\n3]\n[[ a . \"c d\" ]]\ne = 1979-05-27T07:32:00Z\n# Trailing\n\n\n";
+    assert_eq!("# Title\ntitle = 'x' #  The title\n\n\"a b\" = { c = 1, d = [] }\n# About a\n\n[a]\nb = [1, 2, 3]\n\n\
+      [[a.\"c d\"]]\ne = 1979-05-27T07:32:00Z\n# Trailing\n", format(doc, &FormatOptions::default()));
+  }
+
+  #[test]
+  fn test_format_options() {
+    let doc = "a = 1\nlonger = [ \"one\", \"two\" ] # Names\n[t]\nx = { y = [1] }\n[t.u]\nz = 2\r\n";
+    let options = FormatOptions{
+      indent: "\t".to_string(),
+      indent_tables: true,
+      align_equals: true,
+      space_around_equals: false,
+      bracket_spacing: true,
+      brace_spacing: false,
+      max_width: 20,
+      trailing_comma: false,
+      blank_lines_between_tables: 2,
+      line_ending: LineEnding::CrLf,
+    };
+    assert_eq!("a     =1\r\nlonger=[\r\n\t\"one\",\r\n\t\"two\"\r\n] # Names\r\n\r\n\r\n[t]\r\nx={y=[ 1 ]}\r\n\r\n\r\n\
+      \t[t.u]\r\n\tz=2\r\n", format(doc, &options));
+  }
+
+  #[test]
+  fn test_format_comments() {
+    let options = FormatOptions::default();
+    let doc = "a = [ # Start\n  1, # One\n\n    # Before two\n  2 # Two\n  # End\n]\nb = [\n[1, # Inner\n2], [3]]\n\
+      c = { x = 1, # X\n  y = [ # Y\n  2 ] }\n";
+    assert_eq!("a = [ # Start\n  1, # One\n  # Before two\n  2, # Two\n  # End\n]\nb = [\n  [\n    1, # Inner\n    \
+      2,\n  ],\n  [3],\n]\nc = { x = 1, # X\n  y = [ # Y\n    2,\n  ] }\n", format(doc, &options));
+    assert_eq!("a = [1, 2]\n", format("a = [\n  1,\n  2,\n]\n", &options));
+    assert_eq!("t = { a = 1 # A\n}\n", format("t = {a = 1 # A\n}\n", &options));
+    let options = FormatOptions{blank_lines_between_tables: 0, ..options};
+    assert_eq!("a = 1\n# Note\n\n# About b\n[b]\n", format("a = 1\n# Note\n\n\n# About b\n[b]\n", &options));
+    assert_eq!("", format("\n\n", &options));
+  }
+
+  #[test]
+  fn test_format_options_from_document() {
+    let (config, _) = TOMLParser::new().parse("indent-tables = true\nmax-width = 1_00\nbrace-spacing = false\n");
+    let options = FormatOptions::from_document(&config).unwrap();
+    assert_eq!(FormatOptions{indent_tables: true, max_width: 100, brace_spacing: false, ..FormatOptions::default()},
+      options);
+    let error = |doc: &str| FormatOptions::from_document(&TOMLParser::new().parse(doc).0).unwrap_err();
+    let invalid = |key: &str, reason: &str| FormatError::InvalidOption(key.to_string(), reason.to_string());
+    assert_eq!(invalid("tabs", "it isn't a format option"), error("tabs = true\n"));
+    assert_eq!(invalid("indent", "it has to be a string of spaces and tabs"), error("indent = \"--\"\n"));
+    assert_eq!(invalid("max-width", "it can't be negative"), error("max-width = -1\n"));
+    assert_eq!(invalid("line-ending", "it has to be \"lf\" or \"crlf\""), error("line-ending = \"cr\"\n"));
+    assert_eq!(invalid("align-equals", "it has to be a boolean"), error("align-equals = 1\n"));
+    assert_eq!(invalid("align", "it has to be a value"), error("[align]\nequals = true\n"));
+  }
+}
@@ -0,0 +1,347 @@
+//! Conversion between INI files and TOML documents.
+//!
+//! `to_toml` turns every `[section]` into a table, splitting section names at dots into nested tables, and every
+//! `key = value` or `key: value` line into a key-value pair. Comment lines starting with `;` or `#` become TOML
+//! comments in front of whatever follows them. Values are read as the TOML type they look like, so `80` becomes an
+//! integer, `true` a boolean and `2016-01-01` a datetime, unless they're quoted or `infer_types` is false, which keeps
+//! everything as strings. Sections that are declared more than once are merged, but keys that are declared more than
+//! once are an error.
+//!
+//! `from_toml` goes the other way, with keys outside of any table at the top and nested tables as sections with
+//! dotted names. Strings that would be read back as another type are quoted. Arrays, arrays of tables, strings that
+//! span lines and names that can't be written as INI keys or sections are reported as errors.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::ini;
+//!
+//! let toml = ini::to_toml("; Where the server listens\nhost = localhost\n\n[db.primary]\nport = 5432\n\
+//!   user: \"admin\"\nssl = true\n", true).unwrap();
+//! assert_eq!("# Where the server listens\nhost = \"localhost\"\n\n[db.primary]\nport = 5432\nuser = \"admin\"\n\
+//!   ssl = true\n", toml);
+//!
+//! let (parser, _) = TOMLParser::new().parse(&toml);
+//! assert_eq!("host = localhost\n\n[db.primary]\nport = 5432\nuser = admin\nssl = true\n",
+//!   ini::from_toml(&parser).unwrap());
+//! ```
+
+use std::borrow::Cow;
+use regex::Regex;
+use TOMLParser;
+use node::{self, Node, entries};
+use key::{key_name, quote_key, escape_basic};
+use json::{owned_datetime, unrepresentable};
+use diff::clean_number;
+use types::{Value, StrType, ConvertError};
+
+const INTEGER: &str = "^[+-]?(0|[1-9][0-9]*)$";
+const FLOAT: &str = r"^[+-]?(0|[1-9][0-9]*)(\.[0-9]+([eE][+-]?[0-9]+)?|[eE][+-]?[0-9]+)$";
+
+// Reads a value as the TOML type it looks like, or as a string if it doesn't look like any or `infer_types` is false
+pub(crate) fn infer(text: &str, infer_types: bool) -> Value<'static> {
+  let is = |pattern: &str| Regex::new(pattern).is_ok_and(|regex| regex.is_match(text));
+  if !infer_types {
+    Value::String(Cow::Owned(escape_basic(text)), StrType::Basic)
+  } else if text == "true" || text == "false" {
+    Value::Boolean(text == "true")
+  } else if is(INTEGER) && text.parse::<i64>().is_ok() {
+    Value::Integer(Cow::Owned(text.to_string()))
+  } else if is(FLOAT) {
+    Value::Float(Cow::Owned(text.to_string()))
+  } else {
+    match Value::datetime_parse(text) {
+      Ok(Value::DateTime(ref dt)) => Value::DateTime(owned_datetime(dt)),
+      _ => Value::String(Cow::Owned(escape_basic(text)), StrType::Basic),
+    }
+  }
+}
+
+fn full_key(names: &[String]) -> String {
+  names.iter().map(|name| quote_key(name)).collect::<Vec<String>>().join(".")
+}
+
+// A table read from an INI or .properties file, with the comments that came before it and before each of its values
+#[derive(Default)]
+pub(crate) struct Section {
+  // Whether the table has a header even if it doesn't have any values
+  pub(crate) explicit: bool,
+  pub(crate) comments: Vec<String>,
+  values: Vec<(String, Vec<String>, Value<'static>)>,
+  tables: Vec<(String, Section)>,
+}
+
+impl Section {
+  // Returns the table at `names` below this one, creating any tables that don't exist yet
+  pub(crate) fn table(&mut self, names: &[String]) -> Result<&mut Section, ConvertError> {
+    let mut section = self;
+    for (i, name) in names.iter().enumerate() {
+      if section.values.iter().any(|value| value.0 == *name) {
+        return Err(unrepresentable(&full_key(&names[..i + 1]), "it's both a value and a table"));
+      }
+      let index = match section.tables.iter().position(|table| table.0 == *name) {
+        Some(index) => index,
+        None => {
+          section.tables.push((name.clone(), Section::default()));
+          section.tables.len() - 1
+        },
+      };
+      section = &mut section.tables[index].1;
+    }
+    Ok(section)
+  }
+
+  // Adds the value at `names`, replacing a value that's already there if `replace` is true. Returns false if there's
+  // already a value and `replace` is false.
+  pub(crate) fn insert(&mut self, names: &[String], val: Value<'static>, comments: Vec<String>, replace: bool)
+    -> Result<bool, ConvertError> {
+    let (name, path) = match names.split_last() {
+      Some(split) => split,
+      None => return Ok(true),
+    };
+    let section = self.table(path)?;
+    if section.tables.iter().any(|table| table.0 == *name) {
+      return Err(unrepresentable(&full_key(names), "it's both a value and a table"));
+    }
+    match section.values.iter().position(|value| value.0 == *name) {
+      Some(_) if !replace => Ok(false),
+      Some(index) => {
+        section.values[index].1.extend(comments);
+        section.values[index].2 = val;
+        Ok(true)
+      },
+      None => {
+        section.values.push((name.clone(), comments, val));
+        Ok(true)
+      },
+    }
+  }
+
+  // Writes the table as TOML, values first and then subtables, followed by the comments that came after everything
+  pub(crate) fn write(&self, trailing: &[String]) -> String {
+    let mut out = String::new();
+    self.write_table(&mut out, &mut vec![]);
+    for comment in trailing {
+      out.push_str(&format!("#{}\n", comment));
+    }
+    out
+  }
+
+  fn write_table(&self, out: &mut String, names: &mut Vec<String>) {
+    if !names.is_empty() && (self.explicit || !self.values.is_empty() || !self.comments.is_empty()) {
+      if !out.is_empty() {
+        out.push('\n');
+      }
+      for comment in &self.comments {
+        out.push_str(&format!("#{}\n", comment));
+      }
+      out.push_str(&format!("[{}]\n", full_key(names)));
+    }
+    for (name, comments, val) in &self.values {
+      for comment in comments {
+        out.push_str(&format!("#{}\n", comment));
+      }
+      out.push_str(&format!("{} = {}\n", quote_key(name), val));
+    }
+    for (name, table) in &self.tables {
+      names.push(name.clone());
+      table.write_table(out, names);
+      names.pop();
+    }
+  }
+}
+
+fn syntax_error(line: usize, reason: &str) -> ConvertError {
+  ConvertError::Parse(format!("line {}: {}", line, reason))
+}
+
+/// Converts the text of an INI file to the text of a TOML document, reading values as the type they look like if
+/// `infer_types` is true. Returns `ConvertError::Parse` if a line isn't a section, a key-value pair, a comment or
+/// blank, or a key is declared twice in the same section, and `ConvertError::Unrepresentable` if a name is used for
+/// both a section and a key.
+///
+/// # Examples
+///
+/// ```
+/// use tomllib::ini;
+/// use tomllib::types::ConvertError;
+///
+/// assert_eq!("[server]\nport = \"80\"\n", ini::to_toml("[server]\nport = 80\n", false).unwrap());
+/// assert_eq!(Err(ConvertError::Parse("line 2: expected a section, a key-value pair or a comment".to_string())),
+///   ini::to_toml("[server]\nport\n", true));
+/// ```
+pub fn to_toml(text: &str, infer_types: bool) -> Result<String, ConvertError> {
+  let mut root = Section::default();
+  let mut section: Vec<String> = vec![];
+  let mut comments = vec![];
+  for (i, line) in text.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() {
+      continue;
+    } else if line.starts_with(';') || line.starts_with('#') {
+      comments.push(line[1..].to_string());
+    } else if line.starts_with('[') {
+      let name = match line.strip_suffix(']') {
+        Some(name) if !name[1..].trim().is_empty() => name[1..].trim(),
+        Some(_) => return Err(syntax_error(i + 1, "the section doesn't have a name")),
+        None => return Err(syntax_error(i + 1, "the section isn't closed with \"]\"")),
+      };
+      section = name.split('.').map(|name| name.trim().to_string()).collect();
+      let table = root.table(&section)?;
+      table.explicit = true;
+      table.comments.append(&mut comments);
+    } else {
+      let (name, val) = match line.find(['=', ':']) {
+        Some(at) if !line[..at].trim().is_empty() => (line[..at].trim(), line[at + 1..].trim()),
+        _ => return Err(syntax_error(i + 1, "expected a section, a key-value pair or a comment")),
+      };
+      let quoted = val.len() >= 2 && (val.starts_with('"') && val.ends_with('"') ||
+        val.starts_with('\'') && val.ends_with('\''));
+      let val = if quoted { infer(&val[1..val.len() - 1], false) } else { infer(val, infer_types) };
+      section.push(name.to_string());
+      let inserted = root.insert(&section, val, comments.split_off(0), false)?;
+      section.pop();
+      if !inserted {
+        return Err(syntax_error(i + 1, &format!("\"{}\" is already defined", name)));
+      }
+    }
+  }
+  Ok(root.write(&comments))
+}
+
+// Returns the text a value is written with in INI and .properties files, which don't have arrays
+pub(crate) fn scalar_text(key: &str, val: &Value, no_arrays: &str) -> Result<String, ConvertError> {
+  match *val {
+    Value::Integer(ref n) | Value::Float(ref n) => Ok(clean_number(n)),
+    Value::Boolean(b) => Ok(b.to_string()),
+    Value::DateTime(ref dt) => Ok(dt.to_string()),
+    Value::String(_, _) => Ok(val.string_content().unwrap_or_default()),
+    _ => Err(unrepresentable(key, no_arrays)),
+  }
+}
+
+fn write_section(out: &mut String, names: &mut Vec<String>, node: &Node) -> Result<(), ConvertError> {
+  let mut values = vec![];
+  let mut tables = vec![];
+  for (name, child) in entries(node).unwrap_or_default() {
+    names.push(key_name(&name));
+    let key = full_key(names);
+    names.pop();
+    match child {
+      Node::Table(_) | Node::Value(Value::InlineTable(_)) => tables.push((name, child)),
+      Node::ArrayOfTables(_) => return Err(unrepresentable(&key, "INI doesn't have arrays of tables")),
+      Node::Value(val) => {
+        let text = scalar_text(&key, &val, "INI doesn't have arrays")?;
+        values.push((key, key_name(&name), text, matches!(val, Value::String(_, _))));
+      },
+    }
+  }
+  if !names.is_empty() && (!values.is_empty() || tables.is_empty()) {
+    if names.iter().any(|name| name.contains(['.', '[', ']']) || name.trim() != name || name.is_empty()) {
+      return Err(unrepresentable(&full_key(names), "the name can't be written as an INI section"));
+    }
+    if !out.is_empty() {
+      out.push('\n');
+    }
+    out.push_str(&format!("[{}]\n", names.join(".")));
+  }
+  for (key, name, text, is_string) in values {
+    if name.contains(['=', ':']) || name.starts_with([';', '#', '[']) || name.trim() != name || name.is_empty() {
+      return Err(unrepresentable(&key, "the name can't be written as an INI key"));
+    }
+    if text.contains(['\n', '\r']) {
+      return Err(unrepresentable(&key, "INI values can't span lines"));
+    }
+    // Strings that would be read back as another type, or without their spaces or quotes, are quoted
+    let quote = is_string && (!matches!(infer(&text, true), Value::String(_, _)) || text.trim() != text ||
+      text.starts_with(['"', '\'']));
+    out.push_str(&format!("{} = {}\n", name, if quote { format!("\"{}\"", text) } else { text }));
+  }
+  for (name, child) in tables {
+    names.push(key_name(&name));
+    write_section(out, names, &child)?;
+    names.pop();
+  }
+  Ok(())
+}
+
+/// Converts a document to the text of an INI file. Keys outside of any table come first, and nested tables become
+/// sections with dotted names like `[db.primary]`. Returns `ConvertError::Unrepresentable` for arrays, arrays of
+/// tables, strings that span lines and names that can't be written as INI keys or sections.
+///
+/// # Examples
+///
+/// ```
+/// use tomllib::TOMLParser;
+/// use tomllib::ini;
+/// use tomllib::types::ConvertError;
+///
+/// let (parser, _) = TOMLParser::new().parse("[server]\nport = \"80\"\nhosts = [\"a\"]\n");
+/// assert_eq!(Err(ConvertError::Unrepresentable("server.hosts".to_string(), "INI doesn't have arrays".to_string())),
+///   ini::from_toml(&parser));
+/// ```
+pub fn from_toml(doc: &TOMLParser) -> Result<String, ConvertError> {
+  let mut out = String::new();
+  write_section(&mut out, &mut vec![], &node::build(&doc.parser, ""))?;
+  Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use ini::{to_toml, from_toml};
+  use types::ConvertError;
+
+  #[test]
+  fn test_to_toml() {
+    let ini = "; Global settings\nname = app\nversion = 1.5\n# The port\nport=8080\n\n[server]\nhost = \" a \"\n\
+      started: 2016-01-01T10:00:00Z\nzip = 007\nempty =\n\n[logging]\n;level = debug\nfile = 'log.txt'\n\n\
+      [server]\n; Merged\ndebug = false\n\n[a . \"b c\"]\nx=-1e3\n; The end\n";
+    assert_eq!("# Global settings\nname = \"app\"\nversion = 1.5\n# The port\nport = 8080\n\n[server]\n\
+      host = \" a \"\nstarted = 2016-01-01T10:00:00Z\nzip = \"007\"\nempty = \"\"\n# Merged\ndebug = false\n\n\
+      [logging]\n#level = debug\nfile = \"log.txt\"\n\n[a.\"\\\"b c\\\"\"]\nx = -1e3\n# The end\n",
+      to_toml(ini, true).unwrap());
+    assert_eq!("[a]\nb = \"true\"\n", to_toml("[a]\nb = true", false).unwrap());
+  }
+
+  #[test]
+  fn test_to_toml_errors() {
+    assert_eq!(Err(ConvertError::Parse("line 3: \"b\" is already defined".to_string())),
+      to_toml("[a]\nb = 1\nb = 2\n", true));
+    assert_eq!(Err(ConvertError::Parse("line 1: the section isn't closed with \"]\"".to_string())),
+      to_toml("[a\n", true));
+    assert_eq!(Err(ConvertError::Parse("line 2: the section doesn't have a name".to_string())),
+      to_toml("\n[ ]\n", true));
+    assert_eq!(Err(ConvertError::Unrepresentable("a".to_string(), "it's both a value and a table".to_string())),
+      to_toml("a = 1\n[a.b]\n", true));
+    assert_eq!(Err(ConvertError::Unrepresentable("a.b".to_string(), "it's both a value and a table".to_string())),
+      to_toml("[a.b]\n[a]\nb = 1\n", true));
+  }
+
+  #[test]
+  fn test_from_toml() {
+    let (parser, _) = TOMLParser::new().parse("title = \"1.5\"\nquoted = \"'a'\"\nspaced = \" a\"\nn = 1_000\n\n\
+      [server]\nhost = \"h\"\nport = 80\n\n[server.tls.cert]\npath = \"c\"\n\n[opts]\nlimits = { max = 5 }\n\n\
+      [empty]\n");
+    let ini = from_toml(&parser).unwrap();
+    assert_eq!("title = \"1.5\"\nquoted = \"'a'\"\nspaced = \" a\"\nn = 1000\n\n[server]\nhost = h\nport = 80\n\n\
+      [server.tls.cert]\npath = c\n\n[opts.limits]\nmax = 5\n\n[empty]\n", ini);
+    let toml = to_toml(&ini, true).unwrap();
+    let (reread, _) = TOMLParser::new().parse(&toml);
+    assert_eq!(ini, from_toml(&reread).unwrap());
+  }
+
+  #[test]
+  fn test_from_toml_errors() {
+    let error = |doc: &str| from_toml(&TOMLParser::new().parse(doc).0).unwrap_err();
+    assert_eq!(ConvertError::Unrepresentable("a".to_string(), "INI doesn't have arrays of tables".to_string()),
+      error("[[a]]\nb = 1\n"));
+    assert_eq!(ConvertError::Unrepresentable("a.b".to_string(), "INI values can't span lines".to_string()),
+      error("[a]\nb = \"1\\n2\"\n"));
+    assert_eq!(ConvertError::Unrepresentable("\"a=b\"".to_string(), "the name can't be written as an INI key"
+      .to_string()), error("\"a=b\" = 1\n"));
+    assert_eq!(ConvertError::Unrepresentable("\"a.b\"".to_string(), "the name can't be written as an INI section"
+      .to_string()), error("[\"a.b\"]\nc = 1\n"));
+  }
+}
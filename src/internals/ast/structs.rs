@@ -5,6 +5,7 @@ use std::cell::{RefCell, Cell};
 use std::option::Option;
 use std::borrow::Cow;
 use types::{DateTime, StrType, Children, Time, TimeOffset, TimeOffsetAmount,Date, PosNeg};
+use key::normalize_key;
 
 /// Compares two Options that contain comparable structs
 pub fn comp_opt<T: Eq>(left: &Option<T>, right: &Option<T>) -> bool {
@@ -261,7 +262,7 @@ impl<'a> Display for TOMLValue<'a> {
   }
 }
 
-#[derive(Debug, Eq)]
+#[derive(Debug, Eq, Clone)]
 pub enum TableType<'a>{
   Standard(Table<'a>),
   Array(Table<'a>),
@@ -287,7 +288,7 @@ impl<'a> Display for TableType<'a> {
 }
 
 // #<text>
-#[derive(Debug, Eq)]
+#[derive(Debug, Eq, Clone)]
 pub struct Comment<'a> {
   pub text: Cow<'a, str>,
 }
@@ -314,7 +315,7 @@ impl<'a> Comment<'a> {
   }
 }
 
-#[derive(Debug, Eq)]
+#[derive(Debug, Eq, Clone)]
 pub struct WSSep<'a> {
   pub ws1: Cow<'a, str>,
   pub ws2: Cow<'a, str>,
@@ -370,7 +371,7 @@ impl<'a> KeyVal<'a> {
 }
 
 // <ws.ws1>.<ws.ws2><key>
-#[derive(Debug, Eq)]
+#[derive(Debug, Eq, Clone)]
 pub struct WSKeySep<'a> {
   pub ws: WSSep<'a>,
   pub key: Cow<'a, str>,
@@ -404,7 +405,7 @@ pub fn get_last_keys(last_table: Option<&Table>, t: &Table) -> Vec<String> {
     None => {
       let mut last_keys = vec!["$Root$".to_string()];
       for i in 0..t.keys.len() {
-        last_keys.push(t.keys[i].key.clone().into_owned());
+        last_keys.push(normalize_key(&t.keys[i].key));
       }
       last_keys
     },
@@ -413,7 +414,7 @@ pub fn get_last_keys(last_table: Option<&Table>, t: &Table) -> Vec<String> {
       let len = t.keys.len();
       let mut last_keys = vec![];
       for i in len_last..len {
-        last_keys.push(t.keys[i].key.clone().into_owned());
+        last_keys.push(normalize_key(&t.keys[i].key));
       }
       last_keys
     }
@@ -424,10 +425,10 @@ pub fn format_keys(t: &Table) -> String {
   let mut s = String::new();
   if t.keys.len() > 0 {
     for i in 0..t.keys.len() - 1 {
-      s.push_str(&t.keys[i].key);
+      s.push_str(&normalize_key(&t.keys[i].key));
       s.push('.');
     }
-    s.push_str(&t.keys[t.keys.len() - 1].key);
+    s.push_str(&normalize_key(&t.keys[t.keys.len() - 1].key));
   }
   s
 }
@@ -438,10 +439,10 @@ pub fn format_tt_keys(tabletype: &TableType) -> String {
       let mut s = String::new();
       if t.keys.len() > 0 {
         for i in 0..t.keys.len() - 1 {
-          s.push_str(&t.keys[i].key);
+          s.push_str(&normalize_key(&t.keys[i].key));
           s.push('.');
         }
-        s.push_str(&t.keys[t.keys.len() - 1].key);
+        s.push_str(&normalize_key(&t.keys[t.keys.len() - 1].key));
       }
       s
     }
@@ -450,7 +451,7 @@ pub fn format_tt_keys(tabletype: &TableType) -> String {
 
 // Standard: [<ws.ws1><key><subkeys*><ws.ws2>]
 // Array: [[<ws.ws1><key><subkeys*><ws.ws2>]]
-#[derive(Debug, Eq)]
+#[derive(Debug, Eq, Clone)]
 pub struct Table<'a> {
   pub keys: Vec<WSKeySep<'a>>,
 }
@@ -508,7 +509,7 @@ impl<'a> TableType<'a> {
 }
 
 // <comment><newlines+>
-#[derive(Debug, Eq)]
+#[derive(Debug, Eq, Clone)]
 pub struct CommentNewLines<'a> {
   pub pre_ws_nl: Cow<'a, str>,
   pub comment: Comment<'a>,
@@ -543,7 +544,7 @@ impl<'a> CommentNewLines<'a> {
     }
 }
 
-#[derive(Debug, Eq)]
+#[derive(Debug, Eq, Clone)]
 pub enum CommentOrNewLines<'a> {
   Comment(CommentNewLines<'a>),
   NewLines(Cow<'a, str>),
@@ -753,11 +754,8 @@ impl<'a> PartialEq for InlineTable<'a> {
 impl<'a> Display for InlineTable<'a> {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     try!(write!(f, "{{{}", self.ws.ws1));
-    for i in 0..self.keyvals.len() - 1 {
-      try!(write!(f, "{}", self.keyvals[i]));
-    }
-    if self.keyvals.len() > 0 {
-      try!(write!(f, "{}", self.keyvals[self.keyvals.len() - 1]));
+    for keyval in self.keyvals.iter() {
+      try!(write!(f, "{}", keyval));
     }
     write!(f, "{}}}", self.ws.ws2)
   }
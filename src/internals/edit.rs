@@ -0,0 +1,642 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::mem;
+use internals::ast::structs::{HashValue, TOMLValue, TableType, Table, WSKeySep, WSSep, KeyVal, Expression,
+  NLExpression, TableKeyVal};
+use internals::parser::Parser;
+use internals::walker::{self, Event};
+use key::{self, KeyPath, KeySegment, normalize_key, quote_key};
+use types::{Value, Children, EditError};
+
+// The key the root table is stored under in the map
+fn map_key(key: &str) -> String {
+  if key.is_empty() {
+    "$Root$".to_string()
+  } else {
+    key.to_string()
+  }
+}
+
+fn is_blank(expr: &Expression) -> bool {
+  expr.keyval.is_none() && expr.table.is_none() && expr.comment.is_none()
+}
+
+// Replaces the key an error refers to with `key`
+fn rekey(err: EditError, key: &str) -> EditError {
+  match err {
+    EditError::KeyNotFound(_, path) => EditError::KeyNotFound(key.to_string(), path),
+    EditError::NotAValue(_, path) => EditError::NotAValue(key.to_string(), path),
+    EditError::InvalidValue(_, path) => EditError::InvalidValue(key.to_string(), path),
+    EditError::InvalidNestedValue(_, path) => EditError::InvalidNestedValue(key.to_string(), path),
+    EditError::NotATable(_, path) => EditError::NotATable(key.to_string(), path),
+    EditError::DuplicateKey(_, path) => EditError::DuplicateKey(key.to_string(), path),
+    EditError::InvalidTable(_, path) => EditError::InvalidTable(key.to_string(), path),
+    EditError::InvalidKey(_, path) => EditError::InvalidKey(key.to_string(), path),
+  }
+}
+
+// What kind of table, if any, a key refers to
+pub enum TableKind<'a> {
+  // The root table, a standard table with a header or an entry in an array of tables
+  Section,
+  // A table that only exists because one of its subtables was defined
+  Implicit,
+  InlineTable(Rc<RefCell<TOMLValue<'a>>>),
+  // An array of tables
+  ArrayOfTables,
+  // Anything that isn't a table
+  Value,
+  Missing,
+}
+
+impl<'a> Parser<'a> {
+  pub fn table_kind(self: &Parser<'a>, key: &str) -> TableKind<'a> {
+    if key.is_empty() {
+      return TableKind::Section;
+    }
+    match self.map.get(key) {
+      Some(hash_value) => match hash_value.value {
+        None => match hash_value.subkeys {
+          Children::Count(_) => TableKind::ArrayOfTables,
+          Children::Keys(_) if key.ends_with(']') => TableKind::Section,
+          Children::Keys(_) => TableKind::Implicit,
+        },
+        Some(ref val) => match *val.borrow() {
+          TOMLValue::Table => TableKind::Section,
+          TOMLValue::InlineTable(_) => TableKind::InlineTable(val.clone()),
+          _ => TableKind::Value,
+        },
+      },
+      None => TableKind::Missing,
+    }
+  }
+
+  // Adds `name` to the children of `parent`
+  pub fn add_child(self: &mut Parser<'a>, parent: &str, name: &str) {
+    if let Some(hash_value) = self.map.get(&map_key(parent)) {
+      match hash_value.subkeys {
+        Children::Keys(ref keys) => {
+          Parser::insert(keys, name.to_string());
+        },
+        Children::Count(ref count) => count.set(count.get() + 1),
+      }
+    }
+  }
+
+  // Returns the full key and expression index of every table header in the document
+  pub fn headers(self: &Parser<'a>) -> Vec<(String, usize)> {
+    let mut keys = vec![];
+    walker::walk(&self.root.borrow(), |event| {
+      match event {
+        Event::Table(key, _) | Event::ArrayTable(key, _) => keys.push(key),
+        _ => (),
+      }
+    });
+    let root = self.root.borrow();
+    let indices = root.exprs.iter().enumerate().filter(|&(_, nlexpr)| nlexpr.expr.table.is_some()).map(|(i, _)| i);
+    keys.into_iter().zip(indices).collect()
+  }
+
+  // Index just past the last expression in `start..end` that isn't blank
+  fn after_last_content(self: &Parser<'a>, start: usize, end: usize) -> usize {
+    let root = self.root.borrow();
+    let mut position = start;
+    for i in start..end {
+      if !is_blank(&root.exprs[i].expr) {
+        position = i + 1;
+      }
+    }
+    position
+  }
+
+  // Index just past the header at `headers[index]`, its key-value pairs and all of its subtables
+  fn extent_end(self: &Parser<'a>, headers: &[(String, usize)], index: usize) -> usize {
+    let key = &headers[index].0;
+    let mut end = self.root.borrow().exprs.len();
+    for header in &headers[index + 1..] {
+      if !header.0.starts_with(&format!("{}.", key)) && !header.0.starts_with(&format!("{}[", key)) {
+        end = header.1;
+        break;
+      }
+    }
+    self.after_last_content(headers[index].1, end)
+  }
+
+  // Index to insert a new table header with key `path` at. Tables nested in an array of tables have to be
+  // inserted inside of that array of table's extent, all other tables go at the end of the document.
+  fn header_position(self: &Parser<'a>, path: &KeyPath) -> usize {
+    let headers = self.headers();
+    for i in (0..path.len()).rev() {
+      if let KeySegment::Index(_) = path.segments()[i] {
+        let entry = key::prefix(path, i + 1);
+        if let Some(index) = headers.iter().position(|header| header.0 == entry) {
+          return self.extent_end(&headers, index);
+        }
+      }
+    }
+    let len = self.root.borrow().exprs.len();
+    self.after_last_content(0, len)
+  }
+
+  // Index and indentation for a new key-value pair in table `key`
+  pub fn keyval_position(self: &Parser<'a>, key: &str) -> (usize, String) {
+    let headers = if key.is_empty() { vec![] } else { self.headers() };
+    let root = self.root.borrow();
+    let (start, end, mut indent) = match headers.iter().position(|header| header.0 == key) {
+      Some(index) => {
+        let end = headers.get(index + 1).map(|header| header.1).unwrap_or(root.exprs.len());
+        let header = headers[index].1;
+        (header + 1, end, root.exprs[header].expr.ws.ws1.to_string())
+      },
+      None => {
+        let end = root.exprs.iter().position(|nlexpr| nlexpr.expr.table.is_some()).unwrap_or(root.exprs.len());
+        (0, end, String::new())
+      },
+    };
+    let mut position = start;
+    for i in start..end {
+      if root.exprs[i].expr.keyval.is_some() {
+        position = i + 1;
+        indent = root.exprs[i].expr.ws.ws1.to_string();
+      }
+    }
+    (position, indent)
+  }
+
+  pub fn insert_expression(self: &Parser<'a>, position: usize, expr: Expression<'a>) {
+    let mut root = self.root.borrow_mut();
+    let newline = root.exprs.iter().map(|nlexpr| nlexpr.nl.clone()).find(|nl| !nl.is_empty())
+      .unwrap_or_else(|| "\n".into());
+    if position == 0 {
+      if let Some(first) = root.exprs.first_mut() {
+        first.nl = newline;
+      }
+      root.exprs.insert(0, NLExpression::new_str("", expr));
+    } else {
+      root.exprs.insert(position, NLExpression{nl: newline, expr});
+    }
+  }
+
+  // Inserts a table header at `position`, preceded by a blank line if it would directly follow other content
+  fn insert_header(self: &Parser<'a>, position: usize, path: &KeyPath, is_array: bool) {
+    let mut keys = vec![];
+    for segment in path.segments() {
+      if let KeySegment::Key(ref name) = *segment {
+        keys.push(WSKeySep::new_string(WSSep::new_str("", ""), quote_key(name)));
+      }
+    }
+    let table = Table{keys};
+    let tabletype = if is_array { TableType::Array(table) } else { TableType::Standard(table) };
+    let blank = position > 0 && !is_blank(&self.root.borrow().exprs[position - 1].expr);
+    let mut position = position;
+    if blank {
+      self.insert_expression(position, Expression::new(WSSep::new_str("", ""), None, None, None));
+      position += 1;
+    }
+    self.insert_expression(position, Expression::new(WSSep::new_str("", ""), None, Some(Rc::new(tabletype)), None));
+  }
+
+  // Makes sure every table along `path`, except for the last one, exists, creating implicit tables for any that don't
+  fn add_ancestors(self: &mut Parser<'a>, key: &str, path: &KeyPath) -> Result<(), EditError> {
+    let segments = path.segments();
+    for j in 1..segments.len() {
+      let prefix = key::prefix(path, j);
+      let next_is_index = match segments[j] {
+        KeySegment::Key(_) => false,
+        KeySegment::Index(_) | KeySegment::FromEnd(_) => true,
+      };
+      match self.table_kind(&prefix) {
+        TableKind::ArrayOfTables if next_is_index => (),
+        TableKind::Section | TableKind::Implicit if !next_is_index => (),
+        TableKind::Missing => {
+          if next_is_index {
+            return Err(EditError::KeyNotFound(key.to_string(), prefix));
+          }
+          match segments[j - 1] {
+            KeySegment::Key(ref name) => {
+              self.map.insert(prefix.clone(), HashValue::none_keys());
+              let parent = key::prefix(path, j - 1);
+              self.add_child(&parent, &quote_key(name));
+            },
+            _ => return Err(EditError::KeyNotFound(key.to_string(), prefix)),
+          }
+        },
+        _ => return Err(EditError::NotATable(key.to_string(), prefix)),
+      }
+    }
+    Ok(())
+  }
+
+  /// Makes sure the standard table `key` exists and has a header, creating it and any tables along its path that
+  /// don't exist.
+  pub fn insert_table(self: &mut Parser<'a>, key: &str) -> Result<(), EditError> {
+    match self.table_kind(key) {
+      TableKind::Section => return Ok(()),
+      TableKind::Implicit | TableKind::Missing => (),
+      _ => return Err(EditError::NotATable(key.to_string(), key.to_string())),
+    }
+    let path = KeyPath::from(key);
+    let name = match path.last() {
+      Some(KeySegment::Key(name)) => quote_key(name),
+      _ => return Err(EditError::KeyNotFound(key.to_string(), key.to_string())),
+    };
+    self.add_ancestors(key, &path)?;
+    let position = self.header_position(&path);
+    self.insert_header(position, &path, false);
+    if self.map.contains_key(key) {
+      if let Some(hash_value) = self.map.get_mut(key) {
+        hash_value.value = Some(Rc::new(RefCell::new(TOMLValue::Table)));
+      }
+    } else {
+      self.map.insert(key.to_string(), HashValue::table_keys());
+      let parent = key::prefix(&path, path.len() - 1);
+      self.add_child(&parent, &name);
+    }
+    Ok(())
+  }
+
+  /// Appends a new table to the array of tables `key`, creating the array of tables and any tables along its path that
+  /// don't exist. Returns the key of the new table.
+  pub fn append_array_table(self: &mut Parser<'a>, key: &str) -> Result<String, EditError> {
+    let path = KeyPath::from(key);
+    let name = match path.last() {
+      Some(KeySegment::Key(name)) => quote_key(name),
+      _ => return Err(EditError::NotATable(key.to_string(), key.to_string())),
+    };
+    let count = match self.table_kind(key) {
+      TableKind::ArrayOfTables => match self.get_children(key) {
+        Some(Children::Count(count)) => count.get(),
+        _ => 0,
+      },
+      TableKind::Missing => {
+        self.add_ancestors(key, &path)?;
+        0
+      },
+      _ => return Err(EditError::NotATable(key.to_string(), key.to_string())),
+    };
+    let position = if count > 0 {
+      let headers = self.headers();
+      let last = format!("{}[{}]", key, count - 1);
+      match headers.iter().position(|header| header.0 == last) {
+        Some(index) => self.extent_end(&headers, index),
+        None => self.header_position(&path),
+      }
+    } else {
+      self.header_position(&path)
+    };
+    self.insert_header(position, &path, true);
+    if count > 0 {
+      self.add_child(key, &name);
+    } else {
+      self.map.insert(key.to_string(), HashValue::one_count());
+      let parent = key::prefix(&path, path.len() - 1);
+      self.add_child(&parent, &name);
+    }
+    let entry = format!("{}[{}]", key, count);
+    self.map.insert(entry.clone(), HashValue::none_keys());
+    Ok(entry)
+  }
+
+  // Removes the expressions in `start..end`. With `blanks` the blank lines in front of them go too, along with any
+  // blank lines that would be left at the start of the document.
+  fn remove_expressions(self: &Parser<'a>, start: usize, end: usize, blanks: bool) {
+    let mut root = self.root.borrow_mut();
+    let mut start = start;
+    while blanks && start > 0 && is_blank(&root.exprs[start - 1].expr) {
+      start -= 1;
+    }
+    root.exprs.drain(start..end);
+    // Nothing is left in front of the next expression, so it becomes the start of the document
+    if start == 0 {
+      while blanks && root.exprs.first().is_some_and(|nlexpr| is_blank(&nlexpr.expr)) {
+        root.exprs.remove(0);
+      }
+      if let Some(first) = root.exprs.first_mut() {
+        first.nl = "".into();
+      }
+    }
+  }
+
+  // Removes the headers of `key` and of all the tables under it, along with their key-value pairs
+  fn remove_headers(self: &Parser<'a>, key: &str) {
+    let (dot, bracket) = (format!("{}.", key), format!("{}[", key));
+    let headers = self.headers();
+    // From last to first so the indices of the headers that are left don't change
+    for index in (0..headers.len()).rev() {
+      let header = &headers[index].0;
+      if header == key || header.starts_with(&dot) || header.starts_with(&bracket) {
+        let len = self.root.borrow().exprs.len();
+        let next = headers.get(index + 1).map_or(len, |header| header.1.min(len));
+        let end = self.after_last_content(headers[index].1, next);
+        self.remove_expressions(headers[index].1, end, true);
+      }
+    }
+  }
+
+  // Removes `key` and all the keys under it from the map
+  fn forget(self: &mut Parser<'a>, key: &str) {
+    let (dot, bracket) = (format!("{}.", key), format!("{}[", key));
+    self.map.retain(|map_key, _| map_key != key && !map_key.starts_with(&dot) && !map_key.starts_with(&bracket));
+  }
+
+  // Removes `name` from the children of `parent`
+  fn remove_child(self: &Parser<'a>, parent: &str, name: &str) {
+    if let Some(hash_value) = self.map.get(&map_key(parent)) {
+      if let Children::Keys(ref keys) = hash_value.subkeys {
+        keys.borrow_mut().retain(|child| child != name);
+      }
+    }
+  }
+
+  // Index of the expression holding the key-value pair `key`
+  fn keyval_index(self: &Parser<'a>, key: &str) -> Option<usize> {
+    let mut keys = vec![];
+    walker::walk(&self.root.borrow(), |event| {
+      if let Event::KeyVal(key, _, _) = event {
+        keys.push(key);
+      }
+    });
+    let root = self.root.borrow();
+    let indices = root.exprs.iter().enumerate().filter(|&(_, nlexpr)| nlexpr.expr.keyval.is_some()).map(|(i, _)| i);
+    keys.into_iter().zip(indices).find(|entry| entry.0 == key).map(|entry| entry.1)
+  }
+
+  /// Removes the array of tables `key` along with the key-value pairs and subtables of all of its tables, and the
+  /// blank lines in front of their headers.
+  pub fn remove_array_of_tables(self: &mut Parser<'a>, key: &str) {
+    if let TableKind::ArrayOfTables = self.table_kind(key) {
+      self.remove_headers(key);
+      self.forget(key);
+      let path = KeyPath::from(key);
+      if let Some(KeySegment::Key(name)) = path.last() {
+        self.remove_child(&key::prefix(&path, path.len() - 1), &quote_key(name));
+      }
+    }
+  }
+
+  // Removes the table at `index` of the array of tables `key`, moving the tables after it down by one
+  fn remove_array_table(self: &mut Parser<'a>, key: &str, index: usize) {
+    let count = match self.get_children(key) {
+      Some(Children::Count(count)) => count.get(),
+      _ => return,
+    };
+    if count == 1 {
+      return self.remove_array_of_tables(key);
+    }
+    let entry = format!("{}[{}]", key, index);
+    self.remove_headers(&entry);
+    self.forget(&entry);
+    let prefix = format!("{}[", key);
+    // Keys of the later tables, split into their index and the rest of the key
+    let later: Vec<(String, usize, String)> = self.map.keys().filter_map(|map_key| {
+      let rest = map_key.strip_prefix(&prefix[..])?;
+      let close = rest.find(']')?;
+      let i: usize = rest[..close].parse().ok()?;
+      if i > index { Some((map_key.clone(), i, rest[close..].to_string())) } else { None }
+    }).collect();
+    let moved: Vec<(String, HashValue<'a>)> = later.into_iter().filter_map(|(map_key, i, rest)| {
+      self.map.remove(&map_key).map(|hash_value| (format!("{}{}{}", prefix, i - 1, rest), hash_value))
+    }).collect();
+    self.map.extend(moved);
+    if let Some(Children::Count(count)) = self.get_children(key) {
+      count.set(count.get() - 1);
+    }
+  }
+
+  /// Removes `key` from the document, whether it's a value, an element of an array, a key of an inline table, a
+  /// table, an array of tables or a table in an array of tables. Tables are removed along with their subtables and the
+  /// blank lines in front of their headers. Removing from an array or inline table gives it default formatting like
+  /// `set_value` does.
+  pub fn remove_key(self: &mut Parser<'a>, key: &str) -> Result<(), EditError> {
+    let path = KeyPath::from(key);
+    let (last, parent) = match path.last() {
+      Some(last) => (last.clone(), key::prefix(&path, path.len() - 1)),
+      None => return Err(EditError::InvalidKey(key.to_string(), key.to_string())),
+    };
+    match (self.table_kind(key), last) {
+      (TableKind::Missing, _) => Err(EditError::KeyNotFound(key.to_string(), self.first_missing_key(key))),
+      (TableKind::ArrayOfTables, _) => {
+        self.remove_array_of_tables(key);
+        Ok(())
+      },
+      (TableKind::Section, KeySegment::Index(index)) => {
+        self.remove_array_table(&parent, index);
+        Ok(())
+      },
+      (TableKind::Section, KeySegment::Key(name)) | (TableKind::Implicit, KeySegment::Key(name)) => {
+        self.remove_headers(key);
+        self.forget(key);
+        self.remove_child(&parent, &quote_key(&name));
+        Ok(())
+      },
+      (_, last) => match (self.table_kind(&parent), last) {
+        (TableKind::Value, KeySegment::Index(index)) => match self.get_value(parent.clone()) {
+          Some(Value::Array(ref arr)) => {
+            let mut values = (**arr).clone();
+            values.remove(index);
+            self.set_value(parent, Value::Array(Rc::new(values))).map_err(|err| rekey(err, key))
+          },
+          _ => Err(EditError::KeyNotFound(key.to_string(), key.to_string())),
+        },
+        (TableKind::InlineTable(_), KeySegment::Key(name)) => match self.get_value(parent.clone()) {
+          Some(Value::InlineTable(ref it)) => {
+            let pairs = it.iter().filter(|pair| normalize_key(&pair.0) != quote_key(&name)).cloned().collect();
+            self.set_value(parent, Value::InlineTable(Rc::new(pairs))).map_err(|err| rekey(err, key))
+          },
+          _ => Err(EditError::KeyNotFound(key.to_string(), key.to_string())),
+        },
+        (_, KeySegment::Key(name)) => match self.keyval_index(key) {
+          Some(index) => {
+            self.remove_expressions(index, index + 1, false);
+            self.forget(key);
+            self.remove_child(&parent, &quote_key(&name));
+            Ok(())
+          },
+          None => Err(EditError::KeyNotFound(key.to_string(), key.to_string())),
+        },
+        _ => Err(EditError::KeyNotFound(key.to_string(), key.to_string())),
+      },
+    }
+  }
+
+  /// Sets the value at `key` like `set_value`, but creates the key, and any tables along its path, if it doesn't
+  /// exist. A key that ends with an index one past the end of an array appends to the array.
+  pub fn insert_value(self: &mut Parser<'a>, key: &str, val: Value<'a>) -> Result<(), EditError> {
+    if self.map.contains_key(key) {
+      return self.set_value(key, val);
+    }
+    let path = KeyPath::from(key);
+    let (last, parent_segments) = match path.segments().split_last() {
+      Some((last, parent_segments)) => (last.clone(), parent_segments),
+      None => return Err(EditError::KeyNotFound(key.to_string(), key.to_string())),
+    };
+    let parent = key::prefix(&path, parent_segments.len());
+    let name = match last {
+      KeySegment::Index(i) => {
+        return match self.get_value(parent.clone()) {
+          Some(Value::Array(ref arr)) if arr.len() == i => {
+            let mut values = (**arr).clone();
+            values.push(val);
+            self.set_value(parent, Value::Array(Rc::new(values))).map_err(|err| rekey(err, key))
+          },
+          _ => Err(EditError::KeyNotFound(key.to_string(), self.first_missing_key(key))),
+        };
+      },
+      KeySegment::Key(name) => quote_key(&name),
+      KeySegment::FromEnd(_) => return Err(EditError::KeyNotFound(key.to_string(), self.first_missing_key(key))),
+    };
+    let tval = Parser::convert_vector(&val, key).map_err(|path| {
+      if path == key {
+        EditError::InvalidValue(key.to_string(), path)
+      } else {
+        EditError::InvalidNestedValue(key.to_string(), path)
+      }
+    })?;
+    let rc = Rc::new(RefCell::new(tval));
+    match self.table_kind(&parent) {
+      TableKind::Section => (),
+      TableKind::Implicit => self.insert_table(&parent).map_err(|err| rekey(err, key))?,
+      TableKind::InlineTable(it_rc) => {
+        if let TOMLValue::InlineTable(ref it) = *it_rc.borrow() {
+          let mut it = it.borrow_mut();
+          let mut table_keyval = TableKeyVal::last(name.clone(), rc.clone());
+          // Whitespace after the last key-value pair moves to the new last key-value pair
+          if let Some(last) = it.keyvals.last_mut() {
+            if last.kv_sep.is_none() {
+              last.kv_sep = Some(WSSep::new_str("", " "));
+              table_keyval.comment_nls = mem::take(&mut last.comment_nls);
+            }
+          }
+          it.keyvals.push(table_keyval);
+        }
+        self.add_child(&parent, &name);
+        self.rebuild_vector(key.to_string(), rc, false);
+        return Ok(());
+      },
+      TableKind::Missing => {
+        // Find the closest table that exists. Keys inside of inline tables are created as nested inline tables.
+        let mut j = parent_segments.len();
+        while j > 0 {
+          j -= 1;
+          if let TableKind::Missing = self.table_kind(&key::prefix(&path, j)) {
+            continue;
+          }
+          break;
+        }
+        let ancestor = key::prefix(&path, j);
+        if let TableKind::InlineTable(_) = self.table_kind(&ancestor) {
+          let mut nested = val;
+          for segment in path.segments()[j + 1..].iter().rev() {
+            match *segment {
+              KeySegment::Key(ref name) => nested = Value::InlineTable(Rc::new(vec![(quote_key(name).into(), nested)])),
+              _ => return Err(EditError::KeyNotFound(key.to_string(), self.first_missing_key(key))),
+            }
+          }
+          return self.insert_value(&key::prefix(&path, j + 1), nested).map_err(|err| rekey(err, key));
+        }
+        self.insert_table(&parent).map_err(|err| rekey(err, key))?;
+      },
+      TableKind::ArrayOfTables | TableKind::Value => return Err(EditError::NotATable(key.to_string(), parent)),
+    }
+    let (position, indent) = self.keyval_position(&parent);
+    let keyval = KeyVal::new_string(name.clone(), WSSep::new_str(" ", " "), rc.clone());
+    self.insert_expression(position, Expression::new(WSSep::new_string(indent, String::new()), Some(keyval), None,
+      None));
+    self.add_child(&parent, &name);
+    self.rebuild_vector(key.to_string(), rc, false);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use internals::parser::Parser;
+  use types::{Value, Children, EditError, StrType};
+
+  #[test]
+  fn test_insert_value_into_existing_tables() {
+    let p = Parser::new();
+    let (mut p, _) = p.parse("title = \"t\"\n\n[server]\n  host = \"a\" # host\n\n[[fruit]]\nname = \"x\"\n");
+    p.insert_value("version", Value::int(2)).unwrap();
+    p.insert_value("server.port", Value::int(80)).unwrap();
+    p.insert_value("fruit[0].color", Value::basic_string("red").unwrap()).unwrap();
+    assert_eq!(format!("{}", p), "title = \"t\"\nversion = 2\n\n[server]\n  host = \"a\" # host\n  port = 80\n\n\
+      [[fruit]]\nname = \"x\"\ncolor = \"red\"\n");
+    assert_eq!(p.get_value("server.port"), Some(Value::int(80)));
+    assert_eq!(p.get_children("server"), Some(&Children::Keys(::std::cell::RefCell::new(vec!["host".to_string(),
+      "port".to_string()]))));
+  }
+
+  #[test]
+  fn test_insert_value_creates_tables() {
+    let p = Parser::new();
+    let (mut p, _) = p.parse("[[fruit]]\nname = \"x\"\n[[fruit]]\nname = \"y\"\n[a.b]\nc = 1\n");
+    p.insert_value("fruit[0].physical.color", Value::basic_string("red").unwrap()).unwrap();
+    p.insert_value("a.d", Value::bool(true)).unwrap();
+    p.insert_value("new.table.key", Value::float(1.5)).unwrap();
+    assert_eq!(format!("{}", p), "[[fruit]]\nname = \"x\"\n\n[fruit.physical]\ncolor = \"red\"\n[[fruit]]\n\
+      name = \"y\"\n[a.b]\nc = 1\n\n[a]\nd = true\n\n[new.table]\nkey = 1.5\n");
+    assert_eq!(p.get_value("fruit[0].physical.color"), Some(Value::basic_string("red").unwrap()));
+    assert_eq!(p.get_value("a.d"), Some(Value::bool(true)));
+    assert_eq!(p.get_value("new.table.key"), Some(Value::float(1.5)));
+    assert_eq!(p.get_value("fruit[1].name"), Some(Value::basic_string("y").unwrap()));
+  }
+
+  #[test]
+  fn test_insert_value_inline_and_array() {
+    let p = Parser::new();
+    let (mut p, _) = p.parse("point = { x = 1 }\nnums = [1, 2]");
+    p.insert_value("point.y", Value::int(2)).unwrap();
+    p.insert_value("point.z.w", Value::int(3)).unwrap();
+    p.insert_value("nums[2]", Value::int(3)).unwrap();
+    assert_eq!(format!("{}", p), "point = { x = 1, y = 2, z = { w = 3 } }\nnums = [1, 2, 3]");
+    assert_eq!(p.get_value("point.z.w"), Some(Value::int(3)));
+    assert_eq!(p.get_value("nums[2]"), Some(Value::int(3)));
+    assert_eq!(p.insert_value("nums[5]", Value::int(3)),
+      Err(EditError::KeyNotFound("nums[5]".to_string(), "nums[5]".to_string())));
+    assert_eq!(p.insert_value("nums.a", Value::int(3)),
+      Err(EditError::NotATable("nums.a".to_string(), "nums".to_string())));
+    assert_eq!(p.insert_value("nums.a.b", Value::int(3)),
+      Err(EditError::NotATable("nums.a.b".to_string(), "nums".to_string())));
+    assert_eq!(p.insert_value("x", Value::String("bad\nbear".into(), StrType::Basic)),
+      Err(EditError::InvalidValue("x".to_string(), "x".to_string())));
+  }
+
+  #[test]
+  fn test_append_array_table() {
+    let p = Parser::new();
+    let (mut p, _) = p.parse("[[fruit]]\nname = \"x\"\n  [fruit.physical]\n  color = \"red\"\n\n[other]\n");
+    assert_eq!(Ok("fruit[1]".to_string()), p.append_array_table("fruit"));
+    p.insert_value("fruit[1].name", Value::basic_string("y").unwrap()).unwrap();
+    assert_eq!(Ok("veg[0]".to_string()), p.append_array_table("veg"));
+    assert_eq!(format!("{}", p), "[[fruit]]\nname = \"x\"\n  [fruit.physical]\n  color = \"red\"\n\n[[fruit]]\n\
+      name = \"y\"\n\n[other]\n\n[[veg]]\n");
+    assert_eq!(p.get_children("fruit"), Some(&Children::Count(::std::cell::Cell::new(2))));
+    assert_eq!(p.get_value("fruit[1].name"), Some(Value::basic_string("y").unwrap()));
+    assert_eq!(p.append_array_table("other"), Err(EditError::NotATable("other".to_string(), "other".to_string())));
+  }
+
+  #[test]
+  fn test_remove_key() {
+    let p = Parser::new();
+    let (mut p, _) = p.parse("a = 1 # one\nb = [1, 2, 3]\nc = { d = 1, e = 2 }\n\n[t]\nx = 1\n\n[t.u]\ny = 2\n\n\
+      [[f]]\nn = 1\n[[f]]\nn = 2\n[f.g]\nz = 3\n[[f]]\nn = 3\n");
+    p.remove_key("a").unwrap();
+    p.remove_key("b[1]").unwrap();
+    p.remove_key("c.d").unwrap();
+    p.remove_key("t").unwrap();
+    p.remove_key("f[1]").unwrap();
+    assert_eq!(format!("{}", p), "b = [1, 3]\nc = { e = 2 }\n\n[[f]]\nn = 1\n[[f]]\nn = 3\n");
+    assert_eq!(p.get_value("f[1].n"), Some(Value::int(3)));
+    assert_eq!(p.get_value("f[2].n"), None);
+    assert_eq!(p.get_value("t.u.y"), None);
+    assert_eq!(p.get_value("a"), None);
+    assert_eq!(p.get_children("c"), Some(&Children::Keys(::std::cell::RefCell::new(vec!["e".to_string()]))));
+    assert_eq!(p.get_children("f"), Some(&Children::Count(::std::cell::Cell::new(2))));
+    p.remove_key("f[0]").unwrap();
+    p.remove_key("f[0]").unwrap();
+    assert_eq!(format!("{}", p), "b = [1, 3]\nc = { e = 2 }\n");
+    assert_eq!(p.remove_key("f"), Err(EditError::KeyNotFound("f".to_string(), "f".to_string())));
+    assert_eq!(p.remove_key(""), Err(EditError::InvalidKey("".to_string(), "".to_string())));
+  }
+}
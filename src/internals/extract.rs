@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use internals::ast::structs::{TOMLValue, TableType, Expression};
+use internals::parser::Parser;
+use key::{self, KeyPath, KeySegment, normalize_key, quote_key};
+use types::{Value, Children};
+
+// Decides whether an expression in the table with the given full key is part of the extracted document
+type Keep = Box<dyn Fn(&str, &Expression) -> bool>;
+
+// Number of keys in the header of the table at `key`
+fn key_count(key: &str) -> usize {
+  KeyPath::from(key).segments().iter().filter(|segment| matches!(segment, KeySegment::Key(_))).count()
+}
+
+// Returns true if `key` is `table` or one of its subtables
+fn is_within(key: &str, table: &str) -> bool {
+  table.is_empty() || key == table || key.starts_with(&format!("{}.", table)) || key.starts_with(&format!("{}[", table))
+}
+
+fn is_comment(expr: &Expression) -> bool {
+  expr.keyval.is_none() && expr.table.is_none() && expr.comment.is_some()
+}
+
+fn is_blank(expr: &Expression) -> bool {
+  expr.keyval.is_none() && expr.table.is_none() && expr.comment.is_none()
+}
+
+// Writes a table header without its first `skip` keys
+fn header(table: &TableType, skip: usize) -> String {
+  let (t, brackets) = match *table {
+    TableType::Standard(ref t) => (t, ("[", "]")),
+    TableType::Array(ref t) => (t, ("[[", "]]")),
+  };
+  let mut text = format!("{}{}{}", brackets.0, t.keys[0].ws.ws1, t.keys[skip].key);
+  for key in &t.keys[skip + 1..] {
+    text.push_str(&key.to_string());
+  }
+  text.push_str(&t.keys[0].ws.ws2);
+  text.push_str(brackets.1);
+  text
+}
+
+// Writes the key-value pairs of an inline table as a document
+fn inline_table_document(val: &Value) -> String {
+  let mut doc = String::new();
+  if let Value::InlineTable(ref pairs) = *val {
+    for (key, val) in pairs.iter() {
+      doc.push_str(&format!("{} = {}\n", key, val));
+    }
+  }
+  doc
+}
+
+impl<'a> Parser<'a> {
+  fn is_section(self: &Parser<'a>, key: &str) -> bool {
+    if key.is_empty() {
+      return true;
+    }
+    match self.map.get(key) {
+      Some(hash_value) => match hash_value.value {
+        None => matches!(hash_value.subkeys, Children::Keys(_)),
+        Some(ref val) => *val.borrow() == TOMLValue::Table,
+      },
+      None => false,
+    }
+  }
+
+  /// Returns a standalone document with the contents of the table at `key`, including its comments and the comments
+  /// on and directly above its header. Subtable headers are written relative to `key`. Arrays of tables and values are
+  /// kept under their own name, and inline tables become key-value pairs. Returns `None` if `key` doesn't exist or is
+  /// an element of an array.
+  pub fn extract_document(self: &Parser<'a>, key: &str) -> Option<String> {
+    let root = self.root.borrow();
+    if key.is_empty() {
+      return Some(format!("{}", *root));
+    }
+    let hash_value = self.map.get(key)?;
+    if let Some(val @ Value::InlineTable(_)) = self.get_value(key) {
+      return Some(inline_table_document(&val));
+    }
+    let path = KeyPath::from(key);
+    let (base, keep): (String, Keep) = if self.is_section(key) {
+      let table = key.to_string();
+      (key.to_string(), Box::new(move |current: &str, expr: &Expression| {
+        // The table's own header is only kept for its comment
+        is_within(current, &table) && !(expr.table.is_some() && current == table && expr.comment.is_none())
+      }))
+    } else {
+      let name = match path.last() {
+        Some(KeySegment::Key(name)) => quote_key(name),
+        _ => return None,
+      };
+      let parent = key::prefix(&path, path.len() - 1);
+      if !self.is_section(&parent) {
+        return self.get_value(key).map(|val| format!("{} = {}\n", name, val));
+      }
+      if hash_value.value.is_none() {
+        let array = format!("{}[", key);
+        (parent, Box::new(move |current: &str, _: &Expression| current.starts_with(&array)))
+      } else {
+        let table = parent.clone();
+        (parent, Box::new(move |current: &str, expr: &Expression| {
+          current == table && expr.keyval.as_ref().is_some_and(|keyval| normalize_key(&keyval.key) == name)
+        }))
+      }
+    };
+
+    let headers: HashMap<usize, String> = self.headers().into_iter().map(|(key, i)| (i, key)).collect();
+    let mut current = String::new();
+    let mut selected = vec![];
+    for (i, nlexpr) in root.exprs.iter().enumerate() {
+      if let Some(header_key) = headers.get(&i) {
+        current = header_key.clone();
+      }
+      if keep(&current, &nlexpr.expr) {
+        selected.push(i);
+      }
+    }
+    // Comments directly above the table's header, or above the first selected expression, describe it
+    let own_header = headers.iter().find(|&(_, header_key)| header_key == key).map(|(&i, _)| i);
+    let mut first = match own_header {
+      Some(first) => first,
+      None => match selected.first() {
+        Some(&first) => first,
+        None => return Some(String::new()),
+      },
+    };
+    while first > 0 && is_comment(&root.exprs[first - 1].expr) && root.exprs[first].nl.matches('\n').count() == 1 {
+      first -= 1;
+      selected.insert(0, first);
+    }
+
+    let skip = key_count(&base);
+    let mut doc = String::new();
+    for i in selected {
+      let nlexpr = &root.exprs[i];
+      if doc.is_empty() && is_blank(&nlexpr.expr) {
+        continue;
+      }
+      if !doc.is_empty() {
+        doc.push_str(&nlexpr.nl);
+      }
+      match nlexpr.expr.table {
+        Some(_) if Some(i) == own_header => {
+          if let Some(ref comment) = nlexpr.expr.comment {
+            doc.push_str(&comment.to_string());
+          }
+        },
+        Some(ref table) => {
+          doc.push_str(&nlexpr.expr.ws.ws1);
+          doc.push_str(&header(table, skip));
+          doc.push_str(&nlexpr.expr.ws.ws2);
+          if let Some(ref comment) = nlexpr.expr.comment {
+            doc.push_str(&comment.to_string());
+          }
+        },
+        None => doc.push_str(&nlexpr.expr.to_string()),
+      }
+    }
+    let len = doc.trim_end_matches(['\n', '\r']).len();
+    doc.truncate(len);
+    if !doc.is_empty() {
+      doc.push('\n');
+    }
+    Some(doc)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use internals::parser::Parser;
+  use types::ParseResult;
+
+  const DOC: &str = r#"title = "doc"
+
+# The database
+[database] # main
+server = "192.168.1.1"
+  # Ports to connect on
+  ports = [ 8001, 8002 ]
+creds = { user = "admin", "pass word" = "x" }
+
+[[database.backups]]
+path = "/a"
+[database.backups.schedule]
+cron = "0 0 * * *"
+
+[[database.backups]] # Backup b
+path = "/b"
+
+[servers.alpha]
+ip = "10.0.0.1"
+[servers.beta]
+ip = "10.0.0.2"
+"#;
+
+  fn extract(key: &str) -> Option<String> {
+    let parser = Parser::new();
+    let (parser, result) = parser.parse(DOC);
+    assert_eq!(ParseResult::Full, result);
+    parser.extract_document(key)
+  }
+
+  #[test]
+  fn test_extract_table() {
+    assert_eq!(Some(DOC.to_string()), extract(""));
+    assert_eq!(Some(r#"# The database
+# main
+server = "192.168.1.1"
+  # Ports to connect on
+  ports = [ 8001, 8002 ]
+creds = { user = "admin", "pass word" = "x" }
+
+[[backups]]
+path = "/a"
+[backups.schedule]
+cron = "0 0 * * *"
+
+[[backups]] # Backup b
+path = "/b"
+"#.to_string()), extract("database"));
+    assert_eq!(Some("[alpha]\nip = \"10.0.0.1\"\n[beta]\nip = \"10.0.0.2\"\n".to_string()), extract("servers"));
+    assert_eq!(Some("ip = \"10.0.0.1\"\n".to_string()), extract("servers.alpha"));
+    assert_eq!(Some("# Backup b\npath = \"/b\"\n".to_string()), extract("database.backups[1]"));
+  }
+
+  #[test]
+  fn test_extract_array_of_tables() {
+    assert_eq!(Some("path = \"/a\"\n[schedule]\ncron = \"0 0 * * *\"\n".to_string()), extract("database.backups[0]"));
+    assert_eq!(Some("[[backups]]\npath = \"/a\"\n[backups.schedule]\ncron = \"0 0 * * *\"\n\n[[backups]] # Backup b\n\
+      path = \"/b\"\n".to_string()), extract("database.backups"));
+  }
+
+  #[test]
+  fn test_extract_values() {
+    assert_eq!(Some("  # Ports to connect on\n  ports = [ 8001, 8002 ]\n".to_string()), extract("database.ports"));
+    assert_eq!(Some("title = \"doc\"\n".to_string()), extract("title"));
+    assert_eq!(Some("user = \"admin\"\n\"pass word\" = \"x\"\n".to_string()), extract("database.creds"));
+    assert_eq!(Some("user = \"admin\"\n".to_string()), extract("database.creds.user"));
+    assert_eq!(None, extract("database.ports[0]"));
+    assert_eq!(None, extract("missing"));
+  }
+}
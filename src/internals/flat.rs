@@ -0,0 +1,214 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use internals::ast::structs::TOMLValue;
+use internals::parser::Parser;
+use key::{KeyPath, KeySegment, quote_key};
+use node::Node;
+use types::{Value, EditError};
+
+// A table, array or value built up from flat keys
+enum Flat<'a> {
+  Leaf(Value<'a>),
+  Object(Vec<(String, Flat<'a>)>),
+  List(BTreeMap<usize, Flat<'a>>),
+}
+
+impl<'a> Flat<'a> {
+  // An empty container for the key that comes after `segment`
+  fn for_segment(segment: &KeySegment) -> Flat<'a> {
+    match *segment {
+      KeySegment::Key(_) => Flat::Object(vec![]),
+      _ => Flat::List(BTreeMap::new()),
+    }
+  }
+
+  // Adds `val` at `rest`, relative to this container at `path`
+  fn insert(&mut self, path: &mut KeyPath, rest: &[KeySegment], key: &str, val: Value<'a>) -> Result<(), EditError> {
+    let (segment, rest) = match rest.split_first() {
+      Some(split) => split,
+      None => return Err(EditError::InvalidKey(key.to_string(), key.to_string())),
+    };
+    let child = match (self, segment) {
+      (Flat::Object(entries), KeySegment::Key(name)) => {
+        path.push_key(name.clone());
+        let position = entries.iter().position(|entry| entry.0 == *name);
+        if rest.is_empty() {
+          if position.is_some() {
+            return Err(EditError::DuplicateKey(key.to_string(), path.to_string()));
+          }
+          entries.push((name.clone(), Flat::Leaf(val)));
+          return Ok(());
+        }
+        let i = position.unwrap_or_else(|| {
+          entries.push((name.clone(), Flat::for_segment(&rest[0])));
+          entries.len() - 1
+        });
+        &mut entries[i].1
+      },
+      (Flat::List(items), KeySegment::Index(index)) => {
+        let index = *index;
+        path.push_index(index);
+        if rest.is_empty() {
+          if items.contains_key(&index) {
+            return Err(EditError::DuplicateKey(key.to_string(), path.to_string()));
+          }
+          items.insert(index, Flat::Leaf(val));
+          return Ok(());
+        }
+        items.entry(index).or_insert_with(|| Flat::for_segment(&rest[0]))
+      },
+      (_, KeySegment::FromEnd(_)) => return Err(EditError::InvalidKey(key.to_string(), key.to_string())),
+      _ => return Err(EditError::NotATable(key.to_string(), path.to_string())),
+    };
+    child.insert(path, rest, key, val)
+  }
+
+  // Checks that the indices of a list have no gaps and returns its elements in order
+  fn elements(items: BTreeMap<usize, Flat<'a>>, path: &KeyPath) -> Result<Vec<Flat<'a>>, EditError> {
+    for (i, &index) in items.keys().enumerate() {
+      if i != index {
+        return Err(EditError::KeyNotFound(path.clone().index(index).to_string(), path.clone().index(i).to_string()));
+      }
+    }
+    Ok(items.into_iter().map(|item| item.1).collect())
+  }
+
+  fn is_object(&self) -> bool {
+    matches!(*self, Flat::Object(_))
+  }
+
+  fn into_value(self, path: &KeyPath) -> Result<Value<'a>, EditError> {
+    match self {
+      Flat::Leaf(val) => Ok(val),
+      Flat::Object(entries) => {
+        let mut pairs = vec![];
+        for (name, child) in entries {
+          let val = child.into_value(&path.clone().key(name.clone()))?;
+          pairs.push((Cow::Owned(quote_key(&name)), val));
+        }
+        Ok(Value::InlineTable(Rc::new(pairs)))
+      },
+      Flat::List(items) => {
+        let mut values = vec![];
+        for (i, item) in Flat::elements(items, path)?.into_iter().enumerate() {
+          values.push(item.into_value(&path.clone().index(i))?);
+        }
+        Ok(Value::Array(Rc::new(values)))
+      },
+    }
+  }
+
+  // Objects become tables, lists of objects become arrays of tables and everything else becomes a value
+  fn into_node(self, path: &KeyPath) -> Result<Node<'a>, EditError> {
+    match self {
+      Flat::Object(entries) => {
+        let mut children = vec![];
+        for (name, child) in entries {
+          let node = child.into_node(&path.clone().key(name.clone()))?;
+          children.push((quote_key(&name), node));
+        }
+        Ok(Node::Table(children))
+      },
+      Flat::List(items) => {
+        let items = Flat::elements(items, path)?;
+        if !items.is_empty() && items.iter().all(Flat::is_object) {
+          let mut tables = vec![];
+          for (i, item) in items.into_iter().enumerate() {
+            tables.push(item.into_node(&path.clone().index(i))?);
+          }
+          Ok(Node::ArrayOfTables(tables))
+        } else {
+          let mut values = vec![];
+          for (i, item) in items.into_iter().enumerate() {
+            values.push(item.into_value(&path.clone().index(i))?);
+          }
+          Ok(Node::Value(Value::Array(Rc::new(values))))
+        }
+      },
+      leaf => leaf.into_value(path).map(Node::Value),
+    }
+  }
+}
+
+/// Builds a table `Node` from a map of full keys to values, like the one returned by `Parser::flatten`
+pub fn unflatten<'a>(map: &BTreeMap<String, Value<'a>>) -> Result<Node<'a>, EditError> {
+  let mut root = Flat::Object(vec![]);
+  for (key, val) in map {
+    let path = KeyPath::parse(key).map_err(|_| EditError::InvalidKey(key.clone(), key.clone()))?;
+    root.insert(&mut KeyPath::root(), path.segments(), key, val.clone())?;
+  }
+  root.into_node(&KeyPath::root())
+}
+
+impl<'a> Parser<'a> {
+  /// Returns every scalar value in the document, including the elements of arrays and the values of inline tables,
+  /// under its full key
+  pub fn flatten(self: &Parser<'a>) -> BTreeMap<String, Value<'a>> {
+    self.map.iter().filter_map(|(key, hash_value)| {
+      match hash_value.value {
+        Some(ref val) => match *val.borrow() {
+          TOMLValue::Table | TOMLValue::Array(_) | TOMLValue::InlineTable(_) => None,
+          _ => self.get_value(&key[..]).map(|val| (key.clone(), val)),
+        },
+        None => None,
+      }
+    }).collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::collections::BTreeMap;
+  use std::rc::Rc;
+  use internals::parser::Parser;
+  use internals::flat::unflatten;
+  use node::Node;
+  use types::{Value, EditError};
+
+  #[test]
+  fn test_flatten() {
+    let p = Parser::new();
+    let (p, _) = p.parse("a = 1\n[b]\nc = [ 1, [ 2, 3 ] ]\nd = { \"e f\" = true, g = { h = 1.5 } }\n\
+      [[i]]\nj = \"x\"\n[i.k]\nl = 2\n[[i]]\nj = \"y\"\n");
+    let flat = p.flatten();
+    let keys: Vec<&str> = flat.keys().map(|key| &key[..]).collect();
+    assert_eq!(vec!["a", "b.c[0]", "b.c[1][0]", "b.c[1][1]", "b.d.\"e f\"", "b.d.g.h", "i[0].j", "i[0].k.l", "i[1].j"],
+      keys);
+    assert_eq!(Some(&Value::float(1.5)), flat.get("b.d.g.h"));
+    assert_eq!(Some(&Value::basic_string("y").unwrap()), flat.get("i[1].j"));
+  }
+
+  #[test]
+  fn test_unflatten() {
+    let mut map = BTreeMap::new();
+    map.insert("a".to_string(), Value::int(1));
+    map.insert("b[0]".to_string(), Value::int(2));
+    map.insert("b[1]".to_string(), Value::int(3));
+    map.insert("c[0].d".to_string(), Value::int(4));
+    map.insert("e[0][0].f".to_string(), Value::int(5));
+    assert_eq!(Node::Table(vec![
+      ("a".to_string(), Node::Value(Value::int(1))),
+      ("b".to_string(), Node::Value(Value::Array(Rc::new(vec![Value::int(2), Value::int(3)])))),
+      ("c".to_string(), Node::ArrayOfTables(vec![Node::Table(vec![("d".to_string(), Node::Value(Value::int(4)))])])),
+      ("e".to_string(), Node::Value(Value::Array(Rc::new(vec![Value::Array(Rc::new(vec![
+        Value::InlineTable(Rc::new(vec![("f".into(), Value::int(5))]))
+      ]))])))),
+    ]), unflatten(&map).unwrap());
+  }
+
+  #[test]
+  fn test_unflatten_errors() {
+    for (keys, err) in vec![
+      (vec!["a", "a.b"], EditError::NotATable("a.b".to_string(), "a".to_string())),
+      (vec!["a.b", "a[0]"], EditError::NotATable("a[0]".to_string(), "a".to_string())),
+      (vec!["a", "\"a\""], EditError::DuplicateKey("a".to_string(), "a".to_string())),
+      (vec!["a[0]", "a[2]"], EditError::KeyNotFound("a[2]".to_string(), "a[1]".to_string())),
+      (vec!["a[-1]"], EditError::InvalidKey("a[-1]".to_string(), "a[-1]".to_string())),
+      (vec!["a.\"b"], EditError::InvalidKey("a.\"b".to_string(), "a.\"b".to_string())),
+    ] {
+      let map: BTreeMap<String, Value> = keys.into_iter().map(|key| (key.to_string(), Value::int(1))).collect();
+      assert_eq!(Err(err), unflatten(&map));
+    }
+  }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::cell::RefCell;
+use internals::ast::structs::{Toml, NLExpression, Expression, TableType, Table, WSKeySep, WSSep, KeyVal};
+use internals::parser::Parser;
+use internals::snapshot::copy_keyval;
+use internals::edit::TableKind;
+use internals::walker::{self, Event, child_key};
+use key::{self, KeyPath, KeySegment, normalize_key, quote_key};
+use node::Node;
+use types::{Value, EditError};
+
+// Returns true if `key` is `table` or is inside of it
+fn is_within(key: &str, table: &str) -> bool {
+  key == table || key.starts_with(&format!("{}.", table)) || key.starts_with(&format!("{}[", table))
+}
+
+// Strips the index from the key of an entry in an array of tables
+fn array_key(entry: &str) -> &str {
+  match entry.rfind('[') {
+    Some(i) => &entry[..i],
+    None => entry,
+  }
+}
+
+fn key_names(key: &str) -> Vec<String> {
+  KeyPath::from(key).segments().iter().filter_map(|segment| match *segment {
+    KeySegment::Key(ref name) => Some(quote_key(name)),
+    _ => None,
+  }).collect()
+}
+
+fn is_blank(expr: &Expression) -> bool {
+  expr.keyval.is_none() && expr.table.is_none() && expr.comment.is_none()
+}
+
+// Full keys of the table headers in `root`, in document order
+fn header_keys(root: &Toml) -> Vec<String> {
+  let mut keys = vec![];
+  walker::walk(root, |event| {
+    match event {
+      Event::Table(key, _) | Event::ArrayTable(key, _) => keys.push(key),
+      _ => (),
+    }
+  });
+  keys
+}
+
+fn header_expr<'a>(names: &[String], is_array: bool) -> Expression<'a> {
+  let keys = names.iter().map(|name| WSKeySep::new_string(WSSep::new_str("", ""), name.clone())).collect();
+  let table = if is_array { TableType::Array(Table{keys}) } else { TableType::Standard(Table{keys}) };
+  Expression::new(WSSep::new_str("", ""), None, Some(Rc::new(table)), None)
+}
+
+// Appends the expressions for the table `node` at `names`, its values first, then its subtables
+fn node_exprs<'a>(exprs: &mut Vec<NLExpression<'a>>, names: &mut Vec<String>, node: &Node<'a>)
+  -> Result<(), EditError> {
+  let entries = match *node {
+    Node::Table(ref entries) => entries,
+    _ => return Ok(()),
+  };
+  for (name, child) in entries {
+    if let Node::Value(ref val) = *child {
+      names.push(name.clone());
+      let key = names.join(".");
+      let tval = Parser::convert_vector(val, &key).map_err(|path| {
+        if path == key {
+          EditError::InvalidValue(key.clone(), path)
+        } else {
+          EditError::InvalidNestedValue(key.clone(), path)
+        }
+      })?;
+      names.pop();
+      let keyval = KeyVal::new_string(name.clone(), WSSep::new_str(" ", " "), Rc::new(RefCell::new(tval)));
+      let nl = if exprs.is_empty() { "" } else { "\n" };
+      exprs.push(NLExpression::new_str(nl, Expression::new(WSSep::new_str("", ""), Some(keyval), None, None)));
+    }
+  }
+  for (name, child) in entries {
+    let tables: Vec<&Node> = match *child {
+      Node::Table(_) => vec![child],
+      Node::ArrayOfTables(ref tables) => tables.iter().collect(),
+      Node::Value(_) => continue,
+    };
+    names.push(name.clone());
+    for table in tables {
+      if !exprs.is_empty() {
+        exprs.push(NLExpression::new_str("\n", Expression::new(WSSep::new_str("", ""), None, None, None)));
+      }
+      let nl = if exprs.is_empty() { "" } else { "\n" };
+      exprs.push(NLExpression::new_str(nl, header_expr(names, child.is_array_of_tables())));
+      node_exprs(exprs, names, table)?;
+    }
+    names.pop();
+  }
+  Ok(())
+}
+
+/// Builds a document fragment for grafting from a table `Node`, or a `Node` holding an inline table value
+pub fn node_fragment<'a>(key: &str, node: &Node<'a>) -> Result<Toml<'a>, EditError> {
+  let table;
+  let node = match *node {
+    Node::Table(_) => node,
+    Node::Value(Value::InlineTable(ref pairs)) => {
+      table = Node::Table(pairs.iter().map(|pair| (normalize_key(&pair.0), Node::Value(pair.1.clone()))).collect());
+      &table
+    },
+    _ => return Err(EditError::NotATable(key.to_string(), key.to_string())),
+  };
+  let mut exprs = vec![];
+  node_exprs(&mut exprs, &mut vec![], node)?;
+  Ok(Toml::new(exprs))
+}
+
+impl<'a> Parser<'a> {
+  // Makes sure every key along the path to the table `target` is a table or doesn't exist yet
+  fn check_table_path(self: &Parser<'a>, key: &str, target: &str) -> Result<(), EditError> {
+    let path = KeyPath::from(target);
+    for j in 1..path.len() {
+      let prefix = key::prefix(&path, j);
+      let next_is_index = !matches!(path.segments()[j], KeySegment::Key(_));
+      match self.table_kind(&prefix) {
+        TableKind::Missing => return Ok(()),
+        TableKind::ArrayOfTables if next_is_index => (),
+        TableKind::Section | TableKind::Implicit if !next_is_index => (),
+        _ => return Err(EditError::InvalidTable(key.to_string(), prefix)),
+      }
+    }
+    Ok(())
+  }
+
+  // Checks every table and key in `fragment` against the document as if the fragment had been written at `key`
+  fn check_fragment(self: &Parser<'a>, key: &str, fragment: &Toml<'a>) -> Result<(), EditError> {
+    match self.table_kind(key) {
+      TableKind::Section | TableKind::Implicit | TableKind::Missing => self.check_table_path(key, key)?,
+      _ => return Err(EditError::InvalidTable(key.to_string(), key.to_string())),
+    }
+    let mut result = Ok(());
+    // Tables appended to arrays of tables are new, so nothing inside of them can conflict
+    let mut entries: Vec<String> = vec![];
+    // Keys, tables and arrays of tables the fragment itself has defined so far
+    let (mut values, mut tables, mut arrays): (Vec<String>, Vec<String>, Vec<String>) = (vec![], vec![], vec![]);
+    walker::walk(fragment, |event| {
+      if result.is_err() {
+        return;
+      }
+      let in_entry = |fragment_key: &str| entries.iter().any(|entry| is_within(fragment_key, entry));
+      let under_value = |fragment_key: &str| values.iter().any(|value| is_within(fragment_key, value));
+      result = match event {
+        Event::Table(ref fragment_key, _) if tables.contains(fragment_key) || arrays.contains(fragment_key) ||
+          under_value(fragment_key) => Err(EditError::InvalidTable(key.to_string(), child_key(key, fragment_key))),
+        Event::Table(fragment_key, _) => {
+          let check = if in_entry(&fragment_key) {
+            Ok(())
+          } else {
+            let target = child_key(key, &fragment_key);
+            self.check_table_path(key, &target).and_then(|_| match self.table_kind(&target) {
+              TableKind::Missing | TableKind::Implicit => Ok(()),
+              _ => Err(EditError::InvalidTable(key.to_string(), target)),
+            })
+          };
+          tables.push(fragment_key);
+          check
+        },
+        Event::ArrayTable(ref fragment_key, _) if tables.iter().any(|table| table == array_key(fragment_key)) ||
+          under_value(fragment_key) =>
+          Err(EditError::InvalidTable(key.to_string(), child_key(key, array_key(fragment_key)))),
+        Event::ArrayTable(fragment_key, _) => {
+          let check = if in_entry(array_key(&fragment_key)) {
+            Ok(())
+          } else {
+            let target = child_key(key, array_key(&fragment_key));
+            self.check_table_path(key, &target).and_then(|_| match self.table_kind(&target) {
+              TableKind::Missing | TableKind::ArrayOfTables => Ok(()),
+              _ => Err(EditError::InvalidTable(key.to_string(), target)),
+            })
+          };
+          arrays.push(array_key(&fragment_key).to_string());
+          entries.push(fragment_key);
+          check
+        },
+        Event::KeyVal(ref fragment_key, _, _) if values.contains(fragment_key) || tables.contains(fragment_key) ||
+          arrays.contains(fragment_key) => Err(EditError::DuplicateKey(key.to_string(), child_key(key, fragment_key))),
+        Event::KeyVal(fragment_key, _, _) => {
+          let target = child_key(key, &fragment_key);
+          let check = if !in_entry(&fragment_key) && self.map.contains_key(&target) {
+            Err(EditError::DuplicateKey(key.to_string(), target))
+          } else {
+            Ok(())
+          };
+          values.push(fragment_key);
+          check
+        },
+        _ => Ok(()),
+      };
+    });
+    result
+  }
+
+  // Replaces the header at expression `index` with the fragment's header, keeping its whitespace and comment
+  fn copy_header(self: &Parser<'a>, index: usize, target: &str, expr: &Expression<'a>) {
+    if let Some(ref tabletype) = expr.table {
+      let (table, is_array) = match **tabletype {
+        TableType::Standard(ref table) => (table, false),
+        TableType::Array(ref table) => (table, true),
+      };
+      let names = key_names(target);
+      let prefix = &names[..names.len() - table.keys.len()];
+      let mut keys: Vec<WSKeySep<'a>> = prefix.iter()
+        .map(|name| WSKeySep::new_string(WSSep::new_str("", ""), name.clone())).collect();
+      keys.extend(table.keys.iter().cloned());
+      if !prefix.is_empty() {
+        // The whitespace inside of the brackets stays with the first key
+        let brackets = keys[prefix.len()].ws.clone();
+        keys[prefix.len()].ws = WSSep::new_str("", "");
+        keys[0].ws = brackets;
+      }
+      let table = Table{keys};
+      let tabletype = if is_array { TableType::Array(table) } else { TableType::Standard(table) };
+      let mut root = self.root.borrow_mut();
+      root.exprs[index].expr = Expression::new(expr.ws.clone(), None, Some(Rc::new(tabletype)), expr.comment.clone());
+    }
+  }
+
+  fn header_index(self: &Parser<'a>, key: &str) -> usize {
+    let headers = self.headers();
+    match headers.iter().find(|header| header.0 == key) {
+      Some(header) => header.1,
+      None => self.root.borrow().exprs.len(),
+    }
+  }
+
+  /// Adds the tables, key-value pairs and comments of `fragment` to the table `key`, which is created if it doesn't
+  /// exist. Tables in the fragment become subtables of `key`. Nothing is changed if any of the fragment's tables or
+  /// keys conflict with the document or with each other, or if writing any of them fails.
+  pub fn graft(self: &mut Parser<'a>, key: &str, fragment: &Toml<'a>) -> Result<(), EditError> {
+    self.check_fragment(key, fragment)?;
+    let snapshot = self.snapshot();
+    let result = self.write_fragment(key, fragment);
+    if result.is_err() {
+      self.restore(snapshot);
+    }
+    result
+  }
+
+  // Writes the expressions of `fragment` into the table `key`
+  fn write_fragment(self: &mut Parser<'a>, key: &str, fragment: &Toml<'a>) -> Result<(), EditError> {
+    if !key.is_empty() {
+      self.insert_table(key)?;
+    }
+    let mut headers = header_keys(fragment).into_iter();
+    // Fragment array of tables entries and the entries they became in the document
+    let mut renames: Vec<(String, String)> = vec![];
+    let mut current = key.to_string();
+    let mut position = self.keyval_position(key).0;
+    let mut blanks = 0;
+    let mut started = false;
+    for nlexpr in &fragment.exprs {
+      let expr = &nlexpr.expr;
+      if let Some(ref tabletype) = expr.table {
+        let fragment_key = headers.next().unwrap_or_default();
+        let translate = |fragment_key: &str| {
+          match renames.iter().filter(|rename| is_within(fragment_key, &rename.0)).max_by_key(|rename| rename.0.len()) {
+            Some(rename) => format!("{}{}", rename.1, &fragment_key[rename.0.len()..]),
+            None => child_key(key, fragment_key),
+          }
+        };
+        current = match **tabletype {
+          TableType::Standard(_) => {
+            let target = translate(&fragment_key);
+            self.insert_table(&target)?;
+            target
+          },
+          TableType::Array(_) => {
+            let entry = self.append_array_table(&translate(array_key(&fragment_key)))?;
+            renames.push((fragment_key, entry.clone()));
+            entry
+          },
+        };
+        let index = self.header_index(&current);
+        self.copy_header(index, &current, expr);
+        position = index + 1;
+        blanks = 0;
+        started = false;
+        continue;
+      }
+      // Blank lines are only kept between other expressions, new headers add their own
+      if is_blank(expr) {
+        if started {
+          blanks += 1;
+        }
+        continue;
+      }
+      for _ in 0..blanks {
+        self.insert_expression(position, Expression::new(WSSep::new_str("", ""), None, None, None));
+        position += 1;
+      }
+      blanks = 0;
+      started = true;
+      let keyval = expr.keyval.as_ref().map(|keyval| copy_keyval(keyval, &mut HashMap::new()));
+      let val = keyval.as_ref().map(|keyval| (normalize_key(&keyval.key), keyval.val.clone()));
+      self.insert_expression(position, Expression::new(expr.ws.clone(), keyval, None, expr.comment.clone()));
+      position += 1;
+      if let Some((name, rc)) = val {
+        self.add_child(&current, &name);
+        self.rebuild_vector(child_key(&current, &name), rc, false);
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use internals::parser::Parser;
+  use internals::graft::node_fragment;
+  use node::Node;
+  use types::{Value, Children, EditError, ParseResult};
+
+  #[test]
+  fn test_graft_document() {
+    let p = Parser::new();
+    let (mut p, _) = p.parse("title = \"t\"\n\n[other]\nx = 1\n");
+    let f = Parser::new();
+    let (f, result) = f.parse("# Connection\nserver = \"10.0.0.1\" # primary\nports = [ 8001, # main\n  8002 ]\n\n\
+      [ limits ] # per user\nmax = 5\n\n[[backups]]\npath = \"/a\"\n[backups.schedule]\ncron = \"daily\"\n");
+    assert_eq!(ParseResult::Full, result);
+    p.graft("database", &f.root.borrow()).unwrap();
+    assert_eq!(format!("{}", p), "title = \"t\"\n\n[other]\nx = 1\n\n[database]\n# Connection\n\
+      server = \"10.0.0.1\" # primary\nports = [ 8001, # main\n  8002 ]\n\n[ database.limits ] # per user\nmax = 5\n\n\
+      [[database.backups]]\npath = \"/a\"\n\n[database.backups.schedule]\ncron = \"daily\"\n");
+    assert_eq!(Some(Value::int(8002)), p.get_value("database.ports[1]"));
+    assert_eq!(Some(Value::basic_string("daily").unwrap()), p.get_value("database.backups[0].schedule.cron"));
+    assert_eq!(Some(&Children::Keys(::std::cell::RefCell::new(vec!["server".to_string(), "ports".to_string(),
+      "limits".to_string(), "backups".to_string()]))), p.get_children("database"));
+    // The copy doesn't share values with the fragment
+    assert!(p.set_value("database.ports[0]", Value::int(1)).is_ok());
+    assert_eq!(Some(Value::int(8001)), f.get_value("ports[0]"));
+  }
+
+  #[test]
+  fn test_graft_appends_and_merges() {
+    let p = Parser::new();
+    let (mut p, _) = p.parse("[[bin]]\nname = \"a\"\n\n[lib.x]\ny = 1\n");
+    let f = Parser::new();
+    let (f, _) = f.parse("[[bin]]\nname = \"b\"\n");
+    p.graft("", &f.root.borrow()).unwrap();
+    assert_eq!(Some(Value::basic_string("b").unwrap()), p.get_value("bin[1].name"));
+    let f = Parser::new();
+    let (f, _) = f.parse("path = \"src\"\n");
+    p.graft("lib", &f.root.borrow()).unwrap();
+    assert_eq!(format!("{}", p), "[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n\n[lib.x]\ny = 1\n\n[lib]\n\
+      path = \"src\"\n");
+  }
+
+  #[test]
+  fn test_graft_conflicts() {
+    let doc = "[a]\nb = 1\n[[c]]\nd = 2\n[e.f]\ng = 3\n";
+    let p = Parser::new();
+    let (mut p, _) = p.parse(doc);
+    for (key, fragment, err) in vec![
+      ("a", "b = 2\n", EditError::DuplicateKey("a".to_string(), "a.b".to_string())),
+      ("", "[a]\nz = 2\n", EditError::InvalidTable("".to_string(), "a".to_string())),
+      ("", "[c]\nz = 2\n", EditError::InvalidTable("".to_string(), "c".to_string())),
+      ("e", "[f]\nz = 2\n", EditError::InvalidTable("e".to_string(), "e.f".to_string())),
+      ("a", "[b]\nz = 2\n", EditError::InvalidTable("a".to_string(), "a.b".to_string())),
+      ("a.b", "z = 2\n", EditError::InvalidTable("a.b".to_string(), "a.b".to_string())),
+      ("a.b.c", "z = 2\n", EditError::InvalidTable("a.b.c".to_string(), "a.b".to_string())),
+      ("e", "z = 1\n[f.h]\ny = 1\n[[f]]\n", EditError::InvalidTable("e".to_string(), "e.f".to_string())),
+      ("t", "x = 1\nx = 2\n", EditError::DuplicateKey("t".to_string(), "t.x".to_string())),
+      ("t", "[a]\nx = 1\n[a]\ny = 2\n", EditError::InvalidTable("t".to_string(), "t.a".to_string())),
+      ("t", "[a]\nx = 1\n[a.x]\ny = 2\n", EditError::InvalidTable("t".to_string(), "t.a.x".to_string())),
+      ("t", "x = 1\n[x]\ny = 1\n", EditError::InvalidTable("t".to_string(), "t.x".to_string())),
+    ] {
+      let f = Parser::new();
+      let (f, _) = f.parse(fragment);
+      assert_eq!(Err(err), p.graft(key, &f.root.borrow()));
+      assert_eq!(doc, format!("{}", p));
+    }
+  }
+
+  #[test]
+  fn test_graft_node() {
+    let p = Parser::new();
+    let (mut p, _) = p.parse("title = \"t\"\n");
+    let node = Node::Table(vec![
+      ("server".to_string(), Node::Value(Value::basic_string("10.0.0.1").unwrap())),
+      ("limits".to_string(), Node::Table(vec![("max".to_string(), Node::Value(Value::int(5)))])),
+      ("backups".to_string(), Node::ArrayOfTables(vec![
+        Node::Table(vec![("path".to_string(), Node::Value(Value::basic_string("/a").unwrap()))]),
+        Node::Table(vec![("path".to_string(), Node::Value(Value::basic_string("/b").unwrap()))]),
+      ])),
+      ("port".to_string(), Node::Value(Value::int(8001))),
+    ]);
+    let fragment = node_fragment("database", &node).unwrap();
+    p.graft("database", &fragment).unwrap();
+    assert_eq!(format!("{}", p), "title = \"t\"\n\n[database]\nserver = \"10.0.0.1\"\nport = 8001\n\n\
+      [database.limits]\nmax = 5\n\n[[database.backups]]\npath = \"/a\"\n\n[[database.backups]]\npath = \"/b\"\n");
+    assert_eq!(Some(Value::basic_string("/b").unwrap()), p.get_value("database.backups[1].path"));
+    assert_eq!(Err(EditError::NotATable("x".to_string(), "x".to_string())),
+      node_fragment("x", &Node::Value(Value::int(1))).map(|_| ()));
+  }
+}
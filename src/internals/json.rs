@@ -0,0 +1,328 @@
+use std::char;
+use std::fmt;
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// A parsed JSON value. Numbers keep the text they were written with and objects keep the order of their members.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Json {
+  Null,
+  Bool(bool),
+  Number(String),
+  String(String),
+  Array(Vec<Json>),
+  Object(Vec<(String, Json)>),
+}
+
+impl Json {
+  /// Returns the member `name` of an object
+  pub fn get(&self, name: &str) -> Option<&Json> {
+    match *self {
+      Json::Object(ref members) => members.iter().find(|member| member.0 == name).map(|member| &member.1),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match *self {
+      Json::String(ref s) => Some(s),
+      _ => None,
+    }
+  }
+}
+
+/// Writes the value as compact JSON, with a space after commas and colons
+impl Display for Json {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Json::Null => write!(f, "null"),
+      Json::Bool(b) => write!(f, "{}", b),
+      Json::Number(ref n) => write!(f, "{}", n),
+      Json::String(ref s) => write!(f, "{}", quote(s)),
+      Json::Array(ref values) => {
+        write!(f, "[")?;
+        for (i, val) in values.iter().enumerate() {
+          write!(f, "{}{}", if i > 0 { ", " } else { "" }, val)?;
+        }
+        write!(f, "]")
+      },
+      Json::Object(ref members) => {
+        write!(f, "{{")?;
+        for (i, member) in members.iter().enumerate() {
+          write!(f, "{}{}: {}", if i > 0 { ", " } else { "" }, quote(&member.0), member.1)?;
+        }
+        write!(f, "}}")
+      },
+    }
+  }
+}
+
+struct Reader<'a> {
+  text: &'a str,
+  chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Reader<'a> {
+  // Builds an error message for the current position
+  fn error(&mut self, expected: &str) -> String {
+    let offset = self.chars.peek().map_or(self.text.len(), |&(i, _)| i);
+    let before = &self.text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+    format!("Expected {} at line {}, column {}", expected, line, column)
+  }
+
+  fn skip_ws(&mut self) {
+    while let Some(&(_, ' ')) | Some(&(_, '\t')) | Some(&(_, '\n')) | Some(&(_, '\r')) = self.chars.peek() {
+      self.chars.next();
+    }
+  }
+
+  fn eat(&mut self, c: char) -> bool {
+    if self.chars.peek().is_some_and(|&(_, next)| next == c) {
+      self.chars.next();
+      true
+    } else {
+      false
+    }
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), String> {
+    if self.eat(c) {
+      Ok(())
+    } else {
+      Err(self.error(&format!("'{}'", c)))
+    }
+  }
+
+  fn value(&mut self) -> Result<Json, String> {
+    self.skip_ws();
+    let json = match self.chars.peek().map(|&(_, c)| c) {
+      Some('{') => {
+        self.chars.next();
+        let mut members = vec![];
+        self.skip_ws();
+        if !self.eat('}') {
+          loop {
+            self.skip_ws();
+            let name = self.string()?;
+            self.skip_ws();
+            self.expect(':')?;
+            members.push((name, self.value()?));
+            if self.eat(',') {
+              continue;
+            }
+            self.expect('}')?;
+            break;
+          }
+        }
+        Json::Object(members)
+      },
+      Some('[') => {
+        self.chars.next();
+        let mut values = vec![];
+        self.skip_ws();
+        if !self.eat(']') {
+          loop {
+            values.push(self.value()?);
+            if self.eat(',') {
+              continue;
+            }
+            self.expect(']')?;
+            break;
+          }
+        }
+        Json::Array(values)
+      },
+      Some('"') => Json::String(self.string()?),
+      Some(c) if c == '-' || c.is_ascii_digit() => self.number()?,
+      Some(c) if c.is_ascii_alphabetic() => {
+        let mut word = String::new();
+        while let Some(&(_, c)) = self.chars.peek() {
+          if !c.is_ascii_alphabetic() {
+            break;
+          }
+          word.push(c);
+          self.chars.next();
+        }
+        match &word[..] {
+          "true" => Json::Bool(true),
+          "false" => Json::Bool(false),
+          "null" => Json::Null,
+          _ => return Err(self.error("a value")),
+        }
+      },
+      _ => return Err(self.error("a value")),
+    };
+    self.skip_ws();
+    Ok(json)
+  }
+
+  fn number(&mut self) -> Result<Json, String> {
+    let mut number = String::new();
+    while let Some(&(_, c)) = self.chars.peek() {
+      if !(c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E') {
+        break;
+      }
+      number.push(c);
+      self.chars.next();
+    }
+    if number.parse::<f64>().is_err() || number.ends_with('.') || number.starts_with('+') {
+      return Err(self.error("a number"));
+    }
+    Ok(Json::Number(number))
+  }
+
+  fn hex4(&mut self) -> Result<u32, String> {
+    let mut code = 0;
+    for _ in 0..4 {
+      match self.chars.peek().and_then(|&(_, c)| c.to_digit(16)) {
+        Some(digit) => code = code * 16 + digit,
+        None => return Err(self.error("a hexadecimal digit")),
+      }
+      self.chars.next();
+    }
+    Ok(code)
+  }
+
+  fn string(&mut self) -> Result<String, String> {
+    self.expect('"')?;
+    let mut s = String::new();
+    loop {
+      match self.chars.next() {
+        Some((_, '"')) => return Ok(s),
+        Some((_, '\\')) => {
+          let c = match self.chars.next() {
+            Some((_, '"')) => '"',
+            Some((_, '\\')) => '\\',
+            Some((_, '/')) => '/',
+            Some((_, 'b')) => '\u{8}',
+            Some((_, 'f')) => '\u{c}',
+            Some((_, 'n')) => '\n',
+            Some((_, 'r')) => '\r',
+            Some((_, 't')) => '\t',
+            Some((_, 'u')) => {
+              let mut code = self.hex4()?;
+              // A surrogate pair
+              if (0xD800..0xDC00).contains(&code) && self.eat('\\') && self.eat('u') {
+                let low = self.hex4()?;
+                code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+              }
+              match char::from_u32(code) {
+                Some(c) => c,
+                None => return Err(self.error("a valid unicode escape")),
+              }
+            },
+            _ => return Err(self.error("an escape sequence")),
+          };
+          s.push(c);
+        },
+        Some((_, c)) if (c as u32) >= 0x20 => s.push(c),
+        _ => return Err(self.error("'\"'")),
+      }
+    }
+  }
+}
+
+/// Parses a JSON document, returning a message with the line and column of the first error if it isn't valid
+pub fn parse(text: &str) -> Result<Json, String> {
+  let mut reader = Reader{text, chars: text.char_indices().peekable()};
+  let json = reader.value()?;
+  if reader.chars.peek().is_some() {
+    return Err(reader.error("the end of the document"));
+  }
+  Ok(json)
+}
+
+/// Writes `s` as a JSON string, with quotes
+pub fn quote(s: &str) -> String {
+  let mut quoted = String::from("\"");
+  for c in s.chars() {
+    match c {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      '\n' => quoted.push_str("\\n"),
+      '\r' => quoted.push_str("\\r"),
+      '\t' => quoted.push_str("\\t"),
+      '\u{8}' => quoted.push_str("\\b"),
+      '\u{c}' => quoted.push_str("\\f"),
+      c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+      c => quoted.push(c),
+    }
+  }
+  quoted.push('"');
+  quoted
+}
+
+/// Writes `json` with every member of an object and every element of an array that contains objects or arrays on a
+/// line of its own, indented by two spaces
+pub fn pretty(json: &Json) -> String {
+  let mut out = String::new();
+  write_pretty(&mut out, json, "");
+  out
+}
+
+fn write_pretty(out: &mut String, json: &Json, indent: &str) {
+  let inner = format!("{}  ", indent);
+  match *json {
+    Json::Object(ref members) if !members.is_empty() => {
+      out.push_str("{\n");
+      for (i, member) in members.iter().enumerate() {
+        out.push_str(&format!("{}{}: ", inner, quote(&member.0)));
+        write_pretty(out, &member.1, &inner);
+        out.push_str(if i + 1 < members.len() { ",\n" } else { "\n" });
+      }
+      out.push_str(&format!("{}}}", indent));
+    },
+    Json::Array(ref values) if values.iter().any(|val| matches!(*val, Json::Object(_) | Json::Array(_))) => {
+      out.push_str("[\n");
+      for (i, val) in values.iter().enumerate() {
+        out.push_str(&inner);
+        write_pretty(out, val, &inner);
+        out.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+      }
+      out.push_str(&format!("{}]", indent));
+    },
+    _ => out.push_str(&json.to_string()),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use internals::json::{parse, quote, pretty, Json};
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(Ok(Json::Object(vec![
+      ("a".to_string(), Json::Array(vec![Json::Number("1".to_string()), Json::Number("-2.5e3".to_string()),
+        Json::Bool(true), Json::Null])),
+      ("b\n".to_string(), Json::String("x\"\u{e9}\u{1F600}".to_string())),
+      ("c".to_string(), Json::Object(vec![])),
+    ])), parse(" {\"a\": [1, -2.5e3, true, null], \"b\\n\": \"x\\\"\\u00e9\\ud83d\\ude00\", \"c\" : {}}\n"));
+    assert_eq!(Err("Expected ']' at line 2, column 4".to_string()), parse("[1,\n 2 3]"));
+    assert_eq!(Err("Expected a value at line 1, column 2".to_string()), parse("[,]"));
+    assert_eq!(Err("Expected the end of the document at line 1, column 3".to_string()), parse("1 2"));
+    assert!(parse("\"a\nb\"").is_err());
+  }
+
+  #[test]
+  fn test_quote() {
+    assert_eq!("\"a\\\"b\\\\c\\n\\u0001\"", quote("a\"b\\c\n\u{1}"));
+    assert_eq!(Ok(Json::String("a\"b\\c\n\u{1}".to_string())), parse(&quote("a\"b\\c\n\u{1}")));
+  }
+
+  #[test]
+  fn test_display() {
+    let text = "{\"a\": [1, -2.5e3, true, null], \"b\\n\": \"x\", \"c\": {}}";
+    assert_eq!(text, format!("{}", parse(text).unwrap()));
+  }
+
+  #[test]
+  fn test_pretty() {
+    let json = parse("{\"a\": [1, 2], \"b\": [{\"c\": {}}, []], \"d\": {\"e\": null}}").unwrap();
+    assert_eq!("{\n  \"a\": [1, 2],\n  \"b\": [\n    {\n      \"c\": {}\n    },\n    []\n  ],\n  \"d\": {\n    \
+      \"e\": null\n  }\n}", pretty(&json));
+    assert_eq!(Ok(json.clone()), parse(&pretty(&json)));
+  }
+}
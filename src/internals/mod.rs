@@ -1,8 +1,15 @@
 #[macro_use]
 mod macros;
-mod ast;
+pub mod ast;
 mod toml;
 mod util;
 mod objects;
 pub mod parser;
 mod primitives;
+pub mod walker;
+pub mod snapshot;
+pub mod edit;
+pub mod extract;
+pub mod graft;
+pub mod flat;
+pub mod json;
@@ -3,6 +3,7 @@ use internals::ast::structs::{TableType, WSKeySep, Table, CommentNewLines, Comme
 use internals::parser::Parser;
 use internals::primitives::Key;
 use types::{ParseError, Children};
+use key::normalize_key;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
@@ -113,14 +114,14 @@ impl<'a> Parser<'a> {
                 if first {
 
                   insert = match &o.get_mut().subkeys {
-                    &Children::Keys(ref vec_rf) => {debug!("Inserting subkey: {}", tb.keys[i].key); Parser::insert(vec_rf, tb.keys[i].key.clone().into_owned())},
+                    &Children::Keys(ref vec_rf) => {debug!("Inserting subkey: {}", tb.keys[i].key); Parser::insert(vec_rf, normalize_key(&tb.keys[i].key))},
                     &Children::Count(ref cell) => { debug!("Incrementing subkey count: {}", cell.get() + 1); cell.set(cell.get() + 1); true },
                   };
                   first = false;
                 } else {
                   debug!("Inserting subkey: {}", tb.keys[i].key);
                   insert = match &o.get_mut().subkeys {
-                    &Children::Keys(ref vec_rf) => Parser::insert(vec_rf, tb.keys[i].key.clone().into_owned()),
+                    &Children::Keys(ref vec_rf) => Parser::insert(vec_rf, normalize_key(&tb.keys[i].key)),
                     _ => panic!("Implicit tables can only be Standard Tables: \"{}\"", format!("{}.{}", last_key, tb.keys[i].key)),
                   };
                 }
@@ -131,7 +132,7 @@ impl<'a> Parser<'a> {
                 } else {
                   last_key.truncate(0);
                 }
-                last_key.push_str(&tb.keys[i].key);
+                last_key.push_str(&normalize_key(&tb.keys[i].key));
                 if insert {
                   debug!("insert last_key {}", last_key);
                   if i == tb.keys.len() - 1 {
@@ -309,7 +310,7 @@ impl<'a> Parser<'a> {
             &self.last_array_tables_index, res.clone());
           if let TableType::Standard(ref tbl) = *res {
             Parser::add_to_table_set(&map, &self.last_array_tables,
-              &self.last_array_tables_index, &tbl.keys[keys_len - 1].key);
+              &self.last_array_tables_index, &normalize_key(&tbl.keys[keys_len - 1].key));
             self.array_error.set(false);
             debug!("insert table_key: {}", table_key);
             let contains_key = map.borrow().contains_key(&table_key);
@@ -325,7 +326,7 @@ impl<'a> Parser<'a> {
             }
             if let Entry::Occupied(mut o) = map.borrow_mut().entry(parent_key) {
               match &o.get_mut().subkeys {
-                &Children::Keys(ref vec_rf) => {Parser::insert(vec_rf, tbl.keys[keys_len - 1].key.clone().into_owned());},
+                &Children::Keys(ref vec_rf) => {Parser::insert(vec_rf, normalize_key(&tbl.keys[keys_len - 1].key));},
                 _ => panic!("Trying to add a key to an array: \"{}\"", table_key),
               }
             }
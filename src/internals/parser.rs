@@ -7,8 +7,9 @@ use std::rc::Rc;
 use std::borrow::Cow;
 use internals::ast::structs::{HashValue, ArrayType, TOMLValue, Toml, TableType, Array, InlineTable, ArrayValue, WSSep,
                               TableKeyVal};
-use types::{ParseError, ParseResult, Value, Children};
+use types::{ParseError, ParseResult, Value, Children, EditError};
 use internals::primitives::Key;
+use key::normalize_key;
 use nom::IResult;
 
 pub struct Parser<'a> {
@@ -101,10 +102,9 @@ impl<'a> Parser<'a> {
     if self.map.contains_key(&s_key) {
       let hashval = self.map.get(&s_key).unwrap();
       let clone = hashval.clone();
-      if let Some(val) = clone.value {
-        Some(to_val!(&*val.borrow()))
-      } else {
-        None
+      match clone.value {
+        Some(ref val) if *val.borrow() != TOMLValue::Table => Some(to_val!(&*val.borrow())),
+        _ => None,
       }
     } else {
       None
@@ -127,34 +127,45 @@ impl<'a> Parser<'a> {
     }
   }
 
-  pub fn set_value<S>(self: &mut Parser<'a>, key: S, val: Value<'a>) -> bool where S: Into<String> {
+  pub fn set_value<S>(self: &mut Parser<'a>, key: S, val: Value<'a>) -> Result<(), EditError> where S: Into<String> {
     let s_key = key.into();
     {
+      if !self.map.contains_key(&s_key) {
+        let missing = self.first_missing_key(&s_key);
+        return Err(EditError::KeyNotFound(s_key, missing));
+      }
       let tval = match self.map.entry(s_key.clone()) {
         Entry::Occupied(entry) => entry.into_mut(),
-        _ => return false,
+        _ => panic!("Map contains key, but map.entry returned a Vacant entry in set_value."),
       };
       let opt_value: &mut Option<Rc<RefCell<TOMLValue<'a>>>> = &mut tval.value;
-      let val_rf = match opt_value {
-        &mut Some(ref mut v) => v,
-        &mut None => return false,
+      let val_rf = match *opt_value {
+        Some(ref mut v) => v,
+        None => return Err(EditError::NotAValue(s_key.clone(), s_key)),
       };
+      if let TOMLValue::Table = *val_rf.borrow() {
+        return Err(EditError::NotAValue(s_key.clone(), s_key));
+      }
       // if the inline table/array has the same structure the just replace the values
       if Parser::same_structure(val_rf, &val) {
-        return Parser::replace_values(val_rf, &val);
+        Parser::replace_values(val_rf, &val);
+        return Ok(());
       }
     }
+    // Convert the new value before touching the map so a failed conversion leaves the document unchanged
+    let new_value = Parser::convert_vector(&val, &s_key).map_err(|path| {
+      if path == s_key {
+        EditError::InvalidValue(s_key.clone(), path)
+      } else {
+        EditError::InvalidNestedValue(s_key.clone(), path)
+      }
+    })?;
     // if the inline table/array has a different structure, delete the existing
     // array/inline table from the map and rebuild it from the new value
     let all_keys = self.get_all_subkeys(&s_key);
     for key in all_keys.iter() {
       self.map.remove(key);
     }
-    let new_value_opt = Parser::convert_vector(&val);
-    if new_value_opt.is_none() {
-      return false;
-    }
-    let new_value = new_value_opt.unwrap();
     let new_value_clone = match new_value {
       TOMLValue::Array(ref rc_rc) => {
         TOMLValue::Array(rc_rc.clone())
@@ -162,7 +173,8 @@ impl<'a> Parser<'a> {
       TOMLValue::InlineTable(ref rc_rc) => {
         TOMLValue::InlineTable(rc_rc.clone())
       },
-      unknown => panic!("In Parser.set_value, new_value should only be an Array or InlineTable. Instead it's a {:?}", unknown),
+      // An array or inline table replaced with a scalar
+      ref scalar => scalar.clone(),
     };
     if self.map.contains_key(&s_key) {
       let existing_value = match self.map.entry(s_key.clone()) {
@@ -178,82 +190,72 @@ impl<'a> Parser<'a> {
     }
     let new_value_rc = Rc::new(RefCell::new(new_value_clone));
     self.rebuild_vector(s_key.clone(), new_value_rc.clone(), true);
-    true
+    Ok(())
+  }
+
+  // Returns the first key along the path of `key` that doesn't exist in the map
+  pub fn first_missing_key(self: &Parser<'a>, key: &str) -> String {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in key.char_indices() {
+      if in_quotes {
+        if escaped {
+          escaped = false;
+        } else if c == '\\' {
+          escaped = true;
+        } else if c == '"' {
+          in_quotes = false;
+        }
+      } else if c == '"' {
+        in_quotes = true;
+      } else if (c == '.' || c == '[') && i > 0 && !self.map.contains_key(&key[..i]) {
+        return key[..i].to_string();
+      }
+    }
+    key.to_string()
   }
 
-  fn convert_vector(tval: &Value<'a>) -> Option<TOMLValue<'a>> {
+  // Converts a `Value` to a `TOMLValue`, on failure returns the key of the value that failed validation
+  pub fn convert_vector(tval: &Value<'a>, key: &str) -> Result<TOMLValue<'a>, String> {
     if !tval.validate() {
-      return None;
+      return Err(key.to_string());
     }
-     match tval {
-      &Value::Array(ref arr) => {
+    match *tval {
+      Value::Array(ref arr) => {
         let mut values = vec![];
-        for i in 0..arr.len() {
-          let subval = &arr[i];
-          let value_opt = Parser::convert_vector(subval);
-          if value_opt.is_none() {
-            return None;
-          }
-          let value = value_opt.unwrap();
-          let array_value;
-          if i < arr.len() - 1 {
-            array_value = ArrayValue::default(Rc::new(RefCell::new(value)));
+        for (i, subval) in arr.iter().enumerate() {
+          let value = Parser::convert_vector(subval, &format!("{}[{}]", key, i))?;
+          let array_value = if i < arr.len() - 1 {
+            ArrayValue::default(Rc::new(RefCell::new(value)))
           } else {
-            array_value = ArrayValue::last(Rc::new(RefCell::new(value)));
-          }
+            ArrayValue::last(Rc::new(RefCell::new(value)))
+          };
           values.push(array_value);
         }
-        return Some(TOMLValue::Array(Rc::new(RefCell::new(
+        Ok(TOMLValue::Array(Rc::new(RefCell::new(
           Array::new(values, vec![], vec![])
-        ))));
+        ))))
       },
-      &Value::InlineTable(ref it) => {
+      Value::InlineTable(ref it) => {
         let mut key_values = vec![];
-        for i in 0..it.len() {
-          let subval = &it[i].1;
-          let value_opt = Parser::convert_vector(subval);
-          let value = value_opt.unwrap();
-          let key_value;
-          if i < it.len() - 1 {
-            key_value = TableKeyVal::default(it[i].0.clone().into_owned(), Rc::new(RefCell::new(value)));
+        for (i, &(ref subkey, ref subval)) in it.iter().enumerate() {
+          let value = Parser::convert_vector(subval, &format!("{}.{}", key, subkey))?;
+          let key_value = if i < it.len() - 1 {
+            TableKeyVal::default(subkey.clone().into_owned(), Rc::new(RefCell::new(value)))
           } else {
-            key_value = TableKeyVal::last(it[i].0.clone().into_owned(), Rc::new(RefCell::new(value)));
-          }
+            TableKeyVal::last(subkey.clone().into_owned(), Rc::new(RefCell::new(value)))
+          };
           key_values.push(key_value);
         }
-        return Some(TOMLValue::InlineTable(Rc::new(RefCell::new(
+        Ok(TOMLValue::InlineTable(Rc::new(RefCell::new(
           InlineTable::new(key_values, WSSep::new_str(" ", " "))
-        ))));
-      },
-      &Value::Integer(ref s) => {
-        if tval.validate() {
-          return Some(TOMLValue::Integer(s.clone()))
-        } else {
-          return None;
-        }
-      },
-      &Value::Float(ref s) => {
-        if tval.validate() {
-          return Some(TOMLValue::Float(s.clone()))
-        } else {
-          return None;
-        }
-      },
-      &Value::Boolean(b) => return Some(TOMLValue::Boolean(b)),
-      &Value::DateTime(ref dt) => {
-        if tval.validate() {
-          return Some(TOMLValue::DateTime(dt.clone()))
-        } else {
-          return None;
-        }
-      },
-      &Value::String(ref s, st) => {
-        if tval.validate() {
-          return Some(TOMLValue::String(s.clone(), st))
-        } else {
-          return None;
-        }
+        ))))
       },
+      Value::Integer(ref s) => Ok(TOMLValue::Integer(s.clone())),
+      Value::Float(ref s) => Ok(TOMLValue::Float(s.clone())),
+      Value::Boolean(b) => Ok(TOMLValue::Boolean(b)),
+      Value::DateTime(ref dt) => Ok(TOMLValue::DateTime(dt.clone())),
+      Value::String(ref s, st) => Ok(TOMLValue::String(s.clone(), st)),
     }
   }
 
@@ -282,7 +284,7 @@ impl<'a> Parser<'a> {
         }
         let len = borrow.keyvals.len();
         for i in 0..len {
-          if borrow.keyvals[i].keyval.key != t_it[i].0 ||
+          if normalize_key(&borrow.keyvals[i].keyval.key) != normalize_key(&t_it[i].0) ||
             !Parser::same_structure(&borrow.keyvals[i].keyval.val, &t_it[i].1) {
             return false;
           }
@@ -297,14 +299,11 @@ impl<'a> Parser<'a> {
     }
   }
 
-  fn rebuild_vector(self: &mut Parser<'a>, key: String, val: Rc<RefCell<TOMLValue<'a>>>, skip: bool) {
+  pub fn rebuild_vector(self: &mut Parser<'a>, key: String, val: Rc<RefCell<TOMLValue<'a>>>, skip: bool) {
     match *val.borrow() {
       TOMLValue::Array(ref arr) => {
         {
-          let value = match self.map.entry(key.clone()) {
-            Entry::Occupied(entry) => entry.into_mut(),
-            _ => panic!("Map contains array key, but map.entry returned a Vacant entry in set_value."),
-          };
+          let value = self.map.entry(key.clone()).or_insert_with(|| HashValue::new_count(val.clone()));
           if !skip {
             value.value = Some(val.clone());
           }
@@ -317,28 +316,26 @@ impl<'a> Parser<'a> {
       },
       TOMLValue::InlineTable(ref it) => {
         {
-          let value = match self.map.entry(key.clone()) {
-            Entry::Occupied(entry) => entry.into_mut(),
-            _ => panic!("Map contains inline table key, but map.entry returned a Vacant entry is set_value."),
-          };
+          let value = self.map.entry(key.clone()).or_insert_with(|| HashValue::new_keys(val.clone()));
           if !skip {
             value.value = Some(val.clone());
           }
+          // The old keys were removed from the map along with their values
+          value.subkeys = Children::Keys(RefCell::new(vec![]));
           if let Children::Keys(ref child_keys) = value.subkeys {
             for i in 0..it.borrow().keyvals.len() {
-              Parser::insert(child_keys, it.borrow().keyvals[i].keyval.key.clone().into_owned());
+              Parser::insert(child_keys, normalize_key(&it.borrow().keyvals[i].keyval.key));
             }
           }
         }
         for i in 0..it.borrow().keyvals.len() {
-          let subkey = format!("{}.{}", key, &it.borrow().keyvals[i].keyval.key);
+          let subkey = format!("{}.{}", key, normalize_key(&it.borrow().keyvals[i].keyval.key));
           self.rebuild_vector(subkey, it.borrow().keyvals[i].keyval.val.clone(), false);
         }
       },
       _ => {
-        self.map.entry(key.clone()).or_insert(
-          HashValue::new_count(val.clone())
-        );
+        let value = self.map.entry(key.clone()).or_insert_with(|| HashValue::new_count(val.clone()));
+        value.subkeys = Children::Count(Cell::new(0));
       },
     }
   }
@@ -430,7 +427,7 @@ mod test {
   use std::cell::{Cell, RefCell};
   use std::rc::Rc;
   use internals::parser::Parser;
-  use types::{Value, Children, StrType, Date, Time, DateTime};
+  use types::{Value, Children, StrType, Date, Time, DateTime, EditError};
   struct TT;
   impl TT {
     fn get<'a>() -> &'a str {
@@ -630,7 +627,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("animal", Value::ml_basic_string("shark").unwrap());
+    p.set_value("animal", Value::ml_basic_string("shark").unwrap()).unwrap();
     assert_eq!(p.get_value("animal"),
       Some(Value::String("shark".into(), StrType::MLBasic)));
   }
@@ -640,7 +637,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("car.model", Value::literal_string("Accord").unwrap());
+    p.set_value("car.model", Value::literal_string("Accord").unwrap()).unwrap();
     assert_eq!(p.get_value("car.model"),
       Some(Value::String("Accord".into(), StrType::Literal)));
   }
@@ -650,7 +647,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("car.drivers[1]", Value::ml_literal_string("Mark").unwrap());
+    p.set_value("car.drivers[1]", Value::ml_literal_string("Mark").unwrap()).unwrap();
     assert_eq!(p.get_value("car.drivers[1]"),
       Some(Value::String("Mark".into(), StrType::MLLiteral)));
   }
@@ -660,7 +657,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("car.properties.accident_dates[2]", Value::float(3443.34));
+    p.set_value("car.properties.accident_dates[2]", Value::float(3443.34)).unwrap();
     assert_eq!(p.get_value("car.properties.accident_dates[2]"),
       Some(Value::Float("3443.34".into())));
   }
@@ -670,7 +667,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("car.properties.color", Value::int(19));
+    p.set_value("car.properties.color", Value::int(19)).unwrap();
     assert_eq!(p.get_value("car.properties.color"),
       Some(Value::Integer("19".into())));
   }
@@ -680,7 +677,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("car.drivers[4].banned", Value::datetime_from_int(2013, 9, 23, 17, 34, 2).unwrap());
+    p.set_value("car.drivers[4].banned", Value::datetime_from_int(2013, 9, 23, 17, 34, 2).unwrap()).unwrap();
     assert_eq!(p.get_value("car.drivers[4].banned"),
       Some(Value::DateTime(DateTime::new(Date::new_str("2013", "09", "23"),
         Some(Time::new_str("17", "34", "02", None, None))))));
@@ -693,7 +690,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let (mut p, _) = p.parse(TT::get());
     p.set_value("car.drivers", Value::Array(Rc::new(
       vec![Value::basic_string("Phil").unwrap(), Value::basic_string("Mary").unwrap()]
-    )));
+    ))).unwrap();
     assert_eq!(p.get_value("car.drivers"),
       Some(Value::Array(Rc::new(
         vec![Value::String("Phil".into(), StrType::Basic),
@@ -710,7 +707,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     p.set_value("car.properties", Value::InlineTable(Rc::new(
       vec![("make".into(), Value::literal_string("Honda").unwrap()),
            ("transmission".into(), Value::bool(true))]
-    )));
+    ))).unwrap();
     assert_eq!(p.get_value("car.properties"),
       Some(Value::InlineTable(Rc::new(
         vec![("make".into(), Value::String("Honda".into(), StrType::Literal)),
@@ -726,7 +723,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     p.set_value("car.drivers", Value::Array(Rc::new(
       vec![Value::int(1), Value::int(2), Value::int(3), Value::int(4),
       Value::int(5), Value::int(6), Value::int(7), Value::int(8)]
-    )));
+    ))).unwrap();
     assert_eq!(p.get_value("car.drivers"),
       Some(Value::Array(Rc::new(
         vec![Value::Integer("1".into()),
@@ -751,7 +748,7 @@ properties = { color = "red", "plate number" = "ABC 345",
            ("prop2".into(), Value::bool_from_str("FALSE").unwrap()),
            ("prop3".into(), Value::bool_from_str("truE").unwrap()),
            ("prop4".into(), Value::bool_from_str("false").unwrap())]
-    )));
+    ))).unwrap();
     assert_eq!(p.get_value("car.properties"),
       Some(Value::InlineTable(Rc::new(
         vec![("prop1".into(), Value::Boolean(true)),
@@ -766,7 +763,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("car.interior.seats.type", Value::basic_string("leather").unwrap());
+    p.set_value("car.interior.seats.type", Value::basic_string("leather").unwrap()).unwrap();
     assert_eq!(p.get_value("car.interior.seats.type"),
       Some(Value::String("leather".into(), StrType::Basic)));
   }
@@ -776,7 +773,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("car.owners[0].Age", Value::float_from_str("19.5").unwrap());
+    p.set_value("car.owners[0].Age", Value::float_from_str("19.5").unwrap()).unwrap();
     assert_eq!(p.get_value("car.owners[0].Age"),
       Some(Value::Float("19.5".into())));
   }
@@ -786,7 +783,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("car.owners[1].Name", Value::ml_basic_string("Steve Parker").unwrap());
+    p.set_value("car.owners[1].Name", Value::ml_basic_string("Steve Parker").unwrap()).unwrap();
     assert_eq!(p.get_value("car.owners[1].Name"),
       Some(Value::String("Steve Parker".into(), StrType::MLBasic)));
   }
@@ -796,7 +793,7 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("database.ports", Value::datetime_from_int(2000, 02, 16, 10, 31, 06).unwrap());
+    assert!(p.set_value("database.ports", Value::datetime_from_int(2000, 02, 16, 10, 31, 06).unwrap()).is_err());
     assert_eq!(p.get_value("database.ports[0]"), None);
     assert_eq!(p.get_value("database.ports[1]"), None);
     assert_eq!(p.get_value("database.ports[2]"), None);
@@ -809,11 +806,41 @@ properties = { color = "red", "plate number" = "ABC 345",
     let _ = env_logger::init();
     let p = Parser::new();
     let (mut p, _) = p.parse(TT::get());
-    p.set_value("database.servers", Value::datetime_from_str("4000", "02", "27", "01", "59", "59").unwrap());
+    assert!(p.set_value("database.servers", Value::datetime_from_str("4000", "02", "27", "01", "59", "59").unwrap()).is_err());
     assert_eq!(p.get_value("database.servers.main"), None);
     assert_eq!(p.get_value("database.servers.failover1"), None);
     assert_eq!(p.get_value("database.servers.failover2"), None);
     assert_eq!(p.get_value("database.servers.failover2.something"), None);
     assert_eq!(p.get_value("database.servers.failover2.nothing"), None);
   }
+
+  #[test]
+  fn test_set_value_errors() {
+    let _ = env_logger::init();
+    let p = Parser::new();
+    let (mut p, _) = p.parse(TT::get());
+    assert_eq!(p.set_value("car.wheels.count", Value::int(4)),
+      Err(EditError::KeyNotFound("car.wheels.count".to_string(), "car.wheels".to_string())));
+    assert_eq!(p.set_value("car.interior", Value::int(4)),
+      Err(EditError::NotAValue("car.interior".to_string(), "car.interior".to_string())));
+    assert_eq!(p.set_value("car", Value::int(4)),
+      Err(EditError::NotAValue("car".to_string(), "car".to_string())));
+    assert_eq!(p.set_value("animal", Value::String("bad\nbear".into(), StrType::Basic)),
+      Err(EditError::InvalidValue("animal".to_string(), "animal".to_string())));
+    assert_eq!(p.set_value("car.drivers", Value::Array(Rc::new(vec![
+      Value::int(1), Value::String("bad\nbear".into(), StrType::Basic)
+    ]))), Err(EditError::InvalidNestedValue("car.drivers".to_string(), "car.drivers[1]".to_string())));
+    assert_eq!(p.get_value("animal"), Some(Value::String("bear".into(), StrType::Basic)));
+    assert_eq!(p.get_value("car.drivers[0]"), Some(Value::String("Bob".into(), StrType::Basic)));
+  }
+
+  #[test]
+  fn test_get_table_value() {
+    let _ = env_logger::init();
+    let p = Parser::new();
+    let (p, _) = p.parse(TT::get());
+    assert_eq!(p.get_value("car"), None);
+    assert_eq!(p.get_value("car.interior"), None);
+    assert_eq!(p.get_value("car.owners[0]"), None);
+  }
 }
\ No newline at end of file
@@ -6,6 +6,7 @@ use std::borrow::Cow;
 use internals::ast::structs::{KeyVal, WSSep, TOMLValue, ErrorCode, HashValue, TableType, Table, get_last_keys};
 use types::{Date, Time, DateTime, TimeOffset, TimeOffsetAmount, ParseError, StrType, Children, Value};
 use internals::parser::Parser;
+use key::normalize_key;
 use nom;
 use nom::{IResult, InputLength};
 
@@ -618,7 +619,7 @@ impl<'a> Parser<'a> {
 
   method!(pub key<Parser<'a>, &'a str, &'a str>, mut self, alt!(
     complete!(call_m!(self.quoted_key))   =>  {|k: &'a str| {
-      self.keychain.borrow_mut().push(Key::Str(k.into())); k
+      self.keychain.borrow_mut().push(Key::Str(normalize_key(k).into())); k
     }}|
     complete!(call_m!(self.unquoted_key)) =>  {|k: &'a str| {
       self.keychain.borrow_mut().push(Key::Str(k.into())); k}}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::cell::RefCell;
+use internals::ast::structs::{NLExpression, Expression, HashValue, KeyVal, TOMLValue, Array, ArrayValue, InlineTable,
+  TableKeyVal};
+use internals::parser::Parser;
+
+/// Copies of values, under the address of the value they were copied from
+pub type Copies<'a> = HashMap<*const RefCell<TOMLValue<'a>>, Rc<RefCell<TOMLValue<'a>>>>;
+
+/// Copies a value so the copy doesn't share any arrays or inline tables with the original, recording every value that
+/// was copied, including nested ones, in `copies`
+pub fn deep_clone<'a>(val: &Rc<RefCell<TOMLValue<'a>>>, copies: &mut Copies<'a>) -> Rc<RefCell<TOMLValue<'a>>> {
+  let copy = match *val.borrow() {
+    TOMLValue::Array(ref arr) => {
+      let arr = arr.borrow();
+      let values = arr.values.iter().map(|array_value| {
+        ArrayValue::new(deep_clone(&array_value.val, copies), array_value.array_sep.clone(),
+          array_value.comment_nls.clone())
+      }).collect();
+      TOMLValue::Array(Rc::new(RefCell::new(Array::new(values, arr.comment_nls1.clone(), arr.comment_nls2.clone()))))
+    },
+    TOMLValue::InlineTable(ref it) => {
+      let it = it.borrow();
+      let keyvals = it.keyvals.iter().map(|table_keyval| TableKeyVal{
+        keyval: copy_keyval(&table_keyval.keyval, copies),
+        kv_sep: table_keyval.kv_sep.clone(),
+        comment_nls: table_keyval.comment_nls.clone(),
+      }).collect();
+      TOMLValue::InlineTable(Rc::new(RefCell::new(InlineTable{keyvals, ws: it.ws.clone()})))
+    },
+    ref scalar => scalar.clone(),
+  };
+  let copy = Rc::new(RefCell::new(copy));
+  copies.insert(Rc::as_ptr(val), copy.clone());
+  copy
+}
+
+pub fn copy_keyval<'a>(keyval: &KeyVal<'a>, copies: &mut Copies<'a>) -> KeyVal<'a> {
+  KeyVal{key: keyval.key.clone(), keyval_sep: keyval.keyval_sep.clone(), val: deep_clone(&keyval.val, copies)}
+}
+
+/// A copy of a document's expressions and map, which `Parser::restore` puts back to undo any edits made after it was
+/// taken
+pub struct Snapshot<'a> {
+  exprs: Vec<NLExpression<'a>>,
+  map: HashMap<String, HashValue<'a>>,
+}
+
+impl<'a> Parser<'a> {
+  pub fn snapshot(self: &Parser<'a>) -> Snapshot<'a> {
+    // Values are edited in place, so the copy needs its own values, shared between its expressions and its map the
+    // same way the document's are
+    let mut copies: Copies<'a> = HashMap::new();
+    let exprs = self.root.borrow().exprs.iter().map(|nlexpr| {
+      let keyval = nlexpr.expr.keyval.as_ref().map(|keyval| copy_keyval(keyval, &mut copies));
+      NLExpression{nl: nlexpr.nl.clone(), expr: Expression::new(nlexpr.expr.ws.clone(), keyval,
+        nlexpr.expr.table.clone(), nlexpr.expr.comment.clone())}
+    }).collect();
+    let map = self.map.iter().map(|(key, hash_value)| {
+      let value = hash_value.value.as_ref().map(|val| match copies.get(&Rc::as_ptr(val)) {
+        Some(copy) => copy.clone(),
+        None => Rc::new(RefCell::new(val.borrow().clone())),
+      });
+      (key.clone(), HashValue{value, subkeys: hash_value.subkeys.clone()})
+    }).collect();
+    Snapshot{exprs, map}
+  }
+
+  pub fn restore(self: &mut Parser<'a>, snapshot: Snapshot<'a>) {
+    self.root.borrow_mut().exprs = snapshot.exprs;
+    self.map = snapshot.map;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use internals::parser::Parser;
+  use types::Value;
+
+  #[test]
+  fn test_restore() {
+    let p = Parser::new();
+    let doc = "a = [1, { b = 2 }] # c\n\n[t]\nx = 1\n\n[[f]]\nn = \"y\"\n";
+    let (mut p, _) = p.parse(doc);
+    let snapshot = p.snapshot();
+    p.set_value("a[1].b", Value::int(3)).unwrap();
+    p.set_value("t.x", Value::int(4)).unwrap();
+    p.remove_key("f").unwrap();
+    p.insert_value("t.y", Value::int(5)).unwrap();
+    p.restore(snapshot);
+    assert_eq!(doc, format!("{}", p));
+    assert_eq!(Some(Value::int(2)), p.get_value("a[1].b"));
+    assert_eq!(Some(Value::int(1)), p.get_value("t.x"));
+    assert_eq!(None, p.get_value("t.y"));
+    p.set_value("a[1].b", Value::int(6)).unwrap();
+    assert_eq!("a = [1, { b = 6 }] # c\n\n[t]\nx = 1\n\n[[f]]\nn = \"y\"\n", format!("{}", p));
+    assert_eq!(Some(Value::basic_string("y").unwrap()), p.get_value("f[0].n"));
+  }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::cell::RefCell;
+use internals::ast::structs::{Toml, Expression, KeyVal, TableType, Table, TOMLValue, CommentOrNewLines};
+use internals::parser::Parser;
+use types::{Value, Position};
+use key::normalize_key;
+
+/// Something encountered while walking a document in document order. Keys are full keys in the same form
+/// `Parser::get_value` takes.
+pub enum Event<'a> {
+  Table(String, Position),
+  ArrayTable(String, Position),
+  KeyVal(String, Rc<RefCell<TOMLValue<'a>>>, Position),
+  // A key-value pair inside of an inline table
+  TableKeyVal(String, Rc<RefCell<TOMLValue<'a>>>, Position),
+  ArrayElement(String, Rc<RefCell<TOMLValue<'a>>>, Position),
+  InlineTable(String, Rc<RefCell<TOMLValue<'a>>>, Position),
+  Comment(String, Position),
+}
+
+/// Converts an AST value into a `Value`
+pub fn to_value<'a>(tval: &TOMLValue<'a>) -> Value<'a> {
+  to_val!(tval)
+}
+
+/// Walks `root` in document order, calling `f` for every table header, key-value pair, array element, inline table
+/// and comment.
+pub fn walk<'a, F>(root: &Toml<'a>, mut f: F) where F: FnMut(Event<'a>) {
+  let mut walker = Walker{line: 1, column: 1, aot_counts: HashMap::new(), table: String::new()};
+  for nlexpr in &root.exprs {
+    walker.advance(&nlexpr.nl);
+    walker.expression(&nlexpr.expr, &mut f);
+  }
+}
+
+/// Joins a table key and a child key the same way the parser does
+pub fn child_key(table: &str, key: &str) -> String {
+  if table.is_empty() {
+    key.to_string()
+  } else {
+    format!("{}.{}", table, key)
+  }
+}
+
+struct Walker {
+  line: usize,
+  column: usize,
+  // Number of tables seen so far for each array of tables
+  aot_counts: HashMap<String, usize>,
+  // Full key of the current table
+  table: String,
+}
+
+impl Walker {
+  fn advance(&mut self, text: &str) {
+    for c in text.chars() {
+      if c == '\n' {
+        self.line += 1;
+        self.column = 1;
+      } else {
+        self.column += 1;
+      }
+    }
+  }
+
+  fn position(&self) -> Position {
+    Position{line: self.line, column: self.column}
+  }
+
+  fn header_key(&mut self, table: &Table, is_array: bool) -> String {
+    let mut key = String::new();
+    for (i, wskey) in table.keys.iter().enumerate() {
+      if i > 0 {
+        key.push('.');
+      }
+      key.push_str(&normalize_key(&wskey.key));
+      if is_array && i == table.keys.len() - 1 {
+        let count = self.aot_counts.entry(key.clone()).or_insert(0);
+        key = format!("{}[{}]", key, *count);
+        *count += 1;
+      } else if let Some(&count) = self.aot_counts.get(&key) {
+        key = format!("{}[{}]", key, count - 1);
+      }
+    }
+    key
+  }
+
+  fn expression<'a, F>(&mut self, expr: &Expression<'a>, f: &mut F) where F: FnMut(Event<'a>) {
+    self.advance(&expr.ws.ws1);
+    if let Some(ref tabletype) = expr.table {
+      let position = self.position();
+      match **tabletype {
+        TableType::Standard(ref table) => {
+          self.table = self.header_key(table, false);
+          f(Event::Table(self.table.clone(), position));
+        },
+        TableType::Array(ref table) => {
+          self.table = self.header_key(table, true);
+          f(Event::ArrayTable(self.table.clone(), position));
+        },
+      }
+      self.advance(&format!("{}", tabletype));
+      self.advance(&expr.ws.ws2);
+    } else if let Some(ref keyval) = expr.keyval {
+      let key = child_key(&self.table, &normalize_key(&keyval.key));
+      f(Event::KeyVal(key.clone(), keyval.val.clone(), self.position()));
+      self.keyval(&key, keyval, f);
+      self.advance(&expr.ws.ws2);
+    }
+    if let Some(ref comment) = expr.comment {
+      f(Event::Comment(comment.text.to_string(), self.position()));
+      self.advance(&format!("{}", comment));
+    }
+  }
+
+  fn keyval<'a, F>(&mut self, key: &str, keyval: &KeyVal<'a>, f: &mut F) where F: FnMut(Event<'a>) {
+    self.advance(&keyval.key);
+    self.advance(&keyval.keyval_sep.ws1);
+    self.advance("=");
+    self.advance(&keyval.keyval_sep.ws2);
+    self.value(key, &keyval.val, f);
+  }
+
+  fn value<'a, F>(&mut self, key: &str, val: &Rc<RefCell<TOMLValue<'a>>>, f: &mut F) where F: FnMut(Event<'a>) {
+    match *val.borrow() {
+      TOMLValue::Array(ref arr) => {
+        let arr = arr.borrow();
+        self.advance("[");
+        self.trivia(&arr.comment_nls1, f);
+        for (i, array_value) in arr.values.iter().enumerate() {
+          let element_key = format!("{}[{}]", key, i);
+          f(Event::ArrayElement(element_key.clone(), array_value.val.clone(), self.position()));
+          self.value(&element_key, &array_value.val, f);
+          if let Some(ref sep) = array_value.array_sep {
+            self.advance(&sep.ws1);
+            self.advance(",");
+            self.advance(&sep.ws2);
+          }
+          self.trivia(&array_value.comment_nls, f);
+        }
+        self.trivia(&arr.comment_nls2, f);
+        self.advance("]");
+      },
+      TOMLValue::InlineTable(ref it) => {
+        f(Event::InlineTable(key.to_string(), val.clone(), self.position()));
+        let it = it.borrow();
+        self.advance("{");
+        self.advance(&it.ws.ws1);
+        for table_keyval in &it.keyvals {
+          let subkey = child_key(key, &normalize_key(&table_keyval.keyval.key));
+          f(Event::TableKeyVal(subkey.clone(), table_keyval.keyval.val.clone(), self.position()));
+          self.keyval(&subkey, &table_keyval.keyval, f);
+          if let Some(ref sep) = table_keyval.kv_sep {
+            self.advance(&sep.ws1);
+            self.advance(",");
+            self.advance(&sep.ws2);
+          }
+          self.trivia(&table_keyval.comment_nls, f);
+        }
+        self.advance(&it.ws.ws2);
+        self.advance("}");
+      },
+      ref scalar => self.advance(&format!("{}", scalar)),
+    }
+  }
+
+  fn trivia<'a, F>(&mut self, comment_nls: &[CommentOrNewLines<'a>], f: &mut F) where F: FnMut(Event<'a>) {
+    for comment_nl in comment_nls {
+      match *comment_nl {
+        CommentOrNewLines::NewLines(ref nl) => self.advance(nl),
+        CommentOrNewLines::Comment(ref c) => {
+          self.advance(&c.pre_ws_nl);
+          f(Event::Comment(c.comment.text.to_string(), self.position()));
+          self.advance(&format!("{}", c.comment));
+          self.advance(&c.newlines);
+        },
+      }
+    }
+  }
+}
@@ -0,0 +1,150 @@
+//! Iterators over the keys and values of a parsed TOML document.
+//!
+//! All of the iterators yield full keys that can be passed straight to `TOMLParser::get_value` and
+//! `TOMLParser::set_value`. Keys and values are collected when the iterator is created, so the document can be
+//! modified while iterating.
+
+use std::vec;
+use internals::parser::Parser;
+use internals::walker::{self, Event};
+use types::Value;
+
+/// An iterator over full keys, created by `TOMLParser::keys`.
+pub struct Keys {
+  keys: vec::IntoIter<String>,
+}
+
+/// An iterator over `(full_key, Value)` pairs, created by `TOMLParser::entries`, `TOMLParser::walk` and
+/// `TOMLParser::table_entries`.
+pub struct Entries<'a> {
+  entries: vec::IntoIter<(String, Value<'a>)>,
+}
+
+impl Iterator for Keys {
+  type Item = String;
+
+  fn next(&mut self) -> Option<String> {
+    self.keys.next()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.keys.size_hint()
+  }
+}
+
+impl<'a> Iterator for Entries<'a> {
+  type Item = (String, Value<'a>);
+
+  fn next(&mut self) -> Option<(String, Value<'a>)> {
+    self.entries.next()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.entries.size_hint()
+  }
+}
+
+pub(crate) fn keys(parser: &Parser) -> Keys {
+  let mut keys = vec![];
+  walker::walk(&parser.root.borrow(), |event| {
+    if let Event::KeyVal(key, _, _) = event {
+      keys.push(key);
+    }
+  });
+  Keys{keys: keys.into_iter()}
+}
+
+pub(crate) fn entries<'a>(parser: &Parser<'a>) -> Entries<'a> {
+  let mut entries = vec![];
+  walker::walk(&parser.root.borrow(), |event| {
+    if let Event::KeyVal(key, val, _) = event {
+      entries.push((key, walker::to_value(&val.borrow())));
+    }
+  });
+  Entries{entries: entries.into_iter()}
+}
+
+pub(crate) fn walk<'a>(parser: &Parser<'a>) -> Entries<'a> {
+  let mut entries = vec![];
+  walker::walk(&parser.root.borrow(), |event| {
+    match event {
+      Event::KeyVal(key, val, _) | Event::TableKeyVal(key, val, _) | Event::ArrayElement(key, val, _) =>
+        entries.push((key, walker::to_value(&val.borrow()))),
+      _ => (),
+    }
+  });
+  Entries{entries: entries.into_iter()}
+}
+
+pub(crate) fn table_entries<'a>(parser: &Parser<'a>, table_key: &str) -> Entries<'a> {
+  let child_keys = match parser.get_children(table_key) {
+    Some(children) => children.combine_child_keys(table_key),
+    None => vec![],
+  };
+  let mut entries = vec![];
+  for key in child_keys {
+    // Tables and arrays of tables don't have values, so they're skipped
+    if let Some(value) = parser.get_value(key.clone()) {
+      entries.push((key, value));
+    }
+  }
+  Entries{entries: entries.into_iter()}
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use types::{Value, StrType};
+
+  const DOC: &str = r#"title = "iter"
+[a.b.c]
+d = 1
+[[fruit]]
+name = "apple"
+variety = [{ name = "red" }, { name = "green" }]
+[[fruit]]
+name = "banana"
+"#;
+
+  #[test]
+  fn test_keys() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    let keys: Vec<String> = parser.keys().collect();
+    assert_eq!(vec!["title", "a.b.c.d", "fruit[0].name", "fruit[0].variety", "fruit[1].name"], keys);
+  }
+
+  #[test]
+  fn test_entries() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    let entries: Vec<(String, Value)> = parser.entries().collect();
+    assert_eq!(5, entries.len());
+    assert_eq!(("a.b.c.d".to_string(), Value::int(1)), entries[1]);
+    assert_eq!(("fruit[1].name".to_string(), Value::String("banana".into(), StrType::Basic)), entries[4]);
+  }
+
+  #[test]
+  fn test_walk() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    let keys: Vec<String> = parser.walk().map(|(key, _)| key).collect();
+    assert_eq!(vec!["title", "a.b.c.d", "fruit[0].name", "fruit[0].variety", "fruit[0].variety[0]",
+      "fruit[0].variety[0].name", "fruit[0].variety[1]", "fruit[0].variety[1].name", "fruit[1].name"], keys);
+  }
+
+  #[test]
+  fn test_table_entries() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    let root: Vec<String> = parser.table_entries("").map(|(key, _)| key).collect();
+    assert_eq!(vec!["title"], root);
+    assert_eq!(0, parser.table_entries("a.b").count());
+    let fruit: Vec<String> = parser.table_entries("fruit[0]").map(|(key, _)| key).collect();
+    assert_eq!(vec!["fruit[0].name", "fruit[0].variety"], fruit);
+    let variety: Vec<(String, Value)> = parser.table_entries("fruit[0].variety[1]").collect();
+    assert_eq!(vec![("fruit[0].variety[1].name".to_string(), Value::String("green".into(), StrType::Basic))],
+      variety);
+    assert_eq!(0, parser.table_entries("missing").count());
+  }
+}
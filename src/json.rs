@@ -0,0 +1,373 @@
+//! Conversion of documents to and from JSON.
+//!
+//! `TOMLParser::to_json` maps a document onto JSON the natural way: tables and inline tables become objects, arrays
+//! of tables become arrays of objects, integers and floats become numbers without underscores or plus signs and
+//! datetimes become strings. `TOMLParser::to_tagged_json` uses the encoding of the
+//! [toml-test](https://github.com/toml-lang/toml-test) compliance suite instead, where every value is an object with
+//! its type and its text, like `{"type": "integer", "value": "42"}`, so nothing about a value's type is lost.
+//!
+//! `tomlkit toml-test decode` reads a document on standard input and writes its tagged JSON on standard output, as
+//! the decoder the suite runs expects, so the suite can be run against this parser with
+//! `toml-test tomlkit -- toml-test decode`.
+//!
+//! `TOMLParser::from_json` goes the other way. Objects become tables, arrays of objects become arrays of tables, and
+//! objects that only hold other values become inline tables if they have fewer keys than `Layout::inline_limit`.
+//! Keys are only quoted if they have to be, and strings are written as basic strings. Numbers with a fraction or an
+//! exponent become floats and other numbers integers. `null`, arrays that mix types, which TOML 0.4 doesn't allow,
+//! and integers that don't fit in 64 bits are reported as errors with the key they were found at.
+//! `TOMLParser::from_tagged_json` reads the encoding of toml-test, and `tomlkit toml-test encode` uses it to run as the
+//! encoder the suite expects.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//!
+//! let (parser, _) = TOMLParser::new().parse("title = \"app\"\n\n[server]\nport = 8_080\nhosts = [\"a\", \"b\"]\n\n\
+//!   [[users]]\nname = \"a\"\nsince = 2016-01-01\n");
+//! assert_eq!("{\n  \"title\": \"app\",\n  \"server\": {\n    \"port\": 8080,\n    \"hosts\": [\"a\", \"b\"]\n  },\n  \
+//!   \"users\": [\n    {\n      \"name\": \"a\",\n      \"since\": \"2016-01-01\"\n    }\n  ]\n}", parser.to_json());
+//! assert_eq!("{\"title\": {\"type\": \"string\", \"value\": \"app\"}}",
+//!   TOMLParser::new().parse("title = 'app'").0.to_tagged_json());
+//!
+//! let parser = TOMLParser::from_json(r#"{"server": {"host name": "a", "ports": [80, 443], "tls": {"cert": "c"}},
+//!   "users": [{"name": "a"}]}"#, &Default::default()).unwrap();
+//! assert_eq!("[server]\n\"host name\" = \"a\"\nports = [80, 443]\ntls = { cert = \"c\" }\n\n[[users]]\n\
+//!   name = \"a\"\n", parser.to_string());
+//! ```
+
+use std::borrow::Cow;
+use std::rc::Rc;
+use regex::Regex;
+use TOMLParser;
+use node::Node;
+use diff::clean_number;
+use key::{key_name, quote_key, escape_basic};
+use schema::Type;
+use types::{Value, StrType, DateTime, Date, Time, TimeOffset, TimeOffsetAmount, ConvertError};
+use internals::json::{self, Json};
+use internals::walker::child_key;
+
+/// How `TOMLParser::from_json` lays out the document it builds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+  /// Objects that don't hold any objects or arrays of objects become inline tables if they have fewer keys than this,
+  /// otherwise they become standard tables. `0` makes every object a standard table. The default is `4`.
+  pub inline_limit: usize,
+}
+
+impl Default for Layout {
+  fn default() -> Layout {
+    Layout{inline_limit: 4}
+  }
+}
+
+// Converts a node to plain JSON
+pub(crate) fn to_json(node: &Node) -> Json {
+  match *node {
+    Node::Table(ref entries) =>
+      Json::Object(entries.iter().map(|entry| (key_name(&entry.0), to_json(&entry.1))).collect()),
+    Node::ArrayOfTables(ref tables) => Json::Array(tables.iter().map(to_json).collect()),
+    Node::Value(ref val) => match *val {
+      Value::Integer(ref n) | Value::Float(ref n) => Json::Number(clean_number(n)),
+      Value::Boolean(b) => Json::Bool(b),
+      Value::DateTime(ref dt) => Json::String(dt.to_string()),
+      Value::String(_, _) => Json::String(val.string_content().unwrap_or_default()),
+      Value::Array(ref values) => Json::Array(values.iter().map(|val| to_json(&Node::Value(val.clone()))).collect()),
+      Value::InlineTable(ref pairs) =>
+        Json::Object(pairs.iter().map(|pair| (key_name(&pair.0), to_json(&Node::Value(pair.1.clone())))).collect()),
+    },
+  }
+}
+
+// The toml-test type of a datetime, which depends on whether it has a time and an offset
+fn datetime_type(dt: &DateTime) -> &'static str {
+  match dt.time {
+    Some(ref time) if time.offset.is_some() => "datetime",
+    Some(_) => "datetime-local",
+    None => "date-local",
+  }
+}
+
+fn tagged(kind: &str, value: String) -> Json {
+  Json::Object(vec![("type".to_string(), Json::String(kind.to_string())), ("value".to_string(), Json::String(value))])
+}
+
+// Converts a node to the JSON encoding of toml-test. Tables and arrays are plain objects and arrays, and every other
+// value is tagged with its type.
+pub(crate) fn to_tagged_json(node: &Node) -> Json {
+  match *node {
+    Node::Table(ref entries) =>
+      Json::Object(entries.iter().map(|entry| (key_name(&entry.0), to_tagged_json(&entry.1))).collect()),
+    Node::ArrayOfTables(ref tables) => Json::Array(tables.iter().map(to_tagged_json).collect()),
+    Node::Value(ref val) => match *val {
+      Value::Integer(ref n) => tagged("integer", clean_number(n)),
+      Value::Float(ref n) => tagged("float", clean_number(n)),
+      Value::Boolean(b) => tagged("bool", b.to_string()),
+      Value::DateTime(ref dt) => tagged(datetime_type(dt), dt.to_string()),
+      Value::String(_, _) => tagged("string", val.string_content().unwrap_or_default()),
+      Value::Array(ref values) =>
+        Json::Array(values.iter().map(|val| to_tagged_json(&Node::Value(val.clone()))).collect()),
+      Value::InlineTable(ref pairs) => Json::Object(pairs.iter()
+        .map(|pair| (key_name(&pair.0), to_tagged_json(&Node::Value(pair.1.clone())))).collect()),
+    },
+  }
+}
+
+// Converts JSON, plain or in the encoding of toml-test, to nodes
+struct Importer<'l> {
+  layout: &'l Layout,
+  tagged: bool,
+}
+
+// The error for a value at `key` that can't be converted, used by every converter
+pub(crate) fn unrepresentable(key: &str, reason: &str) -> ConvertError {
+  ConvertError::Unrepresentable(key.to_string(), reason.to_string())
+}
+
+fn contains_object(json: &Json) -> bool {
+  match *json {
+    Json::Object(_) => true,
+    Json::Array(ref values) => values.iter().any(contains_object),
+    _ => false,
+  }
+}
+
+impl<'l> Importer<'l> {
+  // Returns the type and the text of a tagged value
+  fn tag<'j>(&self, json: &'j Json) -> Option<(&'j str, &'j str)> {
+    match *json {
+      Json::Object(ref members) if self.tagged && members.len() == 2 =>
+        Some((json.get("type")?.as_str()?, json.get("value")?.as_str()?)),
+      _ => None,
+    }
+  }
+
+  fn is_table(&self, json: &Json) -> bool {
+    matches!(*json, Json::Object(_)) && self.tag(json).is_none()
+  }
+
+  fn table(&self, key: &str, members: &[(String, Json)]) -> Result<Node<'static>, ConvertError> {
+    let mut entries = vec![];
+    for (name, json) in members {
+      let name = quote_key(name);
+      let entry = self.entry(&child_key(key, &name), json)?;
+      entries.push((name, entry));
+    }
+    Ok(Node::Table(entries))
+  }
+
+  // Converts the JSON at a key of a table, which can be a table or an array of tables rather than a value
+  fn entry(&self, key: &str, json: &Json) -> Result<Node<'static>, ConvertError> {
+    match *json {
+      Json::Object(ref members) if self.is_table(json) && (members.len() >= self.layout.inline_limit ||
+        members.iter().any(|member| self.tag(&member.1).is_none() && contains_object(&member.1))) =>
+        self.table(key, members),
+      Json::Array(ref values) if !values.is_empty() && values.iter().all(|val| self.is_table(val)) => {
+        let mut tables = vec![];
+        for (i, val) in values.iter().enumerate() {
+          if let Json::Object(ref members) = *val {
+            tables.push(self.table(&format!("{}[{}]", key, i), members)?);
+          }
+        }
+        Ok(Node::ArrayOfTables(tables))
+      },
+      _ => Ok(Node::Value(self.value(key, json)?)),
+    }
+  }
+
+  fn value(&self, key: &str, json: &Json) -> Result<Value<'static>, ConvertError> {
+    if let Some((kind, text)) = self.tag(json) {
+      return tagged_value(key, kind, text);
+    }
+    match *json {
+      Json::Null => Err(unrepresentable(key, "TOML doesn't have null")),
+      Json::Bool(b) => Ok(Value::Boolean(b)),
+      Json::Number(ref n) if n.contains(['.', 'e', 'E']) => Ok(Value::Float(Cow::Owned(n.clone()))),
+      Json::Number(ref n) => match n.parse::<i64>() {
+        Ok(_) => Ok(Value::Integer(Cow::Owned(n.clone()))),
+        Err(_) => Err(unrepresentable(key, "the integer doesn't fit in 64 bits")),
+      },
+      Json::String(ref s) => Ok(Value::String(Cow::Owned(escape_basic(s)), StrType::Basic)),
+      Json::Array(ref values) => {
+        let mut array: Vec<Value> = vec![];
+        for (i, val) in values.iter().enumerate() {
+          let element_key = format!("{}[{}]", key, i);
+          let element = self.value(&element_key, val)?;
+          if let Some(first) = array.first() {
+            let (a, b) = (Type::of(&Node::Value(first.clone())), Type::of(&Node::Value(element.clone())));
+            if a != b {
+              return Err(unrepresentable(&element_key, &format!("TOML 0.4 doesn't allow arrays of mixed types, \
+                found {} after {}", b, a)));
+            }
+          }
+          array.push(element);
+        }
+        Ok(Value::Array(Rc::new(array)))
+      },
+      Json::Object(ref members) => {
+        let mut pairs = vec![];
+        for (name, val) in members {
+          let name = quote_key(name);
+          let val = self.value(&child_key(key, &name), val)?;
+          pairs.push((Cow::Owned(name), val));
+        }
+        Ok(Value::InlineTable(Rc::new(pairs)))
+      },
+    }
+  }
+}
+
+// Copies a datetime so it doesn't borrow the text it was parsed from
+pub(crate) fn owned_datetime(dt: &DateTime) -> DateTime<'static> {
+  let own = |s: &Cow<str>| -> Cow<'static, str> { Cow::Owned(s.to_string()) };
+  let date = Date{year: own(&dt.date.year), month: own(&dt.date.month), day: own(&dt.date.day)};
+  let time = dt.time.as_ref().map(|time| Time{
+    hour: own(&time.hour),
+    minute: own(&time.minute),
+    second: own(&time.second),
+    fraction: time.fraction.as_ref().map(own),
+    offset: time.offset.as_ref().map(|offset| match *offset {
+      TimeOffset::Zulu => TimeOffset::Zulu,
+      TimeOffset::Time(ref amount) => TimeOffset::Time(TimeOffsetAmount{
+        pos_neg: amount.pos_neg,
+        hour: own(&amount.hour),
+        minute: own(&amount.minute),
+      }),
+    }),
+  });
+  DateTime::new(date, time)
+}
+
+// Copies a value so it doesn't borrow the text it was parsed from
+pub(crate) fn owned_value(val: &Value) -> Value<'static> {
+  let own = |s: &Cow<str>| -> Cow<'static, str> { Cow::Owned(s.to_string()) };
+  match *val {
+    Value::Integer(ref n) => Value::Integer(own(n)),
+    Value::Float(ref n) => Value::Float(own(n)),
+    Value::Boolean(b) => Value::Boolean(b),
+    Value::DateTime(ref dt) => Value::DateTime(owned_datetime(dt)),
+    Value::String(ref s, kind) => Value::String(own(s), kind),
+    Value::Array(ref values) => Value::Array(Rc::new(values.iter().map(owned_value).collect())),
+    Value::InlineTable(ref pairs) =>
+      Value::InlineTable(Rc::new(pairs.iter().map(|(name, val)| (own(name), owned_value(val))).collect())),
+  }
+}
+
+// Converts a value in the encoding of toml-test, checking that its text is valid TOML 0.4 for its type
+fn tagged_value(key: &str, kind: &str, text: &str) -> Result<Value<'static>, ConvertError> {
+  let invalid = || unrepresentable(key, &format!("\"{}\" isn't a valid TOML 0.4 {}", text, kind));
+  let digits = "(0|[1-9](_?[0-9])*)";
+  let matches = |pattern: &str| Regex::new(pattern).is_ok_and(|regex| regex.is_match(text));
+  match kind {
+    "string" => Ok(Value::String(Cow::Owned(escape_basic(text)), StrType::Basic)),
+    "integer" if matches(&format!("^[+-]?{}$", digits)) && text.replace('_', "").parse::<i64>().is_ok() =>
+      Ok(Value::Integer(Cow::Owned(text.to_string()))),
+    "float" if matches(&format!("^[+-]?{}(\\.[0-9](_?[0-9])*)?([eE][+-]?[0-9](_?[0-9])*)?$", digits)) &&
+      text.contains(['.', 'e', 'E']) => Ok(Value::Float(Cow::Owned(text.to_string()))),
+    "bool" if text == "true" || text == "false" => Ok(Value::Boolean(text == "true")),
+    "datetime" | "datetime-local" | "date-local" => match Value::datetime_parse(text) {
+      Ok(Value::DateTime(ref dt)) if datetime_type(dt) == kind => Ok(Value::DateTime(owned_datetime(dt))),
+      _ => Err(invalid()),
+    },
+    "integer" | "float" | "bool" | "time-local" => Err(invalid()),
+    _ => Err(ConvertError::Parse(format!("\"{}\" has an unknown type \"{}\"", key, kind))),
+  }
+}
+
+// Builds a document from JSON, plain or in the encoding of toml-test
+pub(crate) fn import<'a>(text: &str, layout: &Layout, tagged: bool) -> Result<TOMLParser<'a>, ConvertError> {
+  let importer = Importer{layout, tagged};
+  let root = match json::parse(text).map_err(ConvertError::Parse)? {
+    Json::Object(ref members) => importer.table("", members)?,
+    _ => return Err(ConvertError::Parse("the document must be a JSON object".to_string())),
+  };
+  let (mut parser, _) = TOMLParser::new().parse("");
+  parser.graft_node("", &root).map_err(|err| ConvertError::Unrepresentable(String::new(), err.to_string()))?;
+  Ok(parser)
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use json::Layout;
+  use types::ConvertError;
+
+  #[test]
+  fn test_to_json() {
+    let (parser, _) = TOMLParser::new().parse("\"a.b\" = +1_000\nc = -1.5e+3\nd = true\ne = 'C:\\path'\n\
+      f = [[1, 2], [\"x\"]]\ng = { \"h i\" = 1979-05-27T07:32:00.5-07:00 }\n");
+    assert_eq!("{\n  \"a.b\": 1000,\n  \"c\": -1.5e+3,\n  \"d\": true,\n  \"e\": \"C:\\\\path\",\n  \"f\": [\n    \
+      [1, 2],\n    [\"x\"]\n  ],\n  \"g\": {\n    \"h i\": \"1979-05-27T07:32:00.5-07:00\"\n  }\n}", parser.to_json());
+    assert_eq!("{}", TOMLParser::new().parse("").0.to_json());
+  }
+
+  #[test]
+  fn test_to_tagged_json() {
+    let (parser, _) = TOMLParser::new().parse("a = 1_0\nb = 2.5\nc = false\nd = \"\\u00E9\\n\"\n\
+      e = 1979-05-27T07:32:00Z\nf = 1979-05-27T07:32:00\ng = 1979-05-27\nh = [[1], [\"x\"]]\n\n[[i]]\nj = {}\n");
+    assert_eq!("{\"a\": {\"type\": \"integer\", \"value\": \"10\"}, \"b\": {\"type\": \"float\", \"value\": \"2.5\"}, \
+      \"c\": {\"type\": \"bool\", \"value\": \"false\"}, \"d\": {\"type\": \"string\", \"value\": \"\u{e9}\\n\"}, \
+      \"e\": {\"type\": \"datetime\", \"value\": \"1979-05-27T07:32:00Z\"}, \
+      \"f\": {\"type\": \"datetime-local\", \"value\": \"1979-05-27T07:32:00\"}, \
+      \"g\": {\"type\": \"date-local\", \"value\": \"1979-05-27\"}, \
+      \"h\": [[{\"type\": \"integer\", \"value\": \"1\"}], [{\"type\": \"string\", \"value\": \"x\"}]], \
+      \"i\": [{\"j\": {}}]}", parser.to_tagged_json());
+  }
+
+  #[test]
+  fn test_from_json() {
+    let text = r#"{"title": "a \"b\"\n", "n": [1.5, 2.5e3], "empty": {}, "point": {"x": 1, "y": [2]},
+      "big": {"a": 1, "b": 2, "c": 3, "d": 4}, "owner": {"tags": [{"id": "t"}]},
+      "servers": [{"name": "a", "env": {"K": "v"}}, {"name": "b", "hosts": [{"ip": "10.0.0.1"}]}],
+      "nested": [[{"a": 1}], []]}"#;
+    let parser = TOMLParser::from_json(text, &Layout::default()).unwrap();
+    assert_eq!("title = \"a \\\"b\\\"\\n\"\nn = [1.5, 2.5e3]\nempty = {  }\npoint = { x = 1, y = [2] }\n\
+      nested = [[{ a = 1 }], []]\n\n[big]\na = 1\nb = 2\nc = 3\nd = 4\n\n[owner]\n\n[[owner.tags]]\nid = \"t\"\n\n\
+      [[servers]]\nname = \"a\"\nenv = { K = \"v\" }\n\n[[servers]]\nname = \"b\"\n\n[[servers.hosts]]\n\
+      ip = \"10.0.0.1\"\n", parser.to_string());
+    let parser = TOMLParser::from_json(r#"{"a": {"b": {}}}"#, &Layout{inline_limit: 0}).unwrap();
+    assert_eq!("[a]\n\n[a.b]\n", parser.to_string());
+    assert_eq!(r#"{"a": {"b": {}}}"#, TOMLParser::from_json(r#"{"a": {"b": {}}}"#, &Layout::default()).unwrap()
+      .to_tagged_json());
+  }
+
+  #[test]
+  fn test_from_json_errors() {
+    let error = |text: &str| TOMLParser::from_json(text, &Layout::default()).map(|_| ()).unwrap_err();
+    assert_eq!(ConvertError::Unrepresentable("a.\"b c\"[1]".to_string(), "TOML doesn't have null".to_string()),
+      error(r#"{"a": {"b c": [1, null]}}"#));
+    assert_eq!("Can't convert a.\"b c\"[1]: TOML doesn't have null.",
+      error(r#"{"a": {"b c": [1, null]}}"#).to_string());
+    assert_eq!(ConvertError::Unrepresentable("a[1]".to_string(), "TOML 0.4 doesn't allow arrays of mixed types, \
+      found a float after an integer".to_string()), error(r#"{"a": [1, 1.5]}"#));
+    assert_eq!(ConvertError::Unrepresentable("a[1]".to_string(), "TOML 0.4 doesn't allow arrays of mixed types, \
+      found a string after a table".to_string()), error(r#"{"a": [{}, "b"]}"#));
+    assert_eq!(ConvertError::Unrepresentable("a".to_string(), "the integer doesn't fit in 64 bits".to_string()),
+      error(r#"{"a": 9223372036854775808}"#));
+    assert_eq!(ConvertError::Parse("the document must be a JSON object".to_string()), error("[1]"));
+    assert!(match error("{\"a\": }") {
+      ConvertError::Parse(ref reason) => reason.starts_with("Expected"),
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn test_from_tagged_json() {
+    let doc = "a = 1_0\nb = -2.5e+3\nc = false\nd = \"\\u00E9\\n\"\ne = 1979-05-27T07:32:00.5+07:00\n\
+      f = 1979-05-27T07:32:00\ng = 1979-05-27\nh = [[1], [\"x\"]]\n\n[[i]]\nj = {}\n";
+    let tagged = TOMLParser::new().parse(doc).0.to_tagged_json();
+    let parser = TOMLParser::from_tagged_json(&tagged).unwrap();
+    assert_eq!(tagged, parser.to_tagged_json());
+    let error = |text: &str| TOMLParser::from_tagged_json(text).map(|_| ()).unwrap_err();
+    assert_eq!(ConvertError::Unrepresentable("a".to_string(), "\"inf\" isn't a valid TOML 0.4 float".to_string()),
+      error(r#"{"a": {"type": "float", "value": "inf"}}"#));
+    assert_eq!(ConvertError::Unrepresentable("a".to_string(), "\"01\" isn't a valid TOML 0.4 integer".to_string()),
+      error(r#"{"a": {"type": "integer", "value": "01"}}"#));
+    assert_eq!(ConvertError::Unrepresentable("a".to_string(),
+      "\"1979-05-27\" isn't a valid TOML 0.4 datetime".to_string()),
+      error(r#"{"a": {"type": "datetime", "value": "1979-05-27"}}"#));
+    assert_eq!(ConvertError::Parse("\"a\" has an unknown type \"int\"".to_string()),
+      error(r#"{"a": {"type": "int", "value": "1"}}"#));
+  }
+}
@@ -0,0 +1,725 @@
+//! Validation of documents against JSON Schemas.
+//!
+//! Documents are checked as if they were JSON: tables and inline tables are objects, arrays of tables are arrays of
+//! objects and datetimes are strings, which match `"format": "date-time"` if they have a time and `"format": "date"`
+//! if they don't. Integers and floats are both numbers, and floats without a fraction are integers too.
+//!
+//! The validation keywords of draft-07 and 2020-12 are supported: `type`, `enum`, `const`, the number, string, array
+//! and object keywords, `allOf`, `anyOf`, `oneOf`, `not`, `if`, `then` and `else`, both forms of `items` as well as
+//! `prefixItems` and `additionalItems`, and `dependencies` as well as `dependentRequired` and `dependentSchemas`.
+//! `$ref` can point to any part of the schema with a JSON pointer like `#/definitions/port` or `#/$defs/port`, but
+//! not to other files. `format` only checks `date-time` and `date`, other keywords like `unevaluatedProperties` and
+//! annotations are ignored.
+//!
+//! Every violation has the JSON pointer of the value, its key in the document, the line the key is on, or for missing
+//! keys the line of the closest table that exists, and the JSON pointer of the keyword in the schema.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::json_schema::JsonSchema;
+//!
+//! let schema = JsonSchema::parse(r##"{
+//!   "$schema": "http://json-schema.org/draft-07/schema#",
+//!   "type": "object",
+//!   "required": ["name"],
+//!   "properties": {
+//!     "servers": {"type": "array", "items": {"$ref": "#/definitions/server"}},
+//!     "released": {"type": "string", "format": "date-time"}
+//!   },
+//!   "definitions": {
+//!     "server": {
+//!       "type": "object",
+//!       "properties": {"port": {"type": "integer", "maximum": 65535}},
+//!       "additionalProperties": false
+//!     }
+//!   }
+//! }"##).unwrap();
+//! let (parser, _) = TOMLParser::new().parse("released = 2016-01-01T10:00:00Z\n\n[[servers]]\nport = 80\n\n\
+//!   [[servers]]\nport = 70000\nhost = \"b\"\n");
+//! let violations = parser.validate_json_schema(&schema);
+//! assert_eq!(3, violations.len());
+//! assert_eq!("name: missing required key", violations[0].to_string());
+//! assert_eq!("/servers/1/port", violations[1].pointer);
+//! assert_eq!("servers[1].port", violations[1].key.to_string());
+//! assert_eq!(Some(7), violations[1].line);
+//! assert_eq!("/properties/servers/items/$ref/properties/port/maximum", violations[1].schema_pointer);
+//! assert_eq!("servers[1].port (line 7, /servers/1/port): 70000 is greater than the maximum 65535",
+//!   violations[1].to_string());
+//! assert_eq!("servers[1].host (line 8, /servers/1/host): unknown key", violations[2].to_string());
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use regex::Regex;
+use node::{self, Node, entries};
+use key::{KeyPath, quote_key, key_name};
+use schema::{Type, key_lines, line_of};
+use internals::parser::Parser;
+use internals::json::{self, Json};
+use json::to_json;
+use diff::clean_number;
+use internals::walker::child_key;
+use types::{Value, StrType, SchemaError};
+
+/// A JSON Schema to validate documents against with `TOMLParser::validate_json_schema`.
+#[derive(Debug, Clone)]
+pub struct JsonSchema {
+  root: Json,
+}
+
+impl JsonSchema {
+  /// Reads a JSON Schema from the text of a schema document. Returns an error if it isn't valid JSON, or isn't an
+  /// object or a boolean.
+  pub fn parse(text: &str) -> Result<JsonSchema, SchemaError> {
+    match json::parse(text).map_err(SchemaError::Parse)? {
+      root @ Json::Object(_) | root @ Json::Bool(_) => Ok(JsonSchema{root}),
+      _ => Err(SchemaError::Parse("a schema must be an object or a boolean".to_string())),
+    }
+  }
+
+  /// Reads a JSON Schema from the file at `path`.
+  pub fn from_file<P>(path: P) -> Result<JsonSchema, SchemaError> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let mut text = String::new();
+    File::open(path).and_then(|mut file| file.read_to_string(&mut text))
+      .map_err(|err| SchemaError::Read(path.display().to_string(), err.to_string()))?;
+    JsonSchema::parse(&text)
+  }
+}
+
+/// A violation of a JSON Schema, returned by `TOMLParser::validate_json_schema`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct JsonViolation {
+  /// The JSON pointer of the value, like `/servers/1/port`. The whole document is `""`.
+  pub pointer: String,
+  /// The full key of the value.
+  pub key: KeyPath,
+  /// The line of the key, or for missing keys, of the closest table that exists. `None` for the whole document and
+  /// missing keys of the top level table.
+  pub line: Option<usize>,
+  /// The JSON pointer of the keyword in the schema that the value doesn't match, including any `$ref`s followed to
+  /// reach it.
+  pub schema_pointer: String,
+  /// What's wrong with the value.
+  pub message: String,
+}
+
+impl Display for JsonViolation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.key.is_empty() {
+      return write!(f, "document: {}", self.message);
+    }
+    match self.line {
+      Some(line) => write!(f, "{} (line {}, {}): {}", self.key, line, self.pointer, self.message),
+      None => write!(f, "{}: {}", self.key, self.message),
+    }
+  }
+}
+
+// Escapes a key for a JSON pointer
+fn escape(name: &str) -> String {
+  name.replace('~', "~0").replace('/', "~1")
+}
+
+// Finds the part of `root` that a `$ref` like `#/definitions/a` points to
+fn resolve<'s>(root: &'s Json, reference: &str) -> Option<&'s Json> {
+  let pointer = reference.strip_prefix('#')?;
+  if pointer.is_empty() {
+    return Some(root);
+  }
+  let mut target = root;
+  for token in pointer.strip_prefix('/')?.split('/') {
+    let token = token.replace("~1", "/").replace("~0", "~");
+    target = match *target {
+      Json::Object(_) => target.get(&token)?,
+      Json::Array(ref values) => values.get(token.parse::<usize>().ok()?)?,
+      _ => return None,
+    };
+  }
+  Some(target)
+}
+
+fn number(json: &Json) -> Option<f64> {
+  match *json {
+    Json::Number(ref n) => n.parse::<f64>().ok(),
+    _ => None,
+  }
+}
+
+// Compares JSON values the way JSON Schema does, numbers by value and objects without regard to order
+fn same(a: &Json, b: &Json) -> bool {
+  match (a, b) {
+    (Json::Number(_), Json::Number(_)) => number(a) == number(b),
+    (Json::Array(a), Json::Array(b)) =>
+      a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same(a, b)),
+    (Json::Object(a), Json::Object(b)) => a.len() == b.len() && a.iter().all(|member| {
+      b.iter().any(|other| other.0 == member.0 && same(&member.1, &other.1))
+    }),
+    _ => a == b,
+  }
+}
+
+fn has_type(node: &Node, name: &str) -> bool {
+  match (Type::of(node), name) {
+    (Type::Table, "object") | (Type::Array, "array") | (Type::ArrayOfTables, "array") | (Type::String, "string") |
+      (Type::DateTime, "string") | (Type::Integer, "number") | (Type::Float, "number") | (Type::Integer, "integer") |
+      (Type::Boolean, "boolean") => true,
+    (Type::Float, "integer") => node_number(node).is_some_and(|n| n.fract() == 0.0),
+    _ => false,
+  }
+}
+
+fn type_name(name: &str) -> String {
+  match name {
+    "object" | "array" | "integer" => format!("an {}", name),
+    "null" => name.to_string(),
+    _ => format!("a {}", name),
+  }
+}
+
+fn node_number(node: &Node) -> Option<f64> {
+  match *node {
+    Node::Value(Value::Integer(ref n)) | Node::Value(Value::Float(ref n)) => clean_number(n).parse::<f64>().ok(),
+    _ => None,
+  }
+}
+
+fn node_string(node: &Node) -> Option<String> {
+  match *node {
+    Node::Value(Value::DateTime(ref dt)) => Some(dt.to_string()),
+    Node::Value(ref val) => val.string_content(),
+    _ => None,
+  }
+}
+
+fn elements<'a>(node: &Node<'a>) -> Option<Vec<Node<'a>>> {
+  match *node {
+    Node::ArrayOfTables(ref tables) => Some(tables.clone()),
+    Node::Value(Value::Array(ref values)) => Some(values.iter().cloned().map(Node::Value).collect()),
+    _ => None,
+  }
+}
+
+// Checks the `date-time` and `date` formats, strings have to be RFC 3339 datetimes
+fn has_format(node: &Node, format: &str) -> bool {
+  let has_time = match *node {
+    Node::Value(Value::DateTime(ref dt)) => dt.time.is_some(),
+    Node::Value(Value::String(_, _)) => {
+      let s = node_string(node).unwrap_or_default();
+      match Value::datetime_parse(&s[..]) {
+        Ok(Value::DateTime(ref dt)) if format != "date-time" || dt.time.as_ref().is_some_and(|t| t.offset.is_some()) =>
+          dt.time.is_some(),
+        _ => return false,
+      }
+    },
+    _ => return true,
+  };
+  match format {
+    "date-time" => has_time,
+    "date" => !has_time,
+    _ => true,
+  }
+}
+
+fn length(json: &Json) -> Option<usize> {
+  number(json).filter(|n| *n >= 0.0).map(|n| n as usize)
+}
+
+// The JSON pointer and key of a value in the document
+struct Place {
+  pointer: String,
+  key: String,
+}
+
+impl Place {
+  fn child(&self, name: &str) -> Place {
+    Place{pointer: format!("{}/{}", self.pointer, escape(name)), key: child_key(&self.key, &quote_key(name))}
+  }
+
+  fn element(&self, i: usize) -> Place {
+    Place{pointer: format!("{}/{}", self.pointer, i), key: format!("{}[{}]", self.key, i)}
+  }
+}
+
+struct Validator<'s> {
+  root: &'s Json,
+  lines: HashMap<String, usize>,
+  regexes: HashMap<String, Option<Regex>>,
+  // The `$ref`s being followed and the pointers of the values they're checked against, to stop at cycles
+  refs: Vec<(&'s str, String)>,
+}
+
+impl<'s> Validator<'s> {
+  fn error(&self, errors: &mut Vec<JsonViolation>, at: &str, place: &Place, message: String) {
+    let key = KeyPath::from(&place.key[..]);
+    errors.push(JsonViolation{pointer: place.pointer.clone(), line: line_of(&self.lines, &key), key,
+      schema_pointer: at.to_string(), message});
+  }
+
+  fn matches(&mut self, schema: &'s Json, at: &str, place: &Place, node: &Node) -> bool {
+    let mut errors = vec![];
+    self.check(schema, at, place, node, &mut errors);
+    errors.is_empty()
+  }
+
+  fn regex(&mut self, pattern: &str) -> Option<Regex> {
+    self.regexes.entry(pattern.to_string()).or_insert_with(|| Regex::new(pattern).ok()).clone()
+  }
+
+  fn check(&mut self, schema: &'s Json, at: &str, place: &Place, node: &Node, errors: &mut Vec<JsonViolation>) {
+    let keywords = match *schema {
+      Json::Object(ref keywords) => keywords,
+      Json::Bool(false) => return self.error(errors, at, place, "isn't allowed".to_string()),
+      _ => return,
+    };
+    // The other keywords would only repeat a wrong type
+    let names: Vec<&str> = match schema.get("type") {
+      Some(Json::String(name)) => vec![name],
+      Some(Json::Array(names)) => names.iter().filter_map(Json::as_str).collect(),
+      _ => vec![],
+    };
+    if !names.is_empty() && !names.iter().any(|name| has_type(node, name)) {
+      let expected: Vec<String> = names.iter().map(|name| type_name(name)).collect();
+      let message = format!("expected {}, found {}", expected.join(" or "), Type::of(node));
+      return self.error(errors, &format!("{}/type", at), place, message);
+    }
+    let shown = || format!("{}", node.to_value());
+    for (keyword, value) in keywords {
+      let here = format!("{}/{}", at, escape(keyword));
+      match &keyword[..] {
+        "$ref" => if let Some(reference) = value.as_str() {
+          let active = (reference, place.pointer.clone());
+          if self.refs.contains(&active) {
+            continue;
+          }
+          match resolve(self.root, reference) {
+            Some(target) => {
+              self.refs.push(active);
+              self.check(target, &here, place, node, errors);
+              self.refs.pop();
+            },
+            None => self.error(errors, &here, place, format!("can't resolve $ref \"{}\"", reference)),
+          }
+        },
+        "enum" => if let Json::Array(ref values) = *value {
+          let json = to_json(node);
+          if !values.iter().any(|val| same(val, &json)) {
+            self.error(errors, &here, place, format!("{} isn't one of the allowed values", shown()));
+          }
+        },
+        "const" if !same(value, &to_json(node)) =>
+          self.error(errors, &here, place, format!("{} isn't the constant {}", shown(), value)),
+        "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" | "multipleOf" => {
+          let (n, limit) = match (node_number(node), number(value)) {
+            (Some(n), Some(limit)) => (n, limit),
+            _ => continue,
+          };
+          let message = match &keyword[..] {
+            "minimum" if n < limit => format!("{} is less than the minimum {}", shown(), value),
+            "maximum" if n > limit => format!("{} is greater than the maximum {}", shown(), value),
+            "exclusiveMinimum" if n <= limit => format!("{} isn't greater than {}", shown(), value),
+            "exclusiveMaximum" if n >= limit => format!("{} isn't less than {}", shown(), value),
+            "multipleOf" if limit > 0.0 && ((n / limit) - (n / limit).round()).abs() > 1e-9 =>
+              format!("{} isn't a multiple of {}", shown(), value),
+            _ => continue,
+          };
+          self.error(errors, &here, place, message);
+        },
+        "minLength" | "maxLength" | "pattern" | "format" => {
+          let s = match node_string(node) {
+            Some(s) => s,
+            None => continue,
+          };
+          let len = s.chars().count();
+          let message = match (&keyword[..], value) {
+            ("minLength", _) if length(value).is_some_and(|min| len < min) =>
+              format!("{} is shorter than {} characters", shown(), value),
+            ("maxLength", _) if length(value).is_some_and(|max| len > max) =>
+              format!("{} is longer than {} characters", shown(), value),
+            ("pattern", Json::String(pattern)) => match self.regex(pattern) {
+              Some(ref regex) if regex.is_match(&s) => continue,
+              Some(_) => format!("{} doesn't match the pattern \"{}\"", shown(), pattern),
+              None => format!("the pattern \"{}\" isn't a valid regular expression", pattern),
+            },
+            ("format", Json::String(format)) if !has_format(node, format) =>
+              format!("{} isn't a valid {}", shown(), format),
+            _ => continue,
+          };
+          self.error(errors, &here, place, message);
+        },
+        "minItems" | "maxItems" | "uniqueItems" | "items" | "prefixItems" | "additionalItems" | "contains" => {
+          if let Some(elements) = elements(node) {
+            self.array(schema, keyword, &here, place, &elements, errors);
+          }
+        },
+        "required" | "properties" | "patternProperties" | "additionalProperties" | "propertyNames" |
+          "minProperties" | "maxProperties" | "dependentRequired" | "dependentSchemas" | "dependencies" => {
+          self.object(schema, keyword, &here, place, node, errors);
+        },
+        "allOf" | "anyOf" | "oneOf" => {
+          let schemas = match *value {
+            Json::Array(ref schemas) => schemas,
+            _ => continue,
+          };
+          if keyword == "allOf" {
+            for (i, schema) in schemas.iter().enumerate() {
+              self.check(schema, &format!("{}/{}", here, i), place, node, errors);
+            }
+            continue;
+          }
+          let count = schemas.iter().enumerate()
+            .filter(|&(i, schema)| self.matches(schema, &format!("{}/{}", here, i), place, node)).count();
+          if count == 0 {
+            self.error(errors, &here, place, format!("doesn't match any of the schemas in {}", keyword));
+          } else if count > 1 && keyword == "oneOf" {
+            let message = format!("matches {} of the schemas in oneOf, instead of exactly one", count);
+            self.error(errors, &here, place, message);
+          }
+        },
+        "not" if self.matches(value, &here, place, node) =>
+          self.error(errors, &here, place, "matches the schema in not".to_string()),
+        "if" => {
+          let branch = if self.matches(value, &here, place, node) { "then" } else { "else" };
+          if let Some(branch_schema) = schema.get(branch) {
+            self.check(branch_schema, &format!("{}/{}", at, branch), place, node, errors);
+          }
+        },
+        _ => (),
+      }
+    }
+  }
+
+  // Checks one of the array keywords of `schema`
+  fn array(&mut self, schema: &'s Json, keyword: &str, here: &str, place: &Place, elements: &[Node],
+    errors: &mut Vec<JsonViolation>) {
+    let value = match schema.get(keyword) {
+      Some(value) => value,
+      None => return,
+    };
+    let len = elements.len();
+    // The element schemas that `keyword` applies, starting at the element `start`
+    let (start, schemas): (usize, Vec<&'s Json>) = match (keyword, value) {
+      ("minItems", _) => {
+        if length(value).is_some_and(|min| len < min) {
+          self.error(errors, here, place, format!("{} elements is fewer than the minimum {}", len, value));
+        }
+        return;
+      },
+      ("maxItems", _) => {
+        if length(value).is_some_and(|max| len > max) {
+          self.error(errors, here, place, format!("{} elements is more than the maximum {}", len, value));
+        }
+        return;
+      },
+      ("uniqueItems", &Json::Bool(true)) => {
+        let json: Vec<Json> = elements.iter().map(to_json).collect();
+        for i in 0..len {
+          if let Some(j) = (i + 1..len).find(|&j| same(&json[i], &json[j])) {
+            self.error(errors, here, place, format!("elements {} and {} are equal", i, j));
+            return;
+          }
+        }
+        return;
+      },
+      ("contains", _) => {
+        let count = (0..len).filter(|&i| self.matches(value, here, &place.element(i), &elements[i])).count();
+        let min = schema.get("minContains").and_then(length).unwrap_or(1);
+        if count < min {
+          self.error(errors, here, place, if min == 1 {
+            "no element matches the schema in contains".to_string()
+          } else {
+            format!("{} elements match the schema in contains, fewer than the minimum {}", count, min)
+          });
+        }
+        if let Some(max) = schema.get("maxContains").and_then(length).filter(|max| count > *max) {
+          self.error(errors, here, place,
+            format!("{} elements match the schema in contains, more than the maximum {}", count, max));
+        }
+        return;
+      },
+      ("prefixItems", &Json::Array(ref schemas)) | ("items", &Json::Array(ref schemas)) =>
+        (0, schemas.iter().collect()),
+      ("items", _) => {
+        let start = match schema.get("prefixItems") {
+          Some(Json::Array(prefix)) => prefix.len(),
+          _ => 0,
+        };
+        (start, vec![value; len.saturating_sub(start)])
+      },
+      ("additionalItems", _) => match schema.get("items") {
+        Some(Json::Array(prefix)) => (prefix.len(), vec![value; len.saturating_sub(prefix.len())]),
+        _ => return,
+      },
+      _ => return,
+    };
+    let tuple = matches!(*value, Json::Array(_));
+    for (offset, element_schema) in schemas.into_iter().enumerate() {
+      let i = start + offset;
+      if i >= len {
+        break;
+      }
+      let at = if tuple { format!("{}/{}", here, offset) } else { here.to_string() };
+      self.check(element_schema, &at, &place.element(i), &elements[i], errors);
+    }
+  }
+
+  // Checks one of the object keywords of `schema`
+  fn object(&mut self, schema: &'s Json, keyword: &str, here: &str, place: &Place, node: &Node,
+    errors: &mut Vec<JsonViolation>) {
+    let (value, entries) = match (schema.get(keyword), entries(node)) {
+      (Some(value), Some(entries)) => (value, entries),
+      _ => return,
+    };
+    let names: Vec<String> = entries.iter().map(|entry| key_name(&entry.0)).collect();
+    let strings = |json: &Json| -> Vec<String> {
+      match *json {
+        Json::Array(ref values) => values.iter().filter_map(Json::as_str).map(str::to_string).collect(),
+        _ => vec![],
+      }
+    };
+    match keyword {
+      "required" => for name in strings(value) {
+        if !names.contains(&name) {
+          self.error(errors, here, &place.child(&name), "missing required key".to_string());
+        }
+      },
+      "minProperties" if length(value).is_some_and(|min| names.len() < min) =>
+        self.error(errors, here, place, format!("{} keys is fewer than the minimum {}", names.len(), value)),
+      "maxProperties" if length(value).is_some_and(|max| names.len() > max) =>
+        self.error(errors, here, place, format!("{} keys is more than the maximum {}", names.len(), value)),
+      "properties" | "patternProperties" | "additionalProperties" | "propertyNames" => {
+        let properties: Vec<&(String, Json)> = match schema.get("properties") {
+          Some(Json::Object(properties)) => properties.iter().collect(),
+          _ => vec![],
+        };
+        let patterns: Vec<(&String, &'s Json, Option<Regex>)> = match schema.get("patternProperties") {
+          Some(Json::Object(patterns)) =>
+            patterns.iter().map(|pattern| (&pattern.0, &pattern.1, self.regex(&pattern.0))).collect(),
+          _ => vec![],
+        };
+        for (name, entry) in names.iter().zip(entries.iter()) {
+          let child = place.child(name);
+          // The schemas `keyword` applies to the entry
+          let mut schemas = vec![];
+          let mut known = false;
+          for property in properties.iter().filter(|property| property.0 == *name) {
+            known = true;
+            if keyword == "properties" {
+              schemas.push((format!("{}/{}", here, escape(name)), &property.1));
+            }
+          }
+          let matching = patterns.iter().filter(|pattern| pattern.2.as_ref().is_some_and(|regex| regex.is_match(name)));
+          for pattern in matching {
+            known = true;
+            if keyword == "patternProperties" {
+              schemas.push((format!("{}/{}", here, escape(pattern.0)), pattern.1));
+            }
+          }
+          match keyword {
+            "additionalProperties" if !known => match *value {
+              Json::Bool(false) => self.error(errors, here, &child, "unknown key".to_string()),
+              _ => schemas.push((here.to_string(), value)),
+            },
+            "propertyNames" => {
+              let name_node = Node::Value(Value::String(name.clone().into(), StrType::Basic));
+              if !self.matches(value, here, &child, &name_node) {
+                self.error(errors, here, &child,
+                  format!("the key name \"{}\" doesn't match the schema in propertyNames", name));
+              }
+            },
+            _ => (),
+          }
+          for (at, property_schema) in schemas {
+            self.check(property_schema, &at, &child, &entry.1, errors);
+          }
+        }
+      },
+      "dependentRequired" | "dependentSchemas" | "dependencies" => {
+        let dependencies = match *value {
+          Json::Object(ref dependencies) => dependencies,
+          _ => return,
+        };
+        for (name, dependency) in dependencies.iter().filter(|dependency| names.contains(&dependency.0)) {
+          let at = format!("{}/{}", here, escape(name));
+          match *dependency {
+            Json::Array(_) if keyword != "dependentSchemas" => for required in strings(dependency) {
+              if !names.contains(&required) {
+                self.error(errors, &at, &place.child(&required), format!("missing key required by \"{}\"", name));
+              }
+            },
+            Json::Object(_) | Json::Bool(_) if keyword != "dependentRequired" =>
+              self.check(dependency, &at, place, node, errors),
+            _ => (),
+          }
+        }
+      },
+      _ => (),
+    }
+  }
+}
+
+/// Validates the document of `parser` against `schema`, see `TOMLParser::validate_json_schema`.
+pub(crate) fn validate(parser: &Parser, schema: &JsonSchema) -> Vec<JsonViolation> {
+  let mut validator = Validator{root: &schema.root, lines: key_lines(parser), regexes: HashMap::new(), refs: vec![]};
+  let mut errors = vec![];
+  let root = Place{pointer: String::new(), key: String::new()};
+  validator.check(&schema.root, "", &root, &node::build(parser, ""), &mut errors);
+  errors
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use json_schema::JsonSchema;
+  use types::SchemaError;
+
+  // The pointer and message of every violation
+  fn check(schema: &str, doc: &str) -> Vec<String> {
+    let schema = JsonSchema::parse(schema).unwrap();
+    let (parser, _) = TOMLParser::new().parse(doc);
+    parser.validate_json_schema(&schema).iter()
+      .map(|violation| format!("{}: {}", violation.pointer, violation.message)).collect()
+  }
+
+  #[test]
+  fn test_types() {
+    let schema = r#"{"properties": {"a": {"type": "integer"}, "b": {"type": ["string", "null"]},
+      "c": {"type": "object"}, "d": {"type": "array"}, "e": {"type": "number"}}}"#;
+    assert!(check(schema, "a = 1.0\nb = 2016-01-01\nc = { x = 1 }\nd = [1]\ne = 5\n").is_empty());
+    assert!(check(schema, "b = \"s\"\n[c]\n[[d]]\n").is_empty());
+    assert_eq!(vec![
+      "/a: expected an integer, found a float",
+      "/b: expected a string or null, found a boolean",
+      "/c: expected an object, found an array",
+      "/d: expected an array, found a table",
+    ], check(schema, "a = 1.5\nb = true\nc = []\n[d]\n"));
+    assert_eq!(vec![": isn't allowed"], check("false", ""));
+    assert!(check("true", "a = 1").is_empty());
+  }
+
+  #[test]
+  fn test_values() {
+    let schema = r#"{"properties": {
+      "n": {"minimum": 1, "exclusiveMaximum": 10, "multipleOf": 0.5},
+      "s": {"minLength": 2, "maxLength": 3, "pattern": "^[a-z]+$"},
+      "e": {"enum": [1, "x", [1, {"a": true}]]},
+      "c": {"const": {"a": 1, "b": "x"}},
+      "d": {"format": "date"},
+      "t": {"format": "date-time"}
+    }}"#;
+    assert!(check(schema, "n = 9.5\ns = \"ab\"\ne = [1.0, { a = true }]\nc = { b = \"x\", a = 1 }\n\
+      d = 2016-01-01\nt = \"2016-01-01T10:00:00+01:00\"\n").is_empty());
+    assert_eq!(vec![
+      "/n: 10 isn't less than 10",
+      "/s: \"ABCD\" is longer than 3 characters",
+      "/s: \"ABCD\" doesn't match the pattern \"^[a-z]+$\"",
+      "/e: \"y\" isn't one of the allowed values",
+      "/c: {a = 2, b = \"x\"} isn't the constant {\"a\": 1, \"b\": \"x\"}",
+      "/d: 2016-01-01T10:00:00Z isn't a valid date",
+      "/t: \"2016-01-01T10:00:00\" isn't a valid date-time",
+    ], check(schema, "n = 10\ns = \"ABCD\"\ne = \"y\"\nc = {a = 2, b = \"x\"}\nd = 2016-01-01T10:00:00Z\n\
+      t = \"2016-01-01T10:00:00\"\n"));
+    assert_eq!(vec!["/n: 0.75 is less than the minimum 1", "/n: 0.75 isn't a multiple of 0.5"],
+      check(schema, "n = 0.75\n"));
+  }
+
+  #[test]
+  fn test_arrays() {
+    let schema = r#"{"properties": {
+      "tuple": {"items": [{"type": "integer"}, {"type": "string"}], "additionalItems": false},
+      "prefix": {"prefixItems": [{"type": "integer"}], "items": {"type": "string"}, "maxItems": 3},
+      "unique": {"uniqueItems": true, "minItems": 2},
+      "contains": {"contains": {"const": 5}, "maxContains": 1}
+    }}"#;
+    assert!(check(schema, "tuple = [1, \"a\"]\nprefix = [1, \"a\", \"b\"]\nunique = [1, 2]\ncontains = [1, 5]\n")
+      .is_empty());
+    assert_eq!(vec![
+      "/tuple/1: expected a string, found an integer",
+      "/tuple/2: isn't allowed",
+      "/prefix/0: expected an integer, found a string",
+      "/prefix/3: expected a string, found an integer",
+      "/prefix: 4 elements is more than the maximum 3",
+      "/unique: 1 elements is fewer than the minimum 2",
+      "/contains: 2 elements match the schema in contains, more than the maximum 1",
+    ], check(schema, "tuple = [1, 2, 3]\nprefix = [\"a\", \"b\", \"c\", 4]\nunique = [1]\ncontains = [5, 5]\n"));
+    assert_eq!(vec!["/unique: elements 0 and 2 are equal", "/contains: no element matches the schema in contains"],
+      check(schema, "unique = [1, 2, 1.0]\ncontains = []\n"));
+  }
+
+  #[test]
+  fn test_objects() {
+    let schema = r#"{
+      "required": ["name"],
+      "properties": {"name": {"type": "string"}},
+      "patternProperties": {"^x-": {"type": "boolean"}},
+      "additionalProperties": {"type": "object"},
+      "propertyNames": {"maxLength": 5},
+      "maxProperties": 3,
+      "dependentRequired": {"name": ["version"]}
+    }"#;
+    assert_eq!(vec![
+      "/name: missing required key",
+      "/x-a: expected a boolean, found an integer",
+      "/other: expected an object, found an integer",
+      "/x-long: the key name \"x-long\" doesn't match the schema in propertyNames",
+    ], check(schema, "x-a = 1\nother = 2\nx-long = true\n"));
+    assert_eq!(vec!["/version: missing key required by \"name\"", ": 4 keys is more than the maximum 3"],
+      check(r#"{"dependencies": {"name": ["version"], "a": {"required": ["b"]}}, "maxProperties": 3}"#,
+        "name = \"n\"\nc = 1\nd = 2\ne = 3\n"));
+    assert_eq!(vec!["/a b~1c/d: expected a string, found an integer"],
+      check(r#"{"additionalProperties": {"additionalProperties": {"type": "string"}}}"#, "[\"a b/c\"]\nd = 1\n"));
+  }
+
+  #[test]
+  fn test_combinators() {
+    let schema = r##"{
+      "properties": {
+        "any": {"anyOf": [{"type": "string"}, {"type": "integer"}]},
+        "one": {"oneOf": [{"minimum": 0}, {"maximum": 10}]},
+        "not": {"not": {"type": "boolean"}},
+        "all": {"allOf": [{"minimum": 0}, {"maximum": 10}]},
+        "cond": {"if": {"type": "integer"}, "then": {"minimum": 0}, "else": {"type": "string"}},
+        "ref": {"$ref": "#/$defs/positive"},
+        "tree": {"$ref": "#/$defs/tree"},
+        "missing": {"$ref": "other.json#/a"}
+      },
+      "$defs": {
+        "positive": {"exclusiveMinimum": 0},
+        "tree": {"properties": {"children": {"items": {"$ref": "#/$defs/tree"}}, "value": {"type": "integer"}}}
+      }
+    }"##;
+    assert!(check(schema, "any = 1\none = -1\nnot = 1\nall = 5\ncond = \"a\"\nref = 1\n\
+      tree = { value = 1, children = [{ value = 2 }] }\n").is_empty());
+    assert_eq!(vec![
+      "/any: doesn't match any of the schemas in anyOf",
+      "/one: matches 2 of the schemas in oneOf, instead of exactly one",
+      "/not: matches the schema in not",
+      "/all: 11 is greater than the maximum 10",
+      "/cond: -1 is less than the minimum 0",
+      "/ref: 0 isn't greater than 0",
+      "/tree/children/0/value: expected an integer, found a string",
+      "/missing: can't resolve $ref \"other.json#/a\"",
+    ], check(schema, "any = true\none = 5\nnot = false\nall = 11\ncond = -1\nref = 0\n\
+      tree = { children = [{ value = \"a\" }] }\nmissing = 1\n"));
+    // A reference to itself doesn't loop forever
+    assert!(check(r##"{"$ref": "#", "type": "object"}"##, "a = 1\n").is_empty());
+  }
+
+  #[test]
+  fn test_schema_errors() {
+    assert_eq!(SchemaError::Parse("Expected a value at line 1, column 7".to_string()),
+      JsonSchema::parse("{\"a\": }").unwrap_err());
+    assert_eq!(SchemaError::Parse("a schema must be an object or a boolean".to_string()),
+      JsonSchema::parse("[]").unwrap_err());
+    assert!(match JsonSchema::from_file("/nonexistent/schema.json") {
+      Err(SchemaError::Read(ref path, _)) => path == "/nonexistent/schema.json",
+      _ => false,
+    });
+  }
+}
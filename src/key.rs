@@ -0,0 +1,485 @@
+//! Structured keys for addressing parts of a TOML document.
+//!
+//! Every `TOMLParser` method that takes a key accepts anything that converts into a `KeyPath`, including string slices
+//! in the usual `table."Key One".sub[3]` syntax. A `KeyPath` is a list of segments, each of which is either a key or
+//! an index into an array or array of tables. Building a `KeyPath` segment by segment avoids having to quote and escape
+//! keys that contain `.`, `[` or quotes. An index can also count back from the end of its array, as in `bin[-1]` or
+//! `bin[last]`, which point to the last table of the `bin` array of tables. They're resolved the same way wherever a
+//! key is taken: by `TOMLParser`'s methods, `TableRef` and `TableMut`, indexing a `Node` or a `DocMut` with a
+//! `KeyPath`, and in queries.
+//!
+//! Keys are matched by name, so a key that's quoted in the document can be found with or without quotes, and with
+//! either kind of quotes. Full keys returned by the parser, for example by `TOMLParser::keys`, are in the same form as
+//! `KeyPath`'s `Display`: keys are only quoted if they have to be.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::key::KeyPath;
+//! use tomllib::types::Value;
+//!
+//! let parser = TOMLParser::new();
+//! let (parser, _) = parser.parse("[\"servers.eu\"]\nhosts = [\"alpha\", \"beta\"]");
+//! let path = KeyPath::root().key("servers.eu").key("hosts").index(1);
+//! assert_eq!("\"servers.eu\".hosts[1]", path.to_string());
+//! assert_eq!(Some(Value::basic_string("beta").unwrap()), parser.get_value(path));
+//! assert_eq!(Some(Value::basic_string("beta").unwrap()), parser.get_value("'servers.eu'.hosts[1]"));
+//! ```
+
+use std::fmt;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::char;
+use types::{TOMLError, Children};
+use internals::parser::Parser;
+
+/// One segment of a `KeyPath`.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum KeySegment {
+  /// A key in a table, without quotes or escapes.
+  Key(String),
+  /// An index into an array or an array of tables.
+  Index(usize),
+  /// An index into an array or an array of tables counted back from its end, so `1` is the last element. Written as
+  /// `[-1]`, or `[last]` for the last element.
+  FromEnd(usize),
+}
+
+/// A full key made up of `KeySegment`s. See the module documentation for details.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Default)]
+pub struct KeyPath {
+  segments: Vec<KeySegment>,
+}
+
+impl KeyPath {
+  /// Returns the empty key path, which points to the root table.
+  pub fn root() -> KeyPath {
+    KeyPath{segments: vec![]}
+  }
+
+  /// Parses `key` in the usual string syntax, returning an error if it's malformed.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::key::{KeyPath, KeySegment};
+  ///
+  /// let path = KeyPath::parse("table.\"Key One\".sub[3]").unwrap();
+  /// assert_eq!(&[KeySegment::Key("table".to_string()), KeySegment::Key("Key One".to_string()),
+  ///   KeySegment::Key("sub".to_string()), KeySegment::Index(3)], path.segments());
+  /// assert!(KeyPath::parse("table.\"Key One").is_err());
+  /// ```
+  pub fn parse(key: &str) -> Result<KeyPath, TOMLError> {
+    parse_segments(key).map(|segments| KeyPath{segments}).map_err(|reason| {
+      TOMLError::new(format!("Error parsing key path \"{}\": {}.", key, reason))
+    })
+  }
+
+  /// Returns this key path with the key `name` appended.
+  pub fn key<S>(mut self, name: S) -> KeyPath where S: Into<String> {
+    self.push_key(name);
+    self
+  }
+
+  /// Returns this key path with the index `index` appended.
+  pub fn index(mut self, index: usize) -> KeyPath {
+    self.push_index(index);
+    self
+  }
+
+  /// Returns this key path with the index `index` counted back from the end of the array appended, so `from_end(1)`
+  /// points to the last element.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::key::KeyPath;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("[[bin]]\nname = \"a\"\n[[bin]]\nname = \"b\"");
+  /// let last = KeyPath::root().key("bin").from_end(1).key("name");
+  /// assert_eq!(Some(Value::basic_string("b").unwrap()), parser.get_value(last));
+  /// assert_eq!(Some(Value::basic_string("a").unwrap()), parser.get_value("bin[-2].name"));
+  /// assert_eq!(Some(Value::basic_string("b").unwrap()), parser.get_value("bin[last].name"));
+  /// assert_eq!(None, parser.get_value("bin[-3].name"));
+  /// ```
+  pub fn from_end(mut self, index: usize) -> KeyPath {
+    self.push_from_end(index);
+    self
+  }
+
+  /// Appends the key `name`.
+  pub fn push_key<S>(&mut self, name: S) where S: Into<String> {
+    self.segments.push(KeySegment::Key(name.into()));
+  }
+
+  /// Appends the index `index`.
+  pub fn push_index(&mut self, index: usize) {
+    self.segments.push(KeySegment::Index(index));
+  }
+
+  /// Appends the index `index` counted back from the end of the array.
+  pub fn push_from_end(&mut self, index: usize) {
+    self.segments.push(KeySegment::FromEnd(index));
+  }
+
+  /// Removes and returns the last segment, or `None` for the root.
+  pub fn pop(&mut self) -> Option<KeySegment> {
+    self.segments.pop()
+  }
+
+  /// Returns the key path without its last segment, or `None` for the root.
+  pub fn parent(&self) -> Option<KeyPath> {
+    if self.segments.is_empty() {
+      None
+    } else {
+      Some(KeyPath{segments: self.segments[..self.segments.len() - 1].to_vec()})
+    }
+  }
+
+  /// Returns the last segment, or `None` for the root.
+  pub fn last(&self) -> Option<&KeySegment> {
+    self.segments.last()
+  }
+
+  /// Returns the segments of the key path.
+  pub fn segments(&self) -> &[KeySegment] {
+    &self.segments
+  }
+
+  /// Returns the number of segments.
+  pub fn len(&self) -> usize {
+    self.segments.len()
+  }
+
+  /// Returns `true` if this is the root key path.
+  pub fn is_empty(&self) -> bool {
+    self.segments.is_empty()
+  }
+
+  /// Returns `true` if `other` is a prefix of, or equal to, this key path.
+  pub fn starts_with(&self, other: &KeyPath) -> bool {
+    self.segments.starts_with(&other.segments)
+  }
+}
+
+/// Formats a key path in the usual string syntax, quoting keys only if they contain characters that aren't allowed in
+/// bare keys.
+impl Display for KeyPath {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for (i, segment) in self.segments.iter().enumerate() {
+      match *segment {
+        KeySegment::Key(ref name) => {
+          if i > 0 {
+            write!(f, ".")?;
+          }
+          write!(f, "{}", quote_key(name))?;
+        },
+        KeySegment::Index(index) => write!(f, "[{}]", index)?,
+        KeySegment::FromEnd(index) => write!(f, "[-{}]", index)?,
+      }
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for KeyPath {
+  type Err = TOMLError;
+
+  fn from_str(key: &str) -> Result<KeyPath, TOMLError> {
+    KeyPath::parse(key)
+  }
+}
+
+/// Parses the string the same way `KeyPath::parse` does. A malformed key becomes a single key segment containing the
+/// whole string, which won't match anything in a document.
+impl<'s> From<&'s str> for KeyPath {
+  fn from(key: &'s str) -> KeyPath {
+    parse_segments(key).map(|segments| KeyPath{segments})
+      .unwrap_or_else(|_| KeyPath{segments: vec![KeySegment::Key(key.to_string())]})
+  }
+}
+
+impl From<String> for KeyPath {
+  fn from(key: String) -> KeyPath {
+    KeyPath::from(&key[..])
+  }
+}
+
+impl<'s> From<&'s String> for KeyPath {
+  fn from(key: &'s String) -> KeyPath {
+    KeyPath::from(&key[..])
+  }
+}
+
+impl<'s> From<&'s KeyPath> for KeyPath {
+  fn from(path: &'s KeyPath) -> KeyPath {
+    path.clone()
+  }
+}
+
+impl From<Vec<KeySegment>> for KeyPath {
+  fn from(segments: Vec<KeySegment>) -> KeyPath {
+    KeyPath{segments}
+  }
+}
+
+fn is_bare(name: &str) -> bool {
+  !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// Quotes `name` as a basic string if it can't be a bare key
+pub(crate) fn quote_key(name: &str) -> String {
+  if is_bare(name) {
+    return name.to_string();
+  }
+  format!("\"{}\"", escape_basic(name))
+}
+
+// Escapes `s` so it can be written between the quotes of a basic string
+pub(crate) fn escape_basic(s: &str) -> String {
+  let mut escaped = String::new();
+  for c in s.chars() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      '\n' => escaped.push_str("\\n"),
+      '\t' => escaped.push_str("\\t"),
+      '\r' => escaped.push_str("\\r"),
+      '\u{8}' => escaped.push_str("\\b"),
+      '\u{c}' => escaped.push_str("\\f"),
+      c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+// Returns the full key `path` is stored under in the parser's map. Indices counted from the end are replaced with
+// their position in the array, if the array is long enough, otherwise the key is returned as is and won't match
+// anything.
+pub(crate) fn resolve(parser: &Parser, path: &KeyPath) -> String {
+  let mut key = String::new();
+  for segment in path.segments() {
+    key = match *segment {
+      KeySegment::Key(ref name) => Children::combine_keys(key, quote_key(name)),
+      KeySegment::Index(index) => Children::combine_keys_index(key, index),
+      KeySegment::FromEnd(index) => match parser.get_children(&key[..]) {
+        Some(Children::Count(count)) if count.get() >= index => Children::combine_keys_index(key, count.get() - index),
+        _ => return path.to_string(),
+      },
+    };
+  }
+  key
+}
+
+// Returns the full key of the first `len` segments of `path`, the way it's stored in the parser's map
+pub(crate) fn prefix(path: &KeyPath, len: usize) -> String {
+  KeyPath::from(path.segments()[..len].to_vec()).to_string()
+}
+
+// Returns the name of a key as written in a document, without its quotes and escapes
+pub(crate) fn key_name(raw: &str) -> String {
+  let mut chars = raw.chars().peekable();
+  match chars.peek() {
+    Some(&'"') | Some(&'\'') => parse_quoted(&mut chars).unwrap_or_else(|_| raw.to_string()),
+    _ => raw.to_string(),
+  }
+}
+
+// Returns the form a key written as `raw` is stored under in the parser's map: its name, quoted only if needed. The
+// placeholder key of the root table is left alone.
+pub(crate) fn normalize_key(raw: &str) -> String {
+  if raw == "$Root$" {
+    raw.to_string()
+  } else {
+    quote_key(&key_name(raw))
+  }
+}
+
+// Parses a quoted key starting at its opening quote
+pub(crate) fn parse_quoted<I>(chars: &mut ::std::iter::Peekable<I>) -> Result<String, String>
+  where I: Iterator<Item=char> {
+  let quote = chars.next();
+  let mut name = String::new();
+  loop {
+    match chars.next() {
+      None => return Err("unterminated quoted key".to_string()),
+      Some(c) if Some(c) == quote => return Ok(name),
+      Some('\\') if quote == Some('"') => {
+        let c = match chars.next() {
+          Some('b') => '\u{8}',
+          Some('t') => '\t',
+          Some('n') => '\n',
+          Some('f') => '\u{c}',
+          Some('r') => '\r',
+          Some('"') => '"',
+          Some('\\') => '\\',
+          Some(u) if u == 'u' || u == 'U' => {
+            let len = if u == 'u' { 4 } else { 8 };
+            let hex: String = chars.by_ref().take(len).collect();
+            match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+              Some(c) if hex.len() == len => c,
+              _ => return Err(format!("invalid unicode escape \"\\{}{}\"", u, hex)),
+            }
+          },
+          Some(c) => return Err(format!("invalid escape \"\\{}\"", c)),
+          None => return Err("unterminated quoted key".to_string()),
+        };
+        name.push(c);
+      },
+      Some(c) => name.push(c),
+    }
+  }
+}
+
+// Parses an index starting at its opening bracket
+fn parse_index<I>(chars: &mut ::std::iter::Peekable<I>) -> Result<KeySegment, String> where I: Iterator<Item=char> {
+  chars.next();
+  let mut index = String::new();
+  loop {
+    match chars.next() {
+      Some(']') => break,
+      Some(c) => index.push(c),
+      None => return Err("unterminated index".to_string()),
+    }
+  }
+  let trimmed = index.trim();
+  if trimmed == "last" {
+    return Ok(KeySegment::FromEnd(1));
+  }
+  let result = match trimmed.strip_prefix('-') {
+    Some(from_end) => from_end.parse::<usize>().ok().filter(|i| *i > 0).map(KeySegment::FromEnd),
+    None => trimmed.parse::<usize>().ok().map(KeySegment::Index),
+  };
+  result.ok_or_else(|| format!("invalid index \"{}\"", index))
+}
+
+fn skip_whitespace<I>(chars: &mut ::std::iter::Peekable<I>) where I: Iterator<Item=char> {
+  while let Some(&' ') | Some(&'\t') = chars.peek() {
+    chars.next();
+  }
+}
+
+fn parse_segments(key: &str) -> Result<Vec<KeySegment>, String> {
+  let mut segments = vec![];
+  let mut chars = key.chars().peekable();
+  if key.is_empty() {
+    return Ok(segments);
+  }
+  // A key is expected at the start and after every dot, an index can follow a key, another index or the start
+  let mut expect_key = true;
+  loop {
+    skip_whitespace(&mut chars);
+    match chars.peek() {
+      Some(&'[') => {
+        if expect_key && !segments.is_empty() {
+          return Err("expected a key after \".\"".to_string());
+        }
+        segments.push(parse_index(&mut chars)?);
+      },
+      Some(&'"') | Some(&'\'') if expect_key => segments.push(KeySegment::Key(parse_quoted(&mut chars)?)),
+      Some(&c) if !expect_key => return Err(format!("unexpected \"{}\"", c)),
+      _ => {
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+          if c == '.' || c == '[' {
+            break;
+          }
+          name.push(c);
+          chars.next();
+        }
+        let name = name.trim();
+        if name.is_empty() {
+          return Err("empty key".to_string());
+        }
+        segments.push(KeySegment::Key(name.to_string()));
+      },
+    }
+    expect_key = false;
+    skip_whitespace(&mut chars);
+    match chars.peek() {
+      None => return Ok(segments),
+      Some(&'.') => {
+        chars.next();
+        expect_key = true;
+      },
+      Some(&'[') => (),
+      Some(&c) => return Err(format!("unexpected \"{}\"", c)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use key::{KeyPath, KeySegment, key_name};
+  use types::Value;
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(KeyPath::root(), KeyPath::parse("").unwrap());
+    assert_eq!(KeyPath::root().key("a").key("b.c").index(0).index(2).key("d"),
+      KeyPath::parse("a.\"b.c\"[0][2].d").unwrap());
+    assert_eq!(KeyPath::root().index(1).key("name"), KeyPath::parse("[1].name").unwrap());
+    assert_eq!(KeyPath::root().key("a b").key("c\\d").key("\"q\""),
+      KeyPath::parse("'a b' . 'c\\d'.\"\\\"q\\\"\"").unwrap());
+    assert_eq!(&[KeySegment::Key("é".to_string())], KeyPath::parse("\"\\u00E9\"").unwrap().segments());
+    for bad in &["a.", "a..b", ".a", "a[x]", "a[1]b", "\"a", "a.[1]", "\"\\q\"", "a[-0]", "a[-x]", "a[first]"] {
+      assert!(KeyPath::parse(bad).is_err(), "{} should be an error", bad);
+    }
+    assert_eq!(&[KeySegment::Key("a..b".to_string())], KeyPath::from("a..b").segments());
+    assert_eq!(KeyPath::root().key("a").from_end(1).from_end(2).key("b"), KeyPath::parse("a[last][ -2 ].b").unwrap());
+    assert_eq!("a[-1][-2].b", KeyPath::parse("a[last][-2].b").unwrap().to_string());
+  }
+
+  #[test]
+  fn test_display() {
+    let path = KeyPath::root().key("table").key("Key One").key("sub").index(3).key("").key("a\"b");
+    assert_eq!("table.\"Key One\".sub[3].\"\".\"a\\\"b\"", path.to_string());
+    assert_eq!(path, KeyPath::parse(&path.to_string()).unwrap());
+    assert_eq!(Some(KeyPath::root().key("a")), KeyPath::from("a.b").parent());
+    assert!(KeyPath::from("a.b[1]").starts_with(&KeyPath::from("a.b")));
+  }
+
+  #[test]
+  fn test_key_name() {
+    assert_eq!("bare", key_name("bare"));
+    assert_eq!("a b", key_name("\"a b\""));
+    assert_eq!("a\\b", key_name("'a\\b'"));
+    assert_eq!("a\tb", key_name("\"a\\tb\""));
+  }
+
+  #[test]
+  fn test_resolve() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse("[\"a.b\"]\n\"c d\" = 1\n\"e\" = [{ \"f\" = 2 }]\n");
+    assert_eq!(Some(Value::int(1)), parser.get_value("\"a.b\".'c d'"));
+    assert_eq!(Some(Value::int(1)), parser.get_value(KeyPath::root().key("a.b").key("c d")));
+    assert_eq!(Some(Value::int(2)), parser.get_value("'a.b'.e[0].f"));
+    assert!(parser.set_value(KeyPath::root().key("a.b").key("e").index(0).key("f"), Value::int(3)));
+    assert_eq!("[\"a.b\"]\n\"c d\" = 1\n\"e\" = [{ \"f\" = 3 }]\n", format!("{}", parser));
+    assert!(parser.get_value("a.b").is_none());
+  }
+
+  #[test]
+  fn test_resolve_from_end() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse("[[bin]]\nname = \"a\"\n[[bin]]\nname = \"b\"\nports = [80, 443]\n");
+    assert_eq!(Some(Value::int(443)), parser.get_value("bin[last].ports[-1]"));
+    assert_eq!(Some(Value::int(80)), parser.get_value("bin[-1].ports[-2]"));
+    assert_eq!(None, parser.get_value("bin[-1].ports[-3]"));
+    assert_eq!(None, parser.get_value("bin[last].name[last]"));
+    assert_eq!(Some(Value::basic_string("a").unwrap()), parser.table("bin[-2]").unwrap().get("name"));
+    assert_eq!(Some(Value::basic_string("b").unwrap()), parser.table("bin").unwrap().get("[last].name"));
+    assert!(parser.set_value("bin[-2].name", Value::basic_string("c").unwrap()));
+    assert!(!parser.set_value("bin[-3].name", Value::basic_string("c").unwrap()));
+    assert_eq!(Ok(Value::basic_string("src").unwrap()),
+      parser.entry("bin[last].path").or_insert(Value::basic_string("src").unwrap()));
+    assert_eq!("[[bin]]\nname = \"c\"\n[[bin]]\nname = \"b\"\nports = [80, 443]\npath = \"src\"\n",
+      format!("{}", parser));
+  }
+}
@@ -77,19 +77,58 @@
 #[macro_use]
 extern crate nom;
 extern crate regex;
+extern crate csv as csv_crate;
 #[macro_use]
 extern crate log;
 mod internals;
 pub mod types;
+pub mod cst;
+pub mod visitor;
+pub mod iter;
+pub mod table;
+pub mod node;
+pub mod key;
+pub mod query;
+pub mod merge;
+pub mod diff;
+pub mod patch;
+pub mod merge3;
+pub mod schema;
+pub mod json_schema;
+pub mod json;
+pub mod ini;
+pub mod properties;
+pub mod csv;
+pub mod format;
 
 use std::fmt;
+use std::collections::BTreeMap;
 use std::fmt::Display;
-use types::{ParseResult, Value, Children};
+use std::cell::OnceCell;
+use std::ops::Index;
+use types::{ParseResult, Value, Children, EditError, QueryError, PatchError, ConvertError};
+use cst::Document;
+use visitor::{Visitor, VisitorMut};
+use iter::{Keys, Entries};
+use table::{TableRef, TableMut};
+use node::{Node, DocMut, Entry};
+use key::KeyPath;
+use query::Query;
+use diff::Change;
+use patch::Operation;
+use merge3::Conflict;
+use schema::{Schema, Violation};
+use json_schema::{JsonSchema, JsonViolation};
+use format::FormatOptions;
 use internals::parser::Parser;
+use internals::graft;
+use internals::flat;
 
 /// A parser, manipulator, and outputter of TOML documents.
 pub struct TOMLParser<'a> {
   parser: Parser<'a>,
+  // Nodes returned by indexing, built on first use and thrown away whenever the document changes
+  nodes: OnceCell<Node<'a>>,
 }
 
 impl<'a> TOMLParser<'a> {
@@ -103,7 +142,7 @@ impl<'a> TOMLParser<'a> {
   /// let mut parser = TOMLParser::new();
   /// ```
   pub fn new() -> TOMLParser<'a> {
-    TOMLParser{parser: Parser::new()}
+    TOMLParser{parser: Parser::new(), nodes: OnceCell::new()}
   }
 
   /// Parses the string slice `input` as a TOML document. The method takes ownership of the parser and then returns it,
@@ -120,11 +159,12 @@ impl<'a> TOMLParser<'a> {
   pub fn parse(mut self, input: &'a str) -> (TOMLParser<'a>, ParseResult<'a>) {
     let (tmp, result) = self.parser.parse(input);
     self.parser = tmp;
+    self.nodes.take();
     (self, result)
   }
 
-  /// Given a string type `key`, returns the associated `Value` or `None` if the key doesn't exist in the parsed
-  /// document.
+  /// Given a `key`, returns the associated `Value` or `None` if the key doesn't exist in the parsed document. Like
+  /// every method that takes a key, `key` can be a `KeyPath` or a string in key path syntax, see the `key` module.
   ///
   /// # Examples
   ///
@@ -147,11 +187,11 @@ impl<'a> TOMLParser<'a> {
   /// assert_eq!(value2.unwrap(), Value::date_from_int(2010, 5, 18).unwrap());
   /// assert_eq!(value3.unwrap(), Value::int(5));
   /// ```
-  pub fn get_value<S>(self: &TOMLParser<'a>, key: S) -> Option<Value<'a>> where S: Into<String> {
-    self.parser.get_value(key)
+  pub fn get_value<K>(self: &TOMLParser<'a>, key: K) -> Option<Value<'a>> where K: Into<KeyPath> {
+    self.parser.get_value(self.resolve(key))
   }
 
-  /// Given a string type `key` and a `Value` `val`, sets `Value` at `key` to `val` and returns true if `key` exists in
+  /// Given a `key` and a `Value` `val`, sets `Value` at `key` to `val` and returns true if `key` exists in
   /// the parsed document. If `key` doesn't exist in the parsed document returns false. Setting a value does not alter
   /// the document's format, including whitespace and comments, unless an `Array` or `InlineTable`'s structure is changed
   /// meaning either:
@@ -178,11 +218,42 @@ impl<'a> TOMLParser<'a> {
   /// let value = parser.get_value("table.AKey");
   /// assert_eq!(value.unwrap(), Value::int_from_str("5_000").unwrap());
   /// ```
-  pub fn set_value<S>(self: &mut TOMLParser<'a>, key: S, val: Value<'a>) -> bool where S: Into<String> {
+  pub fn set_value<K>(self: &mut TOMLParser<'a>, key: K, val: Value<'a>) -> bool where K: Into<KeyPath> {
+    self.try_set_value(key, val).is_ok()
+  }
+
+  /// Given a `key` and a `Value` `val`, sets `Value` at `key` to `val` the same way `set_value` does, but
+  /// returns a `Result` that says why the value couldn't be set instead of `false`. On failure the document is left
+  /// unchanged and an `EditError` is returned with the key that was passed in and the key of the part of the
+  /// document or new value that caused the failure.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::rc::Rc;
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::{Value, EditError, StrType};
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (mut parser, _) = parser.parse("[table]\nAKey = \"A Value\"\nArray = [1, 2]");
+  /// assert_eq!(Ok(()), parser.try_set_value("table.AKey", Value::int(5)));
+  /// assert_eq!(Err(EditError::KeyNotFound("other.AKey".to_string(), "other".to_string())),
+  ///   parser.try_set_value("other.AKey", Value::int(5)));
+  /// assert_eq!(Err(EditError::NotAValue("table".to_string(), "table".to_string())),
+  ///   parser.try_set_value("table", Value::int(5)));
+  /// assert_eq!(Err(EditError::InvalidNestedValue("table.Array".to_string(), "table.Array[1]".to_string())),
+  ///   parser.try_set_value("table.Array", Value::Array(Rc::new(vec![
+  ///     Value::int(1), Value::String("Line\nBreak".into(), StrType::Basic), Value::int(3)
+  ///   ]))));
+  /// ```
+  pub fn try_set_value<K>(self: &mut TOMLParser<'a>, key: K, val: Value<'a>) -> Result<(), EditError>
+    where K: Into<KeyPath> {
+    self.nodes.take();
+    let key = self.resolve(key);
     self.parser.set_value(key, val)
   }
 
-  /// Given a string type `key` returns all the child keys of the `key` if it exists in the parsed document, otherwise
+  /// Given a `key` returns all the child keys of the `key` if it exists in the parsed document, otherwise
   /// returns `None`.
   ///
   /// # Examples
@@ -210,8 +281,678 @@ impl<'a> TOMLParser<'a> {
   /// let aot_child_keys = parser.get_children("array_of_tables");
   /// assert_eq!(*aot_child_keys.unwrap(), Children::Count(Cell::new(3)));
   /// ```
-  pub fn get_children<S>(self: &TOMLParser<'a>, key: S) -> Option<&Children> where S: Into<String> {
-    self.parser.get_children(key)
+  pub fn get_children<K>(self: &TOMLParser<'a>, key: K) -> Option<&Children> where K: Into<KeyPath> {
+    self.parser.get_children(self.resolve(key))
+  }
+
+  /// Returns the whole subtree at `key` as an owned `Node`, or `None` if `key` doesn't exist. Unlike `get_value`, this
+  /// works for standard tables, implicit tables and arrays of tables, as well as values. Use `""` for the whole
+  /// document and `Node::to_value` to turn a table into an inline table `Value`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::node::Node;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("[database.primary]\nport = 5432\n[[database.replicas]]\nport = 5433\n");
+  /// assert_eq!(None, parser.get_value("database"));
+  /// let database = parser.get_subtree("database").unwrap();
+  /// assert_eq!(Some(&Value::int(5433)), database["replicas"][0]["port"].as_value());
+  /// assert_eq!(Node::Table(vec![("port".to_string(), Node::Value(Value::int(5432)))]),
+  ///   parser.get_subtree("database.primary").unwrap());
+  /// assert_eq!(None, parser.get_subtree("database.missing"));
+  /// ```
+  pub fn get_subtree<K>(self: &TOMLParser<'a>, key: K) -> Option<Node<'a>> where K: Into<KeyPath> {
+    let key = self.resolve(key);
+    if key.is_empty() || self.parser.map.contains_key(&key) {
+      Some(node::build(&self.parser, &key))
+    } else {
+      None
+    }
+  }
+
+  /// Returns a standalone TOML document containing the subtree at `key`, or `None` if `key` doesn't exist or is an
+  /// element of an array. The contents of a table, including its comments and the comments on and directly above its
+  /// header, become the top level of the new document, and the headers of its subtables are written relative to it.
+  /// Arrays of tables and values keep their own key, and the key-value pairs of an inline table become the top level.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("title = \"t\"\n\n# Main database\n[database] # production\n\
+  ///   server = \"192.168.1.1\" # primary\n[[database.replicas]]\nport = 5432\n");
+  /// assert_eq!(Some("# Main database\n# production\nserver = \"192.168.1.1\" # primary\n[[replicas]]\nport = 5432\n"
+  ///   .to_string()), parser.extract_document("database"));
+  /// assert_eq!(Some("title = \"t\"\n".to_string()), parser.extract_document("title"));
+  /// ```
+  pub fn extract_document<K>(self: &TOMLParser<'a>, key: K) -> Option<String> where K: Into<KeyPath> {
+    self.parser.extract_document(&self.resolve(key))
+  }
+
+  /// Copies the tables, key-value pairs and comments of the document in `fragment` into the table at `key`, creating
+  /// it if it doesn't exist. The fragment's tables and arrays of tables become subtables of `key`, so the result is a
+  /// `[key]` section rather than an inline table. Returns `EditError::DuplicateKey` or `EditError::InvalidTable` and
+  /// leaves the document unchanged if anything in the fragment conflicts with the document or with the rest of the
+  /// fragment, following the same rules as the parser.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (mut parser, _) = parser.parse("title = \"t\"\n");
+  /// let fragment = TOMLParser::new();
+  /// let (fragment, _) = fragment.parse("# Primary\nserver = \"10.0.0.1\"\n\n[[replicas]]\nport = 5433\n");
+  /// parser.graft("database", &fragment).unwrap();
+  /// assert_eq!("title = \"t\"\n\n[database]\n# Primary\nserver = \"10.0.0.1\"\n\n[[database.replicas]]\n\
+  ///   port = 5433\n", format!("{}", parser));
+  /// assert_eq!(Some(Value::int(5433)), parser.get_value("database.replicas[0].port"));
+  /// assert!(parser.graft("database", &fragment).is_err());
+  /// ```
+  pub fn graft<K>(self: &mut TOMLParser<'a>, key: K, fragment: &TOMLParser<'a>) -> Result<(), EditError>
+    where K: Into<KeyPath> {
+    self.nodes.take();
+    let key = self.resolve(key);
+    let root = fragment.parser.root.borrow();
+    self.parser.graft(&key, &root)
+  }
+
+  /// Like `graft`, but copies a `Node` tree, such as one returned by `get_subtree`, into the table at `key`. The node
+  /// must be a table, or a value holding an inline table, otherwise `EditError::NotATable` is returned.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::node::Node;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (mut parser, _) = parser.parse("title = \"t\"\n");
+  /// let node = Node::Table(vec![
+  ///   ("server".to_string(), Node::Value(Value::basic_string("10.0.0.1").unwrap())),
+  ///   ("limits".to_string(), Node::Table(vec![("max".to_string(), Node::Value(Value::int(5)))])),
+  /// ]);
+  /// parser.graft_node("database", &node).unwrap();
+  /// assert_eq!("title = \"t\"\n\n[database]\nserver = \"10.0.0.1\"\n\n[database.limits]\nmax = 5\n",
+  ///   format!("{}", parser));
+  /// ```
+  pub fn graft_node<K>(self: &mut TOMLParser<'a>, key: K, node: &Node<'a>) -> Result<(), EditError>
+    where K: Into<KeyPath> {
+    self.nodes.take();
+    let key = self.resolve(key);
+    let fragment = graft::node_fragment(&key, node)?;
+    self.parser.graft(&key, &fragment)
+  }
+
+  /// Returns every scalar value in the document under its full key, for example `"a.b[2].c"`. Tables and arrays of
+  /// tables are followed down to their values, as are arrays and inline tables, so only strings, numbers, booleans
+  /// and datetimes are returned.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("[server]\nports = [ 80, 443 ]\n[[users]]\nname = \"a\"\n");
+  /// let flat = parser.flatten();
+  /// assert_eq!(vec!["server.ports[0]", "server.ports[1]", "users[0].name"],
+  ///   flat.keys().map(|key| &key[..]).collect::<Vec<&str>>());
+  /// assert_eq!(Some(&Value::int(443)), flat.get("server.ports[1]"));
+  /// ```
+  pub fn flatten(self: &TOMLParser<'a>) -> BTreeMap<String, Value<'a>> {
+    self.parser.flatten()
+  }
+
+  /// Builds a new document from a map of full keys to values, like the one returned by `flatten`. Keys followed by
+  /// another key become standard tables, indexed keys followed by another key become arrays of tables, and other
+  /// indexed keys become arrays. Returns an `EditError` if a key isn't valid, a key is used both as a value and as a
+  /// table, or an array is missing an index.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::collections::BTreeMap;
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let mut map = BTreeMap::new();
+  /// map.insert("title".to_string(), Value::basic_string("t").unwrap());
+  /// map.insert("server.ports[0]".to_string(), Value::int(80));
+  /// map.insert("server.ports[1]".to_string(), Value::int(443));
+  /// map.insert("users[0].name".to_string(), Value::basic_string("a").unwrap());
+  /// let parser = TOMLParser::from_flat(&map).unwrap();
+  /// assert_eq!("title = \"t\"\n\n[server]\nports = [80, 443]\n\n[[users]]\nname = \"a\"\n", format!("{}", parser));
+  /// assert_eq!(map, parser.flatten());
+  /// ```
+  pub fn from_flat(map: &BTreeMap<String, Value<'a>>) -> Result<TOMLParser<'a>, EditError> {
+    let node = flat::unflatten(map)?;
+    let (mut parser, _) = TOMLParser::new().parse("");
+    parser.graft_node("", &node)?;
+    Ok(parser)
+  }
+
+  /// Returns the differences in meaning between this document and `other`, ignoring formatting, comments, the order
+  /// of keys, how numbers and strings are written and whether tables are sections or inline tables. See the `diff`
+  /// module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::diff::Change;
+  /// use tomllib::key::KeyPath;
+  /// use tomllib::types::Value;
+  ///
+  /// let (old, _) = TOMLParser::new().parse("[database]\nport = 5432 # Default\nuser = \"admin\"\npool = 1_000\n");
+  /// let (new, _) = TOMLParser::new().parse("[database]\nuser = 'admin'\npool = 1000\nport = 5433\n");
+  /// assert_eq!(vec![Change::Changed(KeyPath::parse("database.port").unwrap(), Value::int(5432), Value::int(5433))],
+  ///   old.diff(&new));
+  /// ```
+  pub fn diff(self: &TOMLParser<'a>, other: &TOMLParser<'a>) -> Vec<Change<'a>> {
+    diff::diff(&node::build(&self.parser, "").to_value(), &node::build(&other.parser, "").to_value())
+  }
+
+  /// Applies the operations of a patch to the document in order, as one transaction: if an operation fails, or a
+  /// `test` operation doesn't match, the document is left exactly as it was and the index of the operation is
+  /// returned in the `PatchError`. See the `patch` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::patch::Operation;
+  /// use tomllib::types::{Value, EditError, PatchError};
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (mut parser, _) = parser.parse("[server]\nhost = \"a\" # Primary\nports = [80, 443]\n");
+  /// parser.apply_patch(&[
+  ///   Operation::Add("server.ports[1]".into(), Value::int(8080)),
+  ///   Operation::Move("server.host".into(), "server.name".into()),
+  /// ]).unwrap();
+  /// assert_eq!("[server]\nports = [80, 8080, 443]\nname = \"a\"\n", format!("{}", parser));
+  /// assert_eq!(Err(PatchError::Edit(1, EditError::KeyNotFound("server.host".to_string(), "server.host".to_string()))),
+  ///   parser.apply_patch(&[
+  ///     Operation::Remove("server.ports".into()),
+  ///     Operation::Remove("server.host".into()),
+  ///   ]));
+  /// assert_eq!(Some(Value::int(443)), parser.get_value("server.ports[2]"));
+  /// ```
+  pub fn apply_patch(self: &mut TOMLParser<'a>, ops: &[Operation<'a>]) -> Result<(), PatchError> {
+    patch::apply_all(self, ops)
+  }
+
+  /// Merges the changes that `theirs` made since `base` into this document, keeping its formatting everywhere theirs
+  /// didn't change anything. Returns the keys that this document and theirs both changed in different ways, which
+  /// keep this document's value. See the `merge3` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let (base, _) = TOMLParser::new().parse("[server]\nhost = \"a\"\nport = 80\n");
+  /// let (mut ours, _) = TOMLParser::new().parse("[server]\nhost = \"b\" # Moved\nport = 80\n");
+  /// let (theirs, _) = TOMLParser::new().parse("[server]\nhost = \"a\"\nport = 8080\n");
+  /// assert!(ours.merge3(&base, &theirs).is_empty());
+  /// assert_eq!("[server]\nhost = \"b\" # Moved\nport = 8080\n", format!("{}", ours));
+  /// ```
+  pub fn merge3(self: &mut TOMLParser<'a>, base: &TOMLParser<'a>, theirs: &TOMLParser<'a>) -> Vec<Conflict<'a>> {
+    self.nodes.take();
+    merge3::merge(&base.parser, &mut self.parser, &theirs.parser)
+  }
+
+  /// Validates the document against `schema`, returning every key that doesn't match it. See the `schema` module for
+  /// details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::schema::{Schema, Field};
+  ///
+  /// let schema = Schema::new().field("server.port", Field::integer().required());
+  /// let (parser, _) = TOMLParser::new().parse("[server]\nport = \"80\"\n");
+  /// let violations = parser.validate(&schema);
+  /// assert_eq!("server.port (line 2): expected an integer, found a string", violations[0].to_string());
+  /// ```
+  pub fn validate(self: &TOMLParser<'a>, schema: &Schema) -> Vec<Violation<'a>> {
+    schema::validate(&self.parser, schema)
+  }
+
+  /// Adds the default of every field of `schema` that's missing from the document, creating tables along the way.
+  /// Defaults are only added inside of tables and arrays of tables that exist, or that were added to the schema by a
+  /// dotted key path. Returns an error if a default can't be added, in which case the defaults before it have been.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::schema::{Schema, Field};
+  /// use tomllib::types::Value;
+  ///
+  /// let schema = Schema::new().field("server.port", Field::integer().default(Value::int(80)));
+  /// let (mut parser, _) = TOMLParser::new().parse("name = \"app\"\n");
+  /// parser.apply_defaults(&schema).unwrap();
+  /// assert_eq!(Some(Value::int(80)), parser.get_value("server.port"));
+  /// ```
+  pub fn apply_defaults(self: &mut TOMLParser<'a>, schema: &Schema<'a>) -> Result<(), EditError> {
+    self.nodes.take();
+    schema::apply_defaults(&mut self.parser, schema)
+  }
+
+  /// Validates the document against a JSON Schema, as if it were JSON, returning every value that doesn't match it.
+  /// See the `json_schema` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::json_schema::JsonSchema;
+  ///
+  /// let schema = JsonSchema::parse(r#"{"properties": {"tags": {"type": "array", "uniqueItems": true}}}"#).unwrap();
+  /// let (parser, _) = TOMLParser::new().parse("tags = [\"a\", \"b\", \"a\"]\n");
+  /// let violations = parser.validate_json_schema(&schema);
+  /// assert_eq!("tags (line 1, /tags): elements 0 and 2 are equal", violations[0].to_string());
+  /// ```
+  pub fn validate_json_schema(self: &TOMLParser<'a>, schema: &JsonSchema) -> Vec<JsonViolation> {
+    json_schema::validate(&self.parser, schema)
+  }
+
+  /// Returns the document rewritten in the style `options` describes, with the same keys, values and comments. See
+  /// the `format` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::format::FormatOptions;
+  ///
+  /// let (parser, _) = TOMLParser::new().parse("[ server ]\nhost=\"a\"   # Primary\n\n\nports = [ 80,443 ]\n[db]\n");
+  /// assert_eq!("[server]\nhost = \"a\" # Primary\n\nports = [80, 443]\n\n[db]\n",
+  ///   parser.format(&FormatOptions::default()));
+  /// ```
+  pub fn format(self: &TOMLParser<'a>, options: &FormatOptions) -> String {
+    format::format(&self.cst(), options)
+  }
+
+  /// Returns the document as indented JSON. Tables become objects, integers and floats become numbers and datetimes
+  /// become strings. See the `json` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  ///
+  /// let (parser, _) = TOMLParser::new().parse("[server]\nport = 8_080\n");
+  /// assert_eq!("{\n  \"server\": {\n    \"port\": 8080\n  }\n}", parser.to_json());
+  /// ```
+  pub fn to_json(self: &TOMLParser<'a>) -> String {
+    internals::json::pretty(&json::to_json(&node::build(&self.parser, "")))
+  }
+
+  /// Returns the document as JSON on one line in the encoding of the toml-test suite, where every value is an object
+  /// with its type and its text. See the `json` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  ///
+  /// let (parser, _) = TOMLParser::new().parse("when = 2016-01-01\n");
+  /// assert_eq!("{\"when\": {\"type\": \"date-local\", \"value\": \"2016-01-01\"}}", parser.to_tagged_json());
+  /// ```
+  pub fn to_tagged_json(self: &TOMLParser<'a>) -> String {
+    json::to_tagged_json(&node::build(&self.parser, "")).to_string()
+  }
+
+  /// Builds a new document from the JSON object `text`. Objects become tables, or inline tables if they're small
+  /// enough for `layout`, and arrays of objects become arrays of tables. Returns a `ConvertError` if `text` isn't a
+  /// JSON object or has values TOML can't represent, like `null`. See the `json` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::json::Layout;
+  /// use tomllib::types::ConvertError;
+  ///
+  /// let parser = TOMLParser::from_json(r#"{"db": {"port": 5432}}"#, &Layout{inline_limit: 0}).unwrap();
+  /// assert_eq!("[db]\nport = 5432\n", parser.to_string());
+  /// assert_eq!(Err(ConvertError::Unrepresentable("db.user".to_string(), "TOML doesn't have null".to_string())),
+  ///   TOMLParser::from_json(r#"{"db": {"user": null}}"#, &Layout::default()).map(|_| ()));
+  /// ```
+  pub fn from_json(text: &str, layout: &json::Layout) -> Result<TOMLParser<'a>, ConvertError> {
+    json::import(text, layout, false)
+  }
+
+  /// Builds a new document from JSON in the encoding of the toml-test suite, where every value is an object with its
+  /// type and its text. Returns a `ConvertError` if a value's text isn't valid TOML 0.4 for its type.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  ///
+  /// let parser = TOMLParser::from_tagged_json(r#"{"a": {"type": "integer", "value": "1_000"}}"#).unwrap();
+  /// assert_eq!("a = 1_000\n", parser.to_string());
+  /// assert_eq!(parser.to_tagged_json(), r#"{"a": {"type": "integer", "value": "1000"}}"#);
+  /// ```
+  pub fn from_tagged_json(text: &str) -> Result<TOMLParser<'a>, ConvertError> {
+    json::import(text, &json::Layout::default(), true)
+  }
+
+  /// Returns a read-only view of the concrete syntax tree of the document, including all whitespace, comments, keys
+  /// as they were written and string quoting styles. See the `cst` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::cst::Item;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("[[tables]] # An array of tables");
+  /// let doc = parser.cst();
+  /// let line = doc.lines().next().unwrap();
+  /// match line.item() {
+  ///   Some(&Item::Table(ref header)) => assert!(header.is_array()),
+  ///   _ => panic!("Expected a table header"),
+  /// }
+  /// assert_eq!(" An array of tables", line.comment().unwrap().text());
+  /// ```
+  pub fn cst(self: &TOMLParser<'a>) -> Document {
+    cst::from_toml(&self.parser.root.borrow())
+  }
+
+  /// Walks the document in document order, calling the `visitor`'s callbacks for every table, key-value pair, array
+  /// element, inline table and comment. See the `visitor` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Position;
+  /// use tomllib::visitor::Visitor;
+  ///
+  /// struct Comments(Vec<(String, Position)>);
+  ///
+  /// impl<'a> Visitor<'a> for Comments {
+  ///   fn visit_comment(&mut self, text: &str, position: Position) {
+  ///     self.0.push((text.to_string(), position));
+  ///   }
+  /// }
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("# First\nkey = [1, # Second\n2]");
+  /// let mut comments = Comments(vec![]);
+  /// parser.accept(&mut comments);
+  /// assert_eq!(vec![(" First".to_string(), Position{line: 1, column: 1}),
+  ///   (" Second".to_string(), Position{line: 2, column: 11})], comments.0);
+  /// ```
+  pub fn accept<V>(self: &TOMLParser<'a>, visitor: &mut V) where V: Visitor<'a> {
+    visitor::walk(&self.parser, visitor)
+  }
+
+  /// Walks the document in document order like `accept`, replacing any values the `visitor` returns replacements for.
+  /// Replacements are applied with `try_set_value` after the walk, and the first one that fails is returned as an
+  /// error, with none of them applied.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::{Value, Position};
+  /// use tomllib::visitor::VisitorMut;
+  ///
+  /// struct Upgrade;
+  ///
+  /// impl<'a> VisitorMut<'a> for Upgrade {
+  ///   fn visit_keyval(&mut self, key: &str, _value: &Value<'a>, _position: Position) -> Option<Value<'a>> {
+  ///     if key.ends_with(".version") {
+  ///       Some(Value::basic_string("2.0").unwrap())
+  ///     } else {
+  ///       None
+  ///     }
+  ///   }
+  /// }
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (mut parser, _) = parser.parse("[lib]\nversion = \"1.0\" # Current version");
+  /// parser.accept_mut(&mut Upgrade).unwrap();
+  /// assert_eq!("[lib]\nversion = \"2.0\" # Current version", format!("{}", parser));
+  /// ```
+  pub fn accept_mut<V>(self: &mut TOMLParser<'a>, visitor: &mut V) -> Result<(), EditError> where V: VisitorMut<'a> {
+    self.nodes.take();
+    visitor::walk_mut(&mut self.parser, visitor)
+  }
+
+  /// Returns an iterator over the full keys of every key-value pair in the document, in the order they appear in the
+  /// document. Key-value pairs nested inside of arrays and inline tables aren't included, use `walk` for those.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("name = \"tomllib\"\n[[bin]]\npath = \"a\"\n[[bin]]\npath = \"b\"");
+  /// let keys: Vec<String> = parser.keys().collect();
+  /// assert_eq!(vec!["name", "bin[0].path", "bin[1].path"], keys);
+  /// ```
+  pub fn keys(self: &TOMLParser<'a>) -> Keys {
+    iter::keys(&self.parser)
+  }
+
+  /// Returns an iterator over `(full_key, Value)` pairs for every key-value pair in the document, in the order they
+  /// appear in the document. Key-value pairs nested inside of arrays and inline tables aren't included, use `walk`
+  /// for those.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("[server]\nport = 8080\nhosts = [\"a\", \"b\"]");
+  /// let mut entries = parser.entries();
+  /// assert_eq!(Some(("server.port".to_string(), Value::int(8080))), entries.next());
+  /// assert_eq!("server.hosts", entries.next().unwrap().0);
+  /// assert_eq!(None, entries.next());
+  /// ```
+  pub fn entries(self: &TOMLParser<'a>) -> Entries<'a> {
+    iter::entries(&self.parser)
+  }
+
+  /// Returns an iterator over `(full_key, Value)` pairs for every value in the document, in the order they appear in
+  /// the document. Unlike `entries` this includes every value in arrays and every key-value pair in inline tables,
+  /// with the containing value coming before the values it contains.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("points = [{ x = 1 }, { x = 2 }]");
+  /// let keys: Vec<String> = parser.walk().map(|(key, _)| key).collect();
+  /// assert_eq!(vec!["points", "points[0]", "points[0].x", "points[1]", "points[1].x"], keys);
+  /// ```
+  pub fn walk(self: &TOMLParser<'a>) -> Entries<'a> {
+    iter::walk(&self.parser)
+  }
+
+  /// Returns an iterator over `(full_key, Value)` pairs for the direct children of `table_key` that have values. Child
+  /// tables and arrays of tables are skipped. Use `""` for the root table. `table_key` can also be the key of an
+  /// inline table or an array, in which case the iterator yields its key-value pairs or values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("[a]\nb = 1\n[a.c]\nd = 2");
+  /// let entries: Vec<(String, Value)> = parser.table_entries("a").collect();
+  /// assert_eq!(vec![("a.b".to_string(), Value::int(1))], entries);
+  /// ```
+  pub fn table_entries<K>(self: &TOMLParser<'a>, table_key: K) -> Entries<'a> where K: Into<KeyPath> {
+    iter::table_entries(&self.parser, &self.resolve(table_key))
+  }
+
+  /// Returns a read-only view of the table, array of tables or inline table at `key`, or `None` if `key` doesn't exist
+  /// or isn't a table. Use `""` for the root table. See the `table` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("[[servers]]\nports = [80, 443]\n[[servers]]\nports = [8080]");
+  /// let server = parser.table("servers[0]").unwrap();
+  /// assert_eq!(Some(Value::int(443)), server.get("ports[1]"));
+  /// ```
+  pub fn table<'p, K>(self: &'p TOMLParser<'a>, key: K) -> Option<TableRef<'p, 'a>> where K: Into<KeyPath> {
+    let key = self.resolve(key);
+    TableRef::new(self, key)
+  }
+
+  /// Returns a mutable view of the table, array of tables or inline table at `key`, or `None` if `key` doesn't exist
+  /// or isn't a table. Use `""` for the root table. See the `table` module for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (mut parser, _) = parser.parse("[[servers]]\nport = 80\n[[servers]]\nport = 8080");
+  /// parser.table_mut("servers[1]").unwrap().set("port", Value::int(9090)).unwrap();
+  /// assert_eq!("[[servers]]\nport = 80\n[[servers]]\nport = 9090", format!("{}", parser));
+  /// ```
+  pub fn table_mut<'p, K>(self: &'p mut TOMLParser<'a>, key: K) -> Option<TableMut<'p, 'a>>
+    where K: Into<KeyPath> {
+    self.nodes.take();
+    let key = self.resolve(key);
+    TableMut::new(self, key)
+  }
+
+  /// Returns a mutable copy of the document's nodes that can be indexed with `IndexMut`. Indexing a key that doesn't
+  /// exist creates it, so missing keys and tables can be assigned directly. The changes are only written to the
+  /// document when `DocMut::commit` is called, either all of them or, if one fails, none. See the `node` module for
+  /// details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (mut parser, _) = parser.parse("[[bin]]\nname = \"a\"\n");
+  /// {
+  ///   let mut doc = parser.edit();
+  ///   doc["bin"][1]["name"] = Value::basic_string("b").unwrap().into();
+  ///   doc["lib"]["path"] = Value::basic_string("lib.rs").unwrap().into();
+  ///   doc.commit().unwrap();
+  /// }
+  /// assert_eq!("[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n\n[lib]\npath = \"lib.rs\"\n", format!("{}", parser));
+  /// ```
+  pub fn edit<'p>(self: &'p mut TOMLParser<'a>) -> DocMut<'p, 'a> {
+    self.nodes.take();
+    DocMut::new(&mut self.parser)
+  }
+
+  /// Returns every `(KeyPath, Value)` pair matched by `query`, in document order. Only keys that hold a value are
+  /// returned: tables and arrays of tables don't have one, so a query that only matches those, like `servers.*` below,
+  /// returns nothing. Arrays and inline tables are values. See the `query` module for the query syntax.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("[servers.alpha]\nip = \"10.0.0.1\"\n[servers.beta]\nip = \"10.0.0.2\"");
+  /// let ips = parser.query("servers.*.ip").unwrap();
+  /// assert_eq!("servers.beta.ip", ips[1].0.to_string());
+  /// assert_eq!(Value::basic_string("10.0.0.2").unwrap(), ips[1].1);
+  /// assert!(parser.query("servers.*").unwrap().is_empty());
+  /// ```
+  pub fn query(self: &TOMLParser<'a>, query: &str) -> Result<Vec<(KeyPath, Value<'a>)>, QueryError> {
+    Query::parse(query).map(|query| query::run(&self.parser, &query))
+  }
+
+  /// Returns the entry for the full key `key`, which can be used to read the key's value or insert a value if the key
+  /// doesn't exist, like `HashMap::entry`. Inserting creates any tables along the key's path that don't exist.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (mut parser, _) = parser.parse("[a]\nb = 2\n");
+  /// assert_eq!(Ok(Value::int(2)), parser.entry("a.b").or_insert(Value::int(1)));
+  /// assert_eq!(Ok(Value::int(1)), parser.entry("a.c").or_insert(Value::int(1)));
+  /// assert_eq!("[a]\nb = 2\nc = 1\n", format!("{}", parser));
+  /// ```
+  pub fn entry<'p, K>(self: &'p mut TOMLParser<'a>, key: K) -> Entry<'p, 'a> where K: Into<KeyPath> {
+    self.nodes.take();
+    let key = self.resolve(key);
+    Entry::new(&mut self.parser, key)
+  }
+
+  // Converts `key` into the full key the parser uses
+  fn resolve<K>(self: &TOMLParser<'a>, key: K) -> String where K: Into<KeyPath> {
+    key::resolve(&self.parser, &key.into())
+  }
+}
+
+/// Returns the table, array of tables or value at the top-level key `key`. Panics if `key` doesn't exist. See the
+/// `node` module for details.
+///
+/// # Examples
+///
+/// ```
+/// use tomllib::TOMLParser;
+/// use tomllib::types::Value;
+///
+/// let parser = TOMLParser::new();
+/// let (parser, _) = parser.parse("[package]\nversion = \"0.1.2\"");
+/// assert_eq!(Some(&Value::basic_string("0.1.2").unwrap()), parser["package"]["version"].as_value());
+/// ```
+impl<'a, 's> Index<&'s str> for TOMLParser<'a> {
+  type Output = Node<'a>;
+
+  fn index(&self, key: &'s str) -> &Node<'a> {
+    &self.nodes.get_or_init(|| node::build(&self.parser, ""))[key]
+  }
+}
+
+impl<'a> Index<KeyPath> for TOMLParser<'a> {
+  type Output = Node<'a>;
+
+  fn index(&self, path: KeyPath) -> &Node<'a> {
+    &self.nodes.get_or_init(|| node::build(&self.parser, ""))[path]
   }
 }
 
@@ -0,0 +1,505 @@
+//! Layered configuration: a base document with other documents merged over it, remembering where every value came
+//! from.
+//!
+//! `Layers` starts from a base document and merges each overlay into it in turn. Tables are merged key by key, so
+//! values, tables and comments of the base that an overlay doesn't mention are kept as they are. Values that both
+//! documents have are replaced by the overlay's, except for inline tables, which are merged key by key like tables,
+//! and arrays and arrays of tables, which are combined according to an `ArrayStrategy`. Tables and values that only
+//! the overlay has are added to the document.
+//!
+//! Every value remembers the source and position it was set at, along with the sources it overrode, which `origins`
+//! returns and `explain` describes.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::merge::{Layers, ArrayStrategy};
+//!
+//! let defaults = TOMLParser::new();
+//! let (defaults, _) = defaults.parse("[database]\nserver = \"localhost\"\nport = 5432 # The default port\n");
+//! let user = TOMLParser::new();
+//! let (user, _) = user.parse("[database]\nport = 5433\n");
+//! let mut layers = Layers::new("defaults.toml", defaults).array_strategy(ArrayStrategy::Append);
+//! layers.overlay("user.toml", &user).unwrap();
+//! assert_eq!("[database]\nserver = \"localhost\"\nport = 5433 # The default port\n",
+//!   format!("{}", layers.document()));
+//! assert_eq!(Some("database.port = 5433, set in user.toml:2:1, overriding defaults.toml:3:1".to_string()),
+//!   layers.explain("database.port"));
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+use std::rc::Rc;
+use TOMLParser;
+use node::{self, Node};
+use key::{self, KeyPath, KeySegment, quote_key, normalize_key};
+use internals::parser::Parser;
+use internals::edit::TableKind;
+use internals::walker::{self, Event, child_key};
+use internals::graft;
+use types::{Value, Children, EditError, Position};
+
+/// How an array or array of tables in an overlay is combined with the same array in the document.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ArrayStrategy {
+  /// The overlay's array replaces the document's.
+  Replace,
+  /// The overlay's elements are added after the document's.
+  Append,
+  /// Elements at the same index are merged, like tables for tables and inline tables, and the overlay's element
+  /// replaces the document's for everything else. Elements past the end of the document's array are added.
+  MergeByIndex,
+}
+
+/// Where a value was set: the name of the source document and the position of the value in it.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Origin {
+  /// The name the source was added to `Layers` with, usually its file name.
+  pub source: String,
+  /// The position of the value in the source.
+  pub position: Position,
+}
+
+impl Display for Origin {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}:{}:{}", self.source, self.position.line, self.position.column)
+  }
+}
+
+/// A base document with other documents merged over it. See the module docs for details.
+pub struct Layers<'a> {
+  document: TOMLParser<'a>,
+  strategy: ArrayStrategy,
+  // Strategies for specific arrays, by full key without indices
+  strategies: HashMap<String, ArrayStrategy>,
+  // Every source that set each value, oldest first
+  origins: HashMap<String, Vec<Origin>>,
+}
+
+// Full keys of the values in `parser` with their positions
+fn positions(parser: &Parser) -> HashMap<String, Position> {
+  let mut positions = HashMap::new();
+  walker::walk(&parser.root.borrow(), |event| {
+    match event {
+      Event::KeyVal(key, _, position) | Event::TableKeyVal(key, _, position) |
+      Event::ArrayElement(key, _, position) => {
+        positions.insert(key, position);
+      },
+      _ => (),
+    }
+  });
+  positions
+}
+
+// Returns true if `key` is inside of `table`, but isn't `table` itself
+fn is_inside(key: &str, table: &str) -> bool {
+  key.starts_with(&format!("{}.", table)) || key.starts_with(&format!("{}[", table))
+}
+
+// The full key of an array without any indices, which is what array strategies are looked up by
+fn strategy_key(path: &KeyPath) -> String {
+  let names: Vec<KeySegment> = path.segments().iter().filter(|segment| matches!(segment, KeySegment::Key(_))).cloned()
+    .collect();
+  KeyPath::from(names).to_string()
+}
+
+fn index_key(key: &str, index: usize) -> String {
+  format!("{}[{}]", key, index)
+}
+
+// What a key refers to
+enum Kind {
+  Missing,
+  Value,
+  InlineTable,
+  Table,
+  ArrayOfTables(usize),
+}
+
+fn kind(parser: &Parser, key: &str) -> Kind {
+  if key.is_empty() {
+    return Kind::Table;
+  }
+  match parser.get_value(key) {
+    Some(Value::InlineTable(_)) => return Kind::InlineTable,
+    Some(_) => return Kind::Value,
+    None => (),
+  }
+  match parser.get_children(key) {
+    Some(Children::Count(count)) => Kind::ArrayOfTables(count.get()),
+    Some(Children::Keys(_)) => Kind::Table,
+    None => Kind::Missing,
+  }
+}
+
+// Merges one overlay into the document. Runs once without changing anything to find conflicts, then again to apply
+// the changes, so a failed merge leaves the document as it was.
+struct Merge<'m, 'a: 'm> {
+  target: &'m mut Parser<'a>,
+  overlay: &'m Parser<'a>,
+  source: &'m str,
+  positions: HashMap<String, Position>,
+  layers: &'m mut HashMap<String, Vec<Origin>>,
+  strategy: ArrayStrategy,
+  strategies: &'m HashMap<String, ArrayStrategy>,
+  apply: bool,
+}
+
+impl<'m, 'a> Merge<'m, 'a> {
+  fn strategy(&self, key: &str) -> ArrayStrategy {
+    *self.strategies.get(&strategy_key(&KeyPath::from(key))).unwrap_or(&self.strategy)
+  }
+
+  // Adds the overlay's origin to the history of `target`
+  fn touched(&mut self, target: &str, key: &str) {
+    if !self.apply {
+      return;
+    }
+    if let Some(&position) = self.positions.get(key) {
+      let origin = Origin{source: self.source.to_string(), position};
+      self.layers.entry(target.to_string()).or_default().push(origin);
+    }
+  }
+
+  // Forgets the origins of everything inside of `target`
+  fn clear(&mut self, target: &str) {
+    self.layers.retain(|layer_key, _| !is_inside(layer_key, target));
+  }
+
+  // Records that the overlay's `key` and everything inside of it replaced `target` and everything inside of it
+  fn replaced(&mut self, target: &str, key: &str) {
+    if !self.apply {
+      return;
+    }
+    self.clear(target);
+    let keys: Vec<String> = self.positions.keys().filter(|k| *k == key || is_inside(k, key)).cloned().collect();
+    for k in keys {
+      self.touched(&format!("{}{}", target, &k[key.len()..]), &k);
+    }
+  }
+
+  fn merge_table(&mut self, target: &str, key: &str) -> Result<(), EditError> {
+    let names = match self.overlay.get_children(key) {
+      Some(Children::Keys(names)) => names.borrow().clone(),
+      _ => vec![],
+    };
+    for name in names {
+      self.merge_key(&child_key(target, &name), &child_key(key, &name))?;
+    }
+    Ok(())
+  }
+
+  fn merge_key(&mut self, target: &str, key: &str) -> Result<(), EditError> {
+    match (kind(self.target, target), kind(self.overlay, key)) {
+      (_, Kind::Missing) => Ok(()),
+      (Kind::InlineTable, Kind::Table) | (Kind::Table, Kind::InlineTable) | (Kind::Table, Kind::Table) =>
+        self.merge_table(target, key),
+      (Kind::Value, Kind::Value) | (Kind::Value, Kind::InlineTable) | (Kind::InlineTable, Kind::Value) |
+      (Kind::InlineTable, Kind::InlineTable) => {
+        let (current, val) = match (self.target.get_value(target), self.overlay.get_value(key)) {
+          (Some(current), Some(val)) => (current, val),
+          _ => return Ok(()),
+        };
+        let merged = self.merge_values(target, key, current, val);
+        if self.apply {
+          self.target.set_value(target, merged)
+        } else {
+          Parser::convert_vector(&merged, target).map(|_| ()).map_err(|path| {
+            EditError::InvalidNestedValue(target.to_string(), path)
+          })
+        }
+      },
+      (Kind::Missing, Kind::Value) | (Kind::Missing, Kind::InlineTable) => {
+        if let (true, Some(val)) = (self.apply, self.overlay.get_value(key)) {
+          self.target.insert_value(target, val)?;
+          self.replaced(target, key);
+        }
+        Ok(())
+      },
+      (Kind::Missing, _) => {
+        let node = node::build(self.overlay, key);
+        self.add_tables(target, key, node, 0, 0)
+      },
+      (Kind::ArrayOfTables(count), Kind::ArrayOfTables(overlay_count)) => {
+        let node = node::build(self.overlay, key);
+        match self.strategy(target) {
+          ArrayStrategy::Replace => {
+            if self.apply {
+              self.target.remove_array_of_tables(target);
+              self.clear(target);
+            }
+            self.add_tables(target, key, node, 0, 0)
+          },
+          ArrayStrategy::Append => self.add_tables(target, key, node, count, 0),
+          ArrayStrategy::MergeByIndex => {
+            for i in 0..count.min(overlay_count) {
+              self.merge_table(&index_key(target, i), &index_key(key, i))?;
+            }
+            self.add_tables(target, key, node, count, count)
+          },
+        }
+      },
+      (Kind::Value, _) | (Kind::InlineTable, _) => Err(EditError::NotATable(target.to_string(), target.to_string())),
+      (Kind::Table, Kind::Value) => Err(EditError::NotAValue(target.to_string(), target.to_string())),
+      _ => Err(EditError::InvalidTable(target.to_string(), target.to_string())),
+    }
+  }
+
+  // Grafts the overlay's table or array of tables `node` at `target`. The first `skip` tables of an array of tables
+  // are left out, and the rest become entries `offset` and up.
+  fn add_tables(&mut self, target: &str, key: &str, node: Node<'a>, offset: usize, skip: usize)
+    -> Result<(), EditError> {
+    let path = KeyPath::from(target);
+    let (name, parent) = match path.last() {
+      Some(KeySegment::Key(name)) => (quote_key(name), key::prefix(&path, path.len() - 1)),
+      _ => return Err(EditError::NotATable(target.to_string(), target.to_string())),
+    };
+    if !self.apply {
+      return match self.target.table_kind(&parent) {
+        TableKind::Section | TableKind::Implicit | TableKind::Missing => Ok(()),
+        _ => Err(EditError::NotATable(target.to_string(), parent)),
+      };
+    }
+    let node = match node {
+      Node::ArrayOfTables(tables) => {
+        let count = tables.len();
+        let node = Node::ArrayOfTables(tables.into_iter().skip(skip).collect());
+        for i in skip..count {
+          self.replaced(&index_key(target, offset + i - skip), &index_key(key, i));
+        }
+        node
+      },
+      node => {
+        self.replaced(target, key);
+        node
+      },
+    };
+    let fragment = graft::node_fragment(&parent, &Node::Table(vec![(name, node)]))?;
+    self.target.graft(&parent, &fragment)
+  }
+
+  fn merge_values(&mut self, target: &str, key: &str, current: Value<'a>, val: Value<'a>) -> Value<'a> {
+    match (current, val) {
+      (Value::InlineTable(current), Value::InlineTable(val)) => {
+        let mut pairs = (*current).clone();
+        for (name, val) in val.iter() {
+          let name_key = normalize_key(name);
+          let (child_target, child) = (child_key(target, &name_key), child_key(key, &name_key));
+          match pairs.iter().position(|pair| normalize_key(&pair.0) == name_key) {
+            Some(i) => {
+              let current = pairs[i].1.clone();
+              pairs[i].1 = self.merge_values(&child_target, &child, current, val.clone());
+            },
+            None => {
+              self.replaced(&child_target, &child);
+              pairs.push((name.clone(), val.clone()));
+            },
+          }
+        }
+        self.touched(target, key);
+        Value::InlineTable(Rc::new(pairs))
+      },
+      (Value::Array(current), Value::Array(val)) => {
+        let mut values = (*current).clone();
+        match self.strategy(target) {
+          ArrayStrategy::Replace => {
+            self.replaced(target, key);
+            return Value::Array(val);
+          },
+          ArrayStrategy::Append => for (i, val) in val.iter().enumerate() {
+            self.replaced(&index_key(target, current.len() + i), &index_key(key, i));
+            values.push(val.clone());
+          },
+          ArrayStrategy::MergeByIndex => for (i, val) in val.iter().enumerate() {
+            let (child_target, child) = (index_key(target, i), index_key(key, i));
+            if i < values.len() {
+              let current = values[i].clone();
+              values[i] = self.merge_values(&child_target, &child, current, val.clone());
+            } else {
+              self.replaced(&child_target, &child);
+              values.push(val.clone());
+            }
+          },
+        }
+        self.touched(target, key);
+        Value::Array(Rc::new(values))
+      },
+      (_, val) => {
+        self.replaced(target, key);
+        val
+      },
+    }
+  }
+}
+
+impl<'a> Layers<'a> {
+  /// Starts a set of layers from the document `base`, naming it `source` in origins.
+  pub fn new<S>(source: S, base: TOMLParser<'a>) -> Layers<'a> where S: Into<String> {
+    let source = source.into();
+    let origins = positions(&base.parser).into_iter().map(|(key, position)| {
+      (key, vec![Origin{source: source.clone(), position}])
+    }).collect();
+    Layers{document: base, strategy: ArrayStrategy::Replace, strategies: HashMap::new(), origins}
+  }
+
+  /// Sets the strategy for combining arrays and arrays of tables that don't have their own strategy. The default is
+  /// `ArrayStrategy::Replace`.
+  pub fn array_strategy(mut self, strategy: ArrayStrategy) -> Layers<'a> {
+    self.strategy = strategy;
+    self
+  }
+
+  /// Sets the strategy for combining the array or array of tables at `key`. Indices in `key` are ignored, so
+  /// `"servers.tags"` applies to the `tags` array of every table in the array of tables `servers`.
+  pub fn array_strategy_at<K>(mut self, key: K, strategy: ArrayStrategy) -> Layers<'a> where K: Into<KeyPath> {
+    self.strategies.insert(strategy_key(&key.into()), strategy);
+    self
+  }
+
+  /// Merges the document `overlay` into the merged document, naming it `source` in origins. If `overlay` conflicts
+  /// with the merged document, for instance by having a table where the merged document has a value, an `EditError`
+  /// is returned and nothing is changed.
+  pub fn overlay<S>(&mut self, source: S, overlay: &TOMLParser<'a>) -> Result<(), EditError> where S: Into<String> {
+    let source = source.into();
+    self.document.nodes.take();
+    for &apply in &[false, true] {
+      let mut merge = Merge{
+        target: &mut self.document.parser,
+        overlay: &overlay.parser,
+        source: &source,
+        positions: positions(&overlay.parser),
+        layers: &mut self.origins,
+        strategy: self.strategy,
+        strategies: &self.strategies,
+        apply,
+      };
+      merge.merge_table("", "")?;
+    }
+    Ok(())
+  }
+
+  /// Returns the merged document.
+  pub fn document(&self) -> &TOMLParser<'a> {
+    &self.document
+  }
+
+  /// Returns the merged document, consuming the layers.
+  pub fn into_document(self) -> TOMLParser<'a> {
+    self.document
+  }
+
+  /// Returns every source that set the value at `key`, oldest first, so the last one is where the current value came
+  /// from. Returns `None` for tables and keys that don't exist.
+  pub fn origins<K>(&self, key: K) -> Option<&[Origin]> where K: Into<KeyPath> {
+    let key = key::resolve(&self.document.parser, &key.into());
+    self.origins.get(&key).map(|origins| &origins[..])
+  }
+
+  /// Describes where the value at `key` came from and which sources it overrode, or returns `None` for tables and keys
+  /// that don't exist.
+  pub fn explain<K>(&self, key: K) -> Option<String> where K: Into<KeyPath> {
+    let key = key::resolve(&self.document.parser, &key.into());
+    let val = self.document.parser.get_value(&key[..])?;
+    let origins = self.origins.get(&key)?;
+    let (last, overridden) = origins.split_last()?;
+    let mut explanation = format!("{} = {}, set in {}", key, val, last);
+    if !overridden.is_empty() {
+      let overridden: Vec<String> = overridden.iter().rev().map(|origin| origin.to_string()).collect();
+      explanation.push_str(&format!(", overriding {}", overridden.join(", ")));
+    }
+    Some(explanation)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use merge::{Layers, ArrayStrategy, Origin};
+  use types::{Value, EditError, Position};
+
+  fn merge(base: &'static str, overlay: &'static str, strategy: ArrayStrategy) -> Result<String, EditError> {
+    let (base, _) = TOMLParser::new().parse(base);
+    let (overlay, _) = TOMLParser::new().parse(overlay);
+    let mut layers = Layers::new("base.toml", base).array_strategy(strategy);
+    layers.overlay("overlay.toml", &overlay).map(|_| format!("{}", layers.document()))
+  }
+
+  #[test]
+  fn test_layers() {
+    let (defaults, _) = TOMLParser::new().parse("# Defaults\ntitle = \"app\"\n\n[database]\nserver = \"localhost\"\n\
+      port = 5432 # Default port\nopts = { timeout = 5, retries = 3 }\n");
+    let (site, _) = TOMLParser::new().parse("[database]\nport = 6432\n\n[cache]\nsize = 10\n");
+    let (user, _) = TOMLParser::new().parse("[database]\nport = 7432\nopts = { retries = 1 }\n");
+    let mut layers = Layers::new("defaults.toml", defaults);
+    layers.overlay("site.toml", &site).unwrap();
+    layers.overlay("user.toml", &user).unwrap();
+    assert_eq!("# Defaults\ntitle = \"app\"\n\n[database]\nserver = \"localhost\"\nport = 7432 # Default port\n\
+      opts = { timeout = 5, retries = 1 }\n\n[cache]\nsize = 10\n", format!("{}", layers.document()));
+    assert_eq!(Some("database.port = 7432, set in user.toml:2:1, overriding site.toml:2:1, defaults.toml:6:1"
+      .to_string()), layers.explain("database.port"));
+    assert_eq!(Some("database.opts.timeout = 5, set in defaults.toml:7:10".to_string()),
+      layers.explain("database.opts.timeout"));
+    assert_eq!(Some(&[Origin{source: "site.toml".to_string(), position: Position{line: 5, column: 1}}][..]),
+      layers.origins("cache.size"));
+    assert_eq!(None, layers.explain("database"));
+    assert_eq!(Some(Value::int(1)), layers.into_document().get_value("database.opts.retries"));
+  }
+
+  #[test]
+  fn test_array_strategies() {
+    let base = "a = [1, 2, 3]\nb = [{ x = 1, y = 2 }]\n";
+    let overlay = "a = [4, 5]\nb = [{ x = 3 }, { x = 4 }]\n";
+    assert_eq!(Ok("a = [4, 5]\nb = [{ x = 3 }, { x = 4 }]\n".to_string()),
+      merge(base, overlay, ArrayStrategy::Replace));
+    assert_eq!(Ok("a = [1, 2, 3, 4, 5]\nb = [{ x = 1, y = 2 }, { x = 3 }, { x = 4 }]\n".to_string()),
+      merge(base, overlay, ArrayStrategy::Append));
+    assert_eq!(Ok("a = [4, 5, 3]\nb = [{ x = 3, y = 2 }, { x = 4 }]\n".to_string()),
+      merge(base, overlay, ArrayStrategy::MergeByIndex));
+
+    let (base, _) = TOMLParser::new().parse(base);
+    let (overlay, _) = TOMLParser::new().parse(overlay);
+    let mut layers = Layers::new("base.toml", base).array_strategy(ArrayStrategy::Append)
+      .array_strategy_at("b", ArrayStrategy::Replace);
+    layers.overlay("overlay.toml", &overlay).unwrap();
+    assert_eq!("a = [1, 2, 3, 4, 5]\nb = [{ x = 3 }, { x = 4 }]\n", format!("{}", layers.document()));
+    assert_eq!(Some(&[Origin{source: "base.toml".to_string(), position: Position{line: 1, column: 12}}][..]),
+      layers.origins("a[2]"));
+    assert_eq!(Some(&[Origin{source: "overlay.toml".to_string(), position: Position{line: 1, column: 9}}][..]),
+      layers.origins("a[4]"));
+    assert_eq!(None, layers.origins("b[0].y"));
+  }
+
+  #[test]
+  fn test_array_of_tables_strategies() {
+    let base = "[[bin]]\nname = \"a\"\npath = \"a.rs\"\n\n[[bin]]\nname = \"b\"\n\n[lib]\npath = \"lib.rs\"\n";
+    let overlay = "[[bin]]\nname = \"c\"\n";
+    assert_eq!(Ok("[lib]\npath = \"lib.rs\"\n\n[[bin]]\nname = \"c\"\n".to_string()),
+      merge(base, overlay, ArrayStrategy::Replace));
+    assert_eq!(Ok("[[bin]]\nname = \"a\"\npath = \"a.rs\"\n\n[[bin]]\nname = \"b\"\n\n[[bin]]\nname = \"c\"\n\n\
+      [lib]\npath = \"lib.rs\"\n".to_string()), merge(base, overlay, ArrayStrategy::Append));
+    assert_eq!(Ok("[[bin]]\nname = \"c\"\npath = \"a.rs\"\n\n[[bin]]\nname = \"b\"\n\n[lib]\npath = \"lib.rs\"\n"
+      .to_string()), merge(base, overlay, ArrayStrategy::MergeByIndex));
+    assert_eq!(Ok("[[bin]]\nname = \"b\"\n\n[[bin]]\nname = \"c\"\n".to_string()),
+      merge("[[bin]]\nname = \"a\"\n", "[[bin]]\nname = \"b\"\n[[bin]]\nname = \"c\"\n", ArrayStrategy::MergeByIndex));
+  }
+
+  #[test]
+  fn test_conflicts() {
+    let base = "a = 1\n[b]\nc = 2\n[[d]]\ne = 3\n";
+    for (overlay, err) in vec![
+      ("f = 1\n[a]\nx = 1\n", EditError::NotATable("a".to_string(), "a".to_string())),
+      ("f = 1\nb = 1\n", EditError::NotAValue("b".to_string(), "b".to_string())),
+      ("f = 1\n[d]\nx = 1\n", EditError::InvalidTable("d".to_string(), "d".to_string())),
+      ("f = 1\n[[b]]\nx = 1\n", EditError::InvalidTable("b".to_string(), "b".to_string())),
+    ] {
+      let (base_parser, _) = TOMLParser::new().parse(base);
+      let (overlay, _) = TOMLParser::new().parse(overlay);
+      let mut layers = Layers::new("base.toml", base_parser);
+      assert_eq!(Err(err), layers.overlay("overlay.toml", &overlay));
+      assert_eq!(base, format!("{}", layers.document()));
+      assert_eq!(None, layers.origins("f"));
+    }
+  }
+}
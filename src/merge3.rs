@@ -0,0 +1,512 @@
+//! Three-way merges: combining the changes two documents made to a common base, keeping the formatting of one of
+//! them.
+//!
+//! `TOMLParser::merge3` is called on "ours", the document that's kept, with the base both documents started from and
+//! "theirs", the other document. Every key that theirs changed, added or removed since the base is changed in ours
+//! the same way with format-preserving edits, so whitespace and comments of ours outside of those keys are kept.
+//! Tables, inline tables and arrays of tables that have the same length in all three documents are merged key by key
+//! or table by table, while other values, including arrays, are merged as a whole. Documents are compared by meaning
+//! the way the `diff` module does, so reformatting a value isn't a change.
+//!
+//! A key that both ours and theirs changed in different ways is a conflict: ours keeps its value and a `Conflict` is
+//! returned with all three versions. `conflict_markers` writes a document with git style conflict markers around the
+//! lines of the conflicting keys.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::merge3;
+//! use tomllib::types::Value;
+//!
+//! let (base, _) = TOMLParser::new().parse("[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\n\
+//!   log = \"0.3\"\n");
+//! let (mut ours, _) = TOMLParser::new().parse("[package]\nname = \"app\"\nversion = \"0.2.0\" # Released\n\n\
+//!   [dependencies]\nlog = \"0.3\"\nnom = \"1.2\"\n");
+//! let (theirs, _) = TOMLParser::new().parse("[package]\nname = \"app\"\nversion = \"0.1.1\"\n\n[dependencies]\n\
+//!   log = \"0.4\"\nregex = \"0.1\"\n");
+//! let conflicts = ours.merge3(&base, &theirs);
+//! assert_eq!(1, conflicts.len());
+//! assert_eq!("package.version", conflicts[0].key.to_string());
+//! assert_eq!(Some(Value::basic_string("0.1.1").unwrap()), conflicts[0].theirs);
+//! assert_eq!("[package]\nname = \"app\"\nversion = \"0.2.0\" # Released\n\n[dependencies]\nlog = \"0.4\"\n\
+//!   nom = \"1.2\"\nregex = \"0.1\"\n", format!("{}", ours));
+//! assert_eq!("[package]\nname = \"app\"\n<<<<<<< ours\nversion = \"0.2.0\" # Released\n=======\n\
+//!   version = \"0.1.1\"\n>>>>>>> theirs\n\n[dependencies]\nlog = \"0.4\"\nnom = \"1.2\"\nregex = \"0.1\"\n",
+//!   merge3::conflict_markers(&ours, &theirs, &conflicts));
+//! ```
+
+use std::fmt;
+use std::fmt::Display;
+use TOMLParser;
+use diff;
+use patch;
+use node::{self, Node, entries, find_entry};
+use key::{KeyPath, KeySegment, quote_key};
+use internals::parser::Parser;
+use internals::edit::TableKind;
+use internals::walker::{self, Event, child_key};
+use types::Value;
+
+/// A key that ours and theirs both changed, in different ways. Each version is `None` if the key doesn't exist in
+/// that document, and tables are given as inline tables.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Conflict<'a> {
+  /// The full key of the conflict.
+  pub key: KeyPath,
+  /// The value in the base document.
+  pub base: Option<Value<'a>>,
+  /// The value in ours, which is what the merged document has.
+  pub ours: Option<Value<'a>>,
+  /// The value in theirs.
+  pub theirs: Option<Value<'a>>,
+}
+
+impl<'a> Display for Conflict<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let show = |val: &Option<Value<'a>>| val.as_ref().map_or("missing".to_string(), |val| format!("{}", val));
+    write!(f, "Conflict at {}: base {}, ours {}, theirs {}", self.key, show(&self.base), show(&self.ours),
+      show(&self.theirs))
+  }
+}
+
+// Returns the node at `key`, if it exists
+fn node_at<'a>(parser: &Parser<'a>, key: &str) -> Option<Node<'a>> {
+  match parser.table_kind(key) {
+    TableKind::Missing => None,
+    _ => Some(node::build(parser, key)),
+  }
+}
+
+fn same(a: Option<&Node>, b: Option<&Node>) -> bool {
+  match (a, b) {
+    (Some(a), Some(b)) => diff::same(&a.to_value(), &b.to_value()),
+    (None, None) => true,
+    _ => false,
+  }
+}
+
+struct Merge<'p, 'a: 'p> {
+  ours: &'p mut Parser<'a>,
+  conflicts: Vec<Conflict<'a>>,
+}
+
+impl<'p, 'a> Merge<'p, 'a> {
+  fn conflict(&mut self, key: &str, base: Option<&Node<'a>>, ours: Option<&Node<'a>>, theirs: Option<&Node<'a>>) {
+    self.conflicts.push(Conflict{
+      key: KeyPath::parse(key).unwrap_or_else(|_| KeyPath::root()),
+      base: base.map(Node::to_value),
+      ours: ours.map(Node::to_value),
+      theirs: theirs.map(Node::to_value),
+    });
+  }
+
+  // Makes ours match theirs at `key`. An edit that fails is reported as a conflict and undone.
+  fn take(&mut self, key: &str, base: Option<&Node<'a>>, ours: Option<&Node<'a>>, theirs: Option<&Node<'a>>) {
+    let snapshot = self.ours.snapshot();
+    let result = match (ours, theirs) {
+      (_, None) => self.ours.remove_key(key),
+      (None, Some(theirs)) => patch::add(self.ours, key, theirs),
+      (Some(_), Some(theirs)) => patch::replace(self.ours, key, theirs),
+    };
+    if result.is_err() {
+      self.ours.restore(snapshot);
+      self.conflict(key, base, ours, theirs);
+    }
+  }
+
+  fn merge(&mut self, key: &str, base: Option<&Node<'a>>, ours: Option<&Node<'a>>, theirs: Option<&Node<'a>>) {
+    if same(base, theirs) || same(ours, theirs) {
+      return;
+    }
+    let (ours_node, theirs_node) = match (ours, theirs) {
+      (Some(ours), Some(theirs)) => (ours, theirs),
+      _ => {
+        if same(base, ours) {
+          self.take(key, base, ours, theirs);
+        } else {
+          self.conflict(key, base, ours, theirs);
+        }
+        return;
+      },
+    };
+    // Tables are merged key by key, even if only theirs changed, so ours keeps its formatting
+    if let (Some(ours_entries), Some(theirs_entries)) = (entries(ours_node), entries(theirs_node)) {
+      let base_entries = base.and_then(entries).unwrap_or_default();
+      let mut names: Vec<&String> = ours_entries.iter().map(|entry| &entry.0).collect();
+      for entry in theirs_entries.iter().chain(base_entries.iter()) {
+        if !names.contains(&&entry.0) {
+          names.push(&entry.0);
+        }
+      }
+      for name in names {
+        let child = child_key(key, name);
+        self.merge(&child, find_entry(&base_entries, name), find_entry(&ours_entries, name),
+          find_entry(&theirs_entries, name));
+      }
+      return;
+    }
+    if let (Some(Node::ArrayOfTables(base_tables)), Node::ArrayOfTables(ours_tables),
+      Node::ArrayOfTables(theirs_tables)) = (base, ours_node, theirs_node) {
+      if base_tables.len() == ours_tables.len() && ours_tables.len() == theirs_tables.len() {
+        for i in 0..ours_tables.len() {
+          let entry = format!("{}[{}]", key, i);
+          self.merge(&entry, Some(&base_tables[i]), Some(&ours_tables[i]), Some(&theirs_tables[i]));
+        }
+        return;
+      }
+    }
+    if same(base, ours) {
+      self.take(key, base, ours, theirs);
+    } else {
+      self.conflict(key, base, ours, theirs);
+    }
+  }
+}
+
+/// Merges the changes `theirs` made since `base` into `ours`, returning the keys that both changed differently. See
+/// the module documentation for details.
+pub(crate) fn merge<'a>(base: &Parser<'a>, ours: &mut Parser<'a>, theirs: &Parser<'a>) -> Vec<Conflict<'a>> {
+  let (base, ours_root, theirs) = (node::build(base, ""), node::build(ours, ""), node::build(theirs, ""));
+  let mut merge = Merge{ours, conflicts: vec![]};
+  merge.merge("", Some(&base), Some(&ours_root), Some(&theirs));
+  merge.conflicts
+}
+
+// The key and line index of every table header and array of tables header
+fn headers(parser: &Parser) -> Vec<(String, usize)> {
+  let mut headers = vec![];
+  walker::walk(&parser.root.borrow(), |event| match event {
+    Event::Table(key, position) | Event::ArrayTable(key, position) => headers.push((key, position.line - 1)),
+    _ => (),
+  });
+  headers
+}
+
+// The index of the line after the last one of the table with the header at `headers[i]`, not counting blank lines
+// at its end. With `i == headers.len()` it's the end of the keys before the first table.
+fn body_end(headers: &[(String, usize)], i: usize, lines: &[&str]) -> usize {
+  let start = if i < headers.len() { headers[i].1 + 1 } else { 0 };
+  let next = if i < headers.len() { headers.get(i + 1) } else { headers.first() };
+  let mut end = next.map_or(lines.len(), |next| next.1);
+  while end > start && lines[end - 1].trim().is_empty() {
+    end -= 1;
+  }
+  end
+}
+
+// The lines of the tables and arrays of tables at or below `key`, as ranges of line indexes. Tables with only blank
+// lines between them are one range.
+fn sections(parser: &Parser, key: &str, lines: &[&str]) -> Vec<(usize, usize)> {
+  let headers = headers(parser);
+  let mut ranges: Vec<(usize, usize)> = vec![];
+  for (i, header) in headers.iter().enumerate() {
+    if header.0 != key && !header.0.starts_with(&format!("{}.", key)) && !header.0.starts_with(&format!("{}[", key)) {
+      continue;
+    }
+    let end = body_end(&headers, i, lines);
+    match ranges.last_mut() {
+      Some(last) if lines[last.1..header.1].iter().all(|line| line.trim().is_empty()) => last.1 = end,
+      _ => ranges.push((header.1, end)),
+    }
+  }
+  ranges
+}
+
+// The lines of theirs at `key` written as tables, the way they are in theirs if it has tables there
+fn theirs_sections(theirs: &TOMLParser, key: &str) -> String {
+  let text = format!("{}", theirs);
+  let lines: Vec<&str> = text.split_terminator('\n').collect();
+  let ranges = sections(&theirs.parser, key, &lines);
+  if !ranges.is_empty() {
+    let blocks: Vec<String> = ranges.iter().map(|range| lines[range.0..range.1].join("\n") + "\n").collect();
+    return blocks.join("\n");
+  }
+  let node = match node_at(&theirs.parser, key) {
+    Some(node) => node,
+    None => return String::new(),
+  };
+  // Inline tables become tables, since a key-value pair would end up in the table before it
+  let node = entries(&node).map_or(node, Node::Table);
+  let (mut scratch, _) = TOMLParser::new().parse("");
+  match patch::add(&mut scratch.parser, key, &node) {
+    Ok(()) => format!("{}\n", format!("{}", scratch).trim_end_matches('\n')),
+    Err(_) => format!("{} = {}\n", quote_key(key), node.to_value()),
+  }
+}
+
+// Lines of `doc` from `start` to `end`, which may be none, to put between conflict markers with `theirs`
+struct Marker {
+  start: usize,
+  end: usize,
+  theirs: String,
+}
+
+/// Writes `doc`, usually the result of `TOMLParser::merge3`, with conflict markers for each of the `conflicts`:
+///
+/// ```text
+/// <<<<<<< ours
+/// port = 5433
+/// =======
+/// port = 5434
+/// >>>>>>> theirs
+/// ```
+///
+/// The lines of ours are the key-value pair the conflict is in, including all of its lines if its value spans more
+/// than one, and the lines of theirs are the same key with the value it has in `theirs`. A conflict on a table or an
+/// array of tables puts the markers around its headers and keys, including those of tables inside of it, with the
+/// tables of `theirs` on the other side. A key that ours removed is written at the end of its table, and a table that
+/// ours removed at the end of the document, so that keeping either side gives a document with the key where it
+/// belongs.
+pub fn conflict_markers(doc: &TOMLParser, theirs: &TOMLParser, conflicts: &[Conflict]) -> String {
+  // The key, first line and number of lines of every key-value pair
+  let mut keyvals = vec![];
+  walker::walk(&doc.parser.root.borrow(), |event| {
+    if let Event::KeyVal(key, val, position) = event {
+      let lines = format!("{}", *val.borrow()).matches('\n').count() + 1;
+      keyvals.push((key, position.line, lines));
+    }
+  });
+  let text = format!("{}", doc);
+  let lines: Vec<&str> = text.split_terminator('\n').collect();
+  let doc_headers = headers(&doc.parser);
+  let mut markers = vec![];
+  for conflict in conflicts {
+    let key = conflict.key.to_string();
+    let keyval = keyvals.iter().find(|keyval| {
+      key == keyval.0 || key.starts_with(&format!("{}.", keyval.0)) || key.starts_with(&format!("{}[", keyval.0))
+    });
+    if let Some(keyval) = keyval.filter(|keyval| keyval.1 <= lines.len()) {
+      let (start, end) = (keyval.1 - 1, (keyval.1 - 1 + keyval.2).min(lines.len()));
+      let mut theirs_lines = String::new();
+      if let Some(node) = node_at(&theirs.parser, &keyval.0) {
+        let indent: String = lines[start].chars().take_while(|c| c.is_whitespace()).collect();
+        let name = match KeyPath::from(&keyval.0).last() {
+          Some(KeySegment::Key(name)) => quote_key(name),
+          _ => quote_key(&keyval.0),
+        };
+        theirs_lines = format!("{}{} = {}\n", indent, name, node.to_value());
+      }
+      markers.push(Marker{start, end, theirs: theirs_lines});
+      continue;
+    }
+    let ranges = sections(&doc.parser, &key, &lines);
+    if !ranges.is_empty() {
+      for (i, range) in ranges.iter().enumerate() {
+        let theirs_lines = if i == 0 { theirs_sections(theirs, &key) } else { String::new() };
+        markers.push(Marker{start: range.0, end: range.1, theirs: theirs_lines});
+      }
+      continue;
+    }
+    // Ours removed the key, theirs changed it
+    let node = match node_at(&theirs.parser, &key) {
+      Some(node) => node,
+      None => continue,
+    };
+    let table = conflict.key.parent().map(|parent| parent.to_string()).unwrap_or_default();
+    let header = doc_headers.iter().position(|header| header.0 == table);
+    let name = match conflict.key.last() {
+      Some(KeySegment::Key(name)) => quote_key(name),
+      _ => String::new(),
+    };
+    let at = match header {
+      Some(i) => Some(body_end(&doc_headers, i, &lines)),
+      None if table.is_empty() => Some(body_end(&doc_headers, doc_headers.len(), &lines)),
+      None => None,
+    };
+    match (at, node.as_value()) {
+      (Some(at), Some(val)) if !name.is_empty() =>
+        markers.push(Marker{start: at, end: at, theirs: format!("{} = {}\n", name, val)}),
+      _ => markers.push(Marker{start: lines.len(), end: lines.len(), theirs: theirs_sections(theirs, &key)}),
+    }
+  }
+  // Markers that add lines come before those around lines that start at the same place, and conflicts in the same
+  // lines get one set of markers
+  markers.sort_by_key(|marker| (marker.start, marker.end > marker.start));
+  let mut kept: Vec<Marker> = vec![];
+  for marker in markers {
+    if kept.last().is_none_or(|last| marker.start >= last.end) {
+      kept.push(marker);
+    }
+  }
+  let mut out = String::new();
+  let mut markers = kept.into_iter().peekable();
+  let mut i = 0;
+  // Marker lines always end with a newline, even at the end of the document
+  let mut in_block = false;
+  while i <= lines.len() {
+    if let Some(marker) = markers.next_if(|marker| marker.start == i) {
+      out.push_str("<<<<<<< ours\n");
+      for line in &lines[marker.start..marker.end] {
+        out.push_str(line);
+        out.push('\n');
+      }
+      out.push_str("=======\n");
+      out.push_str(&marker.theirs);
+      out.push_str(">>>>>>> theirs\n");
+      i = marker.end;
+      in_block = true;
+      continue;
+    }
+    if i < lines.len() {
+      out.push_str(lines[i]);
+      out.push('\n');
+      in_block = false;
+    }
+    i += 1;
+  }
+  if !text.ends_with('\n') && !in_block && !out.is_empty() {
+    out.pop();
+  }
+  out
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use merge3::conflict_markers;
+  use types::{ParseResult, Value};
+
+  fn merge(base: &str, ours: &str, theirs: &str) -> (String, Vec<String>, String) {
+    let (base, _) = TOMLParser::new().parse(base);
+    let (mut ours, _) = TOMLParser::new().parse(ours);
+    let (theirs, _) = TOMLParser::new().parse(theirs);
+    let conflicts = ours.merge3(&base, &theirs);
+    let markers = conflict_markers(&ours, &theirs, &conflicts);
+    (format!("{}", ours), conflicts.iter().map(|conflict| conflict.key.to_string()).collect(), markers)
+  }
+
+  #[test]
+  fn test_merge_values() {
+    let (merged, conflicts, _) = merge("a = 1\nb = 2\nc = 3\n", "a = 1 # One\nb = 5\nc = 3\n",
+      "a = 1\nb = 2\nd = 4\n");
+    assert_eq!("a = 1 # One\nb = 5\nd = 4\n", merged);
+    assert!(conflicts.is_empty());
+    // The same change on both sides isn't a conflict
+    let (merged, conflicts, _) = merge("a = 1.0\n", "a = 2.50\n", "a = 2.5\n");
+    assert_eq!("a = 2.50\n", merged);
+    assert!(conflicts.is_empty());
+  }
+
+  #[test]
+  fn test_merge_tables() {
+    let (merged, conflicts, _) = merge("[a]\nx = 1\n\n[b]\ny = 2\n", "[a]\nx = 1\nz = { p = 1, q = 2 }\n\n[b]\ny = 2\n",
+      "[a]\nx = 7\n\n[c]\nw = 3\n");
+    assert!(conflicts.is_empty());
+    assert_eq!("[a]\nx = 7\nz = { p = 1, q = 2 }\n\n[c]\nw = 3\n", merged);
+    let (merged, conflicts, _) = merge("t = { p = 1, q = 2 }\n", "t = { p = 5, q = 2 }\n", "t = { p = 1, q = 6 }\n");
+    assert!(conflicts.is_empty());
+    assert_eq!("t = { p = 5, q = 6 }\n", merged);
+  }
+
+  #[test]
+  fn test_merge_array_of_tables() {
+    let (merged, conflicts, _) = merge("[[u]]\nn = \"a\"\n\n[[u]]\nn = \"b\"\n",
+      "[[u]]\nn = \"a\" # First\n\n[[u]]\nn = \"b\"\n", "[[u]]\nn = \"a\"\n\n[[u]]\nn = \"c\"\n");
+    assert!(conflicts.is_empty());
+    assert_eq!("[[u]]\nn = \"a\" # First\n\n[[u]]\nn = \"c\"\n", merged);
+  }
+
+  #[test]
+  fn test_conflicts() {
+    let (base, _) = TOMLParser::new().parse("a = 1\nb = [1, 2]\n");
+    let (mut ours, _) = TOMLParser::new().parse("a = 2\nb = [1, 3]\n");
+    let (theirs, _) = TOMLParser::new().parse("a = 3\n");
+    let conflicts = ours.merge3(&base, &theirs);
+    assert_eq!(2, conflicts.len());
+    assert_eq!("a", conflicts[0].key.to_string());
+    assert_eq!(Some(Value::int(1)), conflicts[0].base);
+    assert_eq!(Some(Value::int(2)), conflicts[0].ours);
+    assert_eq!(Some(Value::int(3)), conflicts[0].theirs);
+    assert_eq!("Conflict at b: base [1, 2], ours [1, 3], theirs missing", format!("{}", conflicts[1]));
+    assert_eq!("a = 2\nb = [1, 3]\n", format!("{}", ours));
+  }
+
+  #[test]
+  fn test_markers() {
+    let (_, conflicts, markers) = merge("[a]\nx = 1\ny = 2\n", "[a]\n  x = \"\"\"\none\"\"\"\ny = 5\n",
+      "[a]\n  x = 3\n");
+    assert_eq!(vec!["a.x", "a.y"], conflicts);
+    assert_eq!("[a]\n<<<<<<< ours\n  x = \"\"\"\none\"\"\"\n=======\n  x = 3\n>>>>>>> theirs\n<<<<<<< ours\n\
+      y = 5\n=======\n>>>>>>> theirs\n", markers);
+    let (_, conflicts, markers) = merge("a = 1\n", "", "a = 2\n");
+    assert_eq!(vec!["a"], conflicts);
+    assert_eq!("<<<<<<< ours\n=======\na = 2\n>>>>>>> theirs\n", markers);
+  }
+
+  // Keeps one side of every set of conflict markers in `markers` and checks that the result parses
+  fn resolve(markers: &str, keep_ours: bool) -> String {
+    let mut out = String::new();
+    let mut side = None;
+    for line in markers.split_terminator('\n') {
+      match line {
+        "<<<<<<< ours" => side = Some(true),
+        "=======" => side = Some(false),
+        ">>>>>>> theirs" => side = None,
+        _ if side.is_none_or(|side| side == keep_ours) => {
+          out.push_str(line);
+          out.push('\n');
+        },
+        _ => (),
+      }
+    }
+    let (_, result) = TOMLParser::new().parse(&out);
+    assert!(matches!(result, ParseResult::Full), "{}", out);
+    out
+  }
+
+  #[test]
+  fn test_table_markers() {
+    // Ours changed a table that theirs removed
+    let (_, conflicts, markers) = merge("[t]\nx = 1\n\n[u]\ny = 1\n", "[t] # T\nx = 2\n\n[u]\ny = 1\n", "[u]\ny = 1\n");
+    assert_eq!(vec!["t"], conflicts);
+    assert_eq!("<<<<<<< ours\n[t] # T\nx = 2\n=======\n>>>>>>> theirs\n\n[u]\ny = 1\n", markers);
+    assert_eq!("[t] # T\nx = 2\n\n[u]\ny = 1\n", resolve(&markers, true));
+    assert_eq!("\n[u]\ny = 1\n", resolve(&markers, false));
+    // Theirs changed a table that ours removed
+    let (_, conflicts, markers) = merge("[t]\nx = 1\n\n[u]\ny = 1\n", "[u]\ny = 1\n", "[t]\nx = 2\n\n[u]\ny = 1\n");
+    assert_eq!(vec!["t"], conflicts);
+    assert_eq!("[u]\ny = 1\n<<<<<<< ours\n=======\n[t]\nx = 2\n>>>>>>> theirs\n", markers);
+    let resolved = resolve(&markers, false);
+    let (doc, _) = TOMLParser::new().parse(&resolved);
+    assert_eq!(Some(Value::int(2)), doc.get_value("t.x"));
+    assert_eq!(None, doc.get_value("u.x"));
+    // Theirs changed a key that ours removed
+    let (_, conflicts, markers) = merge("[a]\nx = 1\n\n[b]\ny = 1\n", "[a]\n\n[b]\ny = 1\n",
+      "[a]\nx = 2\n\n[b]\ny = 1\n");
+    assert_eq!(vec!["a.x"], conflicts);
+    assert_eq!("[a]\n<<<<<<< ours\n=======\nx = 2\n>>>>>>> theirs\n\n[b]\ny = 1\n", markers);
+    let resolved = resolve(&markers, false);
+    let (doc, _) = TOMLParser::new().parse(&resolved);
+    assert_eq!(Some(Value::int(2)), doc.get_value("a.x"));
+    // Quoted keys keep a single set of quotes
+    let (_, conflicts, markers) = merge("[a]\n\"x y\" = 1\n", "[a]\n", "[a]\n\"x y\" = 2\n");
+    assert_eq!(vec!["a.\"x y\""], conflicts);
+    assert_eq!("[a]\n<<<<<<< ours\n=======\n\"x y\" = 2\n>>>>>>> theirs\n", markers);
+    let resolved = resolve(&markers, false);
+    let (doc, _) = TOMLParser::new().parse(&resolved);
+    assert_eq!(Some(Value::int(2)), doc.get_value("a.'x y'"));
+  }
+
+  #[test]
+  fn test_array_of_tables_markers() {
+    let (_, conflicts, markers) = merge("[[p]]\nn = 1\n\n[z]\nk = 1\n", "[[p]]\nn = 1\n\n[[p]]\nn = 2\n\n[z]\nk = 1\n",
+      "[[p]]\nn = 3\n\n[z]\nk = 1\n");
+    assert_eq!(vec!["p"], conflicts);
+    assert_eq!("<<<<<<< ours\n[[p]]\nn = 1\n\n[[p]]\nn = 2\n=======\n[[p]]\nn = 3\n>>>>>>> theirs\n\n[z]\nk = 1\n",
+      markers);
+    let resolved = resolve(&markers, true);
+    let (doc, _) = TOMLParser::new().parse(&resolved);
+    assert_eq!(Some(Value::int(2)), doc.get_value("p[1].n"));
+    let resolved = resolve(&markers, false);
+    let (doc, _) = TOMLParser::new().parse(&resolved);
+    assert_eq!(Some(Value::int(3)), doc.get_value("p[0].n"));
+    assert_eq!(None, doc.get_value("p[1].n"));
+    assert_eq!(Some(Value::int(1)), doc.get_value("z.k"));
+    // Theirs has an inline table where ours has a table
+    let (_, _, markers) = merge("[[p]]\nn = 1\n", "[[p]]\nn = 1\n\n[[p]]\nn = 2\n", "p = [{n = 3}]\n");
+    let resolved = resolve(&markers, false);
+    let (doc, _) = TOMLParser::new().parse(&resolved);
+    assert_eq!(Some(Value::int(3)), doc.get_value("p[0].n"));
+  }
+}
@@ -0,0 +1,557 @@
+//! Index-style access to a parsed TOML document and a `HashMap`-style entry API.
+//!
+//! Indexing a `TOMLParser` with a key returns a `Node`, which is a table, an array of tables or a value. Nodes can be
+//! indexed further, by key for tables and by position for arrays of tables. Indexing panics if the key doesn't exist,
+//! use `Node::get` and `Node::get_index` to check first. Arrays and inline tables are values, use
+//! `TOMLParser::get_value` with a full key to reach inside of them.
+//!
+//! `TOMLParser::edit` returns a `DocMut` that can be indexed mutably. Indexing a missing key with `IndexMut` creates it
+//! as an empty table, and indexing one past the end of an array of tables appends a table to it, so new keys can be
+//! assigned directly. Changes are only written back to the document by `DocMut::commit`.
+//!
+//! Both can also be indexed with a `KeyPath`, which follows all of its segments at once and resolves indexes counted
+//! from the end, so `doc[KeyPath::from("bin[-1].name")]` is the name of the last `[[bin]]` table.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::types::Value;
+//!
+//! let parser = TOMLParser::new();
+//! let (mut parser, _) = parser.parse("[package]\nname = \"tomllib\"\nversion = \"0.1.2\"\n");
+//! assert_eq!(Some(&Value::basic_string("0.1.2").unwrap()), parser["package"]["version"].as_value());
+//! {
+//!   let mut doc = parser.edit();
+//!   doc["package"]["version"] = Value::basic_string("0.2.0").unwrap().into();
+//!   doc["dependencies"]["nom"] = Value::basic_string("1.2").unwrap().into();
+//!   doc.commit().unwrap();
+//! }
+//! assert_eq!("[package]\nname = \"tomllib\"\nversion = \"0.2.0\"\n\n[dependencies]\nnom = \"1.2\"\n",
+//!   format!("{}", parser));
+//! ```
+
+use std::ops::{Index, IndexMut, Deref, DerefMut};
+use std::rc::Rc;
+use internals::parser::Parser;
+use internals::walker::child_key;
+use key::{KeyPath, KeySegment, normalize_key, quote_key};
+use types::{Value, Children, EditError};
+
+/// A table, an array of tables or a value in a parsed TOML document.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Node<'a> {
+  /// A table with its key-value pairs and subtables in the order their keys were added to the document.
+  Table(Vec<(String, Node<'a>)>),
+  /// An array of tables.
+  ArrayOfTables(Vec<Node<'a>>),
+  /// A value, including arrays and inline tables.
+  Value(Value<'a>),
+}
+
+impl<'a> Node<'a> {
+  /// Returns the value if this node is a value, otherwise `None`.
+  pub fn as_value(&self) -> Option<&Value<'a>> {
+    match *self {
+      Node::Value(ref val) => Some(val),
+      _ => None,
+    }
+  }
+
+  /// Returns `true` if this node is a table.
+  pub fn is_table(&self) -> bool {
+    matches!(*self, Node::Table(_))
+  }
+
+  /// Returns `true` if this node is an array of tables.
+  pub fn is_array_of_tables(&self) -> bool {
+    matches!(*self, Node::ArrayOfTables(_))
+  }
+
+  /// Returns the node at `key` if this is a table that contains `key`, otherwise `None`. `key` is a single key, which
+  /// can be written with or without quotes.
+  pub fn get(&self, key: &str) -> Option<&Node<'a>> {
+    match *self {
+      Node::Table(ref entries) => find_entry(entries, &normalize_key(key)),
+      _ => None,
+    }
+  }
+
+  /// Returns the table at `index` if this is an array of tables with more than `index` tables, otherwise `None`.
+  pub fn get_index(&self, index: usize) -> Option<&Node<'a>> {
+    match *self {
+      Node::ArrayOfTables(ref tables) => tables.get(index),
+      _ => None,
+    }
+  }
+
+  /// Returns the node at `path`, relative to this node, or `None` if it doesn't exist. Unlike `get` and `get_index`,
+  /// `path` can have any number of segments, and indexes counted from the end of an array of tables are resolved the
+  /// same way `TOMLParser`'s methods resolve them.
+  pub fn get_path<K>(&self, path: K) -> Option<&Node<'a>> where K: Into<KeyPath> {
+    let path = path.into();
+    path.segments().iter().try_fold(self, |node, segment| match *segment {
+      KeySegment::Key(ref name) => node.get(&quote_key(name)),
+      KeySegment::Index(index) => node.get_index(index),
+      KeySegment::FromEnd(index) => node.len().checked_sub(index).and_then(|index| node.get_index(index)),
+    })
+  }
+
+  /// Returns the number of keys in a table, the number of tables in an array of tables, or 0 for a value.
+  pub fn len(&self) -> usize {
+    match *self {
+      Node::Table(ref entries) => entries.len(),
+      Node::ArrayOfTables(ref tables) => tables.len(),
+      Node::Value(_) => 0,
+    }
+  }
+
+  /// Returns `true` if `len` is 0.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Converts this node into a `Value`. Tables become inline tables and arrays of tables become arrays of inline
+  /// tables.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::types::Value;
+  ///
+  /// let parser = TOMLParser::new();
+  /// let (parser, _) = parser.parse("[[points]]\nx = 1\n[[points]]\nx = 2\n");
+  /// assert_eq!("[{x = 1}, {x = 2}]", format!("{}", parser.get_subtree("points").unwrap().to_value()));
+  /// ```
+  pub fn to_value(&self) -> Value<'a> {
+    match *self {
+      Node::Table(ref entries) =>
+        Value::InlineTable(Rc::new(entries.iter().map(|entry| (entry.0.clone().into(), entry.1.to_value())).collect())),
+      Node::ArrayOfTables(ref tables) => Value::Array(Rc::new(tables.iter().map(Node::to_value).collect())),
+      Node::Value(ref val) => val.clone(),
+    }
+  }
+}
+
+impl<'a> From<Value<'a>> for Node<'a> {
+  fn from(val: Value<'a>) -> Node<'a> {
+    Node::Value(val)
+  }
+}
+
+impl<'a, 's> Index<&'s str> for Node<'a> {
+  type Output = Node<'a>;
+
+  fn index(&self, key: &'s str) -> &Node<'a> {
+    match self.get(key) {
+      Some(node) => node,
+      None => panic!("Key \"{}\" doesn't exist or isn't in a table.", key),
+    }
+  }
+}
+
+impl<'a, 's> IndexMut<&'s str> for Node<'a> {
+  fn index_mut(&mut self, key: &'s str) -> &mut Node<'a> {
+    match *self {
+      Node::Table(ref mut entries) => {
+        let name = normalize_key(key);
+        let index = match entries.iter().position(|entry| entry.0 == name) {
+          Some(index) => index,
+          None => {
+            entries.push((name, Node::Table(vec![])));
+            entries.len() - 1
+          },
+        };
+        &mut entries[index].1
+      },
+      _ => panic!("Key \"{}\" can't be indexed, its parent isn't a table.", key),
+    }
+  }
+}
+
+impl<'a> Index<usize> for Node<'a> {
+  type Output = Node<'a>;
+
+  fn index(&self, index: usize) -> &Node<'a> {
+    match self.get_index(index) {
+      Some(node) => node,
+      None => panic!("Index {} is out of bounds or isn't in an array of tables.", index),
+    }
+  }
+}
+
+impl<'a> IndexMut<usize> for Node<'a> {
+  fn index_mut(&mut self, index: usize) -> &mut Node<'a> {
+    match *self {
+      Node::ArrayOfTables(ref mut tables) => {
+        if index == tables.len() {
+          tables.push(Node::Table(vec![]));
+        }
+        &mut tables[index]
+      },
+      _ => panic!("Index {} can't be indexed, its parent isn't an array of tables.", index),
+    }
+  }
+}
+
+impl<'a> Index<KeyPath> for Node<'a> {
+  type Output = Node<'a>;
+
+  fn index(&self, path: KeyPath) -> &Node<'a> {
+    match self.get_path(&path) {
+      Some(node) => node,
+      None => panic!("Key \"{}\" doesn't exist.", path),
+    }
+  }
+}
+
+impl<'a> IndexMut<KeyPath> for Node<'a> {
+  fn index_mut(&mut self, path: KeyPath) -> &mut Node<'a> {
+    let mut node = self;
+    for segment in path.segments() {
+      node = match *segment {
+        KeySegment::Key(ref name) => &mut node[&quote_key(name)[..]],
+        KeySegment::Index(index) => &mut node[index],
+        KeySegment::FromEnd(index) => match node.len().checked_sub(index) {
+          Some(index) if node.is_array_of_tables() => &mut node[index],
+          _ => panic!("Index -{} of \"{}\" is out of bounds or isn't in an array of tables.", index, path),
+        },
+      };
+    }
+    node
+  }
+}
+
+// Builds the node for `key` from the parser's map
+pub(crate) fn build<'a>(parser: &Parser<'a>, key: &str) -> Node<'a> {
+  if let Some(val) = parser.get_value(key) {
+    return Node::Value(val);
+  }
+  match parser.get_children(key) {
+    Some(Children::Count(count)) =>
+      Node::ArrayOfTables((0..count.get()).map(|i| build(parser, &format!("{}[{}]", key, i))).collect()),
+    Some(Children::Keys(keys)) =>
+      Node::Table(keys.borrow().iter().map(|name| (name.clone(), build(parser, &child_key(key, name)))).collect()),
+    None => Node::Table(vec![]),
+  }
+}
+
+// The entries of a table or inline table
+pub(crate) fn entries<'a>(node: &Node<'a>) -> Option<Vec<(String, Node<'a>)>> {
+  match *node {
+    Node::Table(ref entries) => Some(entries.clone()),
+    Node::Value(Value::InlineTable(ref pairs)) =>
+      Some(pairs.iter().map(|pair| (normalize_key(&pair.0), Node::Value(pair.1.clone()))).collect()),
+    _ => None,
+  }
+}
+
+// Returns the node named `name` in `entries`, as returned by `entries`
+pub(crate) fn find_entry<'n, 'a>(entries: &'n [(String, Node<'a>)], name: &str) -> Option<&'n Node<'a>> {
+  entries.iter().find(|entry| entry.0 == name).map(|entry| &entry.1)
+}
+
+// Writes every difference between `node` and `original` at `key` to the document
+fn flush<'a>(parser: &mut Parser<'a>, key: &str, original: Option<&Node<'a>>, node: &Node<'a>)
+  -> Result<(), EditError> {
+  match *node {
+    Node::Value(ref val) => {
+      if original != Some(node) {
+        parser.insert_value(key, val.clone())?;
+      }
+    },
+    Node::Table(ref entries) => {
+      match original {
+        Some(Node::Table(_)) => (),
+        Some(_) => return Err(EditError::NotATable(key.to_string(), key.to_string())),
+        None => parser.insert_table(key)?,
+      }
+      for (name, child) in entries {
+        flush(parser, &child_key(key, name), original.and_then(|table| table.get(name)), child)?;
+      }
+    },
+    Node::ArrayOfTables(ref tables) => {
+      let len = match original {
+        Some(Node::ArrayOfTables(original_tables)) => original_tables.len(),
+        None => 0,
+        Some(_) => return Err(EditError::NotATable(key.to_string(), key.to_string())),
+      };
+      // Newly appended tables already exist, they start out empty
+      let empty = Node::Table(vec![]);
+      for (i, table) in tables.iter().enumerate() {
+        if i >= len {
+          parser.append_array_table(key)?;
+        }
+        let table_original = original.and_then(|aot| aot.get_index(i)).unwrap_or(&empty);
+        flush(parser, &format!("{}[{}]", key, i), Some(table_original), table)?;
+      }
+    },
+  }
+  Ok(())
+}
+
+/// A mutable copy of a document's nodes, created by `TOMLParser::edit`. See the module documentation for details.
+///
+/// Changes are written to the document with the same rules as `TOMLParser::try_set_value`, except that missing keys
+/// and tables are created. New tables get a header at the end of the document, or at the end of the array of tables
+/// they belong to. Removing keys or tables from the copy doesn't remove them from the document. Changes are only
+/// written by `commit`, all at once: if one of them fails, the document is left as it was. Dropping a `DocMut` without
+/// calling `commit` discards the changes.
+#[must_use = "changes are only written to the document by `commit`"]
+pub struct DocMut<'p, 'a: 'p> {
+  parser: &'p mut Parser<'a>,
+  original: Node<'a>,
+  tree: Node<'a>,
+}
+
+impl<'p, 'a> DocMut<'p, 'a> {
+  pub(crate) fn new(parser: &'p mut Parser<'a>) -> DocMut<'p, 'a> {
+    let original = build(parser, "");
+    DocMut{parser, tree: original.clone(), original}
+  }
+
+  /// Writes the changes to the document, returning the first error encountered. If there's an error, none of the
+  /// changes are written.
+  pub fn commit(self) -> Result<(), EditError> {
+    let snapshot = self.parser.snapshot();
+    let result = flush(self.parser, "", Some(&self.original), &self.tree);
+    if result.is_err() {
+      self.parser.restore(snapshot);
+    }
+    result
+  }
+}
+
+impl<'p, 'a> Deref for DocMut<'p, 'a> {
+  type Target = Node<'a>;
+
+  fn deref(&self) -> &Node<'a> {
+    &self.tree
+  }
+}
+
+impl<'p, 'a> DerefMut for DocMut<'p, 'a> {
+  fn deref_mut(&mut self) -> &mut Node<'a> {
+    &mut self.tree
+  }
+}
+
+/// A view of a single key in a document, created by `TOMLParser::entry`.
+pub enum Entry<'p, 'a: 'p> {
+  /// The key exists in the document.
+  Occupied(OccupiedEntry<'p, 'a>),
+  /// The key doesn't exist in the document.
+  Vacant(VacantEntry<'p, 'a>),
+}
+
+/// A key that exists in the document.
+pub struct OccupiedEntry<'p, 'a: 'p> {
+  parser: &'p mut Parser<'a>,
+  key: String,
+}
+
+/// A key that doesn't exist in the document.
+pub struct VacantEntry<'p, 'a: 'p> {
+  parser: &'p mut Parser<'a>,
+  key: String,
+}
+
+impl<'p, 'a> Entry<'p, 'a> {
+  pub(crate) fn new(parser: &'p mut Parser<'a>, key: String) -> Entry<'p, 'a> {
+    if parser.map.contains_key(&key) {
+      Entry::Occupied(OccupiedEntry{parser, key})
+    } else {
+      Entry::Vacant(VacantEntry{parser, key})
+    }
+  }
+
+  /// Returns the full key of the entry.
+  pub fn key(&self) -> &str {
+    match *self {
+      Entry::Occupied(ref entry) => &entry.key,
+      Entry::Vacant(ref entry) => &entry.key,
+    }
+  }
+
+  /// Returns the entry's value, inserting `default` first if the key doesn't exist. Returns an error if the key is a
+  /// table or `default` can't be inserted.
+  pub fn or_insert(self, default: Value<'a>) -> Result<Value<'a>, EditError> {
+    self.or_insert_with(|| default)
+  }
+
+  /// Returns the entry's value, inserting the result of `default` first if the key doesn't exist. Returns an error if
+  /// the key is a table or the new value can't be inserted.
+  pub fn or_insert_with<F>(self, default: F) -> Result<Value<'a>, EditError> where F: FnOnce() -> Value<'a> {
+    match self {
+      Entry::Occupied(entry) => match entry.get() {
+        Some(val) => Ok(val),
+        None => Err(EditError::NotAValue(entry.key.clone(), entry.key)),
+      },
+      Entry::Vacant(entry) => {
+        let val = default();
+        entry.insert(val.clone())?;
+        Ok(val)
+      },
+    }
+  }
+}
+
+impl<'p, 'a> OccupiedEntry<'p, 'a> {
+  /// Returns the full key of the entry.
+  pub fn key(&self) -> &str {
+    &self.key
+  }
+
+  /// Returns the entry's value, or `None` if the key is a table or an array of tables.
+  pub fn get(&self) -> Option<Value<'a>> {
+    self.parser.get_value(self.key.clone())
+  }
+
+  /// Replaces the entry's value with `val` the same way `TOMLParser::try_set_value` does.
+  pub fn insert(&mut self, val: Value<'a>) -> Result<(), EditError> {
+    self.parser.set_value(self.key.clone(), val)
+  }
+}
+
+impl<'p, 'a> VacantEntry<'p, 'a> {
+  /// Returns the full key of the entry.
+  pub fn key(&self) -> &str {
+    &self.key
+  }
+
+  /// Inserts `val` at the entry's key, creating any tables along its path that don't exist.
+  pub fn insert(self, val: Value<'a>) -> Result<(), EditError> {
+    self.parser.insert_value(&self.key, val)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use key::KeyPath;
+  use node::{Node, Entry};
+  use types::{Value, EditError, ParseResult};
+
+  const DOC: &str = "title = \"nodes\"\n[owner]\nname = \"Tom\"\n[[servers]]\nip = \"a\"\n[[servers]]\nip = \"b\"\n";
+
+  #[test]
+  fn test_index() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    assert_eq!(Some(&Value::basic_string("nodes").unwrap()), parser["title"].as_value());
+    assert!(parser["owner"].is_table());
+    assert!(parser["servers"].is_array_of_tables());
+    assert_eq!(2, parser["servers"].len());
+    assert_eq!(Node::Value(Value::basic_string("b").unwrap()), parser["servers"][1]["ip"]);
+    assert!(parser["owner"].get("missing").is_none());
+    assert!(parser["servers"].get_index(2).is_none());
+  }
+
+  #[test]
+  fn test_index_key_path() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse(DOC);
+    assert_eq!(Node::Value(Value::basic_string("b").unwrap()), parser[KeyPath::from("servers[-1].ip")]);
+    assert_eq!(Some(&Value::basic_string("b").unwrap()), parser["servers"][KeyPath::from("[last]")]["ip"].as_value());
+    assert_eq!(Some(&Node::Value(Value::basic_string("a").unwrap())), parser["servers"].get_path("[-2].ip"));
+    assert!(parser["servers"].get_path("[-3]").is_none());
+    assert!(parser["owner"].get_path("[-1]").is_none());
+    {
+      let mut doc = parser.edit();
+      doc[KeyPath::from("servers[-1]")]["port"] = Value::int(80).into();
+      doc["servers"][KeyPath::root().from_end(2)]["ip"] = Value::basic_string("c").unwrap().into();
+      doc.commit().unwrap();
+    }
+    assert_eq!(Some(Value::int(80)), parser.get_value("servers[1].port"));
+    assert_eq!(Some(Value::basic_string("c").unwrap()), parser.get_value("servers[0].ip"));
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_index_key_path_out_of_bounds() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse(DOC);
+    let mut doc = parser.edit();
+    doc[KeyPath::from("servers[-3]")]["ip"] = Value::int(1).into();
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_index_missing() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    let _ = &parser["owner"]["missing"];
+  }
+
+  #[test]
+  fn test_edit() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse(DOC);
+    assert_eq!(2, parser["servers"].len());
+    {
+      let mut doc = parser.edit();
+      doc["owner"]["name"] = Value::basic_string("Ann").unwrap().into();
+      doc["owner"]["age"] = Value::int(30).into();
+      doc["servers"][2]["ip"] = Value::basic_string("c").unwrap().into();
+      doc["database"]["ports"] = Value::int(8000).into();
+      doc.commit().unwrap();
+    }
+    assert_eq!("title = \"nodes\"\n[owner]\nname = \"Ann\"\nage = 30\n[[servers]]\nip = \"a\"\n[[servers]]\nip = \"b\"\
+      \n\n[[servers]]\nip = \"c\"\n\n[database]\nports = 8000\n", format!("{}", parser));
+    assert_eq!(3, parser["servers"].len());
+    assert_eq!(Some(&Value::int(8000)), parser["database"]["ports"].as_value());
+    // Nothing is written if one of the changes fails
+    let text = format!("{}", parser);
+    let mut doc = parser.edit();
+    doc["owner"]["name"] = Value::basic_string("Bob").unwrap().into();
+    doc["database"]["ports"] = Node::Table(vec![("sub".to_string(), Value::int(1).into())]);
+    assert_eq!(Err(EditError::NotATable("database.ports".to_string(), "database.ports".to_string())), doc.commit());
+    assert_eq!(text, format!("{}", parser));
+    assert_eq!(Some(Value::basic_string("Ann").unwrap()), parser.get_value("owner.name"));
+    // Changes that aren't committed are discarded
+    {
+      let mut doc = parser.edit();
+      doc["owner"]["name"] = Value::basic_string("Bob").unwrap().into();
+    }
+    assert_eq!(text, format!("{}", parser));
+  }
+
+  #[test]
+  fn test_quoted_keys() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse("[t]\n\"a b\" = 1\n");
+    assert_eq!(Some(&Value::int(1)), parser["t"]["a b"].as_value());
+    assert_eq!(Some(&Value::int(1)), parser["t"]["'a b'"].as_value());
+    {
+      let mut doc = parser.edit();
+      doc["t"]["a b"] = Value::int(2).into();
+      doc["t"]["c d"] = Value::int(3).into();
+      doc["u v"]["e"] = Value::int(4).into();
+      doc.commit().unwrap();
+    }
+    let text = format!("{}", parser);
+    assert_eq!("[t]\n\"a b\" = 2\n\"c d\" = 3\n\n[\"u v\"]\ne = 4\n", text);
+    let (parser, result) = TOMLParser::new().parse(&text);
+    assert_eq!(ParseResult::Full, result);
+    assert_eq!(Some(Value::int(3)), parser.get_value("t.'c d'"));
+  }
+
+  #[test]
+  fn test_entry() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse(DOC);
+    assert_eq!(Ok(Value::basic_string("nodes").unwrap()), parser.entry("title").or_insert(Value::int(1)));
+    assert_eq!(Ok(Value::int(1)), parser.entry("a.b").or_insert(Value::int(1)));
+    assert_eq!(Ok(Value::int(1)), parser.entry("a.b").or_insert_with(|| Value::int(2)));
+    assert_eq!(Err(EditError::NotAValue("owner".to_string(), "owner".to_string())),
+      parser.entry("owner").or_insert(Value::int(1)));
+    match parser.entry("owner.name") {
+      Entry::Occupied(mut entry) => entry.insert(Value::basic_string("Ann").unwrap()).unwrap(),
+      Entry::Vacant(_) => panic!("owner.name should exist"),
+    }
+    assert_eq!("servers[1].port", parser.entry("servers[1].port").key());
+    assert_eq!(Some(Value::int(1)), parser.get_value("a.b"));
+    assert_eq!(Some(Value::basic_string("Ann").unwrap()), parser.get_value("owner.name"));
+  }
+}
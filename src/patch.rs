@@ -0,0 +1,416 @@
+//! Patches: lists of edit operations that are applied to a document as one transaction, modelled on JSON Patch.
+//!
+//! A patch is a list of `Operation`s, which `TOMLParser::apply_patch` applies in order. If any operation fails the
+//! document is put back exactly as it was, so either every operation is applied or none are. Operations go through
+//! the same format-preserving edits as `try_set_value`, so whitespace and comments that an operation doesn't touch
+//! are kept.
+//!
+//! Keys are full keys like `"servers[0].ip"`, and can index arrays from the end with `[-1]` or `[last]`. Values that
+//! are tables are added as inline tables, except that tables moved or copied from elsewhere in the document stay
+//! tables with a `[header]`.
+//!
+//! Patches can be read from JSON with `from_json`, in the same shape as a JSON Patch document except that `path` and
+//! `from` hold keys instead of JSON Pointers:
+//!
+//! ```json
+//! [
+//!   {"op": "test", "path": "database.port", "value": 5432},
+//!   {"op": "replace", "path": "database.port", "value": 5433},
+//!   {"op": "add", "path": "database.replicas", "value": ["10.0.0.2"]}
+//! ]
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::patch;
+//! use tomllib::types::PatchError;
+//!
+//! let parser = TOMLParser::new();
+//! let (mut parser, _) = parser.parse("[database]\nport = 5432 # The default port\n");
+//! let ops = patch::from_json(r#"[
+//!   {"op": "test", "path": "database.port", "value": 5432},
+//!   {"op": "replace", "path": "database.port", "value": 5433},
+//!   {"op": "add", "path": "database.user", "value": "admin"}
+//! ]"#).unwrap();
+//! parser.apply_patch(&ops).unwrap();
+//! assert_eq!("[database]\nport = 5433 # The default port\nuser = \"admin\"\n", format!("{}", parser));
+//!
+//! // The port isn't 5432 anymore, so nothing is removed
+//! let ops = patch::from_json(r#"[
+//!   {"op": "remove", "path": "database.user"},
+//!   {"op": "test", "path": "database.port", "value": 5432}
+//! ]"#).unwrap();
+//! assert_eq!(Err(PatchError::TestFailed(1, "database.port".to_string())), parser.apply_patch(&ops));
+//! assert_eq!("[database]\nport = 5433 # The default port\nuser = \"admin\"\n", format!("{}", parser));
+//! ```
+
+use std::borrow::Cow;
+use std::rc::Rc;
+use TOMLParser;
+use diff;
+use node::{self, Node};
+use key::{self, KeyPath, KeySegment, quote_key, escape_basic};
+use internals::parser::Parser;
+use internals::edit::TableKind;
+use internals::graft;
+use internals::json::{self, Json};
+use types::{Value, Children, EditError, PatchError};
+
+/// One edit of a patch.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operation<'a> {
+  /// Adds the value at the key, replacing whatever is there. An index into an array inserts the value in front of the
+  /// element at that index, and an index one past the end of an array or array of tables appends it. Missing tables
+  /// along the key are created.
+  Add(KeyPath, Value<'a>),
+  /// Removes the value, table or array of tables at the key. Removing an element of an array moves the elements
+  /// after it down by one.
+  Remove(KeyPath),
+  /// Replaces the value, table or array of tables at the key, which has to exist.
+  Replace(KeyPath, Value<'a>),
+  /// Removes the value or table at the first key and adds it at the second key.
+  Move(KeyPath, KeyPath),
+  /// Adds a copy of the value or table at the first key at the second key.
+  Copy(KeyPath, KeyPath),
+  /// Checks that the value or table at the key means the same as the value, as `diff::same` decides, and fails the
+  /// patch if it doesn't.
+  Test(KeyPath, Value<'a>),
+}
+
+// Why an operation failed
+enum Failure {
+  Edit(EditError),
+  Test(String),
+}
+
+impl From<EditError> for Failure {
+  fn from(err: EditError) -> Failure {
+    Failure::Edit(err)
+  }
+}
+
+/// Reads a patch from a JSON Patch style document: an array of objects with an `op` of `add`, `remove`, `replace`,
+/// `move`, `copy` or `test`, a `path` key and either a `value` or a `from` key. JSON numbers with a fraction or
+/// exponent become floats and all other numbers become integers, strings become basic strings, arrays become arrays
+/// and objects become inline tables. `null` has no TOML equivalent and is rejected.
+pub fn from_json(patch: &str) -> Result<Vec<Operation<'static>>, PatchError> {
+  let json = json::parse(patch).map_err(PatchError::InvalidPatch)?;
+  let ops = match json {
+    Json::Array(ops) => ops,
+    _ => return Err(PatchError::InvalidPatch("Expected an array of operations".to_string())),
+  };
+  ops.iter().enumerate().map(|(i, op)| {
+    let invalid = |reason: &str| PatchError::InvalidPatch(format!("Operation {} {}", i, reason));
+    let key = |name: &str| match op.get(name).and_then(Json::as_str) {
+      Some(key) => KeyPath::parse(key).map_err(|_| invalid(&format!("has an invalid \"{}\" key \"{}\"", name, key))),
+      None => Err(invalid(&format!("is missing \"{}\"", name))),
+    };
+    let value = || match op.get("value") {
+      Some(json) => to_value(json).ok_or_else(|| invalid("has a null value")),
+      None => Err(invalid("is missing \"value\"")),
+    };
+    match op.get("op").and_then(Json::as_str) {
+      Some("add") => Ok(Operation::Add(key("path")?, value()?)),
+      Some("remove") => Ok(Operation::Remove(key("path")?)),
+      Some("replace") => Ok(Operation::Replace(key("path")?, value()?)),
+      Some("move") => Ok(Operation::Move(key("from")?, key("path")?)),
+      Some("copy") => Ok(Operation::Copy(key("from")?, key("path")?)),
+      Some("test") => Ok(Operation::Test(key("path")?, value()?)),
+      Some(name) => Err(invalid(&format!("has an unknown op \"{}\"", name))),
+      None => Err(invalid("is missing \"op\"")),
+    }
+  }).collect()
+}
+
+// Converts a JSON value into a `Value`, or `None` if it contains a null
+fn to_value(json: &Json) -> Option<Value<'static>> {
+  Some(match *json {
+    Json::Null => return None,
+    Json::Bool(b) => Value::Boolean(b),
+    Json::Number(ref n) if n.contains(['.', 'e', 'E']) => Value::Float(Cow::Owned(n.clone())),
+    Json::Number(ref n) => Value::Integer(Cow::Owned(n.clone())),
+    Json::String(ref s) => Value::basic_string(escape_basic(s)).ok()?,
+    Json::Array(ref values) => Value::Array(Rc::new(values.iter().map(to_value).collect::<Option<Vec<_>>>()?)),
+    Json::Object(ref members) => Value::InlineTable(Rc::new(members.iter().map(|member| {
+      to_value(&member.1).map(|val| (Cow::Owned(quote_key(&member.0)), val))
+    }).collect::<Option<Vec<_>>>()?)),
+  })
+}
+
+// Returns the node at `key`, which has to exist
+fn get<'a>(parser: &Parser<'a>, key: &str) -> Result<Node<'a>, EditError> {
+  match parser.table_kind(key) {
+    TableKind::Missing => Err(EditError::KeyNotFound(key.to_string(), parser.first_missing_key(key))),
+    _ => Ok(node::build(parser, key)),
+  }
+}
+
+// The name of the last segment of `key` and the key of its parent
+fn split_last(key: &str) -> Option<(KeySegment, String)> {
+  let path = KeyPath::from(key);
+  path.last().map(|last| (last.clone(), key::prefix(&path, path.len() - 1)))
+}
+
+// Adds `node` at `key`, following the rules of `Operation::Add`
+pub(crate) fn add<'a>(parser: &mut Parser<'a>, key: &str, node: &Node<'a>) -> Result<(), EditError> {
+  if let Some((KeySegment::Index(index), parent)) = split_last(key) {
+    match parser.table_kind(&parent) {
+      TableKind::Value => {
+        let mut values = match parser.get_value(parent.clone()) {
+          Some(Value::Array(ref values)) if index <= values.len() => (**values).clone(),
+          _ => return Err(EditError::KeyNotFound(key.to_string(), key.to_string())),
+        };
+        values.insert(index, node.to_value());
+        return parser.set_value(parent, Value::Array(Rc::new(values)));
+      },
+      TableKind::ArrayOfTables => {
+        // Tables can only be added at the end of an array of tables
+        match parser.get_children(parent.clone()) {
+          Some(Children::Count(count)) if count.get() == index => (),
+          _ => return Err(EditError::InvalidTable(key.to_string(), key.to_string())),
+        }
+        let entry = parser.append_array_table(&parent)?;
+        return graft(parser, &entry, node);
+      },
+      _ => (),
+    }
+  }
+  match parser.table_kind(key) {
+    TableKind::Missing => insert(parser, key, node),
+    _ => replace(parser, key, node),
+  }
+}
+
+// Adds `node` at `key`, which doesn't exist
+fn insert<'a>(parser: &mut Parser<'a>, key: &str, node: &Node<'a>) -> Result<(), EditError> {
+  let (last, parent) = match split_last(key) {
+    Some(split) => split,
+    None => return Err(EditError::InvalidKey(key.to_string(), key.to_string())),
+  };
+  match (node, parser.table_kind(&parent), last) {
+    (Node::Value(val), _, _) => parser.insert_value(key, val.clone()),
+    // Tables inside of inline tables and arrays become inline tables
+    (_, TableKind::InlineTable(_), _) | (_, TableKind::Value, _) => parser.insert_value(key, node.to_value()),
+    (Node::Table(_), _, _) => graft(parser, key, node),
+    (_, _, KeySegment::Key(name)) => graft(parser, &parent, &Node::Table(vec![(quote_key(&name), node.clone())])),
+    (_, _, _) => Err(EditError::NotATable(key.to_string(), parent)),
+  }
+}
+
+// Replaces whatever is at `key` with `node`
+pub(crate) fn replace<'a>(parser: &mut Parser<'a>, key: &str, node: &Node<'a>) -> Result<(), EditError> {
+  let is_table = matches!(*node, Node::Table(_) | Node::Value(Value::InlineTable(_)));
+  match (parser.table_kind(key), node) {
+    (TableKind::Missing, _) => Err(EditError::KeyNotFound(key.to_string(), parser.first_missing_key(key))),
+    (TableKind::Value, Node::Value(val)) | (TableKind::InlineTable(_), Node::Value(val)) =>
+      parser.set_value(key, val.clone()),
+    // The table keeps its header and position, only what's in it is replaced
+    (TableKind::Section, _) if is_table => {
+      let children = match parser.get_children(key) {
+        Some(Children::Keys(keys)) => keys.borrow().clone(),
+        _ => vec![],
+      };
+      for name in children {
+        let child = if key.is_empty() { name } else { format!("{}.{}", key, name) };
+        if let TableKind::Missing = parser.table_kind(&child) {
+          continue;
+        }
+        parser.remove_key(&child)?;
+      }
+      graft(parser, key, node)
+    },
+    _ => {
+      parser.remove_key(key)?;
+      add(parser, key, node)
+    },
+  }
+}
+
+// Grafts the table `node` into the table at `key`
+fn graft<'a>(parser: &mut Parser<'a>, key: &str, node: &Node<'a>) -> Result<(), EditError> {
+  let fragment = graft::node_fragment(key, node)?;
+  parser.graft(key, &fragment)
+}
+
+// Applies `op` to the document
+fn apply<'a>(doc: &mut TOMLParser<'a>, op: &Operation<'a>) -> Result<(), Failure> {
+  match *op {
+    Operation::Add(ref path, ref val) => {
+      let key = doc.resolve(path.clone());
+      add(&mut doc.parser, &key, &Node::Value(val.clone()))?;
+    },
+    Operation::Remove(ref path) => {
+      let key = doc.resolve(path.clone());
+      doc.parser.remove_key(&key)?;
+    },
+    Operation::Replace(ref path, ref val) => {
+      let key = doc.resolve(path.clone());
+      replace(&mut doc.parser, &key, &Node::Value(val.clone()))?;
+    },
+    Operation::Move(ref from, ref path) => {
+      let from = doc.resolve(from.clone());
+      let node = get(&doc.parser, &from)?;
+      let to = doc.resolve(path.clone());
+      if to == from {
+        return Ok(());
+      }
+      // A table can't be moved into itself
+      if to.starts_with(&format!("{}.", from)) || to.starts_with(&format!("{}[", from)) {
+        return Err(Failure::Edit(EditError::InvalidKey(to, from)));
+      }
+      doc.parser.remove_key(&from)?;
+      // The key is resolved after the removal, like JSON Patch does
+      let key = doc.resolve(path.clone());
+      add(&mut doc.parser, &key, &node)?;
+    },
+    Operation::Copy(ref from, ref path) => {
+      let from = doc.resolve(from.clone());
+      let node = get(&doc.parser, &from)?;
+      let key = doc.resolve(path.clone());
+      add(&mut doc.parser, &key, &node)?;
+    },
+    Operation::Test(ref path, ref val) => {
+      let key = doc.resolve(path.clone());
+      match get(&doc.parser, &key) {
+        Ok(ref node) if diff::same(&node.to_value(), val) => (),
+        _ => return Err(Failure::Test(key)),
+      }
+    },
+  }
+  Ok(())
+}
+
+/// Applies every operation in `ops` to `doc`, putting the document back the way it was if one of them fails
+pub(crate) fn apply_all<'a>(doc: &mut TOMLParser<'a>, ops: &[Operation<'a>]) -> Result<(), PatchError> {
+  doc.nodes.take();
+  let snapshot = doc.parser.snapshot();
+  for (i, op) in ops.iter().enumerate() {
+    if let Err(failure) = apply(doc, op) {
+      doc.parser.restore(snapshot);
+      return Err(match failure {
+        Failure::Edit(err) => PatchError::Edit(i, err),
+        Failure::Test(key) => PatchError::TestFailed(i, key),
+      });
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use std::rc::Rc;
+  use TOMLParser;
+  use patch::{from_json, Operation};
+  use key::KeyPath;
+  use types::{Value, EditError, PatchError};
+
+  fn key(key: &str) -> KeyPath {
+    KeyPath::parse(key).unwrap()
+  }
+
+  #[test]
+  fn test_from_json() {
+    assert_eq!(Ok(vec![
+      Operation::Add(key("a.b[0]"), Value::Array(Rc::new(vec![Value::int(1), Value::float(2.5),
+        Value::basic_string("x\\n").unwrap()]))),
+      Operation::Remove(key("\"c d\"")),
+      Operation::Replace(key("e"), Value::InlineTable(Rc::new(vec![("\"f g\"".into(), Value::bool(false))]))),
+      Operation::Move(key("h"), key("i")),
+      Operation::Copy(key("j"), key("k[-1]")),
+      Operation::Test(key("l"), Value::Integer("-3".into())),
+    ]), from_json(r#"[
+      {"op": "add", "path": "a.b[0]", "value": [1, 2.5, "x\n"]},
+      {"op": "remove", "path": "\"c d\""},
+      {"op": "replace", "path": "e", "value": {"f g": false}},
+      {"op": "move", "from": "h", "path": "i"},
+      {"op": "copy", "from": "j", "path": "k[-1]"},
+      {"op": "test", "path": "l", "value": -3}
+    ]"#));
+    for (patch, reason) in vec![
+      ("{}", "Expected an array of operations"),
+      ("[{\"op\": \"add\", \"path\": \"a\"}]", "Operation 0 is missing \"value\""),
+      ("[{\"op\": \"test\", \"path\": \"a\", \"value\": [null]}]", "Operation 0 has a null value"),
+      ("[{\"op\": \"remove\", \"path\": \"a\"}, {\"op\": \"move\", \"path\": \"a\"}]",
+        "Operation 1 is missing \"from\""),
+      ("[{\"op\": \"remove\", \"path\": \"a.\\\"b\"}]", "Operation 0 has an invalid \"path\" key \"a.\"b\""),
+      ("[{\"op\": \"delete\", \"path\": \"a\"}]", "Operation 0 has an unknown op \"delete\""),
+      ("[{\"path\": \"a\"}]", "Operation 0 is missing \"op\""),
+      ("[", "Expected a value at line 1, column 2"),
+    ] {
+      assert_eq!(Err(PatchError::InvalidPatch(reason.to_string())), from_json(patch));
+    }
+  }
+
+  #[test]
+  fn test_values() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse("title = \"t\" # The title\nnums = [1, 2]\npoint = { x = 1, y = 2 }\n\n\
+      [owner]\nname = \"a\"\n");
+    parser.apply_patch(&[
+      Operation::Replace(key("title"), Value::basic_string("u").unwrap()),
+      Operation::Add(key("nums[0]"), Value::int(0)),
+      Operation::Add(key("nums[3]"), Value::int(3)),
+      Operation::Remove(key("point.x")),
+      Operation::Add(key("point.z.w"), Value::int(4)),
+      Operation::Add(key("owner.name"), Value::basic_string("b").unwrap()),
+      Operation::Add(key("owner.address.city"), Value::basic_string("c").unwrap()),
+      Operation::Replace(key("nums"), Value::int(5)),
+    ]).unwrap();
+    assert_eq!("title = \"u\" # The title\nnums = 5\npoint = { y = 2, z = { w = 4 } }\n\n[owner]\nname = \"b\"\n\n\
+      [owner.address]\ncity = \"c\"\n", format!("{}", parser));
+    assert_eq!(Err(PatchError::Edit(0, EditError::KeyNotFound("missing".to_string(), "missing".to_string()))),
+      parser.apply_patch(&[Operation::Replace(key("missing"), Value::int(1))]));
+    assert_eq!(Err(PatchError::Edit(0, EditError::NotATable("title.a".to_string(), "title".to_string()))),
+      parser.apply_patch(&[Operation::Add(key("title.a"), Value::int(1))]));
+  }
+
+  #[test]
+  fn test_tables() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse("[server]\nip = \"a\" # Primary\n\n[[users]]\nname = \"x\"\n\n\
+      [[users]]\nname = \"y\"\n");
+    parser.apply_patch(&[
+      Operation::Copy(key("server"), key("backup")),
+      Operation::Move(key("users[0]"), key("users[1]")),
+      Operation::Add(key("users[2]"), Value::InlineTable(Rc::new(vec![("name".into(),
+        Value::basic_string("z").unwrap())]))),
+      Operation::Replace(key("server"), Value::InlineTable(Rc::new(vec![("ip".into(),
+        Value::basic_string("b").unwrap())]))),
+    ]).unwrap();
+    assert_eq!("[server]\nip = \"b\"\n\n[[users]]\nname = \"y\"\n\n[[users]]\nname = \"x\"\n\n\
+      [[users]]\nname = \"z\"\n\n[backup]\nip = \"a\"\n", format!("{}", parser));
+    assert_eq!(Some(Value::basic_string("x").unwrap()), parser.get_value("users[1].name"));
+    assert_eq!(Err(PatchError::Edit(0, EditError::InvalidKey("server.ip".to_string(), "server".to_string()))),
+      parser.apply_patch(&[Operation::Move(key("server"), key("server.ip"))]));
+    assert_eq!(Err(PatchError::Edit(0, EditError::InvalidTable("users[0]".to_string(), "users[0]".to_string()))),
+      parser.apply_patch(&[Operation::Add(key("users[0]"), Value::InlineTable(Rc::new(vec![])))]));
+  }
+
+  #[test]
+  fn test_transaction() {
+    let doc = "# Config\ntitle = \"t\"\nnums = [1, 2, 3] # Numbers\n\n[server]\nip = \"a\"\n\n[[users]]\nname = \"x\"\n\
+      [users.role]\nadmin = true\n\n[[users]]\nname = \"y\"\n";
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse(doc);
+    assert_eq!(Err(PatchError::TestFailed(5, "server.ip".to_string())), parser.apply_patch(&[
+      Operation::Remove(key("title")),
+      Operation::Replace(key("nums[1]"), Value::int(5)),
+      Operation::Remove(key("users[0]")),
+      Operation::Move(key("server"), key("host")),
+      Operation::Test(key("host.ip"), Value::literal_string("a").unwrap()),
+      Operation::Test(key("server.ip"), Value::basic_string("a").unwrap()),
+    ]));
+    assert_eq!(doc, format!("{}", parser));
+    assert_eq!(Some(Value::basic_string("t").unwrap()), parser.get_value("title"));
+    assert_eq!(Some(Value::int(2)), parser.get_value("nums[1]"));
+    assert_eq!(Some(Value::bool(true)), parser.get_value("users[0].role.admin"));
+    assert_eq!(Some(Value::basic_string("y").unwrap()), parser.get_value("users[1].name"));
+    assert_eq!(Some(&Value::basic_string("a").unwrap()), parser["server"]["ip"].as_value());
+    assert_eq!(Ok(()), parser.apply_patch(&[
+      Operation::Test(key("nums[-1]"), Value::Integer("+3".into())),
+      Operation::Test(key("users[0].role"), Value::InlineTable(Rc::new(vec![("admin".into(), Value::bool(true))]))),
+    ]));
+  }
+}
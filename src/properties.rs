@@ -0,0 +1,235 @@
+//! Conversion between Java `.properties` files and TOML documents.
+//!
+//! `to_toml` reads a file the way `java.util.Properties.load` does: keys are separated from their values by `=`, `:`
+//! or whitespace, a backslash at the end of a line continues it on the next one and backslash escapes like `\n` and
+//! `\u00e9` are decoded. Keys are split at dots into nested tables, so `db.port = 5432` becomes `port` in the table
+//! `db`. Comment lines starting with `#` or `!` become TOML comments in front of the key that follows them, and a key
+//! that's given more than once keeps its last value. Values are read as the TOML type they look like, unless
+//! `infer_types` is false, which keeps everything as strings.
+//!
+//! `from_toml` writes every value with its dotted key. Arrays, arrays of tables and names with dots in them, which
+//! would be read back as nested tables, are reported as errors.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::properties;
+//!
+//! let toml = properties::to_toml("# Connection\ndb.host = localhost\ndb.port: 5432\nname  my\\\n  app\n", true)
+//!   .unwrap();
+//! assert_eq!("name = \"myapp\"\n\n[db]\n# Connection\nhost = \"localhost\"\nport = 5432\n", toml);
+//!
+//! let (parser, _) = TOMLParser::new().parse(&toml);
+//! assert_eq!("name=myapp\ndb.host=localhost\ndb.port=5432\n", properties::from_toml(&parser).unwrap());
+//! ```
+
+use std::char;
+use TOMLParser;
+use node::{self, Node, entries};
+use key::{key_name, quote_key};
+use ini::{Section, infer, scalar_text};
+use json::unrepresentable;
+use types::{Value, ConvertError};
+
+// Joins lines that end with an odd number of backslashes to the next line, without its leading whitespace
+fn logical_lines(text: &str) -> Vec<(usize, String)> {
+  let mut lines = vec![];
+  let mut current: Option<(usize, String)> = None;
+  for (i, line) in text.lines().enumerate() {
+    let line = match current {
+      Some(_) => line.trim_start(),
+      None => {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with(['#', '!']) {
+          lines.push((i + 1, trimmed.to_string()));
+          continue;
+        }
+        trimmed
+      },
+    };
+    let continues = line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1;
+    let part = if continues { &line[..line.len() - 1] } else { line };
+    let (number, mut joined) = current.take().unwrap_or((i + 1, String::new()));
+    joined.push_str(part);
+    if continues {
+      current = Some((number, joined));
+    } else {
+      lines.push((number, joined));
+    }
+  }
+  lines.extend(current);
+  lines
+}
+
+// Decodes the escapes of a key or a value
+fn unescape(line: usize, text: &str) -> Result<String, ConvertError> {
+  let mut unescaped = String::new();
+  let mut chars = text.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      unescaped.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('t') => unescaped.push('\t'),
+      Some('n') => unescaped.push('\n'),
+      Some('r') => unescaped.push('\r'),
+      Some('f') => unescaped.push('\u{c}'),
+      Some('u') => {
+        let hex: String = chars.by_ref().take(4).collect();
+        match u32::from_str_radix(&hex, 16).ok().filter(|_| hex.len() == 4).and_then(char::from_u32) {
+          Some(c) => unescaped.push(c),
+          None => return Err(ConvertError::Parse(format!("line {}: \"\\u{}\" isn't a valid escape", line, hex))),
+        }
+      },
+      Some(c) => unescaped.push(c),
+      None => (),
+    }
+  }
+  Ok(unescaped)
+}
+
+// Splits a logical line into its key and value, which are still escaped
+fn split_entry(line: &str) -> (&str, &str) {
+  let mut escaped = false;
+  let mut end = line.len();
+  for (i, c) in line.char_indices() {
+    if escaped {
+      escaped = false;
+    } else if c == '\\' {
+      escaped = true;
+    } else if c == '=' || c == ':' || c.is_whitespace() {
+      end = i;
+      break;
+    }
+  }
+  let rest = line[end..].trim_start();
+  (&line[..end], rest.strip_prefix(['=', ':']).unwrap_or(rest).trim_start())
+}
+
+/// Converts the text of a `.properties` file to the text of a TOML document, reading values as the type they look
+/// like if `infer_types` is true. Returns `ConvertError::Parse` if an escape isn't valid, and
+/// `ConvertError::Unrepresentable` if a key is used for both a value and a table, like `db` and `db.port`.
+///
+/// # Examples
+///
+/// ```
+/// use tomllib::properties;
+/// use tomllib::types::ConvertError;
+///
+/// assert_eq!("path = \"C:\\\\app\"\n", properties::to_toml("path=C:\\\\app", false).unwrap());
+/// assert_eq!(Err(ConvertError::Unrepresentable("db.port".to_string(), "it's both a value and a table".to_string())),
+///   properties::to_toml("db.port=1\ndb.port.max=2\n", true));
+/// ```
+pub fn to_toml(text: &str, infer_types: bool) -> Result<String, ConvertError> {
+  let mut root = Section::default();
+  let mut comments = vec![];
+  for (number, line) in logical_lines(text) {
+    if line.is_empty() {
+      continue;
+    } else if line.starts_with(['#', '!']) {
+      comments.push(line[1..].to_string());
+      continue;
+    }
+    let (key, val) = split_entry(&line);
+    let names: Vec<String> = unescape(number, key)?.split('.').map(|name| name.to_string()).collect();
+    let val = infer(&unescape(number, val)?, infer_types);
+    root.insert(&names, val, comments.split_off(0), true)?;
+  }
+  Ok(root.write(&comments))
+}
+
+fn escape(text: &str, is_key: bool) -> String {
+  let mut escaped = String::new();
+  for (i, c) in text.chars().enumerate() {
+    match c {
+      '\\' => escaped.push_str("\\\\"),
+      '\t' => escaped.push_str("\\t"),
+      '\n' => escaped.push_str("\\n"),
+      '\r' => escaped.push_str("\\r"),
+      '\u{c}' => escaped.push_str("\\f"),
+      ' ' if is_key || i == 0 => escaped.push_str("\\ "),
+      '=' | ':' | '#' | '!' if is_key => {
+        escaped.push('\\');
+        escaped.push(c);
+      },
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+fn write_values(out: &mut String, names: &mut Vec<String>, node: &Node) -> Result<(), ConvertError> {
+  for (name, child) in entries(node).unwrap_or_default() {
+    names.push(key_name(&name));
+    let key = names.iter().map(|name| quote_key(name)).collect::<Vec<String>>().join(".");
+    if names.last().is_some_and(|name| name.contains('.')) {
+      return Err(unrepresentable(&key, "the name has a dot, which would make it a table"));
+    }
+    match child {
+      Node::Table(_) | Node::Value(Value::InlineTable(_)) => write_values(out, names, &child)?,
+      Node::ArrayOfTables(_) => return Err(unrepresentable(&key, ".properties files don't have arrays of tables")),
+      Node::Value(ref val) => {
+        let text = scalar_text(&key, val, ".properties files don't have arrays")?;
+        out.push_str(&format!("{}={}\n", escape(&names.join("."), true), escape(&text, false)));
+      },
+    }
+    names.pop();
+  }
+  Ok(())
+}
+
+/// Converts a document to the text of a `.properties` file, with every value under its dotted key. Returns
+/// `ConvertError::Unrepresentable` for arrays, arrays of tables and names with dots in them.
+///
+/// # Examples
+///
+/// ```
+/// use tomllib::TOMLParser;
+/// use tomllib::properties;
+/// use tomllib::types::ConvertError;
+///
+/// let (parser, _) = TOMLParser::new().parse("[[users]]\nname = \"a\"\n");
+/// assert_eq!(Err(ConvertError::Unrepresentable("users".to_string(),
+///   ".properties files don't have arrays of tables".to_string())), properties::from_toml(&parser));
+/// ```
+pub fn from_toml(doc: &TOMLParser) -> Result<String, ConvertError> {
+  let mut out = String::new();
+  write_values(&mut out, &mut vec![], &node::build(&doc.parser, ""))?;
+  Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use properties::{to_toml, from_toml};
+  use types::ConvertError;
+
+  #[test]
+  fn test_to_toml() {
+    let properties = "# Application\n! Name\napp.name = My App\napp.version:1.5\n  app.tags\\ list    a,\\\n    b\n\
+      key\\=with\\:separators = \\u00e9\\t\\\\\nempty\nport = 80\nport = 8080\n\n# Trailing\n";
+    assert_eq!("\"key=with:separators\" = \"\u{e9}\\t\\\\\"\nempty = \"\"\nport = 8080\n\n[app]\n# Application\n\
+      # Name\nname = \"My App\"\nversion = 1.5\n\"tags list\" = \"a,b\"\n# Trailing\n",
+      to_toml(properties, true).unwrap());
+    assert_eq!("a = \"1\"\n", to_toml("a 1", false).unwrap());
+    assert_eq!(Err(ConvertError::Parse("line 2: \"\\u00g9\" isn't a valid escape".to_string())),
+      to_toml("a = 1\nb = \\u00g9\n", true));
+  }
+
+  #[test]
+  fn test_from_toml() {
+    let (parser, _) = TOMLParser::new().parse("\"a b\" = \" x:y\\n\"\nn = 1_000\n\n[db]\nwhen = 2016-01-01\n\
+      opts = { \"#x\" = true }\n");
+    let properties = from_toml(&parser).unwrap();
+    assert_eq!("a\\ b=\\ x:y\\n\nn=1000\ndb.when=2016-01-01\ndb.opts.\\#x=true\n", properties);
+    let toml = to_toml(&properties, true).unwrap();
+    assert_eq!("\"a b\" = \" x:y\\n\"\nn = 1000\n\n[db]\nwhen = 2016-01-01\n\n[db.opts]\n\"#x\" = true\n", toml);
+    let error = |doc: &str| from_toml(&TOMLParser::new().parse(doc).0).unwrap_err();
+    assert_eq!(ConvertError::Unrepresentable("a".to_string(), ".properties files don't have arrays".to_string()),
+      error("a = [1]\n"));
+    assert_eq!(ConvertError::Unrepresentable("a.\"b.c\"".to_string(),
+      "the name has a dot, which would make it a table".to_string()), error("[a]\n\"b.c\" = 1\n"));
+  }
+}
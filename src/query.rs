@@ -0,0 +1,484 @@
+//! Queries that match many keys of a parsed TOML document at once.
+//!
+//! A query is a key path in the usual `table."Key One".sub[3]` syntax that can also contain:
+//!
+//! * `*` in place of a key, matching every key of a table or inline table.
+//! * `[*]` in place of an index, matching every element of an array or array of tables.
+//! * `[-1]` or `[last]` in place of an index, counting back from the end of an array or array of tables the same way
+//!   key paths do.
+//! * `..` before a key, `*` or bracket, matching it at any depth below the keys matched so far.
+//! * `[?path op value]`, matching the children of the keys matched so far whose `path` compares to `value`. `path` is
+//!   a key relative to the child, or `@` for the child itself, and `op` is one of `==`, `!=`, `<`, `<=`, `>` or `>=`.
+//!   `value` is a quoted string, `true`, `false`, a number or a date-time. Values of different types never match.
+//! * `[?path]`, matching the children of the keys matched so far that have `path`.
+//!
+//! Only keys with a value are returned, which includes arrays and inline tables. A query that ends on a table or an
+//! array of tables matches nothing, so `servers.*` returns nothing if every key of `servers` is a table; `servers.*.*`
+//! returns their values.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::types::Value;
+//!
+//! let parser = TOMLParser::new();
+//! let doc = "[[products]]\nname = \"Hammer\"\nsku = 738594937\n[[products]]\nname = \"Nail\"\nsku = 284";
+//! let (parser, _) = parser.parse(doc);
+//! let names: Vec<Value> = parser.query("products[?sku > 1000].name").unwrap().into_iter().map(|(_, v)| v).collect();
+//! assert_eq!(vec![Value::basic_string("Hammer").unwrap()], names);
+//! ```
+
+use std::cmp::Ordering;
+use internals::parser::Parser;
+use key::{self, KeyPath, quote_key, parse_quoted};
+use types::{Value, Children, QueryError};
+
+// One step of a query, applied to every key matched by the previous steps
+#[derive(Debug, PartialEq, Clone)]
+enum Step {
+  Key(String),
+  AnyKey,
+  Index(usize),
+  // An index counted back from the end, 1 being the last element
+  FromEnd(usize),
+  AnyIndex,
+  // The matched keys and all of their descendants
+  Descend,
+  // The children of the matched keys that satisfy the predicate
+  Filter(Predicate),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Predicate {
+  // Key relative to the child being tested, empty for the child itself
+  path: Vec<Step>,
+  test: Option<(Op, Literal)>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Op {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Literal {
+  String(String),
+  Number(f64),
+  Boolean(bool),
+  // Anything else, like a date-time, is compared to the value's text
+  Other(String),
+}
+
+/// A parsed query. See `TOMLParser::query` for the syntax.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Query {
+  steps: Vec<Step>,
+}
+
+struct QueryParser<'q> {
+  query: &'q str,
+  chars: Vec<char>,
+  pos: usize,
+}
+
+fn is_keychar(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+impl<'q> QueryParser<'q> {
+  fn error<T, S>(&self, reason: S) -> Result<T, QueryError> where S: Into<String> {
+    Err(QueryError::Syntax(self.query.to_string(), self.pos, reason.into()))
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.pos).cloned()
+  }
+
+  fn starts_with(&self, s: &str) -> bool {
+    s.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+  }
+
+  fn skip_whitespace(&mut self) {
+    while let Some(' ') | Some('\t') = self.peek() {
+      self.pos += 1;
+    }
+  }
+
+  fn parse(&mut self) -> Result<Vec<Step>, QueryError> {
+    let mut steps = vec![];
+    if self.chars.is_empty() {
+      return self.error("empty query");
+    }
+    while let Some(c) = self.peek() {
+      if self.starts_with("..") {
+        self.pos += 2;
+        steps.push(Step::Descend);
+        if self.peek() == Some('[') {
+          steps.push(self.bracket()?);
+        } else {
+          steps.push(self.member()?);
+        }
+      } else if c == '.' && !steps.is_empty() {
+        self.pos += 1;
+        steps.push(self.member()?);
+      } else if c == '[' {
+        steps.push(self.bracket()?);
+      } else if steps.is_empty() {
+        steps.push(self.member()?);
+      } else {
+        return self.error(format!("unexpected \"{}\"", c));
+      }
+    }
+    Ok(steps)
+  }
+
+  fn name(&mut self) -> Result<String, QueryError> {
+    match self.peek() {
+      Some('"') | Some('\'') => {
+        let mut chars = self.chars[self.pos..].iter().cloned().peekable();
+        match parse_quoted(&mut chars) {
+          Ok(name) => {
+            self.pos = self.chars.len() - chars.count();
+            Ok(name)
+          },
+          Err(reason) => self.error(reason),
+        }
+      },
+      _ => {
+        let start = self.pos;
+        while self.peek().is_some_and(is_keychar) {
+          self.pos += 1;
+        }
+        if start == self.pos {
+          return self.error("expected a key");
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+      },
+    }
+  }
+
+  fn member(&mut self) -> Result<Step, QueryError> {
+    if self.peek() == Some('*') {
+      self.pos += 1;
+      Ok(Step::AnyKey)
+    } else {
+      self.name().map(Step::Key)
+    }
+  }
+
+  fn index(&mut self) -> Result<Step, QueryError> {
+    if self.starts_with("last") {
+      self.pos += 4;
+      return Ok(Step::FromEnd(1));
+    }
+    let from_end = self.peek() == Some('-');
+    if from_end {
+      self.pos += 1;
+    }
+    let start = self.pos;
+    while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+      self.pos += 1;
+    }
+    let digits: String = self.chars[start..self.pos].iter().collect();
+    match digits.parse::<usize>() {
+      Ok(index) if from_end && index > 0 => Ok(Step::FromEnd(index)),
+      Ok(index) if !from_end => Ok(Step::Index(index)),
+      _ => self.error("expected an index"),
+    }
+  }
+
+  fn close_bracket(&mut self) -> Result<(), QueryError> {
+    self.skip_whitespace();
+    if self.peek() == Some(']') {
+      self.pos += 1;
+      Ok(())
+    } else {
+      self.error("expected \"]\"")
+    }
+  }
+
+  fn bracket(&mut self) -> Result<Step, QueryError> {
+    self.pos += 1;
+    self.skip_whitespace();
+    let step = match self.peek() {
+      Some('*') => {
+        self.pos += 1;
+        Step::AnyIndex
+      },
+      Some('?') => {
+        self.pos += 1;
+        Step::Filter(self.predicate()?)
+      },
+      _ => self.index()?,
+    };
+    self.close_bracket()?;
+    Ok(step)
+  }
+
+  fn predicate(&mut self) -> Result<Predicate, QueryError> {
+    self.skip_whitespace();
+    let mut path = vec![];
+    if self.peek() == Some('@') {
+      self.pos += 1;
+    } else {
+      path.push(Step::Key(self.name()?));
+      loop {
+        match self.peek() {
+          Some('.') => {
+            self.pos += 1;
+            path.push(Step::Key(self.name()?));
+          },
+          Some('[') => {
+            self.pos += 1;
+            path.push(self.index()?);
+            self.close_bracket()?;
+          },
+          _ => break,
+        }
+      }
+    }
+    self.skip_whitespace();
+    if self.peek() == Some(']') {
+      return Ok(Predicate{path, test: None});
+    }
+    let ops = [("==", Op::Eq), ("!=", Op::Ne), ("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt)];
+    let op = match ops.iter().find(|op| self.starts_with(op.0)) {
+      Some(&(text, op)) => {
+        self.pos += text.len();
+        op
+      },
+      None => return self.error("expected a comparison operator"),
+    };
+    self.skip_whitespace();
+    Ok(Predicate{path, test: Some((op, self.literal()?))})
+  }
+
+  fn literal(&mut self) -> Result<Literal, QueryError> {
+    if let Some('"') | Some('\'') = self.peek() {
+      return self.name().map(Literal::String);
+    }
+    let start = self.pos;
+    while self.peek().is_some_and(|c| c != ']' && c != ' ' && c != '\t') {
+      self.pos += 1;
+    }
+    let text: String = self.chars[start..self.pos].iter().collect();
+    match &text[..] {
+      "" => self.error("expected a value"),
+      "true" => Ok(Literal::Boolean(true)),
+      "false" => Ok(Literal::Boolean(false)),
+      _ => match text.replace('_', "").parse::<f64>() {
+        Ok(n) => Ok(Literal::Number(n)),
+        Err(_) => Ok(Literal::Other(text)),
+      },
+    }
+  }
+}
+
+impl Query {
+  /// Parses `query`, returning an error that points at the offending character if it isn't valid query syntax.
+  pub fn parse(query: &str) -> Result<Query, QueryError> {
+    let mut parser = QueryParser{query, chars: query.chars().collect(), pos: 0};
+    parser.parse().map(|steps| Query{steps})
+  }
+}
+
+fn exists(parser: &Parser, key: &str) -> bool {
+  key.is_empty() || parser.map.contains_key(key)
+}
+
+fn children(parser: &Parser, key: &str) -> Vec<String> {
+  match parser.get_children(key) {
+    Some(children) => children.combine_child_keys(key),
+    None => vec![],
+  }
+}
+
+fn descendants(parser: &Parser, key: &str, result: &mut Vec<String>) {
+  result.push(key.to_string());
+  for child in children(parser, key) {
+    descendants(parser, &child, result);
+  }
+}
+
+fn step_key(parser: &Parser, key: &str, step: &Step) -> String {
+  match *step {
+    Step::Key(ref name) => Children::combine_keys(key.to_string(), quote_key(name)),
+    Step::Index(index) => Children::combine_keys_index(key, index),
+    Step::FromEnd(index) => key::resolve(parser, &KeyPath::from(key).from_end(index)),
+    _ => unreachable!("Predicate paths only contain keys and indexes"),
+  }
+}
+
+fn compare(val: &Value, literal: &Literal) -> Option<Ordering> {
+  match (val, literal) {
+    (Value::Integer(s), Literal::Number(n)) | (Value::Float(s), Literal::Number(n)) =>
+      s.replace('_', "").parse::<f64>().ok().and_then(|v| v.partial_cmp(n)),
+    (Value::String(s, _), Literal::String(l)) => Some((**s).cmp(&l[..])),
+    (Value::Boolean(b), Literal::Boolean(l)) => Some(b.cmp(l)),
+    (Value::DateTime(dt), Literal::Other(l)) | (Value::DateTime(dt), Literal::String(l)) =>
+      Some(dt.to_string().cmp(l)),
+    _ => None,
+  }
+}
+
+impl Predicate {
+  fn holds(&self, parser: &Parser, key: &str) -> bool {
+    let target = self.path.iter().fold(key.to_string(), |target, step| step_key(parser, &target, step));
+    match self.test {
+      None => exists(parser, &target),
+      Some((op, ref literal)) => {
+        let ordering = match parser.get_value(target) {
+          Some(val) => compare(&val, literal),
+          None => None,
+        };
+        match (op, ordering) {
+          (_, None) => false,
+          (Op::Eq, Some(o)) => o == Ordering::Equal,
+          (Op::Ne, Some(o)) => o != Ordering::Equal,
+          (Op::Lt, Some(o)) => o == Ordering::Less,
+          (Op::Le, Some(o)) => o != Ordering::Greater,
+          (Op::Gt, Some(o)) => o == Ordering::Greater,
+          (Op::Ge, Some(o)) => o != Ordering::Less,
+        }
+      },
+    }
+  }
+}
+
+pub(crate) fn run<'a>(parser: &Parser<'a>, query: &Query) -> Vec<(KeyPath, Value<'a>)> {
+  let mut current = vec![String::new()];
+  for step in &query.steps {
+    let mut next = vec![];
+    for key in &current {
+      match *step {
+        Step::Key(_) | Step::Index(_) | Step::FromEnd(_) => next.push(step_key(parser, key, step)),
+        Step::AnyKey => if let Some(&Children::Keys(_)) = parser.get_children(key.clone()) {
+          next.extend(children(parser, key));
+        },
+        Step::AnyIndex => if let Some(&Children::Count(_)) = parser.get_children(key.clone()) {
+          next.extend(children(parser, key));
+        },
+        Step::Descend => descendants(parser, key, &mut next),
+        Step::Filter(ref predicate) =>
+          next.extend(children(parser, key).into_iter().filter(|child| predicate.holds(parser, child))),
+      }
+    }
+    let mut seen = vec![];
+    next.retain(|key| {
+      if exists(parser, key) && !seen.contains(key) {
+        seen.push(key.clone());
+        true
+      } else {
+        false
+      }
+    });
+    current = next;
+  }
+  current.into_iter().filter_map(|key| {
+    parser.get_value(key.clone()).map(|val| (KeyPath::from(key), val))
+  }).collect()
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use key::KeyPath;
+  use query::Query;
+  use types::{Value, QueryError};
+
+  const DOC: &str = r#"title = "inventory"
+[servers.alpha]
+ip = "10.0.0.1"
+[servers.beta]
+ip = "10.0.0.2"
+ports = [80, 443]
+[[products]]
+name = "Hammer"
+sku = 738594937
+[[products]]
+name = "Nail"
+sku = 284
+color = "gray"
+[[products]]
+name = "Saw"
+sku = 1_001
+meta = { ip = "n/a", added = 2016-04-01 }
+"#;
+
+  fn keys(query: &str) -> Vec<String> {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    parser.query(query).unwrap().into_iter().map(|(key, _)| key.to_string()).collect()
+  }
+
+  #[test]
+  fn test_wildcards() {
+    assert_eq!(vec!["servers.alpha.ip", "servers.beta.ip"], keys("servers.*.ip"));
+    assert_eq!(vec!["products[0].name", "products[1].name", "products[2].name"], keys("products[*].name"));
+    assert_eq!(vec!["servers.beta.ports[0]", "servers.beta.ports[1]"], keys("servers.beta.ports[*]"));
+    assert_eq!(vec!["title"], keys("*"));
+    assert_eq!(vec!["products[1].name"], keys("products[1].name"));
+    assert_eq!(vec!["products[2].name"], keys("products[-1].name"));
+    assert_eq!(vec!["products[2].name"], keys("products[last].name"));
+    assert_eq!(vec!["products[0].name"], keys("products[-3].name"));
+    assert_eq!(vec!["servers.beta.ports[1]"], keys("servers.*.ports[-1]"));
+    assert_eq!(Vec::<String>::new(), keys("products[-4].name"));
+    assert_eq!(Vec::<String>::new(), keys("missing.*"));
+  }
+
+  #[test]
+  fn test_recursive_descent() {
+    assert_eq!(vec!["servers.alpha.ip", "servers.beta.ip", "products[2].meta.ip"], keys("..ip"));
+    assert_eq!(vec!["servers.beta.ports[1]"], keys("servers..[1]"));
+  }
+
+  #[test]
+  fn test_filters() {
+    assert_eq!(vec!["products[0].name", "products[2].name"], keys("products[?sku > 1000].name"));
+    assert_eq!(vec!["products[1].name"], keys("products[? color == 'gray' ].name"));
+    assert_eq!(vec!["products[1].sku"], keys("products[?color].sku"));
+    assert_eq!(vec!["products[2].name"], keys("products[?meta.added >= 2016-01-01].name"));
+    assert_eq!(vec!["servers.beta.ports[1]"], keys("servers.beta.ports[?@ != 80]"));
+    assert_eq!(vec!["servers.beta.ip"], keys("servers[?ports[-1] == 443].ip"));
+    assert_eq!(vec!["products[0].name", "products[2].name"], keys("products[?name != \"Nail\"].name"));
+    assert_eq!(Vec::<String>::new(), keys("products[?name > 5].name"));
+  }
+
+  #[test]
+  fn test_tables_have_no_values() {
+    assert_eq!(Vec::<String>::new(), keys("servers.*"));
+    assert_eq!(Vec::<String>::new(), keys("servers"));
+    assert_eq!(Vec::<String>::new(), keys("products"));
+    assert_eq!(Vec::<String>::new(), keys("products[?name == \"Saw\"]"));
+    assert_eq!(vec!["products[2].name", "products[2].sku", "products[2].meta"], keys("products[?name == \"Saw\"].*"));
+    assert_eq!(vec!["servers.alpha.ip", "servers.beta.ip", "servers.beta.ports"], keys("servers.*.*"));
+  }
+
+  #[test]
+  fn test_query_values() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    assert_eq!(vec![(KeyPath::root().key("products").index(2).key("sku"), Value::int_from_str("1_001").unwrap())],
+      parser.query("products[?name == \"Saw\"].sku").unwrap());
+  }
+
+  #[test]
+  fn test_syntax_errors() {
+    assert_eq!(Err(QueryError::Syntax("a.".to_string(), 2, "expected a key".to_string())), Query::parse("a."));
+    assert_eq!(Err(QueryError::Syntax("a[x]".to_string(), 2, "expected an index".to_string())), Query::parse("a[x]"));
+    assert!(Query::parse("").is_err());
+    assert!(Query::parse("a[?b ~ 1]").is_err());
+    assert!(Query::parse("a[?b == ]").is_err());
+    assert!(Query::parse("a[*").is_err());
+    assert_eq!(Err(QueryError::Syntax("a[-0]".to_string(), 4, "expected an index".to_string())), Query::parse("a[-0]"));
+    assert!(Query::parse("a[-]").is_err());
+    assert!(Query::parse("a b").is_err());
+    assert!(Query::parse("\"a.b\".c[0]..d").is_ok());
+  }
+}
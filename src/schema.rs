@@ -0,0 +1,1167 @@
+//! Schemas that describe the keys a document is expected to have, and validation of documents against them.
+//!
+//! A `Schema` is a table of `Field`s. Each field has a type and optionally whether it's required, a default, a range
+//! for numbers, a regular expression for strings, the values it's allowed to have, the type and number of elements for
+//! arrays and a schema of its own for tables and arrays of tables. Fields can be added with a dotted key path, which
+//! adds the tables along the path as optional tables. Unknown keys are allowed unless a schema says otherwise, and
+//! tables inherit that setting from the schema they're in.
+//!
+//! `TOMLParser::validate` returns every violation of a schema with its key and the line it's on, or the line of the
+//! closest table that exists for missing keys. Keys with a default aren't reported when they're missing,
+//! `TOMLParser::apply_defaults` adds them to the document instead.
+//!
+//! Schemas can also be written in TOML and read with `Schema::from_toml`. Every field is a table under `fields` with
+//! the attributes `type`, `required`, `default`, `min`, `max`, `pattern`, `one-of`, `items`, `min-length`,
+//! `max-length`, `allow-unknown` and `fields`, where `items` is a field and `fields` has the fields of a table:
+//!
+//! ```toml
+//! allow-unknown = false
+//!
+//! [fields."server.host"]
+//! type = "string"
+//! required = true
+//!
+//! [fields.users]
+//! type = "array-of-tables"
+//! min-length = 1
+//!
+//! [fields.users.fields.name]
+//! type = "string"
+//! pattern = "^[a-z]+$"
+//! ```
+//!
+//! Field names are key paths, so `"server.host"` is the key `host` in the table `server`. The types are `any`,
+//! `integer`, `float`, `boolean`, `string`, `datetime`, `array`, `table` and `array-of-tables`, and a field with
+//! `fields` is a table if it doesn't have a type.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::schema::{Schema, Field, Problem};
+//! use tomllib::types::Value;
+//!
+//! let schema = Schema::new()
+//!   .field("server.host", Field::string().required())
+//!   .field("server.port", Field::integer().min(1).max(65535).default(Value::int(8080)))
+//!   .field("log.level", Field::string().one_of(vec![Value::basic_string("info").unwrap(),
+//!     Value::basic_string("debug").unwrap()]))
+//!   .allow_unknown(false);
+//! let (mut parser, _) = TOMLParser::new().parse("[server]\nport = 70000\n\n[log]\nlevel = \"trace\"\nfile = \"a\"\n");
+//! let violations = parser.validate(&schema);
+//! assert_eq!(4, violations.len());
+//! assert_eq!("server.host", violations[0].key.to_string());
+//! assert_eq!(Some(1), violations[0].line);
+//! assert_eq!(Problem::Missing, violations[0].problem);
+//! assert_eq!("server.port (line 2): 70000 is greater than the maximum 65535", violations[1].to_string());
+//! assert_eq!("log.level (line 5): \"trace\" isn't one of the allowed values", violations[2].to_string());
+//! assert_eq!("log.file (line 6): unknown key", violations[3].to_string());
+//!
+//! let (mut parser, _) = TOMLParser::new().parse("[server]\nhost = \"a\"\n");
+//! parser.apply_defaults(&schema).unwrap();
+//! assert_eq!("[server]\nhost = \"a\"\nport = 8080\n", format!("{}", parser));
+//! assert!(parser.validate(&schema).is_empty());
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+use std::rc::Rc;
+use regex::Regex;
+use TOMLParser;
+use diff;
+use node::{self, Node, entries, find_entry};
+use key::{KeyPath, KeySegment, quote_key, key_name, escape_basic};
+use internals::parser::Parser;
+use internals::walker::{self, Event, child_key};
+use internals::json::{self, Json};
+use json::to_json;
+use types::{Value, StrType, ParseResult, EditError, SchemaError};
+
+/// The type of a field.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Type {
+  /// Any value, table or array of tables.
+  Any,
+  Integer,
+  Float,
+  Boolean,
+  String,
+  DateTime,
+  Array,
+  /// A table or an inline table.
+  Table,
+  ArrayOfTables,
+}
+
+impl Type {
+  /// Returns the type of `node`.
+  pub fn of(node: &Node) -> Type {
+    match *node {
+      Node::Table(_) | Node::Value(Value::InlineTable(_)) => Type::Table,
+      Node::ArrayOfTables(_) => Type::ArrayOfTables,
+      Node::Value(Value::Integer(_)) => Type::Integer,
+      Node::Value(Value::Float(_)) => Type::Float,
+      Node::Value(Value::Boolean(_)) => Type::Boolean,
+      Node::Value(Value::String(_, _)) => Type::String,
+      Node::Value(Value::DateTime(_)) => Type::DateTime,
+      Node::Value(Value::Array(_)) => Type::Array,
+    }
+  }
+
+  /// Returns the name of the type in schema documents, like `"array-of-tables"`.
+  pub fn name(&self) -> &'static str {
+    match *self {
+      Type::Any => "any",
+      Type::Integer => "integer",
+      Type::Float => "float",
+      Type::Boolean => "boolean",
+      Type::String => "string",
+      Type::DateTime => "datetime",
+      Type::Array => "array",
+      Type::Table => "table",
+      Type::ArrayOfTables => "array-of-tables",
+    }
+  }
+
+  /// Returns the type with the name `name` in schema documents.
+  pub fn from_name(name: &str) -> Option<Type> {
+    [Type::Any, Type::Integer, Type::Float, Type::Boolean, Type::String, Type::DateTime, Type::Array, Type::Table,
+      Type::ArrayOfTables].iter().find(|kind| kind.name() == name).cloned()
+  }
+}
+
+impl Display for Type {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Type::Any => write!(f, "any value"),
+      Type::Integer => write!(f, "an integer"),
+      Type::Float => write!(f, "a float"),
+      Type::Boolean => write!(f, "a boolean"),
+      Type::String => write!(f, "a string"),
+      Type::DateTime => write!(f, "a datetime"),
+      Type::Array => write!(f, "an array"),
+      Type::Table => write!(f, "a table"),
+      Type::ArrayOfTables => write!(f, "an array of tables"),
+    }
+  }
+}
+
+/// The expected type and constraints of a key. Constraints that don't apply to the key's type are ignored, for
+/// example `min` on a string.
+#[derive(Debug, Clone)]
+pub struct Field<'a> {
+  pub(crate) kind: Type,
+  pub(crate) required: bool,
+  pub(crate) default: Option<Value<'a>>,
+  pub(crate) min: Option<f64>,
+  pub(crate) max: Option<f64>,
+  pub(crate) pattern: Option<Regex>,
+  pub(crate) one_of: Vec<Value<'a>>,
+  pub(crate) items: Option<Box<Field<'a>>>,
+  pub(crate) min_length: Option<usize>,
+  pub(crate) max_length: Option<usize>,
+  pub(crate) schema: Option<Schema<'a>>,
+  // Added for a dotted key path passed to `Schema::field`, rather than by the user
+  pub(crate) implicit: bool,
+}
+
+impl<'a> Field<'a> {
+  /// Creates an optional field of type `kind` without any constraints.
+  pub fn new(kind: Type) -> Field<'a> {
+    Field{kind, required: false, default: None, min: None, max: None, pattern: None, one_of: vec![], items: None,
+      min_length: None, max_length: None, schema: None, implicit: false}
+  }
+
+  /// Creates a field that can have any value, table or array of tables.
+  pub fn any() -> Field<'a> {
+    Field::new(Type::Any)
+  }
+
+  pub fn integer() -> Field<'a> {
+    Field::new(Type::Integer)
+  }
+
+  pub fn float() -> Field<'a> {
+    Field::new(Type::Float)
+  }
+
+  pub fn boolean() -> Field<'a> {
+    Field::new(Type::Boolean)
+  }
+
+  pub fn string() -> Field<'a> {
+    Field::new(Type::String)
+  }
+
+  pub fn datetime() -> Field<'a> {
+    Field::new(Type::DateTime)
+  }
+
+  /// Creates an array field whose elements have the type and constraints of `items`.
+  pub fn array(items: Field<'a>) -> Field<'a> {
+    Field::new(Type::Array).items(items)
+  }
+
+  /// Creates a table field with the fields of `schema`. Inline tables match table fields too.
+  pub fn table(schema: Schema<'a>) -> Field<'a> {
+    Field{schema: Some(schema), ..Field::new(Type::Table)}
+  }
+
+  /// Creates an array of tables field, where every table has the fields of `schema`.
+  pub fn array_of_tables(schema: Schema<'a>) -> Field<'a> {
+    Field{schema: Some(schema), ..Field::new(Type::ArrayOfTables)}
+  }
+
+  /// Makes the key required. A required key with a default isn't reported when it's missing.
+  pub fn required(mut self) -> Field<'a> {
+    self.required = true;
+    self
+  }
+
+  /// Sets the value `TOMLParser::apply_defaults` adds when the key is missing.
+  pub fn default(mut self, val: Value<'a>) -> Field<'a> {
+    self.default = Some(val);
+    self
+  }
+
+  /// Sets the smallest integer or float the key can have.
+  pub fn min<N>(mut self, min: N) -> Field<'a> where N: Into<f64> {
+    self.min = Some(min.into());
+    self
+  }
+
+  /// Sets the largest integer or float the key can have.
+  pub fn max<N>(mut self, max: N) -> Field<'a> where N: Into<f64> {
+    self.max = Some(max.into());
+    self
+  }
+
+  /// Sets a regular expression that strings have to match, using the syntax of the `regex` crate. The expression
+  /// isn't anchored, use `^` and `$` to match the whole string. Returns an error if `pattern` isn't a valid regular
+  /// expression.
+  pub fn pattern(mut self, pattern: &str) -> Result<Field<'a>, SchemaError> {
+    let regex = Regex::new(pattern).map_err(|err| SchemaError::InvalidPattern(pattern.to_string(), err.to_string()))?;
+    self.pattern = Some(regex);
+    Ok(self)
+  }
+
+  /// Sets the values the key is allowed to have. Values are compared by meaning, like `diff::same` does.
+  pub fn one_of(mut self, values: Vec<Value<'a>>) -> Field<'a> {
+    self.one_of = values;
+    self
+  }
+
+  /// Sets the type and constraints of the elements of an array.
+  pub fn items(mut self, items: Field<'a>) -> Field<'a> {
+    self.items = Some(Box::new(items));
+    self
+  }
+
+  /// Sets the smallest number of elements of an array or tables of an array of tables.
+  pub fn min_length(mut self, min: usize) -> Field<'a> {
+    self.min_length = Some(min);
+    self
+  }
+
+  /// Sets the largest number of elements of an array or tables of an array of tables.
+  pub fn max_length(mut self, max: usize) -> Field<'a> {
+    self.max_length = Some(max);
+    self
+  }
+}
+
+/// The fields of a table. See the module documentation for details.
+#[derive(Debug, Clone, Default)]
+pub struct Schema<'a> {
+  pub(crate) fields: Vec<(String, Field<'a>)>,
+  pub(crate) allow_unknown: Option<bool>,
+}
+
+impl<'a> Schema<'a> {
+  /// Creates a schema without any fields, which inherits whether unknown keys are allowed from the schema it's in.
+  pub fn new() -> Schema<'a> {
+    Schema{fields: vec![], allow_unknown: None}
+  }
+
+  /// Adds `field` at `key`, a key path relative to the table. Tables along the path that aren't fields yet are added
+  /// as optional tables, which aren't reported when they're missing but whose required keys are. Array indexes in
+  /// `key` are ignored. Adding a field that already exists replaces it, keeping the fields of its table.
+  pub fn field(mut self, key: &str, field: Field<'a>) -> Schema<'a> {
+    let names: Vec<String> = KeyPath::from(key).segments().iter().filter_map(|segment| match *segment {
+      KeySegment::Key(ref name) => Some(quote_key(name)),
+      _ => None,
+    }).collect();
+    self.insert(&names, field);
+    self
+  }
+
+  /// Sets whether the table can have keys that aren't fields of the schema. The top level schema allows them unless
+  /// this is set, other schemas inherit the setting.
+  pub fn allow_unknown(mut self, allow: bool) -> Schema<'a> {
+    self.allow_unknown = Some(allow);
+    self
+  }
+
+  /// Reads a schema written in TOML, see the module documentation for the format.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::TOMLParser;
+  /// use tomllib::schema::{Schema, Problem, Type};
+  ///
+  /// let schema = Schema::from_toml("[fields.name]\ntype = \"string\"\nrequired = true\n\n[fields.tags]\n\
+  ///   type = \"array\"\nitems = { type = \"string\" }\nmax-length = 2\n").unwrap();
+  /// let (parser, _) = TOMLParser::new().parse("tags = [\"a\", 1, \"c\"]\n");
+  /// let problems: Vec<Problem> = parser.validate(&schema).into_iter().map(|violation| violation.problem).collect();
+  /// assert_eq!(vec![Problem::Missing, Problem::TooLong(3, 2), Problem::WrongType(Type::String, Type::Integer)],
+  ///   problems);
+  /// ```
+  pub fn from_toml(text: &'a str) -> Result<Schema<'a>, SchemaError> {
+    let (parser, result) = TOMLParser::new().parse(text);
+    match result {
+      ParseResult::Full => (),
+      _ => return Err(SchemaError::Parse("it isn't a valid TOML document".to_string())),
+    }
+    let mut schema = Schema::new();
+    for (name, node) in entries(&node::build(&parser.parser, "")).unwrap_or_default() {
+      match &name[..] {
+        "allow-unknown" => schema.allow_unknown = Some(read_bool("", &name, &node)?),
+        "fields" => schema.fields = read_fields("", &node)?.fields,
+        _ => return Err(unknown_attribute("", &name)),
+      }
+    }
+    Ok(schema)
+  }
+
+  /// Writes the schema in the TOML format `Schema::from_toml` reads. Tables that were added by a dotted key path are
+  /// written as part of the key paths of their fields.
+  pub fn to_toml(&self) -> String {
+    let mut out = String::new();
+    if let Some(allow) = self.allow_unknown {
+      out.push_str(&format!("allow-unknown = {}\n", allow));
+    }
+    write_fields(&mut out, "fields", "", self);
+    out
+  }
+
+  /// Writes the schema as a 2020-12 JSON Schema for documents checked with `TOMLParser::validate_json_schema`.
+  /// Tables are objects whose required keys include tables added by a dotted key path that have required keys, and
+  /// datetimes are strings with the `date-time` format.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use tomllib::schema::{Schema, Field};
+  ///
+  /// let schema = Schema::new().field("server.port", Field::integer().required().max(65535)).allow_unknown(false);
+  /// assert_eq!("[fields.\"server.port\"]\ntype = \"integer\"\nrequired = true\nmax = 65535\n", schema.to_toml()
+  ///   .split("\n\n").nth(1).unwrap());
+  /// assert!(schema.to_json_schema().contains("\"port\": {\n          \"type\": \"integer\",\n          \
+  ///   \"maximum\": 65535\n        }"));
+  /// ```
+  pub fn to_json_schema(&self) -> String {
+    let dialect = Json::String("https://json-schema.org/draft/2020-12/schema".to_string());
+    let mut members = vec![("$schema".to_string(), dialect)];
+    members.extend(object_schema(self, true));
+    json::pretty(&Json::Object(members)) + "\n"
+  }
+
+  fn insert(&mut self, names: &[String], field: Field<'a>) {
+    let (name, rest) = match names.split_first() {
+      Some(split) => split,
+      None => return,
+    };
+    let index = self.fields.iter().position(|entry| entry.0 == *name);
+    if rest.is_empty() {
+      match index {
+        Some(i) => {
+          let old = ::std::mem::replace(&mut self.fields[i].1, field);
+          if let (Some(old), Some(new)) = (old.schema, self.fields[i].1.schema.as_mut()) {
+            for entry in old.fields {
+              if !new.fields.iter().any(|new_entry| new_entry.0 == entry.0) {
+                new.fields.push(entry);
+              }
+            }
+          }
+        },
+        None => self.fields.push((name.clone(), field)),
+      }
+      return;
+    }
+    let i = index.unwrap_or_else(|| {
+      self.fields.push((name.clone(), Field{implicit: true, ..Field::table(Schema::new())}));
+      self.fields.len() - 1
+    });
+    let parent = &mut self.fields[i].1;
+    if parent.kind == Type::Any {
+      parent.kind = Type::Table;
+    }
+    parent.schema.get_or_insert_with(Schema::new).insert(rest, field);
+  }
+}
+
+fn unknown_attribute(key: &str, name: &str) -> SchemaError {
+  SchemaError::InvalidField(key.to_string(), format!("unknown attribute \"{}\"", name))
+}
+
+fn read_bool(key: &str, name: &str, node: &Node) -> Result<bool, SchemaError> {
+  match *node {
+    Node::Value(Value::Boolean(b)) => Ok(b),
+    _ => Err(SchemaError::InvalidField(key.to_string(), format!("\"{}\" must be a boolean", name))),
+  }
+}
+
+fn read_number(key: &str, name: &str, node: &Node) -> Result<f64, SchemaError> {
+  match node.as_value().and_then(number) {
+    Some(n) => Ok(n),
+    None => Err(SchemaError::InvalidField(key.to_string(), format!("\"{}\" must be a number", name))),
+  }
+}
+
+fn read_length(key: &str, name: &str, node: &Node) -> Result<usize, SchemaError> {
+  match *node {
+    Node::Value(Value::Integer(ref n)) => n.replace('_', "").parse::<usize>().ok(),
+    _ => None,
+  }.ok_or_else(|| SchemaError::InvalidField(key.to_string(), format!("\"{}\" must be a positive integer", name)))
+}
+
+// Reads the `fields` table of the table at `key`
+fn read_fields<'a>(key: &str, node: &Node<'a>) -> Result<Schema<'a>, SchemaError> {
+  let mut schema = Schema::new();
+  let fields = entries(node)
+    .ok_or_else(|| SchemaError::InvalidField(key.to_string(), "\"fields\" must be a table".to_string()))?;
+  for (name, child) in fields {
+    let path = key_name(&name);
+    let child_key = if key.is_empty() { path.clone() } else { format!("{}.{}", key, path) };
+    schema = schema.field(&path, read_field(&child_key, &child)?);
+  }
+  Ok(schema)
+}
+
+fn read_field<'a>(key: &str, node: &Node<'a>) -> Result<Field<'a>, SchemaError> {
+  let attributes = entries(node)
+    .ok_or_else(|| SchemaError::InvalidField(key.to_string(), "a field must be a table".to_string()))?;
+  let mut field = Field::any();
+  let mut kind = None;
+  let mut schema = None;
+  let mut allow_unknown = None;
+  for (name, child) in attributes {
+    match &name[..] {
+      "type" => kind = match child.as_value().and_then(Value::string_content) {
+        Some(type_name) => match Type::from_name(&type_name) {
+          Some(kind) => Some(kind),
+          None => return Err(SchemaError::InvalidField(key.to_string(), format!("unknown type \"{}\"", type_name))),
+        },
+        None => return Err(SchemaError::InvalidField(key.to_string(), "\"type\" must be a string".to_string())),
+      },
+      "required" => field.required = read_bool(key, &name, &child)?,
+      "default" => field.default = Some(child.to_value()),
+      "min" => field.min = Some(read_number(key, &name, &child)?),
+      "max" => field.max = Some(read_number(key, &name, &child)?),
+      "pattern" => match child.as_value().and_then(Value::string_content) {
+        Some(pattern) => field = field.pattern(&pattern)?,
+        None => return Err(SchemaError::InvalidField(key.to_string(), "\"pattern\" must be a string".to_string())),
+      },
+      "one-of" => match child {
+        Node::Value(Value::Array(ref values)) => field.one_of = values.to_vec(),
+        _ => return Err(SchemaError::InvalidField(key.to_string(), "\"one-of\" must be an array".to_string())),
+      },
+      "items" => field.items = Some(Box::new(read_field(&format!("{}[]", key), &child)?)),
+      "min-length" => field.min_length = Some(read_length(key, &name, &child)?),
+      "max-length" => field.max_length = Some(read_length(key, &name, &child)?),
+      "allow-unknown" => allow_unknown = Some(read_bool(key, &name, &child)?),
+      "fields" => schema = Some(read_fields(key, &child)?),
+      _ => return Err(unknown_attribute(key, &name)),
+    }
+  }
+  if schema.is_some() || allow_unknown.is_some() {
+    let mut schema = schema.unwrap_or_default();
+    schema.allow_unknown = allow_unknown;
+    field.schema = Some(schema);
+  }
+  field.kind = kind.unwrap_or(if field.schema.is_some() { Type::Table } else { Type::Any });
+  Ok(field)
+}
+
+// Writes the fields of `schema` as tables named `prefix` followed by their key path. `path` is the key path of the
+// table the fields are in, relative to the schema that's being written.
+fn write_fields(out: &mut String, prefix: &str, path: &str, schema: &Schema) {
+  for (name, field) in &schema.fields {
+    let field_path = format!("{}{}", path, name);
+    match field.schema {
+      Some(ref table) if field.implicit && table.allow_unknown.is_none() =>
+        write_fields(out, prefix, &format!("{}.", field_path), table),
+      _ => write_field(out, &format!("{}.{}", prefix, quote_key(&field_path)), field),
+    }
+  }
+}
+
+fn write_field(out: &mut String, header: &str, field: &Field) {
+  if !out.is_empty() {
+    out.push('\n');
+  }
+  out.push_str(&format!("[{}]\n", header));
+  for (name, val) in attributes(field) {
+    out.push_str(&format!("{} = {}\n", name, val));
+  }
+  if let Some(ref schema) = field.schema {
+    write_fields(out, &format!("{}.fields", header), "", schema);
+  }
+}
+
+// The attributes of a field, apart from the fields of a table, as TOML values. Items are written as an inline table
+// since the parser doesn't accept a table directly below one that has keys.
+fn attributes(field: &Field) -> Vec<(&'static str, String)> {
+  let mut attributes = vec![("type", format!("\"{}\"", field.kind.name()))];
+  if field.required {
+    attributes.push(("required", "true".to_string()));
+  }
+  if let Some(ref default) = field.default {
+    attributes.push(("default", format!("{}", default)));
+  }
+  if let Some(min) = field.min {
+    attributes.push(("min", format!("{}", min)));
+  }
+  if let Some(max) = field.max {
+    attributes.push(("max", format!("{}", max)));
+  }
+  if let Some(ref pattern) = field.pattern {
+    attributes.push(("pattern", format!("\"{}\"", escape_basic(pattern.as_str()))));
+  }
+  if !field.one_of.is_empty() {
+    attributes.push(("one-of", format!("{}", Value::Array(Rc::new(field.one_of.clone())))));
+  }
+  if let Some(min) = field.min_length {
+    attributes.push(("min-length", format!("{}", min)));
+  }
+  if let Some(max) = field.max_length {
+    attributes.push(("max-length", format!("{}", max)));
+  }
+  if let Some(allow) = field.schema.as_ref().and_then(|schema| schema.allow_unknown) {
+    attributes.push(("allow-unknown", format!("{}", allow)));
+  }
+  if let Some(ref items) = field.items {
+    attributes.push(("items", inline_field(items)));
+  }
+  attributes
+}
+
+fn inline_field(field: &Field) -> String {
+  let mut attributes: Vec<String> = attributes(field).into_iter().map(|(name, val)| format!("{} = {}", name, val))
+    .collect();
+  if let Some(ref schema) = field.schema {
+    let fields: Vec<String> = schema.fields.iter()
+      .map(|entry| format!("{} = {}", quote_key(&entry.0), inline_field(&entry.1))).collect();
+    attributes.push(format!("fields = {{ {} }}", fields.join(", ")));
+  }
+  format!("{{ {} }}", attributes.join(", "))
+}
+
+// Returns true if a missing table that was added by a dotted key path would have a missing required key
+fn has_required(field: &Field) -> bool {
+  match field.schema {
+    Some(ref schema) if field.implicit => schema.fields.iter().any(|entry| {
+      (entry.1.required && entry.1.default.is_none()) || has_required(&entry.1)
+    }),
+    _ => false,
+  }
+}
+
+fn json_number(n: f64) -> Json {
+  Json::Number(format!("{}", n))
+}
+
+// The JSON Schema keywords of an object with the fields of `schema`
+fn object_schema(schema: &Schema, allow_unknown: bool) -> Vec<(String, Json)> {
+  let allow_unknown = schema.allow_unknown.unwrap_or(allow_unknown);
+  let mut members = vec![("type".to_string(), Json::String("object".into()))];
+  let properties: Vec<(String, Json)> = schema.fields.iter()
+    .map(|entry| (key_name(&entry.0), field_schema(&entry.1, allow_unknown))).collect();
+  let required: Vec<Json> = schema.fields.iter()
+    .filter(|entry| (entry.1.required && entry.1.default.is_none()) || has_required(&entry.1))
+    .map(|entry| Json::String(key_name(&entry.0))).collect();
+  if !properties.is_empty() {
+    members.push(("properties".to_string(), Json::Object(properties)));
+  }
+  if !required.is_empty() {
+    members.push(("required".to_string(), Json::Array(required)));
+  }
+  if !allow_unknown {
+    members.push(("additionalProperties".to_string(), Json::Bool(false)));
+  }
+  members
+}
+
+fn field_schema(field: &Field, allow_unknown: bool) -> Json {
+  let mut members = vec![];
+  let json_type = |name: &str| ("type".to_string(), Json::String(name.to_string()));
+  match field.kind {
+    Type::Any => (),
+    Type::Integer => members.push(json_type("integer")),
+    Type::Float => members.push(json_type("number")),
+    Type::Boolean => members.push(json_type("boolean")),
+    Type::String => members.push(json_type("string")),
+    Type::DateTime => {
+      members.push(json_type("string"));
+      members.push(("format".to_string(), Json::String("date-time".into())));
+    },
+    Type::Array => members.push(json_type("array")),
+    Type::Table => match field.schema {
+      Some(ref schema) => members.extend(object_schema(schema, allow_unknown)),
+      None => members.push(json_type("object")),
+    },
+    Type::ArrayOfTables => {
+      members.push(json_type("array"));
+      let table = field.schema.as_ref().map_or_else(|| vec![json_type("object")],
+        |schema| object_schema(schema, allow_unknown));
+      members.push(("items".to_string(), Json::Object(table)));
+    },
+  }
+  if let Some(min) = field.min {
+    members.push(("minimum".to_string(), json_number(min)));
+  }
+  if let Some(max) = field.max {
+    members.push(("maximum".to_string(), json_number(max)));
+  }
+  if let Some(ref pattern) = field.pattern {
+    members.push(("pattern".to_string(), Json::String(pattern.as_str().to_string())));
+  }
+  if !field.one_of.is_empty() {
+    let values = field.one_of.iter().map(|val| to_json(&Node::Value(val.clone()))).collect();
+    members.push(("enum".to_string(), Json::Array(values)));
+  }
+  if let Some(min) = field.min_length {
+    members.push(("minItems".to_string(), json_number(min as f64)));
+  }
+  if let Some(max) = field.max_length {
+    members.push(("maxItems".to_string(), json_number(max as f64)));
+  }
+  if let Some(ref items) = field.items {
+    members.push(("items".to_string(), field_schema(items, allow_unknown)));
+  }
+  if let Some(ref default) = field.default {
+    members.push(("default".to_string(), to_json(&Node::Value(default.clone()))));
+  }
+  Json::Object(members)
+}
+
+// Strings with at most this many different values are given as allowed values, if some of them repeat
+const MAX_VALUES: usize = 5;
+
+// What was seen at a key, or in the elements of an array, of the documents a schema is inferred from
+#[derive(Default)]
+struct Observed {
+  // The number of times the key was seen
+  count: usize,
+  kinds: Vec<Type>,
+  numbers: Option<(f64, f64)>,
+  strings: Vec<String>,
+  string_count: usize,
+  // The number of tables seen at the key and their keys
+  tables: usize,
+  entries: Vec<(String, Observed)>,
+  items: Option<Box<Observed>>,
+}
+
+impl Observed {
+  fn observe(&mut self, node: &Node) {
+    self.count += 1;
+    let kind = Type::of(node);
+    if !self.kinds.contains(&kind) {
+      self.kinds.push(kind);
+    }
+    match *node {
+      Node::Table(_) | Node::Value(Value::InlineTable(_)) => self.observe_table(node),
+      Node::ArrayOfTables(ref tables) => for table in tables {
+        self.observe_table(table);
+      },
+      Node::Value(Value::Array(ref values)) => {
+        let items = self.items.get_or_insert_with(Default::default);
+        for val in values.iter() {
+          items.observe(&Node::Value(val.clone()));
+        }
+      },
+      Node::Value(ref val) => {
+        if let Some(n) = number(val) {
+          self.numbers = Some(self.numbers.map_or((n, n), |(min, max)| (min.min(n), max.max(n))));
+        }
+        if let (Value::String(_, _), Some(s)) = (val, val.string_content()) {
+          self.string_count += 1;
+          if self.strings.len() <= MAX_VALUES && !self.strings.contains(&s) {
+            self.strings.push(s);
+          }
+        }
+      },
+    }
+  }
+
+  fn observe_table(&mut self, node: &Node) {
+    self.tables += 1;
+    for (name, child) in entries(node).unwrap_or_default() {
+      let i = match self.entries.iter().position(|entry| entry.0 == name) {
+        Some(i) => i,
+        None => {
+          self.entries.push((name, Observed::default()));
+          self.entries.len() - 1
+        },
+      };
+      self.entries[i].1.observe(&child);
+    }
+  }
+
+  // The fields of the tables that were seen
+  fn schema<'a>(&self) -> Schema<'a> {
+    let fields = self.entries.iter().map(|entry| {
+      let mut field = entry.1.field();
+      field.required = entry.1.count == self.tables;
+      (entry.0.clone(), field)
+    }).collect();
+    Schema{fields, allow_unknown: None}
+  }
+
+  fn field<'a>(&self) -> Field<'a> {
+    let kind = if self.kinds.len() == 1 { self.kinds[0] } else { Type::Any };
+    let mut field = Field::new(kind);
+    match kind {
+      Type::Integer | Type::Float => if let Some((min, max)) = self.numbers {
+        field.min = Some(min);
+        field.max = Some(max);
+      },
+      Type::String if self.strings.len() <= MAX_VALUES && self.string_count > self.strings.len() => {
+        field.one_of = self.strings.iter().map(|s| Value::String(escape_basic(s).into(), StrType::Basic)).collect();
+      },
+      Type::Array => field.items = self.items.as_ref().map(|items| Box::new(items.field())),
+      Type::Table | Type::ArrayOfTables => field.schema = Some(self.schema()),
+      _ => (),
+    }
+    field
+  }
+}
+
+/// Infers a schema from sample documents. Every key that any of the documents has is a field with the type it has in
+/// all of them, or `any` if the types differ, and it's required if every table it could be in has it. Integer and
+/// float fields get the smallest and largest value seen as their range, and string fields with at most five
+/// different values, some of which repeat, get the values seen as their allowed values. Tables and arrays of tables
+/// get the fields of all of their tables, and arrays get the fields of all of their elements as their items.
+///
+/// # Examples
+///
+/// ```
+/// use tomllib::TOMLParser;
+/// use tomllib::schema::infer_schema;
+///
+/// let (a, _) = TOMLParser::new().parse("[server]\nport = 80\nmode = \"fast\"\n\n[[users]]\nname = \"a\"\n");
+/// let (b, _) = TOMLParser::new().parse("[server]\nport = 8080\nmode = \"fast\"\nhost = \"h\"\n");
+/// let schema = infer_schema(&[&a, &b]);
+/// assert_eq!("[fields.server]\ntype = \"table\"\nrequired = true\n\n\
+///   [fields.server.fields.port]\ntype = \"integer\"\nrequired = true\nmin = 80\nmax = 8080\n\n\
+///   [fields.server.fields.mode]\ntype = \"string\"\nrequired = true\none-of = [\"fast\"]\n\n\
+///   [fields.server.fields.host]\ntype = \"string\"\n\n\
+///   [fields.users]\ntype = \"array-of-tables\"\n\n\
+///   [fields.users.fields.name]\ntype = \"string\"\nrequired = true\n", schema.to_toml());
+/// assert!(a.validate(&schema).is_empty() && b.validate(&schema).is_empty());
+/// ```
+pub fn infer_schema<'a>(docs: &[&TOMLParser<'a>]) -> Schema<'a> {
+  let mut observed = Observed::default();
+  for doc in docs {
+    observed.observe_table(&node::build(&doc.parser, ""));
+  }
+  observed.schema()
+}
+
+/// Something wrong with a key of a document, found by `TOMLParser::validate`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Problem<'a> {
+  /// A required key is missing.
+  Missing,
+  /// The key isn't a field of a schema that doesn't allow unknown keys.
+  Unknown,
+  /// The key has the wrong type. Contains the expected type and the type it has.
+  WrongType(Type, Type),
+  /// The value is smaller than the minimum. Contains the value and the minimum.
+  TooSmall(Value<'a>, f64),
+  /// The value is larger than the maximum. Contains the value and the maximum.
+  TooLarge(Value<'a>, f64),
+  /// The string doesn't match the pattern. Contains the value and the pattern.
+  NoMatch(Value<'a>, String),
+  /// The value isn't one of the allowed values. Contains the value.
+  NotOneOf(Value<'a>),
+  /// The array or array of tables has fewer elements than the minimum. Contains the length and the minimum.
+  TooShort(usize, usize),
+  /// The array or array of tables has more elements than the maximum. Contains the length and the maximum.
+  TooLong(usize, usize),
+}
+
+impl<'a> Display for Problem<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Problem::Missing => write!(f, "missing required key"),
+      Problem::Unknown => write!(f, "unknown key"),
+      Problem::WrongType(expected, found) => write!(f, "expected {}, found {}", expected, found),
+      Problem::TooSmall(ref val, min) => write!(f, "{} is less than the minimum {}", val, min),
+      Problem::TooLarge(ref val, max) => write!(f, "{} is greater than the maximum {}", val, max),
+      Problem::NoMatch(ref val, ref pattern) => write!(f, "{} doesn't match the pattern \"{}\"", val, pattern),
+      Problem::NotOneOf(ref val) => write!(f, "{} isn't one of the allowed values", val),
+      Problem::TooShort(len, min) => write!(f, "{} elements is fewer than the minimum {}", len, min),
+      Problem::TooLong(len, max) => write!(f, "{} elements is more than the maximum {}", len, max),
+    }
+  }
+}
+
+/// A violation of a schema, returned by `TOMLParser::validate`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Violation<'a> {
+  /// The full key of the violation.
+  pub key: KeyPath,
+  /// The line of the key, or for missing keys, of the closest table that exists. `None` for missing keys of the top
+  /// level table.
+  pub line: Option<usize>,
+  pub problem: Problem<'a>,
+}
+
+impl<'a> Display for Violation<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.line {
+      Some(line) => write!(f, "{} (line {}): {}", self.key, line, self.problem),
+      None => write!(f, "{}: {}", self.key, self.problem),
+    }
+  }
+}
+
+// The value of an integer or float
+fn number(val: &Value) -> Option<f64> {
+  match *val {
+    Value::Integer(ref n) | Value::Float(ref n) => n.replace('_', "").parse::<f64>().ok(),
+    _ => None,
+  }
+}
+
+struct Validator<'a> {
+  // The first line of every key in the document
+  lines: HashMap<String, usize>,
+  violations: Vec<Violation<'a>>,
+}
+
+impl<'a> Validator<'a> {
+  fn report(&mut self, key: &str, problem: Problem<'a>) {
+    let key = KeyPath::from(key);
+    let line = line_of(&self.lines, &key);
+    self.violations.push(Violation{key, line, problem});
+  }
+
+  fn table(&mut self, key: &str, node: Option<&Node<'a>>, schema: &Schema, allow_unknown: bool) {
+    let allow_unknown = schema.allow_unknown.unwrap_or(allow_unknown);
+    let entries = node.and_then(entries).unwrap_or_default();
+    for (name, field) in &schema.fields {
+      self.field(&child_key(key, name), find_entry(&entries, name), field, allow_unknown);
+    }
+    if !allow_unknown {
+      for (name, _) in &entries {
+        if !schema.fields.iter().any(|field| field.0 == *name) {
+          self.report(&child_key(key, name), Problem::Unknown);
+        }
+      }
+    }
+  }
+
+  fn length(&mut self, key: &str, len: usize, field: &Field) {
+    if field.min_length.is_some_and(|min| len < min) {
+      self.report(key, Problem::TooShort(len, field.min_length.unwrap_or(0)));
+    }
+    if field.max_length.is_some_and(|max| len > max) {
+      self.report(key, Problem::TooLong(len, field.max_length.unwrap_or(0)));
+    }
+  }
+
+  fn field(&mut self, key: &str, node: Option<&Node<'a>>, field: &Field, allow_unknown: bool) {
+    let node = match node {
+      Some(node) => node,
+      None => {
+        match field.schema {
+          Some(ref schema) if field.implicit => self.table(key, None, schema, allow_unknown),
+          _ if field.required && field.default.is_none() => self.report(key, Problem::Missing),
+          _ => (),
+        }
+        return;
+      },
+    };
+    let kind = Type::of(node);
+    if field.kind != Type::Any && field.kind != kind {
+      self.report(key, Problem::WrongType(field.kind, kind));
+      return;
+    }
+    match *node {
+      Node::Value(Value::Array(ref values)) => {
+        self.length(key, values.len(), field);
+        if let Some(ref items) = field.items {
+          for (i, val) in values.iter().enumerate() {
+            self.field(&format!("{}[{}]", key, i), Some(&Node::Value(val.clone())), items, allow_unknown);
+          }
+        }
+      },
+      Node::Value(ref val) => {
+        if let Some(n) = number(val) {
+          if field.min.is_some_and(|min| n < min) {
+            self.report(key, Problem::TooSmall(val.clone(), field.min.unwrap_or(0.0)));
+          }
+          if field.max.is_some_and(|max| n > max) {
+            self.report(key, Problem::TooLarge(val.clone(), field.max.unwrap_or(0.0)));
+          }
+        }
+        if let (Some(pattern), Some(s)) = (field.pattern.as_ref(), val.string_content()) {
+          if !pattern.is_match(&s) {
+            self.report(key, Problem::NoMatch(val.clone(), pattern.as_str().to_string()));
+          }
+        }
+        if !field.one_of.is_empty() && !field.one_of.iter().any(|allowed| diff::same(allowed, val)) {
+          self.report(key, Problem::NotOneOf(val.clone()));
+        }
+      },
+      Node::ArrayOfTables(ref tables) => self.length(key, tables.len(), field),
+      Node::Table(_) => (),
+    }
+    if let Some(ref schema) = field.schema {
+      match *node {
+        Node::ArrayOfTables(ref tables) => for (i, table) in tables.iter().enumerate() {
+          self.table(&format!("{}[{}]", key, i), Some(table), schema, allow_unknown);
+        },
+        _ => self.table(key, Some(node), schema, allow_unknown),
+      }
+    }
+  }
+}
+
+// Returns the first line of every key in the document of `parser`
+pub(crate) fn key_lines(parser: &Parser) -> HashMap<String, usize> {
+  let mut lines = HashMap::new();
+  walker::walk(&parser.root.borrow(), |event| {
+    let (key, position) = match event {
+      Event::Table(key, position) | Event::ArrayTable(key, position) => (key, position),
+      Event::KeyVal(key, _, position) | Event::TableKeyVal(key, _, position) |
+        Event::ArrayElement(key, _, position) | Event::InlineTable(key, _, position) => (key, position),
+      Event::Comment(_, _) => return,
+    };
+    // An array of tables starts at its first table
+    if let Some(index) = key.rfind('[').filter(|_| key.ends_with(']')) {
+      lines.entry(key[..index].to_string()).or_insert(position.line);
+    }
+    lines.entry(key).or_insert(position.line);
+  });
+  lines
+}
+
+// Returns the line of `key` in `lines`, or of the closest of its parents that's in it
+pub(crate) fn line_of(lines: &HashMap<String, usize>, key: &KeyPath) -> Option<usize> {
+  let mut path = Some(key.clone());
+  while let Some(current) = path {
+    if let Some(line) = lines.get(&current.to_string()) {
+      return Some(*line);
+    }
+    path = current.parent();
+  }
+  None
+}
+
+/// Validates the document of `parser` against `schema`, see `TOMLParser::validate`.
+pub(crate) fn validate<'a>(parser: &Parser<'a>, schema: &Schema) -> Vec<Violation<'a>> {
+  let mut validator = Validator{lines: key_lines(parser), violations: vec![]};
+  validator.table("", Some(&node::build(parser, "")), schema, true);
+  validator.violations
+}
+
+fn defaults<'a>(parser: &mut Parser<'a>, key: &str, node: Option<&Node<'a>>, schema: &Schema<'a>)
+  -> Result<(), EditError> {
+  let entries = node.and_then(entries).unwrap_or_default();
+  for (name, field) in &schema.fields {
+    let child = child_key(key, name);
+    match (find_entry(&entries, name), field.default.as_ref(), field.schema.as_ref()) {
+      (None, Some(default), _) => parser.insert_value(&child, default.clone())?,
+      (None, None, Some(schema)) if field.implicit => defaults(parser, &child, None, schema)?,
+      (Some(table @ Node::Table(_)), _, Some(schema)) => defaults(parser, &child, Some(table), schema)?,
+      (Some(Node::ArrayOfTables(tables)), _, Some(schema)) => for (i, table) in tables.iter().enumerate() {
+        defaults(parser, &format!("{}[{}]", child, i), Some(table), schema)?;
+      },
+      _ => (),
+    }
+  }
+  Ok(())
+}
+
+/// Adds the defaults of `schema` to the document of `parser`, see `TOMLParser::apply_defaults`.
+pub(crate) fn apply_defaults<'a>(parser: &mut Parser<'a>, schema: &Schema<'a>) -> Result<(), EditError> {
+  let root = node::build(parser, "");
+  defaults(parser, "", Some(&root), schema)
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use schema::{Schema, Field, Problem, Type, infer_schema};
+  use json_schema::JsonSchema;
+  use types::{Value, SchemaError};
+
+  fn problems(schema: &Schema, doc: &str) -> Vec<String> {
+    let (parser, _) = TOMLParser::new().parse(doc);
+    parser.validate(schema).iter().map(|violation| violation.to_string()).collect()
+  }
+
+  #[test]
+  fn test_validate() {
+    let schema = Schema::new()
+      .field("name", Field::string().required().pattern("^[a-z]+$").unwrap())
+      .field("ratio", Field::float().min(0).max(1))
+      .field("ports", Field::array(Field::integer().min(1)).min_length(1).max_length(2))
+      .field("owner", Field::table(Schema::new().field("email", Field::string().required()).allow_unknown(false)))
+      .field("db.primary.host", Field::string().required())
+      .field("when", Field::datetime());
+    assert_eq!(vec![
+      "name (line 1): \"App\" doesn't match the pattern \"^[a-z]+$\"",
+      "ratio (line 2): 1.5 is greater than the maximum 1",
+      "ports (line 3): 3 elements is more than the maximum 2",
+      "ports[1] (line 3): 0 is less than the minimum 1",
+      "owner.email (line 5): missing required key",
+      "owner.phone (line 6): unknown key",
+      "db.primary.host: missing required key",
+      "when (line 4): expected a datetime, found an integer",
+    ], problems(&schema, "name = \"App\"\nratio = 1.5\nports = [1, 0, 2]\nwhen = 2016\n[owner]\nphone = \"1\"\n"));
+    assert_eq!(vec!["name: missing required key", "owner (line 1): expected a table, found an integer",
+      "db.primary.host (line 2): missing required key"], problems(&schema, "owner = 1\n[db]\n"));
+    assert!(problems(&schema, "name = \"app\"\nports = [8080]\nowner = { email = \"a@b\" }\n\n[db.primary]\n\
+      host = \"h\"\n").is_empty());
+  }
+
+  #[test]
+  fn test_array_of_tables() {
+    let users = Schema::new().field("name", Field::string().required()).field("admin", Field::boolean());
+    let schema = Schema::new().field("users", Field::array_of_tables(users).max_length(2)).allow_unknown(false);
+    assert_eq!(vec![
+      "users (line 1): 3 elements is more than the maximum 2",
+      "users[1].name (line 4): missing required key",
+      "users[2].admin (line 8): expected a boolean, found a string",
+      "users[2].nick (line 9): unknown key",
+      "other (line 10): unknown key",
+    ], problems(&schema, "[[users]]\nname = \"a\"\n\n[[users]]\n\n[[users]]\nname = \"c\"\nadmin = \"yes\"\n\
+      nick = \"c\"\n[other]\n"));
+    assert_eq!(vec!["users (line 1): expected an array of tables, found an array"],
+      problems(&schema, "users = [{ name = \"a\" }]\n"));
+  }
+
+  #[test]
+  fn test_field_merging() {
+    // Replacing a table field keeps the fields that were added to it by key paths
+    let schema = Schema::new()
+      .field("a.x", Field::integer().required())
+      .field("a", Field::table(Schema::new().field("y", Field::integer())).required())
+      .field("a.y", Field::integer().required());
+    assert_eq!(vec!["a: missing required key"], problems(&schema, ""));
+    assert_eq!(vec!["a.y (line 1): missing required key", "a.x (line 1): missing required key"],
+      problems(&schema, "[a]\n"));
+  }
+
+  #[test]
+  fn test_from_toml() {
+    let schema = Schema::from_toml("allow-unknown = false\n\n[fields.\"server.port\"]\ntype = \"integer\"\n\
+      min = 1\nmax = 65_535\nrequired = true\n\n[fields.mode]\none-of = [\"a\", \"b\"]\ndefault = \"a\"\n\n\
+      [fields.users]\ntype = \"array-of-tables\"\nmin-length = 1\n\n[fields.users.fields.name]\ntype = \"string\"\n\
+      pattern = \"^[a-z]+$\"\n").unwrap();
+    let (parser, _) = TOMLParser::new().parse("[server]\nport = 0\nmode = \"c\"\n\n[[users]]\nname = \"A\"\nid = 1\n");
+    let problems: Vec<Problem> = parser.validate(&schema).into_iter().map(|violation| violation.problem).collect();
+    assert_eq!(vec![
+      Problem::TooSmall(Value::int(0), 1.0),
+      Problem::Unknown,
+      Problem::NoMatch(Value::basic_string("A").unwrap(), "^[a-z]+$".to_string()),
+      Problem::Unknown,
+    ], problems);
+    let schema = Schema::from_toml("[fields.server]\nallow-unknown = true\n").unwrap();
+    assert_eq!(Type::Table, schema.fields[0].1.kind);
+    assert_eq!(Err(SchemaError::InvalidField("a.b".to_string(), "unknown type \"int\"".to_string())),
+      Schema::from_toml("[fields.a.fields.b]\ntype = \"int\"\n").map(|_| ()));
+    assert_eq!(Err(SchemaError::InvalidField("a".to_string(), "unknown attribute \"requried\"".to_string())),
+      Schema::from_toml("[fields.a]\nrequried = true\n").map(|_| ()));
+    assert_eq!(Err(SchemaError::InvalidField("a".to_string(), "\"min-length\" must be a positive integer".to_string())),
+      Schema::from_toml("[fields.a]\nmin-length = -1\n").map(|_| ()));
+    assert!(match Schema::from_toml("[fields.a]\npattern = \"(\"\n") {
+      Err(SchemaError::InvalidPattern(ref pattern, _)) => pattern == "(",
+      _ => false,
+    });
+    assert_eq!(Err(SchemaError::Parse("it isn't a valid TOML document".to_string())),
+      Schema::from_toml("[fields.a\n").map(|_| ()));
+  }
+
+  #[test]
+  fn test_apply_defaults() {
+    let schema = Schema::new()
+      .field("server.port", Field::integer().default(Value::int(80)))
+      .field("users", Field::array_of_tables(Schema::new()
+        .field("admin", Field::boolean().default(Value::bool(false)))))
+      .field("log", Field::table(Schema::new().field("level", Field::string().default(Value::basic_string("info")
+        .unwrap()))));
+    let (mut parser, _) = TOMLParser::new().parse("[[users]]\nname = \"a\"\n\n[[users]]\nname = \"b\"\nadmin = true\n");
+    parser.apply_defaults(&schema).unwrap();
+    assert_eq!(Some(Value::int(80)), parser.get_value("server.port"));
+    assert_eq!(Some(Value::bool(false)), parser.get_value("users[0].admin"));
+    assert_eq!(Some(Value::bool(true)), parser.get_value("users[1].admin"));
+    assert_eq!(None, parser.get_children("log"));
+  }
+
+  #[test]
+  fn test_infer_schema() {
+    let docs = ["title = \"a\"\nlevel = \"info\"\nratio = 0.5\ntags = [\"x\", \"y\"]\n\n[owner]\nname = \"a\"\n\n\
+      [[hosts]]\nip = \"10.0.0.1\"\nport = 80\n", "title = \"b\"\nlevel = \"info\"\nratio = 2\n\n[[hosts]]\n\
+      port = 8080\n\n[[hosts]]\nip = \"10.0.0.3\"\nport = 443\n"];
+    let parsers: Vec<_> = docs.iter().map(|doc| TOMLParser::new().parse(doc).0).collect();
+    let schema = infer_schema(&parsers.iter().collect::<Vec<_>>());
+    let field = |name: &str| schema.fields.iter().find(|entry| entry.0 == name).map(|entry| &entry.1).unwrap();
+    assert_eq!(Type::String, field("title").kind);
+    assert!(field("title").required && field("title").one_of.is_empty());
+    assert_eq!(vec![Value::basic_string("info").unwrap()], field("level").one_of);
+    assert_eq!(Type::Any, field("ratio").kind);
+    assert_eq!(Type::String, field("tags").items.as_ref().unwrap().kind);
+    assert!(!field("tags").required && !field("owner").required);
+    let hosts = field("hosts").schema.as_ref().unwrap();
+    assert_eq!(Type::ArrayOfTables, field("hosts").kind);
+    assert_eq!(vec![("ip", false, None, None), ("port", true, Some(80.0), Some(8080.0))],
+      hosts.fields.iter().map(|entry| (&entry.0[..], entry.1.required, entry.1.min, entry.1.max)).collect::<Vec<_>>());
+    for parser in &parsers {
+      assert!(parser.validate(&schema).is_empty());
+    }
+    let (other, _) = TOMLParser::new().parse("level = \"debug\"\n[[hosts]]\nport = 22\n");
+    assert_eq!(vec!["title: missing required key", "level (line 1): \"debug\" isn't one of the allowed values",
+      "ratio: missing required key", "hosts[0].port (line 3): 22 is less than the minimum 80"],
+      other.validate(&schema).iter().map(|violation| violation.to_string()).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn test_write_schema() {
+    let schema = Schema::new()
+      .field("server.port", Field::integer().required().min(1).max(65535).default(Value::int(80)))
+      .field("server.tls", Field::table(Schema::new().field("cert", Field::string().pattern("\\.pem$").unwrap()))
+        .required())
+      .field("users", Field::array_of_tables(Schema::new().field("name", Field::string().required())
+        .allow_unknown(false)).min_length(1))
+      .field("tags", Field::array(Field::string().one_of(vec![Value::basic_string("a").unwrap()])).max_length(3))
+      .allow_unknown(true);
+    let toml = schema.to_toml();
+    assert_eq!("allow-unknown = true\n\n[fields.\"server.port\"]\ntype = \"integer\"\nrequired = true\n\
+      default = 80\nmin = 1\nmax = 65535\n\n[fields.\"server.tls\"]\ntype = \"table\"\nrequired = true\n\n\
+      [fields.\"server.tls\".fields.cert]\ntype = \"string\"\npattern = \"\\\\.pem$\"\n\n[fields.users]\n\
+      type = \"array-of-tables\"\nmin-length = 1\nallow-unknown = false\n\n[fields.users.fields.name]\n\
+      type = \"string\"\nrequired = true\n\n[fields.tags]\ntype = \"array\"\nmax-length = 3\n\
+      items = { type = \"string\", one-of = [\"a\"] }\n", toml);
+    let doc = "[server]\nport = 0\ntls = { cert = \"a.crt\" }\n\n[[users]]\nname = \"a\"\nid = 1\n\n\
+      [other]\ntags = [\"b\"]\n";
+    let (parser, _) = TOMLParser::new().parse(doc);
+    let problems: Vec<String> = parser.validate(&schema).iter().map(|violation| violation.to_string()).collect();
+    let reread = Schema::from_toml(&toml).unwrap();
+    assert_eq!(problems, parser.validate(&reread).iter().map(|violation| violation.to_string()).collect::<Vec<_>>());
+    assert_eq!(3, problems.len());
+    let json = JsonSchema::parse(&schema.to_json_schema()).unwrap();
+    let pointers: Vec<String> = parser.validate_json_schema(&json).into_iter().map(|violation| violation.pointer)
+      .collect();
+    assert_eq!(vec!["/server/port", "/server/tls/cert", "/users/0/id"], pointers);
+    let (parser, _) = TOMLParser::new().parse("tags = [\"a\"]\n[server]\n[server.tls]\n[[users]]\nname = \"a\"\n");
+    assert!(parser.validate(&schema).is_empty() && parser.validate_json_schema(&json).is_empty());
+  }
+}
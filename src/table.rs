@@ -0,0 +1,327 @@
+//! Scoped views of a single table in a parsed TOML document.
+//!
+//! `TOMLParser::table` returns a `TableRef` and `TOMLParser::table_mut` returns a `TableMut`. Both take keys relative
+//! to the table they point to, so code that reads one table of a document doesn't need to know where that table is.
+//! A relative key that starts with `[` indexes into an array of tables, for example `"[1].name"`. Relative keys can be
+//! anything that converts into a `KeyPath`, and indexes counted from the end work as they do everywhere else, so
+//! `"[-1].name"` is the name in the last table.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::types::Value;
+//! use tomllib::table::TableRef;
+//!
+//! fn address<'a>(server: &TableRef<'_, 'a>) -> (Value<'a>, Value<'a>) {
+//!   (server.get("host").unwrap(), server.get("port").unwrap())
+//! }
+//!
+//! let parser = TOMLParser::new();
+//! let (parser, _) = parser.parse("[[servers]]\nhost = \"a\"\nport = 80\n[[servers]]\nhost = \"b\"\nport = 8080");
+//! let servers = parser.table("servers").unwrap();
+//! assert_eq!(2, servers.len());
+//! let ports: Vec<Value> = servers.iter().map(|server| address(&server).1).collect();
+//! assert_eq!(vec![Value::int(80), Value::int(8080)], ports);
+//! ```
+
+use TOMLParser;
+use internals::ast::structs::TOMLValue;
+use internals::parser::Parser;
+use types::{Value, Children, EditError};
+use key::{self, KeyPath};
+
+/// A read-only view of a table, an array of tables or an inline table.
+pub struct TableRef<'p, 'a: 'p> {
+  parser: &'p Parser<'a>,
+  key: String,
+}
+
+/// A mutable view of a table, an array of tables or an inline table.
+pub struct TableMut<'p, 'a: 'p> {
+  parser: &'p mut Parser<'a>,
+  key: String,
+}
+
+/// An iterator over the tables of an array of tables, created by `TableRef::iter`.
+pub struct Tables<'p, 'a: 'p> {
+  parser: &'p Parser<'a>,
+  key: String,
+  index: usize,
+  len: usize,
+}
+
+// Joins the key of a table with a key relative to it, resolving indexes counted from the end
+fn join(parser: &Parser, table: &str, relative: KeyPath) -> String {
+  let mut segments = KeyPath::from(table).segments().to_vec();
+  segments.extend_from_slice(relative.segments());
+  key::resolve(parser, &KeyPath::from(segments))
+}
+
+// Returns true if `key` is the root table, a table, an array of tables, an entry in an array of tables or an inline
+// table
+fn is_table(parser: &Parser, key: &str) -> bool {
+  if key.is_empty() {
+    return true;
+  }
+  match parser.map.get(key) {
+    Some(hash_value) => match hash_value.value {
+      None => true,
+      Some(ref val) => matches!(*val.borrow(), TOMLValue::Table | TOMLValue::InlineTable(_)),
+    },
+    None => false,
+  }
+}
+
+// Number of tables in an array of tables, or number of child keys for any other table
+fn len(parser: &Parser, key: &str) -> usize {
+  match parser.get_children(key) {
+    Some(Children::Count(count)) => count.get(),
+    Some(Children::Keys(keys)) => keys.borrow().len(),
+    None => 0,
+  }
+}
+
+// Returns true if `key` is an array of tables
+fn is_array(parser: &Parser, key: &str) -> bool {
+  match parser.map.get(key) {
+    Some(hash_value) => hash_value.value.is_none() && match hash_value.subkeys {
+      Children::Count(_) => true,
+      Children::Keys(_) => false,
+    },
+    None => false,
+  }
+}
+
+impl<'p, 'a> TableRef<'p, 'a> {
+  pub(crate) fn new(doc: &'p TOMLParser<'a>, key: String) -> Option<TableRef<'p, 'a>> {
+    TableRef::from_parser(&doc.parser, key)
+  }
+
+  fn from_parser(parser: &'p Parser<'a>, key: String) -> Option<TableRef<'p, 'a>> {
+    if is_table(parser, &key) {
+      Some(TableRef{parser, key})
+    } else {
+      None
+    }
+  }
+
+  /// Returns the full key of the table. The root table's key is `""`.
+  pub fn key(&self) -> &str {
+    &self.key
+  }
+
+  /// Returns the value at `key`, relative to this table, or `None` if it doesn't exist.
+  pub fn get<K>(&self, key: K) -> Option<Value<'a>> where K: Into<KeyPath> {
+    self.parser.get_value(join(self.parser, &self.key, key.into()))
+  }
+
+  /// Returns the children of `key`, relative to this table, or `None` if it doesn't exist. Use `""` for the children
+  /// of this table.
+  pub fn children<K>(&self, key: K) -> Option<&'p Children> where K: Into<KeyPath> {
+    self.parser.get_children(join(self.parser, &self.key, key.into()))
+  }
+
+  /// Returns a view of the table at `key`, relative to this table, or `None` if it doesn't exist or isn't a table.
+  pub fn table<K>(&self, key: K) -> Option<TableRef<'p, 'a>> where K: Into<KeyPath> {
+    TableRef::from_parser(self.parser, join(self.parser, &self.key, key.into()))
+  }
+
+  /// Returns the number of tables if this is an array of tables, otherwise the number of child keys.
+  pub fn len(&self) -> usize {
+    len(self.parser, &self.key)
+  }
+
+  /// Returns `true` if `len` is 0.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Returns `true` if this is an array of tables.
+  pub fn is_array(&self) -> bool {
+    is_array(self.parser, &self.key)
+  }
+
+  /// Returns an iterator over the tables of an array of tables. The iterator is empty for any other kind of table.
+  pub fn iter(&self) -> Tables<'p, 'a> {
+    Tables{
+      parser: self.parser,
+      key: self.key.clone(),
+      index: 0,
+      len: if self.is_array() { self.len() } else { 0 },
+    }
+  }
+}
+
+impl<'p, 'a> Iterator for Tables<'p, 'a> {
+  type Item = TableRef<'p, 'a>;
+
+  fn next(&mut self) -> Option<TableRef<'p, 'a>> {
+    if self.index < self.len {
+      let key = format!("{}[{}]", self.key, self.index);
+      self.index += 1;
+      TableRef::from_parser(self.parser, key)
+    } else {
+      None
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let remaining = self.len - self.index;
+    (remaining, Some(remaining))
+  }
+}
+
+impl<'p, 'a> TableMut<'p, 'a> {
+  pub(crate) fn new(doc: &'p mut TOMLParser<'a>, key: String) -> Option<TableMut<'p, 'a>> {
+    TableMut::from_parser(&mut doc.parser, key)
+  }
+
+  fn from_parser(parser: &'p mut Parser<'a>, key: String) -> Option<TableMut<'p, 'a>> {
+    if is_table(parser, &key) {
+      Some(TableMut{parser, key})
+    } else {
+      None
+    }
+  }
+
+  /// Returns the full key of the table. The root table's key is `""`.
+  pub fn key(&self) -> &str {
+    &self.key
+  }
+
+  /// Returns the value at `key`, relative to this table, or `None` if it doesn't exist.
+  pub fn get<K>(&self, key: K) -> Option<Value<'a>> where K: Into<KeyPath> {
+    self.parser.get_value(join(self.parser, &self.key, key.into()))
+  }
+
+  /// Sets the value at `key`, relative to this table, the same way `TOMLParser::try_set_value` does.
+  pub fn set<K>(&mut self, key: K, val: Value<'a>) -> Result<(), EditError> where K: Into<KeyPath> {
+    self.parser.set_value(join(self.parser, &self.key, key.into()), val)
+  }
+
+  /// Returns the children of `key`, relative to this table, or `None` if it doesn't exist. Use `""` for the children
+  /// of this table.
+  pub fn children<K>(&self, key: K) -> Option<&Children> where K: Into<KeyPath> {
+    self.parser.get_children(join(self.parser, &self.key, key.into()))
+  }
+
+  /// Returns a read-only view of the table at `key`, relative to this table, or `None` if it doesn't exist or isn't a
+  /// table.
+  pub fn table<K>(&self, key: K) -> Option<TableRef<'_, 'a>> where K: Into<KeyPath> {
+    TableRef::from_parser(self.parser, join(self.parser, &self.key, key.into()))
+  }
+
+  /// Returns a mutable view of the table at `key`, relative to this table, or `None` if it doesn't exist or isn't a
+  /// table.
+  pub fn table_mut<K>(&mut self, key: K) -> Option<TableMut<'_, 'a>> where K: Into<KeyPath> {
+    let key = join(self.parser, &self.key, key.into());
+    TableMut::from_parser(self.parser, key)
+  }
+
+  /// Returns the number of tables if this is an array of tables, otherwise the number of child keys.
+  pub fn len(&self) -> usize {
+    len(self.parser, &self.key)
+  }
+
+  /// Returns `true` if `len` is 0.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Returns `true` if this is an array of tables.
+  pub fn is_array(&self) -> bool {
+    is_array(self.parser, &self.key)
+  }
+
+  /// Returns a read-only view of this table.
+  pub fn as_ref(&self) -> TableRef<'_, 'a> {
+    TableRef{parser: self.parser, key: self.key.clone()}
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use key::KeyPath;
+  use types::{Value, Children, EditError};
+
+  const DOC: &str = r#"name = "cluster"
+[[servers]]
+host = "alpha"
+ports = [80, 443]
+  [servers.limits]
+  cpu = 2
+[[servers]]
+host = "beta"
+ports = [8080]
+meta = { owner = "ops" }
+"#;
+
+  #[test]
+  fn test_table_ref() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    assert!(parser.table("name").is_none());
+    assert!(parser.table("missing").is_none());
+    let root = parser.table("").unwrap();
+    assert_eq!(Some(Value::basic_string("cluster").unwrap()), root.get("name"));
+    let servers = parser.table("servers").unwrap();
+    assert!(servers.is_array());
+    assert_eq!(2, servers.len());
+    assert_eq!(Some(Value::int(443)), servers.get("[0].ports[1]"));
+    let first = servers.table("[0]").unwrap();
+    assert_eq!("servers[0]", first.key());
+    assert_eq!(Some(Value::int(2)), first.table("limits").unwrap().get("cpu"));
+    assert_eq!(Some(&Children::Count(::std::cell::Cell::new(2))), first.children("ports"));
+    let meta = parser.table("servers[1].meta").unwrap();
+    assert_eq!(Some(Value::basic_string("ops").unwrap()), meta.get("owner"));
+  }
+
+  #[test]
+  fn test_table_key_paths() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    let servers = parser.table("servers").unwrap();
+    assert_eq!(Some(Value::basic_string("beta").unwrap()), servers.get("[-1].host"));
+    assert_eq!(Some(Value::basic_string("alpha").unwrap()), servers.get(KeyPath::root().from_end(2).key("host")));
+    assert_eq!(Some(Value::int(443)), servers.get(KeyPath::root().index(0).key("ports").from_end(1)));
+    assert_eq!("servers[1]", servers.table(KeyPath::root().from_end(1)).unwrap().key());
+    assert!(servers.table("[-3]").is_none());
+    let root = parser.table("").unwrap();
+    assert_eq!(Some(&Children::Count(::std::cell::Cell::new(1))), root.children("servers[last].ports"));
+  }
+
+  #[test]
+  fn test_table_ref_iter() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(DOC);
+    let hosts: Vec<Value> = parser.table("servers").unwrap().iter().map(|t| t.get("host").unwrap()).collect();
+    assert_eq!(vec![Value::basic_string("alpha").unwrap(), Value::basic_string("beta").unwrap()], hosts);
+    assert_eq!(0, parser.table("servers[0]").unwrap().iter().count());
+  }
+
+  #[test]
+  fn test_table_mut() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse(DOC);
+    {
+      let mut second = parser.table_mut("servers[1]").unwrap();
+      second.set("host", Value::basic_string("gamma").unwrap()).unwrap();
+      second.set("ports[0]", Value::int(9090)).unwrap();
+      assert_eq!(Err(EditError::KeyNotFound("servers[1].missing".to_string(), "servers[1].missing".to_string())),
+        second.set("missing", Value::int(1)));
+      second.table_mut("meta").unwrap().set("owner", Value::basic_string("dev").unwrap()).unwrap();
+      assert_eq!(Some(Value::basic_string("dev").unwrap()), second.as_ref().get("meta.owner"));
+    }
+    assert_eq!(Some(Value::basic_string("gamma").unwrap()), parser.get_value("servers[1].host"));
+    assert_eq!(Some(Value::int(9090)), parser.get_value("servers[1].ports[0]"));
+    {
+      let mut servers = parser.table_mut("servers").unwrap();
+      servers.set(KeyPath::root().from_end(1).key("ports").from_end(1), Value::int(9091)).unwrap();
+      servers.table_mut("[-2]").unwrap().set("host", Value::basic_string("delta").unwrap()).unwrap();
+      assert_eq!(Some(Value::basic_string("delta").unwrap()), servers.get(KeyPath::root().index(0).key("host")));
+    }
+    assert_eq!(Some(Value::int(9091)), parser.get_value("servers[1].ports[0]"));
+  }
+}
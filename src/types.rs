@@ -279,13 +279,14 @@ impl<'a> Display for Value<'a> {
       &Value::Boolean(ref b) => write!(f, "{}", b),
       &Value::DateTime(ref v) => write!(f, "{}", v),
       &Value::Array(ref arr) => {
+        if arr.is_empty() {
+          return write!(f, "[]");
+        }
         try!(write!(f, "["));
         for i in 0..arr.len() - 1 {
           try!(write!(f, "{}, ", arr[i]));
         }
-        if arr.len() > 0 {
-          try!(write!(f, "{}", arr[arr.len()-1]));
-        }
+        try!(write!(f, "{}", arr[arr.len()-1]));
         write!(f, "]")
       },
       &Value::String(ref s, ref t) => {
@@ -297,13 +298,14 @@ impl<'a> Display for Value<'a> {
         }
       },
       &Value::InlineTable(ref it) => {
+        if it.is_empty() {
+          return write!(f, "{{}}");
+        }
         try!(write!(f, "{{"));
         for i in 0..it.len() - 1 {
           try!(write!(f, "{} = {}, ", it[i].0, it[i].1));
         }
-        if it.len() > 0 {
-          try!(write!(f, "{} = {}", it[it.len()-1].0, it[it.len()-1].1));
-        }
+        try!(write!(f, "{} = {}", it[it.len()-1].0, it[it.len()-1].1));
         write!(f, "}}")
       }
     }
@@ -992,6 +994,61 @@ impl<'a> Value<'a> {
       _ => true,
     }
   }
+
+  // Returns the text a string value stands for, with its escapes processed and the newline that can follow the
+  // opening quotes of a multi-line string removed, or `None` for other values
+  pub(crate) fn string_content(&self) -> Option<String> {
+    let (s, st) = match *self {
+      Value::String(ref s, st) => (s, st),
+      _ => return None,
+    };
+    let multi_line = st == StrType::MLBasic || st == StrType::MLLiteral;
+    let text = if multi_line {
+      s.strip_prefix("\r\n").or_else(|| s.strip_prefix('\n')).unwrap_or(s)
+    } else {
+      &s[..]
+    };
+    if st == StrType::Literal || st == StrType::MLLiteral {
+      return Some(text.to_string());
+    }
+    let mut content = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+      if c != '\\' {
+        content.push(c);
+        continue;
+      }
+      match chars.next() {
+        Some('b') => content.push('\u{8}'),
+        Some('t') => content.push('\t'),
+        Some('n') => content.push('\n'),
+        Some('f') => content.push('\u{c}'),
+        Some('r') => content.push('\r'),
+        Some('"') => content.push('"'),
+        Some('\\') => content.push('\\'),
+        Some(u) if u == 'u' || u == 'U' => {
+          let len = if u == 'u' { 4 } else { 8 };
+          let hex: String = chars.by_ref().take(len).collect();
+          match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+            Some(c) => content.push(c),
+            None => content.push_str(&format!("\\{}{}", u, hex)),
+          }
+        },
+        // A backslash at the end of a line in a multi-line string removes all whitespace up to the next text
+        Some(c) if multi_line && c.is_whitespace() => {
+          while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+          }
+        },
+        Some(c) => {
+          content.push('\\');
+          content.push(c);
+        },
+        None => content.push('\\'),
+      }
+    }
+    Some(content)
+  }
 }
 
 /// Error type returned by `Value` creation convenience functions on invalid input.
@@ -1032,12 +1089,257 @@ impl Display for TOMLError {
 }
 
 impl TOMLError {
-  fn new(msg: String) -> TOMLError {
+  pub(crate) fn new(msg: String) -> TOMLError {
     warn!("{}", msg);
     TOMLError{message: msg}
   }
 }
 
+/// Error type returned by `TOMLParser::try_set_value` and other editing functions that describes why an edit could
+/// not be made. Every variant contains the `String` key that was passed to the editing function followed by the
+/// `String` key of the part of the document or value that caused the failure.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum EditError {
+  /// The key doesn't exist in the document. The second `String` is the first key along the path that couldn't be
+  /// found, for example setting `"a.b.c"` in a document that only contains `a` reports `"a.b"`.
+  KeyNotFound(String, String),
+  /// The key exists, but it refers to a table or array of tables which don't hold a value. The second `String` is the
+  /// key of the table.
+  NotAValue(String, String),
+  /// The new value failed validation, for instance a basic string containing a newline. The second `String` is the key
+  /// of the value.
+  InvalidValue(String, String),
+  /// An element nested inside of a new `Array` or `InlineTable` value failed validation and couldn't be converted. The
+  /// second `String` is the key the offending element would have had, for example `"a.b[2].c"`.
+  InvalidNestedValue(String, String),
+  /// The key can't be created because a key along its path isn't a table, or a table can't replace an existing value.
+  /// The second `String` is the key of the value that's in the way.
+  NotATable(String, String),
+  /// A key that's being added already exists, under the same rules the parser uses for duplicate keys. The second
+  /// `String` is the key that already exists.
+  DuplicateKey(String, String),
+  /// A table that's being added is already defined or is in the way of a value, under the same rules the parser uses
+  /// for invalid tables. The second `String` is the key of the table.
+  InvalidTable(String, String),
+  /// The key isn't valid key path syntax, or uses an index counted from the end where there's no array to count from.
+  /// The second `String` is the key.
+  InvalidKey(String, String),
+}
+
+impl Error for EditError {
+
+  /// Gives a short description of the kind of edit error.
+  fn description(&self) -> &str {
+    match *self {
+      EditError::KeyNotFound(_, _) => "key not found",
+      EditError::NotAValue(_, _) => "key refers to a table, not a value",
+      EditError::InvalidValue(_, _) => "invalid value",
+      EditError::InvalidNestedValue(_, _) => "invalid nested value",
+      EditError::NotATable(_, _) => "key along the path is not a table",
+      EditError::DuplicateKey(_, _) => "duplicate key",
+      EditError::InvalidTable(_, _) => "invalid table",
+      EditError::InvalidKey(_, _) => "invalid key",
+    }
+  }
+}
+
+impl Display for EditError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      EditError::KeyNotFound(ref key, ref path) if key == path => write!(f, "Key \"{}\" not found.", key),
+      EditError::KeyNotFound(ref key, ref path) =>
+        write!(f, "Key \"{}\" not found, \"{}\" does not exist.", key, path),
+      EditError::NotAValue(ref key, _) =>
+        write!(f, "Key \"{}\" is a table or array of tables and doesn't have a value.", key),
+      EditError::InvalidValue(ref key, _) => write!(f, "The new value for key \"{}\" is invalid.", key),
+      EditError::InvalidNestedValue(ref key, ref path) =>
+        write!(f, "The new value for key \"{}\" contains an invalid value at \"{}\".", key, path),
+      EditError::NotATable(ref key, ref path) =>
+        write!(f, "Key \"{}\" can't be created, \"{}\" is not a table.", key, path),
+      EditError::DuplicateKey(ref key, ref path) =>
+        write!(f, "Key \"{}\" can't be added, \"{}\" already exists.", key, path),
+      EditError::InvalidTable(ref key, ref path) =>
+        write!(f, "Key \"{}\" can't be added, table \"{}\" is already defined or invalid.", key, path),
+      EditError::InvalidKey(ref key, _) => write!(f, "Key \"{}\" is not a valid key path.", key),
+    }
+  }
+}
+
+/// Error type returned by `TOMLParser::query` when a query can't be parsed.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum QueryError {
+  /// The query isn't valid query syntax. Contains the query, the character offset where parsing failed and the reason.
+  Syntax(String, usize, String),
+}
+
+impl Error for QueryError {
+
+  /// Gives a short description of the kind of query error.
+  fn description(&self) -> &str {
+    match *self {
+      QueryError::Syntax(_, _, _) => "invalid query syntax",
+    }
+  }
+}
+
+impl Display for QueryError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      QueryError::Syntax(ref query, offset, ref reason) =>
+        write!(f, "Invalid query \"{}\" at offset {}: {}.", query, offset, reason),
+    }
+  }
+}
+
+/// Error type returned by `TOMLParser::apply_patch` when a patch can't be applied, and by `patch::from_json` when a
+/// patch can't be read. The `usize` is the index of the operation in the patch.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum PatchError {
+  /// The operation couldn't be applied to the document. Contains the index of the operation and the reason.
+  Edit(usize, EditError),
+  /// A `test` operation found a different value, or no value, at its key. Contains the index of the operation and the
+  /// key.
+  TestFailed(usize, String),
+  /// The patch isn't valid JSON or one of its operations is malformed. Contains the reason.
+  InvalidPatch(String),
+}
+
+impl Error for PatchError {
+
+  /// Gives a short description of the kind of patch error.
+  fn description(&self) -> &str {
+    match *self {
+      PatchError::Edit(_, _) => "patch operation failed",
+      PatchError::TestFailed(_, _) => "patch test failed",
+      PatchError::InvalidPatch(_) => "invalid patch",
+    }
+  }
+}
+
+impl Display for PatchError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      PatchError::Edit(index, ref err) => write!(f, "Operation {} failed: {}", index, err),
+      PatchError::TestFailed(index, ref key) =>
+        write!(f, "Operation {} failed: the value at key \"{}\" doesn't match the test value.", index, key),
+      PatchError::InvalidPatch(ref reason) => write!(f, "Invalid patch: {}.", reason),
+    }
+  }
+}
+
+/// Error type returned when a schema can't be built, by `schema::Schema::from_toml`, `schema::Field::pattern` and the
+/// constructors of `json_schema::JsonSchema`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum SchemaError {
+  /// The schema document isn't a valid TOML document. Contains the reason.
+  Parse(String),
+  /// A field of a schema document is malformed. Contains the field's key and the reason.
+  InvalidField(String, String),
+  /// A string pattern isn't a valid regular expression. Contains the pattern and the reason.
+  InvalidPattern(String, String),
+  /// The schema file couldn't be read. Contains the path and the reason.
+  Read(String, String),
+}
+
+impl Error for SchemaError {
+
+  /// Gives a short description of the kind of schema error.
+  fn description(&self) -> &str {
+    match *self {
+      SchemaError::Parse(_) => "invalid schema document",
+      SchemaError::InvalidField(_, _) => "invalid schema field",
+      SchemaError::InvalidPattern(_, _) => "invalid pattern",
+      SchemaError::Read(_, _) => "unable to read schema",
+    }
+  }
+}
+
+impl Display for SchemaError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      SchemaError::Parse(ref reason) => write!(f, "Invalid schema document: {}.", reason),
+      SchemaError::InvalidField(ref key, ref reason) => write!(f, "Invalid schema field \"{}\": {}.", key, reason),
+      SchemaError::InvalidPattern(ref pattern, ref reason) =>
+        write!(f, "Invalid pattern \"{}\": {}.", pattern, reason),
+      SchemaError::Read(ref path, ref reason) => write!(f, "Unable to read schema \"{}\": {}.", path, reason),
+    }
+  }
+}
+
+/// Error type returned when a document can't be converted from or to another format, by `TOMLParser::from_json`,
+/// `TOMLParser::from_tagged_json` and the functions of the `ini`, `properties` and `csv` modules.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ConvertError {
+  /// The input isn't valid in its format, or isn't a document. Contains the reason.
+  Parse(String),
+  /// A value can't be represented in the format it's converted to. Contains the full key of the value in the TOML
+  /// document, with its keys quoted only if they have to be the way `KeyPath` writes them, and the reason. The key is
+  /// empty if the whole document can't be converted.
+  Unrepresentable(String, String),
+}
+
+impl Error for ConvertError {
+
+  /// Gives a short description of the kind of conversion error.
+  fn description(&self) -> &str {
+    match *self {
+      ConvertError::Parse(_) => "invalid input",
+      ConvertError::Unrepresentable(_, _) => "value can't be represented",
+    }
+  }
+}
+
+impl Display for ConvertError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ConvertError::Parse(ref reason) => write!(f, "Invalid input: {}.", reason),
+      ConvertError::Unrepresentable(ref key, ref reason) if key.is_empty() =>
+        write!(f, "Can't convert the document: {}.", reason),
+      ConvertError::Unrepresentable(ref key, ref reason) => write!(f, "Can't convert {}: {}.", key, reason),
+    }
+  }
+}
+
+/// Error type returned by `FormatOptions::from_document` when a document doesn't describe valid format options.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum FormatError {
+  /// An option isn't known or its value isn't valid. Contains the key of the option and the reason.
+  InvalidOption(String, String),
+}
+
+impl Error for FormatError {
+
+  /// Gives a short description of the kind of format error.
+  fn description(&self) -> &str {
+    match *self {
+      FormatError::InvalidOption(_, _) => "invalid format option",
+    }
+  }
+}
+
+impl Display for FormatError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      FormatError::InvalidOption(ref key, ref reason) => write!(f, "Invalid format option \"{}\": {}.", key, reason),
+    }
+  }
+}
+
+/// A position in a TOML document. Both `line` and `column` start at 1, and `column` counts characters, not bytes.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Position {
+  /// The line number.
+  pub line: usize,
+  /// The column number.
+  pub column: usize,
+}
+
+impl Display for Position {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}:{}", self.line, self.column)
+  }
+}
+
 /// Represents a plus sign or minus sign for positive and negative timezone offsets.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum PosNeg {
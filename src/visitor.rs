@@ -0,0 +1,246 @@
+//! Walk every table, key-value pair, array element, inline table and comment of a parsed TOML document in document
+//! order.
+//!
+//! Implement `Visitor` and pass it to `TOMLParser::accept` to inspect a document, or implement `VisitorMut` and pass it
+//! to `TOMLParser::accept_mut` to replace values while walking. Every callback has a default implementation that does
+//! nothing, so only the callbacks you care about need to be implemented. Keys passed to the callbacks are full keys
+//! that can be passed straight to `TOMLParser::get_value` and `TOMLParser::set_value`.
+//!
+//! # Examples
+//!
+//! ```
+//! use tomllib::TOMLParser;
+//! use tomllib::types::{Value, Position};
+//! use tomllib::visitor::Visitor;
+//!
+//! struct KeyCollector {
+//!   keys: Vec<String>,
+//! }
+//!
+//! impl<'a> Visitor<'a> for KeyCollector {
+//!   fn visit_keyval(&mut self, key: &str, _value: &Value<'a>, position: Position) {
+//!     self.keys.push(format!("{} {}", position, key));
+//!   }
+//! }
+//!
+//! let parser = TOMLParser::new();
+//! let (parser, _) = parser.parse("[[servers]]\nname = \"alpha\"\n[[servers]]\nname = \"beta\"");
+//! let mut collector = KeyCollector{keys: vec![]};
+//! parser.accept(&mut collector);
+//! assert_eq!(vec!["2:1 servers[0].name", "4:1 servers[1].name"], collector.keys);
+//! ```
+
+use internals::parser::Parser;
+use internals::walker::{self, Event};
+use types::{Value, Position, EditError};
+
+/// Read-only callbacks for walking a document. See the module documentation for details.
+pub trait Visitor<'a> {
+  /// Called for every standard table header (`[table]`) with the full key of the table.
+  fn visit_table(&mut self, _key: &str, _position: Position) {}
+
+  /// Called for every array of tables header (`[[table]]`) with the full key of the table, including its index.
+  fn visit_array_table(&mut self, _key: &str, _position: Position) {}
+
+  /// Called for every key-value pair, including key-value pairs inside of inline tables.
+  fn visit_keyval(&mut self, _key: &str, _value: &Value<'a>, _position: Position) {}
+
+  /// Called for every value in an array. `key` ends with the index of the value, for example `numbers[2]`.
+  fn visit_array_element(&mut self, _key: &str, _value: &Value<'a>, _position: Position) {}
+
+  /// Called when an inline table is entered, before the key-value pairs it contains.
+  fn visit_inline_table(&mut self, _key: &str, _value: &Value<'a>, _position: Position) {}
+
+  /// Called for every comment. `text` doesn't include the leading `#`.
+  fn visit_comment(&mut self, _text: &str, _position: Position) {}
+}
+
+/// Callbacks for walking a document that can replace values. `visit_keyval` and `visit_array_element` return a new
+/// value to replace the visited value with, or `None` to leave it as is. Replacements are applied in document order
+/// after the walk has finished, using the same rules as `TOMLParser::set_value`. If one of them fails, none of them
+/// are applied.
+pub trait VisitorMut<'a> {
+  /// Called for every standard table header (`[table]`) with the full key of the table.
+  fn visit_table(&mut self, _key: &str, _position: Position) {}
+
+  /// Called for every array of tables header (`[[table]]`) with the full key of the table, including its index.
+  fn visit_array_table(&mut self, _key: &str, _position: Position) {}
+
+  /// Called for every key-value pair, including key-value pairs inside of inline tables. Returns the value to replace
+  /// the visited value with, if any.
+  fn visit_keyval(&mut self, _key: &str, _value: &Value<'a>, _position: Position) -> Option<Value<'a>> {
+    None
+  }
+
+  /// Called for every value in an array. Returns the value to replace the visited value with, if any.
+  fn visit_array_element(&mut self, _key: &str, _value: &Value<'a>, _position: Position) -> Option<Value<'a>> {
+    None
+  }
+
+  /// Called when an inline table is entered, before the key-value pairs it contains.
+  fn visit_inline_table(&mut self, _key: &str, _value: &Value<'a>, _position: Position) {}
+
+  /// Called for every comment. `text` doesn't include the leading `#`.
+  fn visit_comment(&mut self, _text: &str, _position: Position) {}
+}
+
+pub(crate) fn walk<'a, V>(parser: &Parser<'a>, visitor: &mut V) where V: Visitor<'a> {
+  walker::walk(&parser.root.borrow(), |event| {
+    match event {
+      Event::Table(ref key, position) => visitor.visit_table(key, position),
+      Event::ArrayTable(ref key, position) => visitor.visit_array_table(key, position),
+      Event::KeyVal(ref key, ref val, position) | Event::TableKeyVal(ref key, ref val, position) =>
+        visitor.visit_keyval(key, &walker::to_value(&val.borrow()), position),
+      Event::ArrayElement(ref key, ref val, position) =>
+        visitor.visit_array_element(key, &walker::to_value(&val.borrow()), position),
+      Event::InlineTable(ref key, ref val, position) =>
+        visitor.visit_inline_table(key, &walker::to_value(&val.borrow()), position),
+      Event::Comment(ref text, position) => visitor.visit_comment(text, position),
+    }
+  });
+}
+
+pub(crate) fn walk_mut<'a, V>(parser: &mut Parser<'a>, visitor: &mut V) -> Result<(), EditError>
+  where V: VisitorMut<'a> {
+  let mut replacements = vec![];
+  walker::walk(&parser.root.borrow(), |event| {
+    let replacement = match event {
+      Event::Table(ref key, position) => {
+        visitor.visit_table(key, position);
+        None
+      },
+      Event::ArrayTable(ref key, position) => {
+        visitor.visit_array_table(key, position);
+        None
+      },
+      Event::KeyVal(ref key, ref val, position) | Event::TableKeyVal(ref key, ref val, position) =>
+        visitor.visit_keyval(key, &walker::to_value(&val.borrow()), position).map(|v| (key.clone(), v)),
+      Event::ArrayElement(ref key, ref val, position) =>
+        visitor.visit_array_element(key, &walker::to_value(&val.borrow()), position).map(|v| (key.clone(), v)),
+      Event::InlineTable(ref key, ref val, position) => {
+        visitor.visit_inline_table(key, &walker::to_value(&val.borrow()), position);
+        None
+      },
+      Event::Comment(ref text, position) => {
+        visitor.visit_comment(text, position);
+        None
+      },
+    };
+    if let Some(replacement) = replacement {
+      replacements.push(replacement);
+    }
+  });
+  let snapshot = parser.snapshot();
+  for (key, val) in replacements {
+    if let Err(err) = parser.set_value(key, val) {
+      parser.restore(snapshot);
+      return Err(err);
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use TOMLParser;
+  use types::{Value, Position, StrType, EditError};
+  use visitor::{Visitor, VisitorMut};
+
+  struct Recorder {
+    events: Vec<String>,
+  }
+
+  impl<'a> Visitor<'a> for Recorder {
+    fn visit_table(&mut self, key: &str, position: Position) {
+      self.events.push(format!("{} table {}", position, key));
+    }
+    fn visit_array_table(&mut self, key: &str, position: Position) {
+      self.events.push(format!("{} array_table {}", position, key));
+    }
+    fn visit_keyval(&mut self, key: &str, value: &Value<'a>, position: Position) {
+      self.events.push(format!("{} keyval {} = {}", position, key, value));
+    }
+    fn visit_array_element(&mut self, key: &str, value: &Value<'a>, position: Position) {
+      self.events.push(format!("{} element {} = {}", position, key, value));
+    }
+    fn visit_inline_table(&mut self, key: &str, _value: &Value<'a>, position: Position) {
+      self.events.push(format!("{} inline_table {}", position, key));
+    }
+    fn visit_comment(&mut self, text: &str, position: Position) {
+      self.events.push(format!("{} comment {}", position, text));
+    }
+  }
+
+  struct Doubler;
+
+  impl<'a> VisitorMut<'a> for Doubler {
+    fn visit_array_element(&mut self, _key: &str, value: &Value<'a>, _position: Position) -> Option<Value<'a>> {
+      match *value {
+        Value::Integer(ref i) => Some(Value::int(i.parse::<i64>().unwrap() * 2)),
+        _ => None,
+      }
+    }
+  }
+
+  // Replaces `a` with a valid value and `b` with an invalid one
+  struct Invalid;
+
+  impl<'a> VisitorMut<'a> for Invalid {
+    fn visit_keyval(&mut self, key: &str, _value: &Value<'a>, _position: Position) -> Option<Value<'a>> {
+      match key {
+        "a" => Some(Value::int(10)),
+        "b" => Some(Value::String("bad\nbear".into(), StrType::Basic)),
+        _ => None,
+      }
+    }
+  }
+
+  #[test]
+  fn test_walk_document_order() {
+    let parser = TOMLParser::new();
+    let (parser, _) = parser.parse(r#"title = "walk" # the title
+[[fruit]]
+  name = "apple"
+  [fruit.physical]
+    nums = [1, # one
+      { x = 2 }]
+[[fruit]]
+  name = "banana"
+[other]"#);
+    let mut recorder = Recorder{events: vec![]};
+    parser.accept(&mut recorder);
+    assert_eq!(vec![
+      "1:1 keyval title = \"walk\"",
+      "1:16 comment  the title",
+      "2:1 array_table fruit[0]",
+      "3:3 keyval fruit[0].name = \"apple\"",
+      "4:3 table fruit[0].physical",
+      "5:5 keyval fruit[0].physical.nums = [1, {x = 2}]",
+      "5:13 element fruit[0].physical.nums[0] = 1",
+      "5:16 comment  one",
+      "6:7 element fruit[0].physical.nums[1] = {x = 2}",
+      "6:7 inline_table fruit[0].physical.nums[1]",
+      "6:9 keyval fruit[0].physical.nums[1].x = 2",
+      "7:1 array_table fruit[1]",
+      "8:3 keyval fruit[1].name = \"banana\"",
+      "9:1 table other",
+    ], recorder.events);
+  }
+
+  #[test]
+  fn test_walk_mut_replaces_values() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse("a = [1, 2] # keep\nb = { c = [3, [4]] }");
+    parser.accept_mut(&mut Doubler).unwrap();
+    assert_eq!("a = [2, 4] # keep\nb = { c = [6, [8]] }", format!("{}", parser));
+  }
+
+  #[test]
+  fn test_walk_mut_failure_applies_nothing() {
+    let parser = TOMLParser::new();
+    let (mut parser, _) = parser.parse("a = 1\nb = 2\n");
+    assert_eq!(Err(EditError::InvalidValue("b".to_string(), "b".to_string())), parser.accept_mut(&mut Invalid));
+    assert_eq!("a = 1\nb = 2\n", format!("{}", parser));
+    assert_eq!(Some(Value::int(1)), parser.get_value("a"));
+  }
+}
@@ -174,8 +174,8 @@ a_key = "a value"
   };
   let error = &errors.borrow()[0];
   if let &ParseError::MixedArray(ref key, line, _col) = error {
-    assert!(key == "foo.\"bar\"[0].array" && line == 4,
-      "key should be \"foo.\"bar\"[0].array\", but is: \"{}\", line number should be 4, but is: {}",
+    assert!(key == "foo.bar[0].array" && line == 4,
+      "key should be \"foo.bar[0].array\", but is: \"{}\", line number should be 4, but is: {}",
         key, line);
   } else {
     assert!(false, "The first error should have been a mixed array error, but it wasn't.");
@@ -284,7 +284,7 @@ fn test_get_children_on_mixed_tables() {
   assert_eq!(&Children::Keys(RefCell::new(vec!["you".to_string(), "fire".to_string()])), parser.get_children("foo.\"bar\"[2].array[2]").unwrap());
   assert_eq!(&Children::Count(Cell::new(2)), parser.get_children("foo.\"bar\"[2].array[2].you").unwrap());
   assert_eq!(&Children::Keys(RefCell::new(vec!["three".to_string()])), parser.get_children("foo.\"bar\"[2].array[3]").unwrap());
-  assert_eq!(&Children::Keys(RefCell::new(vec!["\"bar\"".to_string(), "quality".to_string(), "\"δïáϱñôƨïƨ\"".to_string(), "\"ƥřôϱñôƨïƨ\"".to_string(), "hypnosis".to_string()])), parser.get_children("foo").unwrap());
+  assert_eq!(&Children::Keys(RefCell::new(vec!["bar".to_string(), "quality".to_string(), "\"δïáϱñôƨïƨ\"".to_string(), "\"ƥřôϱñôƨïƨ\"".to_string(), "hypnosis".to_string()])), parser.get_children("foo").unwrap());
 }
 
 #[test]
@@ -484,4 +484,64 @@ key = 1234
     ParseResult::Full => assert!(false, "There should be an error with a nested table if the parent table has defined key-value pairs."),
     _ => (),
   }
-}
\ No newline at end of file
+}
+
+fn first_error<'a>(result: ParseResult<'a>) -> ParseError<'a> {
+  match result {
+    ParseResult::FullError(e) | ParseResult::PartialError(_,_,_,e) => e.borrow()[0].clone(),
+    _ => panic!("There should have been an error, but there wasn't."),
+  }
+}
+
+#[test]
+fn test_quoted_duplicate_keys_fail() {
+  let _ = env_logger::init();
+  // assets/invalid/duplicate-keys.toml with the second key quoted
+  let parser = TOMLParser::new();
+  let (_, result) = parser.parse("dupe = false\n\"dupe\" = true");
+  match first_error(result) {
+    ParseError::DuplicateKey(key, line, _, value) => {
+      assert_eq!(("dupe", 2, Value::bool(true)), (&key[..], line, value));
+    },
+    error => panic!("Expected a duplicate key error, got {:?}", error),
+  }
+  let parser = TOMLParser::new();
+  let (_, result) = parser.parse("[fruit]\n\"\\u0064upe\" = 1\ndupe = 2");
+  match first_error(result) {
+    ParseError::DuplicateKey(key, line, _, _) => assert_eq!(("fruit.dupe", 3), (&key[..], line)),
+    error => panic!("Expected a duplicate key error, got {:?}", error),
+  }
+}
+
+#[test]
+fn test_quoted_duplicate_tables_fail() {
+  let _ = env_logger::init();
+  // assets/invalid/duplicate-tables.toml with the second table quoted
+  let parser = TOMLParser::new();
+  let (_, result) = parser.parse("[a]\n[\"a\"]");
+  match first_error(result) {
+    ParseError::InvalidTable(key, line, _, _) => assert_eq!(("a", 2), (&key[..], line)),
+    error => panic!("Expected an invalid table error, got {:?}", error),
+  }
+  // assets/invalid/duplicate-key-table.toml with the table's keys quoted
+  let parser = TOMLParser::new();
+  let (_, result) = parser.parse("[fruit]\ntype = \"apple\"\n\n[\"fruit\".\"type\"]\napple = \"yes\"");
+  match first_error(result) {
+    ParseError::InvalidTable(key, line, _, _) => assert_eq!(("fruit.type", 4), (&key[..], line)),
+    error => panic!("Expected an invalid table error, got {:?}", error),
+  }
+}
+
+#[test]
+fn test_quoted_and_bare_keys_are_identical() {
+  let _ = env_logger::init();
+  let parser = TOMLParser::new();
+  let (mut parser, result) = parser.parse("\"a\" = 1\n[\"b\".c]\n\"d e\" = 2\n");
+  assert_eq!(ParseResult::Full, result);
+  assert_eq!(Some(Value::int(1)), parser.get_value("a"));
+  assert_eq!(Some(Value::int(1)), parser.get_value("\"a\""));
+  assert_eq!(Some(Value::int(2)), parser.get_value("b.\"c\".\"d e\""));
+  assert_eq!(&Children::Keys(RefCell::new(vec!["a".to_string(), "b".to_string()])), parser.get_children("").unwrap());
+  assert!(parser.set_value("b.c.\"d e\"", Value::int(3)));
+  assert_eq!("\"a\" = 1\n[\"b\".c]\n\"d e\" = 3\n", format!("{}", parser));
+}